@@ -0,0 +1,254 @@
+//!Derive macro of `sec_store::record::Record`.
+//!
+//!Refer to `sec_store::record` for usage.
+
+extern crate proc_macro;
+
+use proc_macro::{Delimiter, TokenStream, TokenTree};
+
+use core::fmt::Write;
+
+struct Field {
+    attrs: String,
+    name: String,
+    ty: String,
+}
+
+struct Input {
+    vis: String,
+    name: String,
+    namespace: Option<String>,
+    fields: Vec<Field>,
+}
+
+#[inline]
+fn compile_error(message: &str) -> TokenStream {
+    format!("compile_error!({:?});", message).parse().expect("To parse compile_error")
+}
+
+///Parses `namespace = "..."` within `#[sec_record(...)]`.
+fn parse_namespace(attr: TokenStream) -> Result<Option<String>, &'static str> {
+    let mut tokens = attr.into_iter();
+    match tokens.next() {
+        Some(TokenTree::Ident(ident)) if ident.to_string() == "sec_record" => (),
+        _ => return Ok(None),
+    }
+
+    let args = match tokens.next() {
+        Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Parenthesis => group.stream(),
+        _ => return Err("Expected #[sec_record(namespace = \"...\")]"),
+    };
+
+    let mut args = args.into_iter();
+    match (args.next(), args.next(), args.next(), args.next()) {
+        (Some(TokenTree::Ident(key)), Some(TokenTree::Punct(eq)), Some(TokenTree::Literal(value)), None) if key.to_string() == "namespace" && eq.as_char() == '=' => {
+            let value = value.to_string();
+            match value.len() >= 2 && value.starts_with('"') && value.ends_with('"') {
+                true => Ok(Some(value[1..value.len() - 1].to_owned())),
+                false => Err("Namespace must be string literal"),
+            }
+        },
+        _ => Err("Expected #[sec_record(namespace = \"...\")]"),
+    }
+}
+
+///Skips visibility at the start of `tokens`, returning it.
+fn parse_vis(tokens: &mut core::iter::Peekable<proc_macro::token_stream::IntoIter>) -> String {
+    let mut result = String::new();
+    if let Some(TokenTree::Ident(ident)) = tokens.peek() {
+        if ident.to_string() == "pub" {
+            result.push_str("pub");
+            tokens.next();
+            if let Some(TokenTree::Group(group)) = tokens.peek() {
+                if group.delimiter() == Delimiter::Parenthesis {
+                    result.push_str(&group.to_string());
+                    tokens.next();
+                }
+            }
+        }
+    }
+    result
+}
+
+fn parse_fields(body: TokenStream) -> Result<Vec<Field>, &'static str> {
+    let mut result = Vec::new();
+    let mut tokens = body.into_iter().peekable();
+
+    while tokens.peek().is_some() {
+        let mut attrs = String::new();
+        while let Some(TokenTree::Punct(punct)) = tokens.peek() {
+            if punct.as_char() != '#' {
+                return Err("Unexpected token within struct");
+            }
+            tokens.next();
+            match tokens.next() {
+                Some(TokenTree::Group(group)) => {
+                    //Keep documentation, so that accessors are documented.
+                    if matches!(group.stream().into_iter().next(), Some(TokenTree::Ident(ident)) if ident.to_string() == "doc") {
+                        let _ = writeln!(attrs, "#{} ///", group);
+                    }
+                },
+                _ => return Err("Expected attribute"),
+            }
+        }
+
+        parse_vis(&mut tokens);
+        let name = match tokens.next() {
+            Some(TokenTree::Ident(ident)) => ident.to_string(),
+            _ => return Err("Expected field name"),
+        };
+        match tokens.next() {
+            Some(TokenTree::Punct(punct)) if punct.as_char() == ':' => (),
+            _ => return Err("Expected field type"),
+        }
+
+        let mut ty = TokenStream::new();
+        let mut depth = 0usize;
+        for token in tokens.by_ref() {
+            if let TokenTree::Punct(punct) = &token {
+                match punct.as_char() {
+                    '<' => depth += 1,
+                    '>' => depth = depth.saturating_sub(1),
+                    ',' if depth == 0 => break,
+                    _ => (),
+                }
+            }
+            ty.extend(Some(token));
+        }
+
+        result.push(Field {
+            attrs,
+            name: name.trim_start_matches("r#").to_owned(),
+            ty: ty.to_string(),
+        });
+    }
+
+    Ok(result)
+}
+
+fn parse(input: TokenStream) -> Result<Input, &'static str> {
+    let mut tokens = input.into_iter().peekable();
+    let mut namespace = None;
+
+    while let Some(TokenTree::Punct(punct)) = tokens.peek() {
+        if punct.as_char() != '#' {
+            break;
+        }
+        tokens.next();
+        match tokens.next() {
+            Some(TokenTree::Group(group)) => if let Some(value) = parse_namespace(group.stream())? {
+                namespace = Some(value);
+            },
+            _ => return Err("Expected attribute"),
+        }
+    }
+
+    let vis = parse_vis(&mut tokens);
+    match tokens.next() {
+        Some(TokenTree::Ident(ident)) if ident.to_string() == "struct" => (),
+        _ => return Err("SecRecord can only be derived for struct"),
+    }
+    let name = match tokens.next() {
+        Some(TokenTree::Ident(ident)) => ident.to_string(),
+        _ => return Err("Expected struct name"),
+    };
+    let fields = match tokens.next() {
+        Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Brace => parse_fields(group.stream())?,
+        Some(TokenTree::Punct(punct)) if punct.as_char() == '<' => return Err("SecRecord cannot be derived for generic struct"),
+        _ => return Err("SecRecord can only be derived for struct with named fields"),
+    };
+
+    Ok(Input {
+        vis,
+        name,
+        namespace,
+        fields,
+    })
+}
+
+#[inline]
+fn byte_string(value: &str) -> String {
+    let mut result = String::from("b\"");
+    for byte in value.bytes() {
+        let _ = write!(result, "\\x{:02x}", byte);
+    }
+    result.push('"');
+    result
+}
+
+fn generate(input: &Input) -> String {
+    let name = &input.name;
+    let vis = &input.vis;
+    let namespace = byte_string(input.namespace.as_deref().unwrap_or(name));
+
+    let mut fields = String::new();
+    let mut save = String::new();
+    let mut load = String::new();
+    let mut accessors = String::new();
+    for field in input.fields.iter() {
+        let key = byte_string(&field.name);
+        let (field_name, ty, attrs) = (&field.name, &field.ty, &field.attrs);
+        let _ = write!(fields, "{}, ", key);
+        let _ = write!(save, "::sec_store::record::write_field(&mut namespace, {}, &self.r#{})?; ", key, field_name);
+        let _ = write!(load, "r#{}: ::sec_store::record::read_field(&namespace, {})?, ", field_name, key);
+        let _ = write!(accessors, "
+            #[inline]
+            {attrs}///Reads field, refer to `sec_store::record::Record::load` for errors.
+            {vis} fn r#{field}(&self) -> Result<{ty}, ::sec_store::Error> {{
+                ::sec_store::record::read_field(&self.namespace, {key})
+            }}
+
+            #[inline]
+            ///Writes field, refer to `sec_store::record::Record::save` for errors.
+            {vis} fn set_{field}(&mut self, value: &{ty}) -> Result<(), ::sec_store::Error> {{
+                ::sec_store::record::write_field(&mut self.namespace, {key}, value)
+            }}
+        ", attrs = attrs, vis = vis, field = field_name, ty = ty, key = key);
+    }
+
+    format!("
+        impl ::sec_store::record::Record for {name} {{
+            const NAMESPACE: &'static [u8] = {namespace};
+            const FIELDS: &'static [&'static [u8]] = &[{fields}];
+
+            fn save<B: ::sec_store::Backend>(&self, store: &mut ::sec_store::Store<B>) -> Result<(), ::sec_store::Error> {{
+                let mut namespace = store.namespace(<Self as ::sec_store::record::Record>::NAMESPACE);
+                {save}
+                Ok(())
+            }}
+
+            fn load<B: ::sec_store::Backend>(store: &mut ::sec_store::Store<B>) -> Result<Self, ::sec_store::Error> {{
+                let namespace = store.namespace(<Self as ::sec_store::record::Record>::NAMESPACE);
+                Ok(Self {{ {load} }})
+            }}
+        }}
+
+        ///Accessor of `{name}` fields within store.
+        {vis} struct {name}Store<'a, B = ::std::collections::BTreeMap<u128, Vec<u8>>> {{
+            namespace: ::sec_store::Namespace<'a, B>,
+        }}
+
+        impl<'a, B: ::sec_store::Backend> {name}Store<'a, B> {{
+            #[inline]
+            ///Accesses fields within `store`.
+            {vis} fn new(store: &'a mut ::sec_store::Store<B>) -> Self {{
+                Self {{
+                    namespace: store.namespace(<{name} as ::sec_store::record::Record>::NAMESPACE),
+                }}
+            }}
+
+            {accessors}
+        }}
+    ", name = name, vis = vis, namespace = namespace, fields = fields, save = save, load = load, accessors = accessors)
+}
+
+#[proc_macro_derive(SecRecord, attributes(sec_record))]
+///Implements `sec_store::record::Record` for struct with named fields, generating `<Name>Store` accessor.
+///
+///Namespace is name of struct, unless it is specified via `#[sec_record(namespace = "...")]`.
+pub fn derive_record(input: TokenStream) -> TokenStream {
+    match parse(input) {
+        Ok(input) => generate(&input).parse().unwrap_or_else(|_| compile_error("SecRecord generated invalid code")),
+        Err(error) => compile_error(error),
+    }
+}
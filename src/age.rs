@@ -0,0 +1,368 @@
+//!Export into [age](https://age-encryption.org/v1) format, so that backups can be decrypted by standard tools.
+//!
+//!Refer to `Store::export_age` and `Store::export_age_entries`.
+
+use crate::{base64, crypto, enc, Backend, Error, ScryptParams, Store};
+
+use core::fmt;
+use std::io;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, CHACHA20_POLY1305};
+use ring::hmac;
+use ring::rand::{SecureRandom, SystemRandom};
+
+const INTRO: &str = "age-encryption.org/v1\n";
+const X25519_INFO: &[u8] = b"age-encryption.org/v1/X25519";
+const SCRYPT_LABEL: &[u8] = b"age-encryption.org/v1/scrypt";
+///Block size of scrypt, fixed by age.
+const SCRYPT_R: u32 = 8;
+const FILE_KEY_LEN: usize = 16;
+const CHUNK_LEN: usize = 64 * 1024;
+///Length of lines of stanza's body.
+const LINE_LEN: usize = 64;
+
+const BECH32: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+const RECIPIENT_HRP: &[u8] = b"age";
+
+///Recipient of age file.
+pub enum Recipient {
+    ///X25519 public key, that must not be point of small order.
+    X25519([u8; enc::X25519_LEN]),
+    ///Passphrase, that must be sole recipient of file.
+    Scrypt {
+        ///Passphrase.
+        passphrase: Vec<u8>,
+        ///Logarithm of scrypt cost, `age` itself uses `18` by default.
+        log_n: u8,
+    },
+}
+
+impl Recipient {
+    #[inline]
+    ///Creates recipient for `passphrase` with scrypt cost `2^log_n`.
+    ///
+    ///Panics if `log_n` is not within `1..=30`.
+    pub fn passphrase(passphrase: &[u8], log_n: u8) -> Self {
+        assert!(ScryptParams::new(log_n, SCRYPT_R, 1).is_some(), "Invalid scrypt cost");
+        Recipient::Scrypt {
+            passphrase: passphrase.to_owned(),
+            log_n,
+        }
+    }
+
+    ///Parses public key in form of `age1...`, as produced by `age-keygen`.
+    ///
+    ///Returns `Err` if key is malformed.
+    pub fn parse(text: &str) -> Result<Self, ()> {
+        if text.bytes().any(|byte| byte.is_ascii_lowercase()) && text.bytes().any(|byte| byte.is_ascii_uppercase()) {
+            return Err(());
+        }
+
+        let text = text.to_ascii_lowercase();
+        let (hrp, data) = match text.rfind('1') {
+            Some(split) => (&text.as_bytes()[..split], &text.as_bytes()[split + 1..]),
+            None => return Err(()),
+        };
+        if hrp != RECIPIENT_HRP || data.len() < 6 {
+            return Err(());
+        }
+
+        let mut values = Vec::with_capacity(hrp.len() * 2 + 1 + data.len());
+        values.extend(hrp.iter().map(|byte| byte >> 5));
+        values.push(0);
+        values.extend(hrp.iter().map(|byte| byte & 0x1f));
+        for byte in data.iter() {
+            values.push(BECH32.iter().position(|ch| ch == byte).ok_or(())? as u8);
+        }
+        if bech32_polymod(&values) != 1 {
+            return Err(());
+        }
+
+        let mut result = [0u8; enc::X25519_LEN];
+        let mut len = 0;
+        let mut acc = 0u32;
+        let mut bits = 0;
+        for value in values[hrp.len() * 2 + 1..values.len() - 6].iter() {
+            acc = ((acc << 5) | *value as u32) & 0xfff;
+            bits += 5;
+            if bits >= 8 {
+                bits -= 8;
+                *result.get_mut(len).ok_or(())? = (acc >> bits) as u8;
+                len += 1;
+            }
+        }
+
+        //Points of small order produce the same shared secret for any key, hence cannot be recipients.
+        match len == result.len() && bits < 5 && acc & ((1 << bits) - 1) == 0 && crypto::agree(&[1; enc::X25519_LEN], &result).is_some() {
+            true => Ok(Recipient::X25519(result)),
+            false => Err(()),
+        }
+    }
+}
+
+impl fmt::Debug for Recipient {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Recipient::X25519(public) => fmt.debug_tuple("X25519").field(public).finish(),
+            Recipient::Scrypt { log_n, .. } => fmt.debug_struct("Scrypt").field("log_n", log_n).finish(),
+        }
+    }
+}
+
+impl Drop for Recipient {
+    fn drop(&mut self) {
+        if let Recipient::Scrypt { passphrase, .. } = self {
+            enc::wipe(passphrase);
+        }
+    }
+}
+
+fn bech32_polymod(values: &[u8]) -> u32 {
+    const GENERATOR: [u32; 5] = [0x3b6a_57b2, 0x2650_8e6d, 0x1ea1_19fa, 0x3d42_33dd, 0x2a14_62b3];
+
+    let mut result = 1u32;
+    for value in values.iter() {
+        let top = result >> 25;
+        result = ((result & 0x1ff_ffff) << 5) ^ *value as u32;
+        for (idx, generator) in GENERATOR.iter().enumerate() {
+            if (top >> idx) & 1 == 1 {
+                result ^= generator;
+            }
+        }
+    }
+    result
+}
+
+#[inline]
+fn random<const N: usize>() -> Result<[u8; N], Error> {
+    let mut result = [0u8; N];
+    match SystemRandom::new().fill(&mut result) {
+        Ok(()) => Ok(result),
+        Err(_) => Err(Error::RandomFailure),
+    }
+}
+
+///Encrypts `file_key` by ChaCha20-Poly1305 with zero nonce, as every wrapping key is used only once.
+fn wrap(key: &[u8; 32], file_key: &[u8; FILE_KEY_LEN]) -> Vec<u8> {
+    let key = LessSafeKey::new(UnboundKey::new(&CHACHA20_POLY1305, key).expect("To create key"));
+    let mut result = file_key.to_vec();
+    match key.seal_in_place_append_tag(Nonce::assume_unique_for_key([0; enc::NONCE_LEN]), Aad::empty(), &mut result) {
+        Ok(()) => result,
+        Err(_) => unreachable!(),
+    }
+}
+
+///Writes stanza as `-> arguments\nbody`, where body is wrapped into lines of `LINE_LEN`, with last one being shorter.
+fn write_stanza(header: &mut String, arguments: &[&str], body: &[u8]) {
+    header.push_str("->");
+    for argument in arguments {
+        header.push(' ');
+        header.push_str(argument);
+    }
+    header.push('\n');
+
+    let body = base64::encode(body, false);
+    let mut rest = body.as_str();
+    loop {
+        let (line, next) = rest.split_at(core::cmp::min(LINE_LEN, rest.len()));
+        header.push_str(line);
+        header.push('\n');
+        if line.len() < LINE_LEN {
+            break;
+        }
+        rest = next;
+    }
+}
+
+///Encrypts `plaintext` to `recipients` as age file.
+///
+///Returns `Error::InvalidRecipient` if there are no `recipients`, passphrase is not the only one, or any of them is invalid,
+///and `Error::RandomFailure` if random keys cannot be generated.
+fn encrypt(recipients: &[Recipient], plaintext: &[u8]) -> Result<Vec<u8>, Error> {
+    let is_valid = match recipients {
+        [] => false,
+        [Recipient::Scrypt { log_n, .. }] => ScryptParams::new(*log_n, SCRYPT_R, 1).is_some(),
+        recipients => recipients.iter().all(|recipient| matches!(recipient, Recipient::X25519(_))),
+    };
+    if !is_valid {
+        return Err(Error::InvalidRecipient);
+    }
+
+    let nonce = random::<16>()?;
+    let mut file_key = random::<FILE_KEY_LEN>()?;
+    let mut header = String::from(INTRO);
+    for recipient in recipients {
+        match recipient {
+            Recipient::X25519(public) => {
+                let mut secret = match random::<{ enc::X25519_LEN }>() {
+                    Ok(secret) => secret,
+                    Err(error) => {
+                        enc::wipe(&mut file_key);
+                        return Err(error);
+                    },
+                };
+                let share = crypto::public_key(&secret);
+                let shared = crypto::agree(&secret, public);
+                enc::wipe(&mut secret);
+                let mut shared = match shared {
+                    Some(shared) => shared,
+                    None => {
+                        enc::wipe(&mut file_key);
+                        return Err(Error::InvalidRecipient);
+                    },
+                };
+
+                let mut salt = [0u8; 2 * enc::X25519_LEN];
+                salt[..enc::X25519_LEN].copy_from_slice(&share);
+                salt[enc::X25519_LEN..].copy_from_slice(public);
+                let mut key = enc::derive_key(&salt, &shared, X25519_INFO);
+                enc::wipe(&mut shared);
+                write_stanza(&mut header, &["X25519", &base64::encode(&share, false)], &wrap(&key, &file_key));
+                enc::wipe(&mut key);
+            },
+            Recipient::Scrypt { passphrase, log_n } => {
+                let salt = match random::<16>() {
+                    Ok(salt) => salt,
+                    Err(error) => {
+                        enc::wipe(&mut file_key);
+                        return Err(error);
+                    },
+                };
+                let mut label = SCRYPT_LABEL.to_vec();
+                label.extend_from_slice(&salt);
+                let mut key = [0u8; 32];
+                enc::scrypt(passphrase, &label, *log_n, SCRYPT_R, 1, &mut key);
+                write_stanza(&mut header, &["scrypt", &base64::encode(&salt, false), &log_n.to_string()], &wrap(&key, &file_key));
+                enc::wipe(&mut key);
+            },
+        }
+    }
+
+    header.push_str("---");
+    let mut key = enc::derive_key(&[], &file_key, b"header");
+    let mac = hmac::sign(&hmac::Key::new(hmac::HMAC_SHA256, &key), header.as_bytes());
+    enc::wipe(&mut key);
+    header.push(' ');
+    base64::encode_to(mac.as_ref(), false, &mut header);
+    header.push('\n');
+
+    let mut key = enc::derive_key(&nonce, &file_key, b"payload");
+    enc::wipe(&mut file_key);
+    let cipher = LessSafeKey::new(UnboundKey::new(&CHACHA20_POLY1305, &key).expect("To create key"));
+    enc::wipe(&mut key);
+
+    let mut result = header.into_bytes();
+    result.reserve(nonce.len() + plaintext.len() + (plaintext.len() / CHUNK_LEN + 1) * enc::TAG_LEN);
+    result.extend_from_slice(&nonce);
+
+    //Payload is STREAM of chunks with nonce `counter: u88 BE | last: u8`, where empty chunk is only allowed for empty payload.
+    let count = core::cmp::max(plaintext.len().div_ceil(CHUNK_LEN), 1);
+    for idx in 0..count {
+        let mut nonce = [0u8; enc::NONCE_LEN];
+        nonce[3..11].copy_from_slice(&(idx as u64).to_be_bytes());
+        nonce[11] = (idx + 1 == count) as u8;
+
+        let start = result.len();
+        result.extend_from_slice(&plaintext[idx * CHUNK_LEN..core::cmp::min((idx + 1) * CHUNK_LEN, plaintext.len())]);
+        match cipher.seal_in_place_separate_tag(Nonce::assume_unique_for_key(nonce), Aad::empty(), &mut result[start..]) {
+            Ok(tag) => result.extend_from_slice(tag.as_ref()),
+            Err(_) => unreachable!(),
+        }
+    }
+
+    Ok(result)
+}
+
+///Encrypts storage, serialized by `write`, to `recipients` as age file.
+///
+///Returns `Error::Serialize` if `write` fails, otherwise refer to `encrypt`.
+fn encrypt_serialized<F: FnOnce(&mut Vec<u8>) -> io::Result<()>>(recipients: &[Recipient], write: F) -> Result<Vec<u8>, Error> {
+    let mut plaintext = Vec::new();
+    match write(&mut plaintext) {
+        Ok(()) => encrypt(recipients, &plaintext),
+        Err(error) => Err(Error::Serialize(error.kind())),
+    }
+}
+
+impl<B: Backend> Store<B> {
+    ///Returns serialized store, as written by `Self::save`, encrypted to `recipients` as age file.
+    ///
+    ///Decrypted file can be opened via `Self::open`, while its content is still protected by store's credentials.
+    ///Refer to `Self::export_age_entries` to export plaintext instead.
+    ///
+    ///Returns `Error::InvalidRecipient` if there are no `recipients`, passphrase is not the only one, or any of them is invalid,
+    ///`Error::RandomFailure` if random keys cannot be generated,
+    ///and `Error::Serialize` if storage cannot be serialized, e.g. some value exceeds 4 GiB.
+    pub fn export_age(&self, recipients: &[Recipient]) -> Result<Vec<u8>, Error> {
+        encrypt_serialized(recipients, |out| self.write_to(out))
+    }
+
+    ///Returns plaintext of `keys`, encrypted to `recipients` as age file.
+    ///
+    ///Decrypted file contains line per entry, consisting of key and value,
+    ///both encoded as padded base64 and separated by space, for example `a2V5 dmFsdWU=`.
+    ///Hence it can be processed by standard tools without this library.
+    ///
+    ///Returns error when:
+    ///
+    ///- `Error::Locked` - store is locked.
+    ///- `Error::NotFound` - some of `keys` doesn't exist.
+    ///- `Error::InvalidEntry` - value cannot be decrypted.
+    ///- `Error::InvalidRecipient` - there are no `recipients`, passphrase is not the only one, or any of them is invalid.
+    ///- `Error::RandomFailure` - random keys cannot be generated.
+    pub fn export_age_entries(&self, keys: &[&[u8]], recipients: &[Recipient]) -> Result<Vec<u8>, Error> {
+        if self.locked {
+            return Err(Error::Locked);
+        }
+
+        let mut plaintext = String::new();
+        let mut result = Ok(());
+        for key in keys {
+            let hash = self.hash_key(key);
+            let mut value = match self.inner.get(hash) {
+                Some(value) => match self.decrypt_value(hash, value) {
+                    Some(value) => value,
+                    None => {
+                        result = Err(Error::InvalidEntry(hash));
+                        break;
+                    },
+                },
+                None => {
+                    result = Err(Error::NotFound);
+                    break;
+                },
+            };
+
+            base64::encode_to(key, true, &mut plaintext);
+            plaintext.push(' ');
+            base64::encode_to(&value, true, &mut plaintext);
+            plaintext.push('\n');
+            enc::wipe(&mut value);
+        }
+
+        let mut plaintext = plaintext.into_bytes();
+        let result = result.and_then(|()| encrypt(recipients, &plaintext));
+        enc::wipe(&mut plaintext);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_fail_serialization() {
+        let recipients = [Recipient::X25519([9; enc::X25519_LEN])];
+        let result = encrypt_serialized(&recipients, |out| {
+            out.extend_from_slice(b"SECSTORE");
+            Err(io::Error::new(io::ErrorKind::InvalidData, "Value is too large"))
+        });
+        assert_eq!(result.unwrap_err(), Error::Serialize(io::ErrorKind::InvalidData));
+
+        let result = encrypt_serialized(&recipients, |out| {
+            out.extend_from_slice(b"SECSTORE");
+            Ok(())
+        });
+        assert!(result.unwrap().starts_with(INTRO.as_bytes()));
+    }
+}
@@ -0,0 +1,191 @@
+use crate::{Backend, ConcurrentStore, Error, KeyHasher, Store};
+
+use std::collections::BTreeMap;
+
+///Operations of secure storage, allowing to substitute its implementation.
+///
+///Application code can depend on this trait, so that tests can use plain `BTreeMap<Vec<u8>, Vec<u8>>`,
+///which implements it without encryption, and therefore without cost of key derivation.
+pub trait SecStore {
+    ///Retrieves value for `key` to store in `dest`, resulting in it being overwritten.
+    ///
+    ///Returns `Err` when key doesn't exist or user has no permission to read it.
+    ///Otherwise returns number of bytes written.
+    fn get_to_vec(&self, key: &[u8], dest: &mut Vec<u8>) -> Result<usize, ()>;
+
+    #[inline]
+    ///Retrieves value for `key`
+    ///
+    ///Returns `None` if decryption failed.
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        let mut result = Vec::new();
+        match self.get_to_vec(key, &mut result) {
+            Ok(_) => Some(result),
+            Err(_) => None,
+        }
+    }
+
+    ///Checks for `key` presence within storage
+    fn contains(&self, key: &[u8]) -> bool;
+
+    ///Inserts new `value` for `key`, returning previous one, if any.
+    ///
+    ///Panics if `value` is empty, refer to `Self::try_insert`.
+    fn insert(&mut self, key: &[u8], value: &[u8]) -> Option<Vec<u8>>;
+
+    ///Inserts new `value` for `key`, returning previous one, if any.
+    ///
+    ///Returns `Error::InvalidEntry` if `value` is empty, or error, specific to storage, leaving it untouched.
+    fn try_insert(&mut self, key: &[u8], value: &[u8]) -> Result<Option<Vec<u8>>, Error>;
+
+    ///Removes value under `key`, returning it.
+    ///
+    ///Returns `None` if key doesn't exist or user has no permission to read it, in which case value is not removed.
+    fn remove(&mut self, key: &[u8]) -> Option<Vec<u8>>;
+
+    ///Removes `key`, returning whether it was set previously.
+    fn remove_key(&mut self, key: &[u8]) -> bool;
+
+    ///Returns number of key-value pairs
+    fn len(&self) -> usize;
+
+    #[inline]
+    ///Returns whether storage is empty.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<B: Backend> SecStore for Store<B> {
+    #[inline]
+    fn get_to_vec(&self, key: &[u8], dest: &mut Vec<u8>) -> Result<usize, ()> {
+        Store::get_to_vec(self, key, dest)
+    }
+
+    #[inline]
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        Store::get(self, key)
+    }
+
+    #[inline]
+    fn contains(&self, key: &[u8]) -> bool {
+        Store::contains(self, key)
+    }
+
+    #[inline]
+    fn insert(&mut self, key: &[u8], value: &[u8]) -> Option<Vec<u8>> {
+        Store::insert(self, key, value)
+    }
+
+    #[inline]
+    fn try_insert(&mut self, key: &[u8], value: &[u8]) -> Result<Option<Vec<u8>>, Error> {
+        Store::try_insert(self, key, value)
+    }
+
+    #[inline]
+    fn remove(&mut self, key: &[u8]) -> Option<Vec<u8>> {
+        Store::remove(self, key)
+    }
+
+    #[inline]
+    fn remove_key(&mut self, key: &[u8]) -> bool {
+        Store::remove_key(self, key)
+    }
+
+    #[inline]
+    fn len(&self) -> usize {
+        Store::len(self)
+    }
+}
+
+impl SecStore for ConcurrentStore {
+    #[inline]
+    fn get_to_vec(&self, key: &[u8], dest: &mut Vec<u8>) -> Result<usize, ()> {
+        ConcurrentStore::get_to_vec(self, key, dest)
+    }
+
+    #[inline]
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        ConcurrentStore::get(self, key)
+    }
+
+    #[inline]
+    fn contains(&self, key: &[u8]) -> bool {
+        ConcurrentStore::contains(self, key)
+    }
+
+    #[inline]
+    fn insert(&mut self, key: &[u8], value: &[u8]) -> Option<Vec<u8>> {
+        ConcurrentStore::insert(self, key, value)
+    }
+
+    #[inline]
+    fn try_insert(&mut self, key: &[u8], value: &[u8]) -> Result<Option<Vec<u8>>, Error> {
+        ConcurrentStore::try_insert(self, key, value)
+    }
+
+    #[inline]
+    fn remove(&mut self, key: &[u8]) -> Option<Vec<u8>> {
+        ConcurrentStore::remove(self, key)
+    }
+
+    #[inline]
+    fn remove_key(&mut self, key: &[u8]) -> bool {
+        ConcurrentStore::remove_key(self, key)
+    }
+
+    #[inline]
+    fn len(&self) -> usize {
+        ConcurrentStore::len(self)
+    }
+}
+
+///Plain, unencrypted, storage, that is only suitable as substitute in tests.
+impl SecStore for BTreeMap<Vec<u8>, Vec<u8>> {
+    fn get_to_vec(&self, key: &[u8], dest: &mut Vec<u8>) -> Result<usize, ()> {
+        match BTreeMap::get(self, key) {
+            Some(value) => {
+                dest.truncate(0);
+                dest.extend_from_slice(value);
+                Ok(value.len())
+            },
+            None => Err(()),
+        }
+    }
+
+    #[inline]
+    fn contains(&self, key: &[u8]) -> bool {
+        self.contains_key(key)
+    }
+
+    #[inline]
+    fn insert(&mut self, key: &[u8], value: &[u8]) -> Option<Vec<u8>> {
+        match SecStore::try_insert(self, key, value) {
+            Ok(result) => result,
+            Err(error) => panic!("Cannot insert value: {}", error),
+        }
+    }
+
+    #[inline]
+    fn try_insert(&mut self, key: &[u8], value: &[u8]) -> Result<Option<Vec<u8>>, Error> {
+        match value.is_empty() {
+            true => Err(Error::InvalidEntry(crate::Xxh3.hash(key))),
+            false => Ok(BTreeMap::insert(self, key.to_owned(), value.to_owned())),
+        }
+    }
+
+    #[inline]
+    fn remove(&mut self, key: &[u8]) -> Option<Vec<u8>> {
+        BTreeMap::remove(self, key)
+    }
+
+    #[inline]
+    fn remove_key(&mut self, key: &[u8]) -> bool {
+        BTreeMap::remove(self, key).is_some()
+    }
+
+    #[inline]
+    fn len(&self) -> usize {
+        BTreeMap::len(self)
+    }
+}
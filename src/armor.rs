@@ -0,0 +1,64 @@
+//!PEM-style armor of serialized storage.
+//!
+//!Storage, as written by `Store::save`, is encoded as padded base64 split into lines of 64 characters,
+//!enclosed by `BEGIN` and `END` lines.
+
+use crate::{base64, format, Backend, Store};
+
+use std::io;
+
+const BEGIN: &str = "-----BEGIN SEC-STORE-----";
+const END: &str = "-----END SEC-STORE-----";
+const LINE_LEN: usize = 64;
+
+impl<B: Backend> Store<B> {
+    ///Returns serialized storage as ASCII armored text, to be decoded by `Store::from_armored`.
+    ///
+    ///Content is the same as written by `Self::save`, except for decoys, hence it is still protected by store's credentials.
+    ///Returns error if storage cannot be serialized, e.g. some value exceeds 4 GiB.
+    pub fn to_armored(&self) -> io::Result<String> {
+        let mut data = Vec::new();
+        self.write_to(&mut data)?;
+
+        let mut result = String::with_capacity(BEGIN.len() + END.len() + data.len() / 3 * 4 + data.len() / LINE_LEN + 8);
+        result.push_str(BEGIN);
+        result.push('\n');
+        //Each 3 bytes are encoded as 4 characters, so only the last line is padded.
+        for line in data.chunks(LINE_LEN / 4 * 3) {
+            base64::encode_to(line, true, &mut result);
+            result.push('\n');
+        }
+        result.push_str(END);
+        result.push('\n');
+        Ok(result)
+    }
+}
+
+impl Store {
+    ///Decodes storage from ASCII armored `text`, produced by `Self::to_armored`.
+    ///
+    ///Text outside of armor is ignored, as well as whitespace within it,
+    ///so armor can be pasted along with surrounding text or re-wrapped.
+    ///Storage is validated as `Self::try_from_inner` does, with error reported as `InvalidData`.
+    pub fn from_armored(text: &str, user: &[u8], pass: &[u8]) -> io::Result<Self> {
+        let body = match text.find(BEGIN) {
+            Some(start) => &text[start + BEGIN.len()..],
+            None => return Err(io::Error::new(io::ErrorKind::InvalidData, "Missing armor header")),
+        };
+        let body = match body.find(END) {
+            Some(end) => &body[..end],
+            None => return Err(io::Error::new(io::ErrorKind::InvalidData, "Missing armor footer")),
+        };
+
+        let data = match base64::decode(body.as_bytes()) {
+            Some(data) => data,
+            None => return Err(io::Error::new(io::ErrorKind::InvalidData, "Invalid armor content")),
+        };
+        let mut input = data.as_slice();
+        let inner = format::read_map(&mut input)?;
+        if !input.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Trailing data after storage"));
+        }
+        Self::try_from_inner(inner, user, pass).map_err(Into::into)
+    }
+}
@@ -0,0 +1,129 @@
+use crate::{enc, Backend, Store, AUDIT_KEY};
+
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const RECORD_LEN: usize = 8 + 1 + 16 + 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+///Audited operation.
+pub enum AuditOp {
+    ///Reading of value.
+    Get,
+    ///Insertion of value.
+    Insert,
+    ///Removal of value.
+    Remove,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+///Record of audit log.
+pub struct AuditRecord {
+    ///Seconds since UNIX epoch.
+    pub timestamp: u64,
+    ///Performed operation.
+    pub op: AuditOp,
+    ///Hash of accessed key.
+    pub key: u128,
+    ///Whether operation succeeded.
+    pub success: bool,
+}
+
+impl AuditRecord {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.timestamp.to_le_bytes());
+        out.push(self.op as u8);
+        out.extend_from_slice(&self.key.to_le_bytes());
+        out.push(self.success as u8);
+    }
+
+    fn decode(input: &[u8]) -> Option<Self> {
+        let mut timestamp = [0u8; 8];
+        timestamp.copy_from_slice(&input[..8]);
+        let op = match input[8] {
+            0 => AuditOp::Get,
+            1 => AuditOp::Insert,
+            2 => AuditOp::Remove,
+            _ => return None,
+        };
+        let mut key = [0u8; 16];
+        key.copy_from_slice(&input[9..25]);
+
+        Some(Self {
+            timestamp: u64::from_le_bytes(timestamp),
+            op,
+            key: u128::from_le_bytes(key),
+            success: input[25] != 0,
+        })
+    }
+}
+
+///Audit log, kept decrypted in memory and sealed into storage on flush.
+///
+///`None` indicates that existing log cannot be decrypted, in which case nothing is recorded to avoid overwriting it.
+pub(crate) struct AuditLog {
+    records: Mutex<Option<Vec<AuditRecord>>>,
+}
+
+impl AuditLog {
+    pub(crate) fn load<B: Backend>(enc: &enc::Manager, inner: &B) -> Self {
+        let records = match inner.get(AUDIT_KEY) {
+            Some(log) => enc.open_random(log).map(|log| log.chunks_exact(RECORD_LEN).filter_map(AuditRecord::decode).collect()),
+            None => Some(Vec::new()),
+        };
+
+        Self {
+            records: Mutex::new(records),
+        }
+    }
+
+    pub(crate) fn record(&self, op: AuditOp, key: u128, success: bool) {
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_secs()).unwrap_or(0);
+        if let Some(records) = self.records.lock().unwrap_or_else(|error| error.into_inner()).as_mut() {
+            records.push(AuditRecord {
+                timestamp,
+                op,
+                key,
+                success,
+            });
+        }
+    }
+}
+
+impl<B: Backend> Store<B> {
+    ///Returns audit log of store's accesses.
+    ///
+    ///Log is kept encrypted within storage and includes records of previous sessions that were flushed.
+    ///Returns empty log, if existing log cannot be decrypted.
+    pub fn audit_log(&self) -> Vec<AuditRecord> {
+        match self.audit.records.lock().unwrap_or_else(|error| error.into_inner()).as_ref() {
+            Some(records) => records.clone(),
+            None => Vec::new(),
+        }
+    }
+
+    ///Writes audit log into storage.
+    ///
+    ///It is done automatically by `Self::update_mac` and `Self::into_inner`.
+    ///The latter also keeps integrity MAC valid, if it was valid before flushing.
+    pub fn flush_audit(&mut self) {
+        if self.locked {
+            return;
+        }
+
+        let log = match self.audit.records.lock().unwrap_or_else(|error| error.into_inner()).as_ref() {
+            Some(records) => {
+                let mut log = Vec::with_capacity(records.len() * RECORD_LEN);
+                for record in records.iter() {
+                    record.encode(&mut log);
+                }
+                self.enc.seal_random(&log)
+            },
+            None => None,
+        };
+
+        if let Some(log) = log {
+            self.inner.insert(AUDIT_KEY, log);
+        }
+    }
+}
@@ -0,0 +1,117 @@
+//!Automatic locking of store after inactivity.
+
+use crate::{Backend, Store};
+
+use core::time::Duration;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread;
+use std::time::Instant;
+
+struct State<B> {
+    store: Store<B>,
+    last_access: Instant,
+    is_stopped: bool,
+}
+
+struct Shared<B> {
+    state: Mutex<State<B>>,
+    wakeup: Condvar,
+}
+
+impl<B> Shared<B> {
+    #[inline]
+    fn state(&self) -> MutexGuard<'_, State<B>> {
+        self.state.lock().unwrap_or_else(|error| error.into_inner())
+    }
+}
+
+///Store, that is locked automatically once it is not accessed for specified timeout.
+///
+///Background thread keeps track of inactivity, locking store via `Store::lock`, so its key doesn't stay in memory.
+///Store is accessed via `Self::with`, which counts as activity, and it is up to user to unlock it when needed.
+pub struct AutoLock<B: Backend + Send + 'static = BTreeMap<u128, Vec<u8>>> {
+    shared: Arc<Shared<B>>,
+    worker: Option<thread::JoinHandle<()>>,
+}
+
+impl<B: Backend + Send + 'static> AutoLock<B> {
+    ///Starts tracking inactivity of `store`, locking it after `timeout` without access.
+    pub fn new(store: Store<B>, timeout: Duration) -> Self {
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State {
+                store,
+                last_access: Instant::now(),
+                is_stopped: false,
+            }),
+            wakeup: Condvar::new(),
+        });
+
+        let worker = {
+            let shared = shared.clone();
+            thread::spawn(move || {
+                let mut state = shared.state();
+                while !state.is_stopped {
+                    if state.store.is_locked() {
+                        state = shared.wakeup.wait(state).unwrap_or_else(|error| error.into_inner());
+                        continue;
+                    }
+
+                    let elapsed = state.last_access.elapsed();
+                    if elapsed >= timeout {
+                        state.store.lock();
+                    } else {
+                        state = shared.wakeup.wait_timeout(state, timeout - elapsed).unwrap_or_else(|error| error.into_inner()).0;
+                    }
+                }
+            })
+        };
+
+        Self {
+            shared,
+            worker: Some(worker),
+        }
+    }
+
+    ///Accesses store, resetting inactivity timer.
+    pub fn with<R, F: FnOnce(&mut Store<B>) -> R>(&self, cb: F) -> R {
+        let mut state = self.shared.state();
+        let result = cb(&mut state.store);
+        state.last_access = Instant::now();
+        self.shared.wakeup.notify_one();
+        result
+    }
+
+    #[inline]
+    ///Returns whether store is locked, without resetting inactivity timer.
+    pub fn is_locked(&self) -> bool {
+        self.shared.state().store.is_locked()
+    }
+
+    #[inline]
+    fn stop(&mut self) {
+        self.shared.state().is_stopped = true;
+        self.shared.wakeup.notify_one();
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+
+    ///Stops tracking inactivity, returning store.
+    pub fn into_inner(mut self) -> Store<B> {
+        self.stop();
+        let shared = self.shared.clone();
+        drop(self);
+        match Arc::try_unwrap(shared) {
+            Ok(shared) => shared.state.into_inner().unwrap_or_else(|error| error.into_inner()).store,
+            Err(_) => unreachable!(),
+        }
+    }
+}
+
+impl<B: Backend + Send + 'static> Drop for AutoLock<B> {
+    #[inline]
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
@@ -0,0 +1,151 @@
+use crate::{Backend, Store};
+
+use core::time::Duration;
+use std::io;
+use std::sync::Mutex;
+use std::time::Instant;
+
+///Persistence sink, invoked by autosave with store to persist.
+pub type AutosaveFn<B> = dyn FnMut(&Store<B>) -> io::Result<()> + Send;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+///Policy of autosave, refer to `Store::set_autosave`.
+pub enum AutosavePolicy {
+    ///Saves after every modification.
+    Immediate,
+    ///Saves once specified number of modifications is accumulated.
+    ///
+    ///Zero is treated as `1`.
+    EveryChanges(usize),
+    ///Saves on modification, if at least specified duration passed since previous save.
+    ///
+    ///Modifications, made within duration, are saved by next modification after it, or by `Store::flush_autosave`.
+    Debounced(Duration),
+}
+
+pub(crate) struct Autosave<B> {
+    policy: AutosavePolicy,
+    //Mutex only to make store `Sync`, as sink is invoked with exclusive access.
+    sink: Mutex<Box<AutosaveFn<B>>>,
+    ///Number of modifications since last save.
+    pending: usize,
+    last_save: Instant,
+    ///Depth of compound modifications, that are saved only once complete.
+    suspended: usize,
+    error: Option<io::Error>,
+}
+
+impl<B> Autosave<B> {
+    #[inline]
+    fn is_due(&self) -> bool {
+        self.pending > 0 && self.suspended == 0 && match self.policy {
+            AutosavePolicy::Immediate => true,
+            AutosavePolicy::EveryChanges(count) => self.pending >= core::cmp::max(count, 1),
+            AutosavePolicy::Debounced(duration) => self.last_save.elapsed() >= duration,
+        }
+    }
+}
+
+impl<B: Backend> Store<B> {
+    ///Saves store via sink of autosave, returning whether it succeeded.
+    fn run_autosave(&mut self) -> bool {
+        let mut autosave = match self.autosave.take() {
+            Some(autosave) => autosave,
+            None => return true,
+        };
+
+        let sink = autosave.sink.get_mut().unwrap_or_else(|error| error.into_inner());
+        let result = match sink(self) {
+            Ok(()) => {
+                autosave.pending = 0;
+                autosave.last_save = Instant::now();
+                true
+            },
+            Err(error) => {
+                autosave.error = Some(error);
+                false
+            },
+        };
+        self.autosave = Some(autosave);
+        result
+    }
+
+    ///Accounts modification of store, saving it if autosave policy requires.
+    pub(crate) fn autosave_changed(&mut self) {
+        if let Some(autosave) = self.autosave.as_mut() {
+            autosave.pending += 1;
+            if autosave.is_due() {
+                self.run_autosave();
+            }
+        }
+    }
+
+    #[inline]
+    ///Postpones autosave until matching `Self::resume_autosave`.
+    pub(crate) fn suspend_autosave(&mut self) {
+        if let Some(autosave) = self.autosave.as_mut() {
+            autosave.suspended += 1;
+        }
+    }
+
+    #[inline]
+    ///Finishes compound modification, saving store if autosave policy requires.
+    pub(crate) fn resume_autosave(&mut self) {
+        if let Some(autosave) = self.autosave.as_mut() {
+            autosave.suspended -= 1;
+            if autosave.is_due() {
+                self.run_autosave();
+            }
+        }
+    }
+
+    ///Sets `sink`, that persists store automatically after modifications according to `policy`.
+    ///
+    ///Sink receives store itself, so it can use any method of persistence (e.g. `Self::save` or `Self::save_incremental`).
+    ///Compound modifications (e.g. transaction or merge) are saved once complete.
+    ///
+    ///Modifications cannot report errors of sink, so the last one is kept until `Self::take_autosave_error`,
+    ///while failed save is retried on next modification.
+    pub fn set_autosave<F: FnMut(&Store<B>) -> io::Result<()> + Send + 'static>(&mut self, sink: F, policy: AutosavePolicy) {
+        self.autosave = Some(Autosave {
+            policy,
+            sink: Mutex::new(Box::new(sink)),
+            pending: 0,
+            last_save: Instant::now(),
+            suspended: 0,
+            error: None,
+        });
+    }
+
+    #[inline]
+    ///Disables autosave, discarding its sink.
+    pub fn disable_autosave(&mut self) {
+        self.autosave = None;
+    }
+
+    #[inline]
+    ///Returns whether there are modifications, that are not saved by autosave yet.
+    pub fn has_pending_autosave(&self) -> bool {
+        matches!(self.autosave.as_ref(), Some(autosave) if autosave.pending > 0)
+    }
+
+    ///Saves pending modifications via sink of autosave, if any.
+    ///
+    ///Should be called before shutdown, unless policy is `AutosavePolicy::Immediate`.
+    pub fn flush_autosave(&mut self) -> io::Result<()> {
+        if !self.has_pending_autosave() || self.run_autosave() {
+            return Ok(());
+        }
+
+        match self.take_autosave_error() {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+
+    #[inline]
+    ///Takes last error of autosave's sink, if any.
+    pub fn take_autosave_error(&mut self) -> Option<io::Error> {
+        self.autosave.as_mut().and_then(|autosave| autosave.error.take())
+    }
+}
@@ -0,0 +1,92 @@
+//!Storage backends.
+
+use std::collections::{BTreeMap, HashMap};
+use std::hash::BuildHasher;
+
+///Storage of encrypted entries, keyed by hash of key.
+///
+///Backend only stores opaque ciphertexts, while all encryption is performed by `Store`.
+///Besides user's entries, it holds internal ones, which are managed by `Store` as well.
+pub trait Backend {
+    ///Returns ciphertext stored under `key`.
+    fn get(&self, key: u128) -> Option<&[u8]>;
+    ///Stores ciphertext under `key`, returning previous one, if any.
+    fn insert(&mut self, key: u128, value: Vec<u8>) -> Option<Vec<u8>>;
+    ///Removes ciphertext under `key`, returning it, if any.
+    fn remove(&mut self, key: u128) -> Option<Vec<u8>>;
+    ///Returns total number of entries.
+    fn len(&self) -> usize;
+    ///Iterates over all entries, in no particular order.
+    fn iter(&self) -> Box<dyn Iterator<Item = (u128, &[u8])> + '_>;
+
+    #[inline]
+    ///Checks for `key` presence.
+    fn contains(&self, key: u128) -> bool {
+        self.get(key).is_some()
+    }
+}
+
+impl Backend for BTreeMap<u128, Vec<u8>> {
+    #[inline]
+    fn get(&self, key: u128) -> Option<&[u8]> {
+        BTreeMap::get(self, &key).map(Vec::as_slice)
+    }
+
+    #[inline]
+    fn insert(&mut self, key: u128, value: Vec<u8>) -> Option<Vec<u8>> {
+        BTreeMap::insert(self, key, value)
+    }
+
+    #[inline]
+    fn remove(&mut self, key: u128) -> Option<Vec<u8>> {
+        BTreeMap::remove(self, &key)
+    }
+
+    #[inline]
+    fn len(&self) -> usize {
+        BTreeMap::len(self)
+    }
+
+    #[inline]
+    fn iter(&self) -> Box<dyn Iterator<Item = (u128, &[u8])> + '_> {
+        Box::new(BTreeMap::iter(self).map(|(key, value)| (*key, value.as_slice())))
+    }
+
+    #[inline]
+    fn contains(&self, key: u128) -> bool {
+        self.contains_key(&key)
+    }
+}
+
+///Unordered backend, that trades ordered traversal for faster lookups.
+impl<S: BuildHasher> Backend for HashMap<u128, Vec<u8>, S> {
+    #[inline]
+    fn get(&self, key: u128) -> Option<&[u8]> {
+        HashMap::get(self, &key).map(Vec::as_slice)
+    }
+
+    #[inline]
+    fn insert(&mut self, key: u128, value: Vec<u8>) -> Option<Vec<u8>> {
+        HashMap::insert(self, key, value)
+    }
+
+    #[inline]
+    fn remove(&mut self, key: u128) -> Option<Vec<u8>> {
+        HashMap::remove(self, &key)
+    }
+
+    #[inline]
+    fn len(&self) -> usize {
+        HashMap::len(self)
+    }
+
+    #[inline]
+    fn iter(&self) -> Box<dyn Iterator<Item = (u128, &[u8])> + '_> {
+        Box::new(HashMap::iter(self).map(|(key, value)| (*key, value.as_slice())))
+    }
+
+    #[inline]
+    fn contains(&self, key: u128) -> bool {
+        self.contains_key(&key)
+    }
+}
@@ -0,0 +1,66 @@
+//!Base64 (RFC 4648) with standard alphabet.
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+///Appends encoding of `input` to `out`, padding it if `pad` is set.
+pub fn encode_to(input: &[u8], pad: bool, out: &mut String) {
+    out.reserve(input.len().div_ceil(3) * 4);
+    for chunk in input.chunks(3) {
+        let word = chunk.iter().enumerate().fold(0u32, |word, (idx, byte)| word | (*byte as u32) << (16 - 8 * idx));
+        for idx in 0..4 {
+            if idx <= chunk.len() {
+                out.push(ALPHABET[(word >> (18 - 6 * idx)) as usize & 0x3f] as char);
+            } else if pad {
+                out.push('=');
+            }
+        }
+    }
+}
+
+#[inline]
+///Returns encoding of `input`, padding it if `pad` is set.
+pub fn encode(input: &[u8], pad: bool) -> String {
+    let mut result = String::new();
+    encode_to(input, pad, &mut result);
+    result
+}
+
+#[inline]
+fn value(byte: u8) -> Option<u8> {
+    match byte {
+        b'A'..=b'Z' => Some(byte - b'A'),
+        b'a'..=b'z' => Some(byte - b'a' + 26),
+        b'0'..=b'9' => Some(byte - b'0' + 52),
+        b'+' => Some(62),
+        b'/' => Some(63),
+        _ => None,
+    }
+}
+
+///Decodes `input` with optional padding, ignoring whitespaces.
+pub fn decode(input: &[u8]) -> Option<Vec<u8>> {
+    let mut result = Vec::with_capacity(input.len() / 4 * 3);
+    let mut acc = 0u32;
+    let mut bits = 0;
+    let mut padding = 0;
+    for byte in input.iter().filter(|byte| !byte.is_ascii_whitespace()) {
+        if *byte == b'=' {
+            padding += 1;
+            continue;
+        } else if padding > 0 {
+            return None;
+        }
+
+        acc = ((acc << 6) | value(*byte)? as u32) & 0xfff;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            result.push((acc >> bits) as u8);
+        }
+    }
+
+    match padding <= 2 && bits < 6 {
+        true => Some(result),
+        false => None,
+    }
+}
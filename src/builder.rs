@@ -0,0 +1,298 @@
+use crate::{format, lru, seal, password_strength, Backend, Error, EvictFn, Kdf, KeyHasher, Padding, Store, Xxh3};
+
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+///Limits on content of store, with `None` meaning no limit.
+pub struct Limits {
+    ///Maximum number of key-value pairs.
+    pub max_entries: Option<usize>,
+    ///Maximum size of single value in bytes, before encryption.
+    pub max_value_size: Option<usize>,
+    ///Maximum size of all ciphertexts in bytes, not counting internal entries.
+    pub max_total_size: Option<usize>,
+}
+
+impl Limits {
+    ///Checks whether store with `entries` pairs of `size` total bytes can have `previous` ciphertext replaced
+    ///with new one of `len` bytes, encrypting `plain_len` bytes.
+    pub(crate) fn check(&self, entries: usize, size: usize, previous: Option<usize>, plain_len: usize, len: usize) -> Result<(), Error> {
+        if matches!(self.max_value_size, Some(max) if plain_len > max) {
+            return Err(Error::LimitExceeded);
+        }
+
+        if previous.is_none() && matches!(self.max_entries, Some(max) if entries >= max) {
+            return Err(Error::LimitExceeded);
+        }
+
+        match self.max_total_size {
+            Some(max) if size - previous.unwrap_or(0) + len > max => Err(Error::LimitExceeded),
+            _ => Ok(()),
+        }
+    }
+}
+
+///Builder of `Store`, allowing to configure it beyond credentials.
+pub struct StoreBuilder<'a, B = BTreeMap<u128, Vec<u8>>> {
+    user: &'a [u8],
+    pass: &'a [u8],
+    backend: B,
+    limits: Limits,
+    eviction: Option<lru::Lru>,
+    sealing: seal::Sealing,
+    decoys: usize,
+    strength: u8,
+    kdf: Kdf,
+    hasher: Arc<dyn KeyHasher>,
+}
+
+impl<'a> StoreBuilder<'a> {
+    #[inline]
+    ///Starts building store with specified credentials, using default backend.
+    pub fn new(user: &'a [u8], pass: &'a [u8]) -> Self {
+        Self {
+            user,
+            pass,
+            backend: BTreeMap::new(),
+            limits: Limits::default(),
+            eviction: None,
+            sealing: seal::Sealing::default(),
+            decoys: 0,
+            strength: 0,
+            kdf: Kdf::Pbkdf2,
+            hasher: Arc::new(Xxh3),
+        }
+    }
+
+    ///Opens storage, previously saved via `Store::save`, validating it on build.
+    ///
+    ///It is the only way to open storage with hasher other than default, refer to `Self::key_hasher`.
+    pub fn open<P: AsRef<Path>>(self, path: P) -> io::Result<Store> {
+        let inner = format::read_file(path.as_ref())?;
+        self.backend(inner).build().map_err(Into::into)
+    }
+}
+
+impl<'a, B: Backend> StoreBuilder<'a, B> {
+    #[inline]
+    ///Sets `backend` to keep entries in.
+    ///
+    ///Non-empty backend is treated as existing storage, and validated on build.
+    pub fn backend<T: Backend>(self, backend: T) -> StoreBuilder<'a, T> {
+        StoreBuilder {
+            user: self.user,
+            pass: self.pass,
+            backend,
+            limits: self.limits,
+            eviction: self.eviction,
+            sealing: self.sealing,
+            decoys: self.decoys,
+            strength: self.strength,
+            kdf: self.kdf,
+            hasher: self.hasher,
+        }
+    }
+
+    #[inline]
+    ///Sets all limits at once.
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    #[inline]
+    ///Sets maximum number of key-value pairs.
+    pub fn max_entries(mut self, max: usize) -> Self {
+        self.limits.max_entries = Some(max);
+        self
+    }
+
+    #[inline]
+    ///Sets maximum size of single value in bytes.
+    pub fn max_value_size(mut self, max: usize) -> Self {
+        self.limits.max_value_size = Some(max);
+        self
+    }
+
+    #[inline]
+    ///Sets maximum size of all ciphertexts in bytes.
+    pub fn max_total_size(mut self, max: usize) -> Self {
+        self.limits.max_total_size = Some(max);
+        self
+    }
+
+    #[inline]
+    ///Enables eviction of least recently used entries, once store has more than `capacity` of them.
+    ///
+    ///Entry is considered used when it is inserted or read.
+    ///Each evicted entry is passed to `on_evict`, so it can be moved to other storage.
+    ///
+    ///Note that `Self::max_entries` takes precedence, making insertion fail instead of evicting.
+    pub fn evict_lru<F: FnMut(u128, Option<Vec<u8>>) + Send + 'static>(mut self, capacity: usize, on_evict: F) -> Self {
+        let on_evict: Box<EvictFn> = Box::new(on_evict);
+        self.eviction = Some(lru::Lru::new(capacity, on_evict));
+        self
+    }
+
+    #[inline]
+    ///Sets whether values are encrypted using random nonce.
+    ///
+    ///Refer to `Store::set_randomized` for details.
+    pub fn randomized(mut self, randomized: bool) -> Self {
+        self.sealing.randomized = randomized;
+        self
+    }
+
+    #[inline]
+    ///Sets padding of values, hiding their exact length.
+    ///
+    ///Refer to `Store::set_padding` for details.
+    pub fn padding(mut self, padding: Padding) -> Self {
+        self.sealing.padding = Some(padding);
+        self
+    }
+
+    #[inline]
+    ///Sets size of chunk, values larger than which are split into chunks, with `None` disabling it.
+    ///
+    ///Refer to `Store::set_chunking` for details.
+    ///
+    ///Panics if `chunk_size` is zero or exceeds `u32::MAX`.
+    pub fn chunking(mut self, chunk_size: Option<usize>) -> Self {
+        if let Some(chunk_size) = chunk_size {
+            assert_ne!(chunk_size, 0);
+            assert!(chunk_size <= u32::MAX as usize);
+        }
+        self.sealing.chunk_size = chunk_size;
+        self
+    }
+
+    #[inline]
+    ///Sets number of decoy entries, written along with values on save.
+    ///
+    ///Refer to `Store::set_decoys` for details.
+    pub fn decoys(mut self, count: usize) -> Self {
+        self.decoys = count;
+        self
+    }
+
+    #[inline]
+    ///Requires password of new store to have at least `score` of estimated strength, from `0` to `4`.
+    ///
+    ///Existing storage is opened regardless of its password's strength.
+    ///Refer to `password_strength` for details.
+    pub fn require_strength(mut self, score: u8) -> Self {
+        self.strength = score;
+        self
+    }
+
+    #[inline]
+    ///Sets function deriving key of new store from credentials.
+    ///
+    ///Existing storage is opened using its own function.
+    ///Refer to `Kdf` for details.
+    pub fn kdf(mut self, kdf: Kdf) -> Self {
+        self.kdf = kdf;
+        self
+    }
+
+    #[inline]
+    ///Sets `hasher` of key names, which is `Xxh3` by default.
+    ///
+    ///It is recorded within header of new store, while existing storage must use the same hasher,
+    ///otherwise build fails with `Error::KeyHasherMismatch`.
+    ///Keys of namespaces are hashed by it as well, refer to `KeyHasher::hash_namespaced`.
+    pub fn key_hasher<H: KeyHasher + 'static>(mut self, hasher: H) -> Self {
+        self.hasher = Arc::new(hasher);
+        self
+    }
+
+    ///Creates store.
+    ///
+    ///Returns error when:
+    ///
+    ///- `Error::LimitExceeded` - existing storage doesn't fit configured limits.
+    ///- `Error::WeakPassword` - password of new store is not strong enough.
+    ///- Any error of `Store::try_new_in_with_kdf` for empty backend or `Store::try_from_backend` otherwise.
+    pub fn build(self) -> Result<Store<B>, Error> {
+        let mut result = match self.backend.len() {
+            0 => {
+                //Strength is checked ahead of expensive key derivation, while empty credentials are reported as such.
+                let score = password_strength(self.pass);
+                if score < self.strength && !self.user.is_empty() && !self.pass.is_empty() {
+                    return Err(Error::WeakPassword { score, required: self.strength });
+                }
+                let mut result = Store::try_new_in_with_kdf(self.backend, self.user, self.pass, self.kdf)?;
+                result.hasher = self.hasher;
+                result.write_header();
+                result
+            },
+            _ => Store::try_from_backend_with_hasher(self.backend, self.user, self.pass, self.hasher)?,
+        };
+
+        if matches!(self.limits.max_entries, Some(max) if result.len() > max) || matches!(self.limits.max_total_size, Some(max) if result.size > max) {
+            return Err(Error::LimitExceeded);
+        }
+
+        if let Some(max) = self.limits.max_value_size {
+            for (key, value) in result.entries() {
+                match result.decrypt_value(key, value) {
+                    Some(value) if value.len() > max => return Err(Error::LimitExceeded),
+                    _ => (),
+                }
+            }
+        }
+
+        result.limits = self.limits;
+        result.sealing = self.sealing;
+        result.decoys = self.decoys;
+        if let Some(eviction) = self.eviction {
+            eviction.reset(result.entries().map(|(key, _)| key));
+            result.eviction = Some(eviction);
+            result.evict();
+        }
+        Ok(result)
+    }
+}
+
+impl Store {
+    #[inline]
+    ///Starts building store with specified credentials.
+    ///
+    ///Refer to `StoreBuilder` for details.
+    pub fn builder<'a>(user: &'a [u8], pass: &'a [u8]) -> StoreBuilder<'a> {
+        StoreBuilder::new(user, pass)
+    }
+}
+
+impl<B: Backend> Store<B> {
+    #[inline]
+    ///Returns limits on content of store.
+    pub fn limits(&self) -> &Limits {
+        &self.limits
+    }
+
+    #[inline]
+    ///Returns size of all ciphertexts in bytes, not counting internal entries.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    ///Checks whether ciphertext of `len` bytes, encrypting `plain_len` bytes, can be stored under `key`.
+    ///
+    ///Returns `Error::Locked` if store is locked, as value cannot be encrypted,
+    ///or `Error::InvalidEntry` if value is empty, as it cannot be stored.
+    pub(crate) fn check_limits(&self, key: u128, plain_len: usize, len: usize) -> Result<(), Error> {
+        if self.locked {
+            return Err(Error::Locked);
+        } else if plain_len == 0 {
+            return Err(Error::InvalidEntry(key));
+        }
+
+        let previous = self.inner.get(key).map(<[u8]>::len);
+        self.limits.check(self.len(), self.size, previous, plain_len, len)
+    }
+}
@@ -0,0 +1,324 @@
+//!Chunked encryption of large values.
+//!
+//!Layout: `nonce | sealed header | chunk 0 | .. | chunk N`, where each chunk is `nonce | sealed chunk`.
+//!Header is made of `MAGIC | chunk size: u32 | length of plaintext: u64 | id: [u8; 16]`,
+//!where `id` is random identifier of value, generated once it is sealed.
+//!Header and each chunk are sealed with own random nonce, while chunk authenticates key hash, `id` and its index,
+//!so chunks can be neither reordered nor mixed with ones of other value, while header authenticates their number.
+
+use crate::enc;
+
+use std::io::{self, Read};
+use ring::rand::{SecureRandom, SystemRandom};
+
+const MAGIC: &[u8; 4] = b"SSCK";
+const ID_LEN: usize = 16;
+const HEADER_LEN: usize = 16 + ID_LEN;
+const SEALED_HEADER_LEN: usize = enc::NONCE_LEN + HEADER_LEN + enc::TAG_LEN;
+///Overhead of each chunk.
+const CHUNK_OVERHEAD: usize = enc::NONCE_LEN + enc::TAG_LEN;
+///Default size of plaintext chunk.
+pub(crate) const DEFAULT_CHUNK_SIZE: usize = 64 * 1024;
+
+#[inline]
+///Returns associated data of header.
+fn header_aad(key: u128) -> [u8; 20] {
+    let mut aad = [0u8; 20];
+    aad[..16].copy_from_slice(&key.to_le_bytes());
+    aad[16..].copy_from_slice(MAGIC);
+    aad
+}
+
+#[inline]
+///Returns associated data of chunk at `index`.
+fn chunk_aad(key: u128, id: &[u8; ID_LEN], index: u64) -> [u8; 16 + ID_LEN + 8] {
+    let mut aad = [0u8; 16 + ID_LEN + 8];
+    aad[..16].copy_from_slice(&key.to_le_bytes());
+    aad[16..16 + ID_LEN].copy_from_slice(id);
+    aad[16 + ID_LEN..].copy_from_slice(&index.to_le_bytes());
+    aad
+}
+
+///Returns length of chunked ciphertext, sealing `plain_len` bytes.
+pub fn sealed_len(plain_len: usize, chunk_size: usize) -> usize {
+    SEALED_HEADER_LEN + plain_len + plain_len.div_ceil(chunk_size) * CHUNK_OVERHEAD
+}
+
+///Parsed chunked value.
+pub struct Chunks<'a> {
+    key: u128,
+    id: [u8; ID_LEN],
+    value: &'a [u8],
+    chunk_size: usize,
+    len: usize,
+    count: u64,
+}
+
+impl<'a> Chunks<'a> {
+    ///Parses chunked value, returning `None` if `value` is not chunked or cannot be authenticated.
+    pub fn parse(enc: &enc::Manager, key: u128, value: &'a [u8]) -> Option<Self> {
+        if value.len() <= SEALED_HEADER_LEN {
+            return None;
+        }
+
+        let mut nonce = [0u8; enc::NONCE_LEN];
+        nonce.copy_from_slice(&value[..enc::NONCE_LEN]);
+        let mut header = [0u8; HEADER_LEN + enc::TAG_LEN];
+        header.copy_from_slice(&value[enc::NONCE_LEN..SEALED_HEADER_LEN]);
+        let header = enc.decrypt_with(nonce, &header_aad(key), &mut header)?;
+        if header[..4] != MAGIC[..] {
+            return None;
+        }
+
+        let mut chunk_size = [0u8; 4];
+        chunk_size.copy_from_slice(&header[4..8]);
+        let chunk_size = u32::from_le_bytes(chunk_size) as usize;
+        let mut len = [0u8; 8];
+        len.copy_from_slice(&header[8..16]);
+        let len = u64::from_le_bytes(len);
+        let mut id = [0u8; ID_LEN];
+        id.copy_from_slice(&header[16..]);
+
+        if chunk_size == 0 || len == 0 || len > (value.len() - SEALED_HEADER_LEN) as u64 {
+            return None;
+        }
+        let len = len as usize;
+        let count = len.div_ceil(chunk_size);
+        if value.len() != SEALED_HEADER_LEN + len + count * CHUNK_OVERHEAD {
+            return None;
+        }
+
+        Some(Self {
+            key,
+            id,
+            value: &value[SEALED_HEADER_LEN..],
+            chunk_size,
+            len,
+            count: count as u64,
+        })
+    }
+
+    #[inline]
+    ///Returns size of plaintext chunk.
+    pub fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
+    #[inline]
+    ///Returns number of chunks.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    #[inline]
+    ///Returns size of buffer, required by `Self::decrypt_to`.
+    pub fn buffer_len(&self) -> usize {
+        core::cmp::min(self.chunk_size, self.len) + enc::TAG_LEN
+    }
+
+    #[inline]
+    ///Returns length of plaintext.
+    pub fn plain_len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    ///Returns nonce of chunk at `index` along with its ciphertext.
+    fn sealed_chunk(&self, index: u64) -> ([u8; enc::NONCE_LEN], &'a [u8]) {
+        let start = index as usize * (self.chunk_size + CHUNK_OVERHEAD);
+        let end = core::cmp::min(start + self.chunk_size + CHUNK_OVERHEAD, self.value.len());
+        let mut nonce = [0u8; enc::NONCE_LEN];
+        nonce.copy_from_slice(&self.value[start..start + enc::NONCE_LEN]);
+        (nonce, &self.value[start + enc::NONCE_LEN..end])
+    }
+
+    ///Decrypts chunk at `index` into `dest`, returning number of written bytes.
+    ///
+    ///`dest` must be able to fit `Self::buffer_len`.
+    pub fn decrypt_to(&self, enc: &enc::Manager, index: u64, dest: &mut [u8]) -> Option<usize> {
+        let (nonce, chunk) = self.sealed_chunk(index);
+        let dest = &mut dest[..chunk.len()];
+        dest.copy_from_slice(chunk);
+        match enc.decrypt_with(nonce, &chunk_aad(self.key, &self.id, index), dest) {
+            Some(written) => Some(written.len()),
+            None => {
+                enc::wipe(dest);
+                None
+            },
+        }
+    }
+
+    ///Decrypts whole value, appending it to `dest`.
+    pub fn decrypt_to_vec(&self, enc: &enc::Manager, dest: &mut Vec<u8>) -> bool {
+        for index in 0..self.count {
+            let (nonce, chunk) = self.sealed_chunk(index);
+            let start = dest.len();
+            dest.extend_from_slice(chunk);
+            match enc.decrypt_with(nonce, &chunk_aad(self.key, &self.id, index), &mut dest[start..]) {
+                Some(written) => {
+                    let len = written.len();
+                    dest.truncate(start + len);
+                },
+                None => return false,
+            }
+        }
+
+        true
+    }
+
+    ///Decrypts whole value into `dest`, which must fit `Self::plain_len`.
+    pub fn decrypt_to_slice(&self, enc: &enc::Manager, dest: &mut [u8]) -> bool {
+        let mut buffer = vec![0u8; self.buffer_len()];
+        let mut written = 0;
+        for index in 0..self.count {
+            match self.decrypt_to(enc, index, &mut buffer) {
+                Some(len) => {
+                    dest[written..written + len].copy_from_slice(&buffer[..len]);
+                    written += len;
+                },
+                None => {
+                    enc::wipe(&mut buffer);
+                    return false;
+                },
+            }
+        }
+
+        enc::wipe(&mut buffer);
+        true
+    }
+}
+
+///Seals `chunk` at `index` with random nonce, appending it to `out`.
+fn seal_chunk(enc: &enc::Manager, key: u128, id: &[u8; ID_LEN], index: u64, chunk: &mut [u8], out: &mut Vec<u8>) -> bool {
+    let nonce = match enc::random_nonce() {
+        Some(nonce) => nonce,
+        None => return false,
+    };
+
+    match enc.encrypt_detached(nonce, &chunk_aad(key, id, index), chunk) {
+        Some(tag) => {
+            out.extend_from_slice(&nonce);
+            out.extend_from_slice(chunk);
+            out.extend_from_slice(&tag);
+            true
+        },
+        None => false,
+    }
+}
+
+///Reads `input` until its end, sealing it chunk by chunk.
+pub fn seal<R: Read>(enc: &enc::Manager, key: u128, input: &mut R, chunk_size: usize) -> io::Result<Vec<u8>> {
+    assert_ne!(chunk_size, 0);
+    assert!(chunk_size <= u32::MAX as usize);
+
+    let mut id = [0u8; ID_LEN];
+    if SystemRandom::new().fill(&mut id).is_err() {
+        return Err(io::Error::other("Unable to generate identifier of value"));
+    }
+
+    let mut result = vec![0u8; SEALED_HEADER_LEN];
+    let mut chunk = Vec::with_capacity(chunk_size);
+    let mut len = 0usize;
+    let mut index = 0u64;
+    loop {
+        let read = match input.take(chunk_size as u64).read_to_end(&mut chunk) {
+            Ok(read) => read,
+            Err(error) => {
+                enc::wipe(&mut chunk);
+                return Err(error);
+            },
+        };
+        if read == 0 {
+            break;
+        }
+
+        let sealed = seal_chunk(enc, key, &id, index, &mut chunk, &mut result);
+        enc::wipe(&mut chunk);
+        chunk.truncate(0);
+        if !sealed {
+            return Err(io::Error::other("Unable to encrypt chunk"));
+        }
+
+        len += read;
+        index += 1;
+        if read < chunk_size {
+            break;
+        }
+    }
+
+    if len == 0 {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "Value must not be empty"));
+    }
+
+    match seal_header(enc, key, chunk_size, len, &id) {
+        Some(header) => result[..SEALED_HEADER_LEN].copy_from_slice(&header),
+        None => return Err(io::Error::other("Unable to encrypt header")),
+    }
+
+    Ok(result)
+}
+
+///Seals header with random nonce.
+fn seal_header(enc: &enc::Manager, key: u128, chunk_size: usize, len: usize, id: &[u8; ID_LEN]) -> Option<[u8; SEALED_HEADER_LEN]> {
+    let nonce = enc::random_nonce()?;
+    let mut header = [0u8; SEALED_HEADER_LEN];
+    header[..enc::NONCE_LEN].copy_from_slice(&nonce);
+    {
+        let plain = &mut header[enc::NONCE_LEN..enc::NONCE_LEN + HEADER_LEN];
+        plain[..4].copy_from_slice(MAGIC);
+        plain[4..8].copy_from_slice(&(chunk_size as u32).to_le_bytes());
+        plain[8..16].copy_from_slice(&(len as u64).to_le_bytes());
+        plain[16..].copy_from_slice(id);
+    }
+    let tag = enc.encrypt_detached(nonce, &header_aad(key), &mut header[enc::NONCE_LEN..enc::NONCE_LEN + HEADER_LEN])?;
+    header[enc::NONCE_LEN + HEADER_LEN..].copy_from_slice(&tag);
+    Some(header)
+}
+
+///Appends `bytes` to chunked `value`, re-sealing only its last chunk and header.
+///
+///Re-sealed chunks and header get fresh random nonces, so none of nonces is ever reused.
+///Returns `false` if `value` is not chunked or cannot be decrypted, leaving it untouched.
+pub fn append(enc: &enc::Manager, key: u128, value: &mut Vec<u8>, bytes: &[u8]) -> bool {
+    let (chunk_size, len, count, id) = match Chunks::parse(enc, key, value) {
+        Some(chunks) => (chunks.chunk_size, chunks.len, chunks.count, chunks.id),
+        None => return false,
+    };
+
+    //Last chunk is the only one, that can be partial, so it is merged with `bytes`.
+    let start = SEALED_HEADER_LEN + (count - 1) as usize * (chunk_size + CHUNK_OVERHEAD);
+    let mut nonce = [0u8; enc::NONCE_LEN];
+    nonce.copy_from_slice(&value[start..start + enc::NONCE_LEN]);
+    let mut tail = Vec::with_capacity(value.len() - start + bytes.len());
+    tail.extend_from_slice(&value[start + enc::NONCE_LEN..]);
+    match enc.decrypt_with(nonce, &chunk_aad(key, &id, count - 1), &mut tail) {
+        Some(written) => {
+            let len = written.len();
+            tail.truncate(len);
+        },
+        None => {
+            enc::wipe(&mut tail);
+            return false;
+        },
+    }
+    tail.extend_from_slice(bytes);
+
+    let mut sealed = Vec::with_capacity(tail.len() + tail.len().div_ceil(chunk_size) * CHUNK_OVERHEAD);
+    for (index, plain) in (count - 1..).zip(tail.chunks_mut(chunk_size)) {
+        if !seal_chunk(enc, key, &id, index, plain, &mut sealed) {
+            enc::wipe(&mut tail);
+            return false;
+        }
+    }
+    enc::wipe(&mut tail);
+
+    match seal_header(enc, key, chunk_size, len + bytes.len(), &id) {
+        Some(header) => {
+            value.truncate(start);
+            value.append(&mut sealed);
+            value[..SEALED_HEADER_LEN].copy_from_slice(&header);
+            true
+        },
+        None => false,
+    }
+}
@@ -0,0 +1,119 @@
+//!Compact binary encoding of storage.
+//!
+//!Layout: `MAGIC | VERSION: u8 | count: varint | entries`, where each entry is `key: u128 | len: varint | value`.
+//!Key is little endian, while varint is LEB128, i.e. 7 bits per byte, starting from the lowest, with high bit set on all bytes but the last.
+//!Version has the same meaning as for `Store::save`.
+
+use crate::format::{MIN_VERSION, VERSION};
+use crate::{Backend, Store};
+
+use core::convert::TryFrom;
+use std::io;
+use std::collections::BTreeMap;
+
+const MAGIC: &[u8; 8] = b"SECSTORC";
+
+#[inline]
+fn invalid_data(text: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, text)
+}
+
+fn push_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn pop_varint(input: &mut &[u8]) -> io::Result<u64> {
+    let mut result = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = match input.split_first() {
+            Some((byte, rest)) => {
+                *input = rest;
+                *byte
+            },
+            None => return Err(io::ErrorKind::UnexpectedEof.into()),
+        };
+
+        if shift == 63 && byte > 1 {
+            break;
+        }
+        result |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(result);
+        }
+    }
+
+    Err(invalid_data("Invalid varint"))
+}
+
+///Decodes entries of storage, encoded by `Store::to_bytes`.
+pub fn read_map(mut input: &[u8]) -> io::Result<BTreeMap<u128, Vec<u8>>> {
+    match input.get(..MAGIC.len() + 1) {
+        Some(header) if header[..MAGIC.len()] == MAGIC[..] => match header[MAGIC.len()] {
+            version if (MIN_VERSION..=VERSION).contains(&version) => input = &input[MAGIC.len() + 1..],
+            _ => return Err(invalid_data("Unsupported storage version")),
+        },
+        Some(_) => return Err(invalid_data("Not a sec-store compact storage")),
+        None => return Err(io::ErrorKind::UnexpectedEof.into()),
+    }
+
+    let count = pop_varint(&mut input)?;
+    let mut result = BTreeMap::new();
+    for _ in 0..count {
+        let mut key = [0u8; 16];
+        let len = match input.get(..16) {
+            Some(bytes) => {
+                key.copy_from_slice(bytes);
+                input = &input[16..];
+                pop_varint(&mut input)?
+            },
+            None => return Err(io::ErrorKind::UnexpectedEof.into()),
+        };
+        let value = match usize::try_from(len).ok().and_then(|len| input.get(..len)) {
+            Some(value) => value,
+            None => return Err(io::ErrorKind::UnexpectedEof.into()),
+        };
+        input = &input[value.len()..];
+        result.insert(u128::from_le_bytes(key), value.to_vec());
+    }
+
+    match input.is_empty() {
+        true => Ok(result),
+        false => Err(invalid_data("Trailing data after storage")),
+    }
+}
+
+impl<B: Backend> Store<B> {
+    ///Returns storage encoded in compact binary format, to be decoded by `Store::from_bytes`.
+    ///
+    ///Unlike file, written by `Self::save`, lengths are encoded as varints, saving few bytes per entry,
+    ///while decoys are never written.
+    ///Encoding is stable and its format follows `Self::format_version`.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut result = Vec::with_capacity(MAGIC.len() + 1 + 10 + self.inner.iter().map(|(_, value)| 16 + 5 + value.len()).sum::<usize>());
+        result.extend_from_slice(MAGIC);
+        result.push(self.format);
+        push_varint(&mut result, self.inner.len() as u64);
+        let versions = self.pending_versions();
+        for (key, value) in self.stored_entries(versions.as_deref()) {
+            result.extend_from_slice(&key.to_le_bytes());
+            push_varint(&mut result, value.len() as u64);
+            result.extend_from_slice(value);
+        }
+        result
+    }
+}
+
+impl Store {
+    #[inline]
+    ///Decodes storage, encoded via `Self::to_bytes`.
+    ///
+    ///Storage is validated as `Self::try_from_inner` does, with error reported as `InvalidData`.
+    pub fn from_bytes(bytes: &[u8], user: &[u8], pass: &[u8]) -> io::Result<Self> {
+        let inner = read_map(bytes)?;
+        Self::try_from_inner(inner, user, pass).map_err(Into::into)
+    }
+}
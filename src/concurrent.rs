@@ -0,0 +1,159 @@
+use crate::{Error, KeyHasher, MasterKey, Store, Xxh3, RESERVED};
+
+use core::convert::TryFrom;
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+///Store, that can be shared across threads, with key space split across independently locked shards.
+///
+///Every shard is regular `Store` using the same master key, so operations on keys of different shards do not contend.
+pub struct ConcurrentStore {
+    shards: Box<[RwLock<Store>]>,
+    hasher: Arc<dyn KeyHasher>,
+}
+
+impl ConcurrentStore {
+    ///Creates new instance with `shards` number of shards, using creds.
+    ///
+    ///Returns `Error::InvalidCredentials` if `user` or `pass` is empty.
+    ///Panics if `shards` is zero.
+    pub fn new(user: &[u8], pass: &[u8], shards: usize) -> Result<Self, Error> {
+        Ok(Self::with_key(&MasterKey::derive(user, pass)?, shards))
+    }
+
+    ///Creates new instance with `shards` number of shards, using encryption `key`.
+    ///
+    ///Panics if `shards` is zero.
+    pub fn with_key(key: &MasterKey, shards: usize) -> Self {
+        assert_ne!(shards, 0);
+
+        Self {
+            shards: (0..shards).map(|_| RwLock::new(Store::with_key(key))).collect(),
+            hasher: Arc::new(Xxh3),
+        }
+    }
+
+    ///Splits `store` into `shards` number of shards.
+    ///
+    ///Only values are moved, while internal entries (e.g. recovery key or integrity MAC) are discarded.
+    ///Returns `Error::Locked` if `store` is locked.
+    ///Panics if `shards` is zero.
+    pub fn from_store(store: Store, shards: usize) -> Result<Self, Error> {
+        let key = store.master_key()?;
+        let mut result = Self::with_key(&key, shards);
+        for shard in result.shards.iter_mut() {
+            let shard = shard.get_mut().unwrap_or_else(|error| error.into_inner());
+            shard.hasher = store.hasher.clone();
+            shard.write_header();
+        }
+        result.hasher = store.hasher.clone();
+        for (hash, value) in store.into_inner().into_iter().filter(|(hash, _)| *hash >= RESERVED) {
+            result.shard_mut(hash).inner_put(hash, value);
+        }
+        Ok(result)
+    }
+
+    ///Merges all shards back into single store.
+    pub fn into_store(self) -> Store {
+        let mut shards = self.shards.into_vec().into_iter().map(|shard| shard.into_inner().unwrap_or_else(|error| error.into_inner()));
+        let mut result = match shards.next() {
+            Some(result) => result,
+            None => unreachable!(),
+        };
+
+        for shard in shards {
+            for (hash, value) in shard.into_inner().into_iter().filter(|(hash, _)| *hash >= RESERVED) {
+                result.inner_put(hash, value);
+            }
+        }
+        result
+    }
+
+    #[inline]
+    fn index(&self, hash: u128) -> usize {
+        (hash % self.shards.len() as u128) as usize
+    }
+
+    #[inline]
+    fn shard(&self, hash: u128) -> RwLockReadGuard<'_, Store> {
+        self.shards[self.index(hash)].read().unwrap_or_else(|error| error.into_inner())
+    }
+
+    #[inline]
+    fn shard_mut(&self, hash: u128) -> RwLockWriteGuard<'_, Store> {
+        self.shards[self.index(hash)].write().unwrap_or_else(|error| error.into_inner())
+    }
+
+    #[inline]
+    ///Returns number of shards.
+    pub fn shards(&self) -> usize {
+        self.shards.len()
+    }
+
+    ///Returns number of key-value pairs
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|shard| shard.read().unwrap_or_else(|error| error.into_inner()).len()).sum()
+    }
+
+    #[inline]
+    ///Retrieves value for `key` to store in `dest`, resulting in it being overwritten.
+    ///
+    ///Refer to `Store::get_to_vec` for details.
+    pub fn get_to_vec(&self, key: &[u8], dest: &mut Vec<u8>) -> Result<usize, ()> {
+        self.shard(self.hasher.hash(key)).get_to_vec(key, dest)
+    }
+
+    #[inline]
+    ///Retrieves value for `key`
+    ///
+    ///Returns `None` if decryption failed.
+    pub fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        self.shard(self.hasher.hash(key)).get(key)
+    }
+
+    #[inline]
+    ///Checks for `key` presence within storage
+    pub fn contains(&self, key: &[u8]) -> bool {
+        self.shard(self.hasher.hash(key)).contains(key)
+    }
+
+    #[inline]
+    ///Inserts new `value` for `key`, returning previous one, if any.
+    ///
+    ///Refer to `Store::insert` for details.
+    pub fn insert(&self, key: &[u8], value: &[u8]) -> Option<Vec<u8>> {
+        self.shard_mut(self.hasher.hash(key)).insert(key, value)
+    }
+
+    #[inline]
+    ///Inserts new `value` for `key`, returning previous one, if any.
+    ///
+    ///Refer to `Store::try_insert` for details.
+    pub fn try_insert(&self, key: &[u8], value: &[u8]) -> Result<Option<Vec<u8>>, Error> {
+        self.shard_mut(self.hasher.hash(key)).try_insert(key, value)
+    }
+
+    #[inline]
+    ///Removes value under `key`, returning it.
+    ///
+    ///Refer to `Store::remove` for details.
+    pub fn remove(&self, key: &[u8]) -> Option<Vec<u8>> {
+        self.shard_mut(self.hasher.hash(key)).remove(key)
+    }
+
+    #[inline]
+    ///Removes `key`, returning whether it was set previously.
+    ///
+    ///Refer to `Store::remove_key` for details.
+    pub fn remove_key(&self, key: &[u8]) -> bool {
+        self.shard_mut(self.hasher.hash(key)).remove_key(key)
+    }
+}
+
+impl TryFrom<Store> for ConcurrentStore {
+    type Error = Error;
+
+    #[inline]
+    fn try_from(store: Store) -> Result<Self, Self::Error> {
+        Self::from_store(store, std::thread::available_parallelism().map_or(1, |threads| threads.get()))
+    }
+}
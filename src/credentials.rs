@@ -0,0 +1,175 @@
+use crate::{enc, format, Backend, Error, Kdf, MasterKey, Store};
+
+use core::fmt;
+use std::collections::BTreeMap;
+use std::io;
+use std::path::Path;
+
+#[derive(Clone)]
+///Credentials of store, validated on creation.
+///
+///Credentials are wiped from memory on drop.
+pub struct Credentials {
+    user: Vec<u8>,
+    pass: Vec<u8>,
+    keyfile: Option<Vec<u8>>,
+    pub(crate) machine: Option<Vec<u8>>,
+}
+
+impl Credentials {
+    ///Creates new credentials.
+    ///
+    ///Parameters:
+    ///
+    ///- `user` - user specific information that can distinguish him from others.
+    ///- `pass` - can be any number of arbitrary bytes.
+    ///
+    ///Returns `Error::InvalidCredentials` if `user` or `pass` is empty.
+    pub fn new(user: &[u8], pass: &[u8]) -> Result<Self, Error> {
+        if user.is_empty() || pass.is_empty() {
+            return Err(Error::InvalidCredentials);
+        }
+
+        Ok(Self {
+            user: user.to_owned(),
+            pass: pass.to_owned(),
+            keyfile: None,
+            machine: None,
+        })
+    }
+
+    ///Requires `contents` of keyfile, in addition to password.
+    ///
+    ///Keyfile is mixed into derived key, so both are required to open store.
+    ///Keyfile can be arbitrary, but its content must stay the same, as store cannot be opened otherwise.
+    ///
+    ///Returns `Error::InvalidCredentials` if `contents` is empty.
+    pub fn with_keyfile(mut self, contents: &[u8]) -> Result<Self, Error> {
+        if contents.is_empty() {
+            return Err(Error::InvalidCredentials);
+        }
+
+        if let Some(keyfile) = self.keyfile.as_mut() {
+            enc::wipe(keyfile);
+        }
+        self.keyfile = Some(contents.to_owned());
+        Ok(self)
+    }
+
+    ///Requires content of keyfile at `path`, in addition to password.
+    ///
+    ///Refer to `Self::with_keyfile` for details.
+    pub fn with_keyfile_at<P: AsRef<Path>>(self, path: P) -> io::Result<Self> {
+        let mut contents = std::fs::read(path)?;
+        let result = self.with_keyfile(&contents);
+        enc::wipe(&mut contents);
+        result.map_err(Into::into)
+    }
+
+    #[inline]
+    ///Returns whether keyfile is required.
+    pub fn has_keyfile(&self) -> bool {
+        self.keyfile.is_some()
+    }
+
+    #[inline]
+    ///Returns whether credentials are bound to machine.
+    ///
+    ///Refer to `Self::bind_to_machine` for details.
+    pub fn is_machine_bound(&self) -> bool {
+        self.machine.is_some()
+    }
+
+    fn finish(&self, mut key: [u8; 32]) -> MasterKey {
+        if let Some(keyfile) = self.keyfile.as_ref() {
+            key = enc::mix_key(&key, keyfile);
+        }
+        if let Some(machine) = self.machine.as_ref() {
+            key = enc::mix_key(&key, machine);
+        }
+        MasterKey::from_bytes(key)
+    }
+
+    #[inline]
+    ///Accesses user.
+    pub fn user(&self) -> &[u8] {
+        &self.user
+    }
+
+    #[inline]
+    ///Derives encryption key of store, using default key derivation.
+    pub fn master_key(&self) -> MasterKey {
+        self.finish(enc::generate_key(&self.user, &self.pass))
+    }
+
+    #[inline]
+    ///Derives encryption key of store, reporting progress via `progress`.
+    ///
+    ///Refer to `MasterKey::derive_with_progress` for details.
+    pub fn master_key_with_progress<F: FnMut(u32, u32)>(&self, progress: F) -> MasterKey {
+        self.finish(enc::generate_key_with_progress(&self.user, &self.pass, progress))
+    }
+}
+
+impl fmt::Debug for Credentials {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.write_str("Credentials(..)")
+    }
+}
+
+impl Drop for Credentials {
+    #[inline]
+    fn drop(&mut self) {
+        enc::wipe(&mut self.user);
+        enc::wipe(&mut self.pass);
+        if let Some(keyfile) = self.keyfile.as_mut() {
+            enc::wipe(keyfile);
+        }
+        if let Some(machine) = self.machine.as_mut() {
+            enc::wipe(machine);
+        }
+    }
+}
+
+impl<B: Backend> Store<B> {
+    #[inline]
+    ///Creates new instance within empty `backend` using `credentials`.
+    pub fn new_in_with_credentials(backend: B, credentials: &Credentials) -> Self {
+        Self::new_in_with_key(backend, &credentials.master_key())
+    }
+
+    #[inline]
+    ///Creates new instance using provided storage and `credentials`, validating it.
+    ///
+    ///Refer to `Self::try_from_backend` for details.
+    pub fn try_from_backend_with_credentials(inner: B, credentials: &Credentials) -> Result<Self, Error> {
+        let key = credentials.finish(Kdf::of(&inner).derive(&credentials.user, &credentials.pass));
+        Self::try_from_backend_with_key(inner, &key)
+    }
+}
+
+impl Store {
+    #[inline]
+    ///Creates new instance using `credentials`.
+    pub fn with_credentials(credentials: &Credentials) -> Self {
+        Self::new_in_with_credentials(BTreeMap::new(), credentials)
+    }
+
+    #[inline]
+    ///Creates new instance using provided storage and `credentials`, validating it.
+    ///
+    ///Refer to `Self::try_from_inner` for details.
+    pub fn from_inner_with_credentials(inner: BTreeMap<u128, Vec<u8>>, credentials: &Credentials) -> Result<Self, Error> {
+        Self::try_from_backend_with_credentials(inner, credentials)
+    }
+
+    #[inline]
+    ///Opens storage, previously saved via `Self::save`, using `credentials`.
+    ///
+    ///Refer to `Self::open` for details.
+    pub fn open_with_credentials<P: AsRef<Path>>(path: P, credentials: &Credentials) -> io::Result<Self> {
+        let inner = format::read_file(path.as_ref())?;
+        Self::from_inner_with_credentials(inner, credentials).map_err(Into::into)
+    }
+}
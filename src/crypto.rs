@@ -0,0 +1,412 @@
+//!Standalone encryption, using the same key derivation and cipher as store.
+
+use crate::{enc, Backend, Error, MasterKey, Store};
+
+use core::fmt;
+use x25519_dalek::{PublicKey, StaticSecret};
+
+///Number of bytes, that sealing adds to plaintext.
+pub const OVERHEAD: usize = enc::NONCE_LEN + enc::TAG_LEN;
+
+const INFO: &[u8] = b"sec-store:sealer";
+const DEPOSIT_INFO: &[u8] = b"sec-store:deposit";
+const SHARE_INFO: &[u8] = b"sec-store:share";
+
+///Size of deposit key, returned by `Store::deposit_key`.
+pub const DEPOSIT_KEY_LEN: usize = enc::X25519_LEN;
+
+#[inline]
+///Returns X25519 public key of `secret`.
+pub(crate) fn public_key(secret: &[u8; enc::X25519_LEN]) -> [u8; DEPOSIT_KEY_LEN] {
+    PublicKey::from(&StaticSecret::from(*secret)).to_bytes()
+}
+
+///Returns X25519 agreement of `secret` with `public`, or `None` if result is degenerate, e.g. due to point of small order.
+pub(crate) fn agree(secret: &[u8; enc::X25519_LEN], public: &[u8; DEPOSIT_KEY_LEN]) -> Option<[u8; enc::X25519_LEN]> {
+    let shared = StaticSecret::from(*secret).diffie_hellman(&PublicKey::from(*public));
+    match shared.was_contributory() {
+        true => Some(shared.to_bytes()),
+        false => None,
+    }
+}
+
+///Derives cipher of entry, sent via `ephemeral` key to `recipient`, optionally by static key of `sender`.
+///
+///Shared secrets are ephemeral agreement, followed by static one, if there is sender.
+fn entry_sealer(info: &[u8], mut shared: Vec<u8>, sender: Option<&[u8; DEPOSIT_KEY_LEN]>, ephemeral: &[u8; DEPOSIT_KEY_LEN], recipient: &[u8; DEPOSIT_KEY_LEN]) -> enc::Manager {
+    let mut context = Vec::with_capacity(info.len() + 3 * DEPOSIT_KEY_LEN);
+    context.extend_from_slice(info);
+    if let Some(sender) = sender {
+        context.extend_from_slice(sender);
+    }
+    context.extend_from_slice(ephemeral);
+    context.extend_from_slice(recipient);
+    let result = enc::Manager::new(enc::expand_key(&shared, &context));
+    enc::wipe(&mut shared);
+    result
+}
+
+///Seals `key` with `value` for `recipient` as `ephemeral key: [u8; 32] | nonce: [u8; 12] | ciphertext | tag: [u8; 16]`,
+///prefixed with public key of `sender`, if its secret key is specified.
+fn seal_entry(info: &[u8], sender: Option<&[u8; enc::X25519_LEN]>, recipient: &[u8; DEPOSIT_KEY_LEN], key: &[u8], value: &[u8]) -> Result<Vec<u8>, ()> {
+    let mut secret = [0u8; enc::X25519_LEN];
+    if ring::rand::SecureRandom::fill(&ring::rand::SystemRandom::new(), &mut secret).is_err() {
+        return Err(());
+    }
+    let ephemeral = public_key(&secret);
+    let mut shared = Vec::with_capacity(2 * enc::X25519_LEN);
+    let result = agree(&secret, recipient);
+    enc::wipe(&mut secret);
+    shared.extend_from_slice(&result.ok_or(())?);
+
+    let sender = match sender {
+        Some(sender) => match agree(sender, recipient) {
+            Some(result) => {
+                shared.extend_from_slice(&result);
+                Some(public_key(sender))
+            },
+            None => {
+                enc::wipe(&mut shared);
+                return Err(());
+            },
+        },
+        None => None,
+    };
+    let sealer = entry_sealer(info, shared, sender.as_ref(), &ephemeral, recipient);
+
+    let mut plain = Vec::with_capacity(4 + key.len() + value.len() + OVERHEAD);
+    plain.extend_from_slice(&(key.len() as u32).to_le_bytes());
+    plain.extend_from_slice(key);
+    plain.extend_from_slice(value);
+    let is_sealed = match enc::random_nonce() {
+        Some(nonce) => sealer.encrypt_prefixed(nonce, &[], &mut plain),
+        None => false,
+    };
+    if !is_sealed {
+        enc::wipe(&mut plain);
+        return Err(());
+    }
+
+    let mut result = Vec::with_capacity(2 * DEPOSIT_KEY_LEN + plain.len());
+    if let Some(sender) = sender.as_ref() {
+        result.extend_from_slice(sender);
+    }
+    result.extend_from_slice(&ephemeral);
+    result.extend_from_slice(&plain);
+    Ok(result)
+}
+
+///Opens `sealed` entry, without public key of sender, returning buffer and range of key within it, followed by value.
+fn open_entry(info: &[u8], secret: &[u8; enc::X25519_LEN], sender: Option<&[u8; DEPOSIT_KEY_LEN]>, sealed: &[u8]) -> Option<(Vec<u8>, core::ops::Range<usize>)> {
+    if sealed.len() < DEPOSIT_KEY_LEN + OVERHEAD + 4 {
+        return None;
+    }
+
+    let mut ephemeral = [0u8; DEPOSIT_KEY_LEN];
+    ephemeral.copy_from_slice(&sealed[..DEPOSIT_KEY_LEN]);
+    let recipient = public_key(secret);
+    let mut shared = Vec::with_capacity(2 * enc::X25519_LEN);
+    shared.extend_from_slice(&agree(secret, &ephemeral)?);
+    if let Some(sender) = sender {
+        match agree(secret, sender) {
+            Some(result) => shared.extend_from_slice(&result),
+            None => {
+                enc::wipe(&mut shared);
+                return None;
+            },
+        }
+    }
+    let sealer = entry_sealer(info, shared, sender, &ephemeral, &recipient);
+
+    let mut result = sealed[DEPOSIT_KEY_LEN..].to_vec();
+    let len = match sealer.decrypt_prefixed(&[], &mut result) {
+        Some(plain) if plain.len() >= 4 => {
+            let mut len = [0u8; 4];
+            len.copy_from_slice(&plain[..4]);
+            let len = u32::from_le_bytes(len) as usize;
+            match plain.len() - 4 >= len {
+                true => Some(plain.len()),
+                false => None,
+            }
+        },
+        Some(_) => None,
+        None => None,
+    };
+    let len = match len {
+        Some(len) => len,
+        None => {
+            enc::wipe(&mut result);
+            return None;
+        },
+    };
+
+    result.copy_within(enc::NONCE_LEN..enc::NONCE_LEN + len, 0);
+    enc::wipe(&mut result[len..]);
+    result.truncate(len);
+    let mut key_len = [0u8; 4];
+    key_len.copy_from_slice(&result[..4]);
+    let key_len = u32::from_le_bytes(key_len) as usize;
+    Some((result, 4..4 + key_len))
+}
+
+///Seals `value` for `key`, so that it can only be inserted into store with `recipient` deposit key, via `Store::accept_deposit`.
+///
+///It requires no credentials, so that any party, knowing public deposit key, can send secrets into store,
+///without being able to read them afterwards.
+///Deposit is `ephemeral key: [u8; 32] | nonce: [u8; 12] | ciphertext | tag: [u8; 16]`,
+///where key of cipher is derived from X25519 agreement of random ephemeral key with `recipient`.
+///Key and value are encrypted together as `key length: u32 | key | value`.
+///
+///Returns `Err` if random numbers cannot be generated, or `recipient` is not valid deposit key.
+#[inline]
+pub fn deposit(recipient: &[u8; DEPOSIT_KEY_LEN], key: &[u8], value: &[u8]) -> Result<Vec<u8>, ()> {
+    seal_entry(DEPOSIT_INFO, None, recipient, key, value)
+}
+
+///Encryption of ad-hoc blobs (e.g. files or network payloads), kept outside of store.
+///
+///Blob is sealed by ChaCha20-Poly1305 as `nonce: [u8; 12] | ciphertext | tag: [u8; 16]`,
+///with random nonce, so the same key should seal no more than `2^32` blobs,
+///and with associated data, which is authenticated along with blob, but not stored within it.
+///
+///Key of sealer is derived from encryption key of store, so blobs cannot be confused with store's entries.
+///Key is wiped from memory on drop.
+pub struct Sealer {
+    enc: enc::Manager,
+}
+
+impl Sealer {
+    #[inline]
+    ///Creates sealer, deriving its key from `key` of store.
+    pub fn new(key: &MasterKey) -> Self {
+        Self {
+            enc: enc::Manager::new(enc::expand_key(key.as_bytes(), INFO)),
+        }
+    }
+
+    #[inline]
+    ///Creates sealer, deriving its key from credentials, same as store does.
+    ///
+    ///Returns `Error::InvalidCredentials` if `user` or `pass` is empty.
+    pub fn derive(user: &[u8], pass: &[u8]) -> Result<Self, Error> {
+        MasterKey::derive(user, pass).map(|key| Self::new(&key))
+    }
+
+    ///Seals `value`, authenticating `aad` along with it.
+    ///
+    ///Returns `Err` only if random nonce cannot be generated.
+    pub fn seal(&self, value: &[u8], aad: &[u8]) -> Result<Vec<u8>, ()> {
+        let mut result = Vec::with_capacity(value.len() + OVERHEAD);
+        result.extend_from_slice(value);
+        match self.seal_in_place(&mut result, aad) {
+            Ok(()) => Ok(result),
+            Err(()) => {
+                enc::wipe(&mut result);
+                Err(())
+            },
+        }
+    }
+
+    ///Seals `in_out` in place, authenticating `aad` along with it.
+    ///
+    ///Returns `Err` only if random nonce cannot be generated, leaving `in_out` untouched.
+    pub fn seal_in_place(&self, in_out: &mut Vec<u8>, aad: &[u8]) -> Result<(), ()> {
+        let nonce = enc::random_nonce().ok_or(())?;
+        match self.enc.encrypt_prefixed(nonce, aad, in_out) {
+            true => Ok(()),
+            false => Err(()),
+        }
+    }
+
+    ///Opens `sealed` blob, returning its plaintext.
+    ///
+    ///Returns `Err` if blob is malformed, or it is not sealed by the same key along with the same `aad`.
+    pub fn open(&self, sealed: &[u8], aad: &[u8]) -> Result<Vec<u8>, ()> {
+        let mut result = sealed.to_vec();
+        let len = match self.open_in_place(&mut result, aad) {
+            Ok(value) => value.len(),
+            Err(()) => {
+                enc::wipe(&mut result);
+                return Err(());
+            },
+        };
+
+        result.copy_within(enc::NONCE_LEN..enc::NONCE_LEN + len, 0);
+        enc::wipe(&mut result[len..]);
+        result.truncate(len);
+        Ok(result)
+    }
+
+    #[inline]
+    ///Opens `sealed` blob in place, returning its plaintext, which follows nonce.
+    ///
+    ///Returns `Err` if blob is malformed, or it is not sealed by the same key along with the same `aad`.
+    pub fn open_in_place<'a>(&self, sealed: &'a mut [u8], aad: &[u8]) -> Result<&'a mut [u8], ()> {
+        self.enc.decrypt_prefixed(aad, sealed).ok_or(())
+    }
+}
+
+impl fmt::Debug for Sealer {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.write_str("Sealer")
+    }
+}
+
+impl Drop for Sealer {
+    #[inline]
+    fn drop(&mut self) {
+        self.enc.wipe();
+    }
+}
+
+impl<B: Backend> Store<B> {
+    ///Creates sealer, deriving its key from encryption key of store.
+    ///
+    ///Refer to `Sealer` for details.
+    ///Returns `Error::Locked` if store is locked.
+    pub fn sealer(&self) -> Result<Sealer, Error> {
+        match self.locked {
+            true => Err(Error::Locked),
+            false => Ok(Sealer {
+                enc: enc::Manager::new(enc::expand_key(self.enc.key(), INFO)),
+            }),
+        }
+    }
+
+    ///Seals `value`, authenticating `aad` along with it, without inserting it into store.
+    ///
+    ///Result is self-contained ciphertext, that can only be opened by `Self::open_detached` of store with the same key,
+    ///or by `Sealer`, created from it, e.g. to protect sidecar files along with store itself.
+    ///
+    ///Returns `Error::Locked` if store is locked, or `Error::RandomFailure` if nonce cannot be generated.
+    pub fn seal_detached(&self, aad: &[u8], value: &[u8]) -> Result<Vec<u8>, Error> {
+        match self.sealer()?.seal(value, aad) {
+            Ok(result) => Ok(result),
+            Err(()) => Err(Error::RandomFailure),
+        }
+    }
+
+    ///Opens `sealed` value, produced by `Self::seal_detached` with the same `aad`.
+    ///
+    ///Returns `Error::Locked` if store is locked, or `Error::IntegrityMismatch` if value is malformed,
+    ///or it is not sealed by the same key along with the same `aad`.
+    pub fn open_detached(&self, aad: &[u8], sealed: &[u8]) -> Result<Vec<u8>, Error> {
+        self.sealer()?.open(sealed, aad).map_err(|()| Error::IntegrityMismatch)
+    }
+
+    #[inline]
+    fn deposit_secret(&self) -> [u8; enc::X25519_LEN] {
+        enc::expand_key(self.enc.key(), DEPOSIT_INFO)
+    }
+
+    ///Returns public deposit key of store, allowing anyone to send values via `deposit`.
+    ///
+    ///Key is derived from encryption key of store, so it changes on re-keying.
+    ///Returns `Error::Locked` if store is locked.
+    pub fn deposit_key(&self) -> Result<[u8; DEPOSIT_KEY_LEN], Error> {
+        if self.locked {
+            return Err(Error::Locked);
+        }
+
+        let mut secret = self.deposit_secret();
+        let result = public_key(&secret);
+        enc::wipe(&mut secret);
+        Ok(result)
+    }
+
+    ///Inserts value, sealed via `deposit` for deposit key of store, returning previous value, if any.
+    ///
+    ///Returns error when:
+    ///
+    ///- `Error::Locked` - store is locked.
+    ///- `Error::IntegrityMismatch` - deposit is malformed or sealed for another store.
+    ///- `Error::InvalidEntry` - deposited value is empty.
+    ///- `Error::LimitExceeded` - value doesn't fit store's limits.
+    ///
+    ///Store is left untouched on error.
+    pub fn accept_deposit(&mut self, deposit: &[u8]) -> Result<Option<Vec<u8>>, Error> {
+        if self.locked {
+            return Err(Error::Locked);
+        }
+
+        let mut secret = self.deposit_secret();
+        let result = open_entry(DEPOSIT_INFO, &secret, None, deposit);
+        enc::wipe(&mut secret);
+        let (mut plain, key) = result.ok_or(Error::IntegrityMismatch)?;
+        let result = self.insert_opened(&plain, key);
+        enc::wipe(&mut plain);
+        result
+    }
+
+    ///Exports value of `key`, sealed for store with `recipient` deposit key, to be imported via `Self::import_shared_entry`.
+    ///
+    ///Unlike `deposit`, sealed entry is prefixed with deposit key of this store,
+    ///and its cipher is additionally derived from X25519 agreement of both stores' keys,
+    ///so that recipient can verify its origin, without sharing any credentials.
+    ///
+    ///Returns error when:
+    ///
+    ///- `Error::Locked` - store is locked.
+    ///- `Error::NotFound` - `key` doesn't exist.
+    ///- `Error::InvalidEntry` - value cannot be decrypted.
+    ///- `Error::InvalidCredentials` - `recipient` is not valid deposit key, or random numbers cannot be generated.
+    pub fn export_entry_for(&self, key: &[u8], recipient: &[u8; DEPOSIT_KEY_LEN]) -> Result<Vec<u8>, Error> {
+        if self.locked {
+            return Err(Error::Locked);
+        }
+
+        let hash = self.hash_key(key);
+        let mut value = match self.inner.get(hash) {
+            Some(value) => self.decrypt_value(hash, value).ok_or(Error::InvalidEntry(hash))?,
+            None => return Err(Error::NotFound),
+        };
+
+        let mut secret = self.deposit_secret();
+        let result = seal_entry(SHARE_INFO, Some(&secret), recipient, key, &value);
+        enc::wipe(&mut secret);
+        enc::wipe(&mut value);
+        result.map_err(|()| Error::InvalidCredentials)
+    }
+
+    ///Imports entry, exported via `Self::export_entry_for` for deposit key of this store.
+    ///
+    ///Returns deposit key of sending store, which should be verified by caller, along with previous value, if any.
+    ///
+    ///Returns error when:
+    ///
+    ///- `Error::Locked` - store is locked.
+    ///- `Error::IntegrityMismatch` - entry is malformed or exported for another store.
+    ///- `Error::InvalidEntry` - value of entry is empty.
+    ///- `Error::LimitExceeded` - value doesn't fit store's limits.
+    ///
+    ///Store is left untouched on error.
+    pub fn import_shared_entry(&mut self, entry: &[u8]) -> Result<([u8; DEPOSIT_KEY_LEN], Option<Vec<u8>>), Error> {
+        if self.locked {
+            return Err(Error::Locked);
+        } else if entry.len() < DEPOSIT_KEY_LEN {
+            return Err(Error::IntegrityMismatch);
+        }
+
+        let mut sender = [0u8; DEPOSIT_KEY_LEN];
+        sender.copy_from_slice(&entry[..DEPOSIT_KEY_LEN]);
+        let mut secret = self.deposit_secret();
+        let result = open_entry(SHARE_INFO, &secret, Some(&sender), &entry[DEPOSIT_KEY_LEN..]);
+        enc::wipe(&mut secret);
+        let (mut plain, key) = result.ok_or(Error::IntegrityMismatch)?;
+        let result = self.insert_opened(&plain, key);
+        enc::wipe(&mut plain);
+        result.map(|previous| (sender, previous))
+    }
+
+    ///Inserts value of opened entry, that follows its `key` within `plain`.
+    ///
+    ///Returns `Error::InvalidEntry` if value is empty.
+    fn insert_opened(&mut self, plain: &[u8], key: core::ops::Range<usize>) -> Result<Option<Vec<u8>>, Error> {
+        let (key, value) = (&plain[key.clone()], &plain[key.end..]);
+        match value.is_empty() {
+            true => Err(Error::InvalidEntry(self.hash_key(key))),
+            false => self.try_insert(key, value),
+        }
+    }
+}
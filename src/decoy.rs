@@ -0,0 +1,99 @@
+//!Decoy entries, obscuring number of values within saved storage.
+//!
+//!Key hash of decoy is made of random half, followed by truncated MAC of it,
+//!so decoys can be recognized only with master key.
+//!Value of decoy is random plaintext of length of random value, sealed the same way as values are,
+//!so it is indistinguishable from value without master key.
+//!
+//!Decoys are kept between saves, as fresh ones would stand out next to values, that stay the same,
+//!and they are only generated anew along with values being sealed anew.
+
+use crate::{enc, seal, Backend, Store, RESERVED};
+
+use ring::hmac;
+use ring::rand::{SecureRandom, SystemRandom};
+
+const INFO: &[u8] = b"sec-store:decoy";
+
+#[inline]
+fn tag(key: &hmac::Key, half: &[u8]) -> [u8; 8] {
+    let mut result = [0u8; 8];
+    result.copy_from_slice(&hmac::sign(key, half).as_ref()[..8]);
+    result
+}
+
+///Recognizes decoys.
+pub(crate) struct Detector(hmac::Key);
+
+impl Detector {
+    #[inline]
+    pub(crate) fn new(enc: &enc::Manager) -> Self {
+        Self(enc.subkey(INFO))
+    }
+
+    #[inline]
+    pub(crate) fn is_decoy(&self, key: u128) -> bool {
+        let key_bytes = key.to_le_bytes();
+        key >= RESERVED && enc::ct_eq(&tag(&self.0, &key_bytes[..8]), &key_bytes[8..])
+    }
+}
+
+///Generates `count` decoys, sealed according to `sealing`, with length of each plaintext picked from `lens`.
+///
+///Returns no decoys if `lens` is empty, as there is no length to mimic.
+pub(crate) fn generate(enc: &enc::Manager, sealing: &seal::Sealing, count: usize, lens: &[usize]) -> Option<Vec<(u128, Vec<u8>)>> {
+    let random = SystemRandom::new();
+    let subkey = enc.subkey(INFO);
+    let mut result = Vec::with_capacity(count);
+
+    while result.len() < count && !lens.is_empty() {
+        let mut key = [0u8; 16];
+        random.fill(&mut key[..8]).ok()?;
+        let tag = tag(&subkey, &key[..8]);
+        key[8..].copy_from_slice(&tag);
+        let key = u128::from_le_bytes(key);
+        if key < RESERVED {
+            continue;
+        }
+
+        let mut pick = [0u8; 8];
+        random.fill(&mut pick).ok()?;
+        let len = lens[(u64::from_le_bytes(pick) % lens.len() as u64) as usize];
+
+        let mut value = vec![0u8; len];
+        random.fill(&mut value).ok()?;
+        if !sealing.seal(enc, key, &mut value) {
+            return None;
+        }
+        result.push((key, value));
+    }
+
+    Some(result)
+}
+
+///Removes decoys out of `inner`, returning them.
+pub(crate) fn strip<B: Backend>(enc: &enc::Manager, inner: &mut B) -> Vec<(u128, Vec<u8>)> {
+    let detector = Detector::new(enc);
+    let decoys: Vec<_> = inner.iter().map(|(key, _)| key).filter(|key| detector.is_decoy(*key)).collect();
+    decoys.into_iter().filter_map(|key| inner.remove(key).map(|value| (key, value))).collect()
+}
+
+impl<B: Backend> Store<B> {
+    #[inline]
+    ///Sets number of decoy entries, written along with values by `Self::save`.
+    ///
+    ///Decoys are indistinguishable from values without master key, hiding number of values within saved storage.
+    ///Length of each decoy is picked out of lengths of values, so they are only written once there are values.
+    ///They are removed once storage is loaded, never being part of `Self::inner`, but kept to be written by next save,
+    ///so that saved storage only changes with values, while decoys are generated anew once values are sealed anew,
+    ///refer to `Self::rekey` and `Self::migrate_format`.
+    pub fn set_decoys(&mut self, count: usize) {
+        self.decoys = count;
+    }
+
+    #[inline]
+    ///Returns number of decoy entries, written along with values by `Self::save`.
+    pub fn decoys(&self) -> usize {
+        self.decoys
+    }
+}
@@ -0,0 +1,254 @@
+//!Incremental persistence.
+//!
+//!Layout: `MAGIC | VERSION: u8 | count: u64 | records | manifest | mac`.
+//!Record is either `REMOVE | key: u128` or `INSERT | key: u128 | len: u32 | value`.
+//!Manifest is `count: u64 | (key: u128 | digest: u128)..`, describing every entry of store after delta is applied.
+//!MAC is HMAC-SHA256 of everything preceding it, using subkey of store, so that manifest cannot be forged.
+
+use crate::{enc, format, names, signing, tags, versions, Backend, Store, RESERVED, VERSIONS_KEY};
+
+use ring::hmac;
+use std::collections::BTreeMap;
+use std::io::{self, Read, Write};
+use xxhash_rust::xxh3::xxh3_128;
+
+const MAGIC: &[u8; 8] = b"SECDELTA";
+const VERSION: u8 = 1;
+const OP_REMOVE: u8 = 0;
+const OP_INSERT: u8 = 1;
+///Information to derive subkey of MAC.
+const MAC_INFO: &[u8] = b"sec-store:delta";
+
+///Digests of ciphertexts, keyed by their hashes.
+pub(crate) type Digests = BTreeMap<u128, u128>;
+
+///Computes digests of all entries within `inner`.
+pub(crate) fn digests<B: Backend>(inner: &B) -> Digests {
+    inner.iter().map(|(key, value)| (key, xxh3_128(value))).collect()
+}
+
+///Returns keys of entries, that differ between `old` and `new`, in ascending order.
+pub(crate) fn changes(old: &Digests, new: &Digests) -> Vec<u128> {
+    let mut result: Vec<_> = new.iter().filter(|(key, digest)| old.get(key) != Some(digest)).map(|(key, _)| *key).collect();
+    result.extend(old.keys().filter(|key| !new.contains_key(key)));
+    result.sort_unstable();
+    result
+}
+
+#[inline]
+fn invalid_data(text: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, text)
+}
+
+///Stream of delta, computing its MAC along the way.
+struct Signed<T> {
+    inner: T,
+    context: hmac::Context,
+}
+
+impl<T> Signed<T> {
+    #[inline]
+    fn new<B: Backend>(inner: T, store: &Store<B>) -> Self {
+        Self {
+            inner,
+            context: hmac::Context::with_key(&store.enc.subkey(MAC_INFO)),
+        }
+    }
+}
+
+impl<W: Write> Write for Signed<W> {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = self.inner.write(buf)?;
+        self.context.update(&buf[..len]);
+        Ok(len)
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<R: Read> Read for Signed<R> {
+    #[inline]
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.inner.read(buf)?;
+        self.context.update(&buf[..len]);
+        Ok(len)
+    }
+}
+
+fn read_u64<R: Read>(input: &mut R) -> io::Result<u64> {
+    let mut value = [0u8; 8];
+    input.read_exact(&mut value)?;
+    Ok(u64::from_le_bytes(value))
+}
+
+impl<B: Backend> Store<B> {
+    #[inline]
+    fn saved(&self) -> std::sync::MutexGuard<'_, Digests> {
+        self.saved.lock().unwrap_or_else(|error| error.into_inner())
+    }
+
+    ///Computes digests of all entries, as they are saved.
+    fn stored_digests(&self) -> Digests {
+        let versions = self.pending_versions();
+        self.stored_entries(versions.as_deref()).map(|(key, value)| (key, xxh3_128(value))).collect()
+    }
+
+    #[inline]
+    ///Marks current content as persisted.
+    pub(crate) fn mark_saved(&self) {
+        *self.saved() = self.stored_digests();
+    }
+
+    ///Returns whether store has been modified since last save, including its internal entries.
+    ///
+    ///Store, that has never been saved, is dirty, unless it is created from existing storage.
+    ///Changes are tracked by comparing digests of all ciphertexts, so it is proportional to size of store.
+    pub fn is_dirty(&self) -> bool {
+        let versions = self.pending_versions();
+        let saved = self.saved();
+        saved.len() != self.inner.len() || self.stored_entries(versions.as_deref()).any(|(key, value)| saved.get(&key) != Some(&xxh3_128(value)))
+    }
+
+    ///Returns hashes of user's keys, inserted, overwritten or removed since last save, in ascending order.
+    ///
+    ///Refer to `Self::is_dirty` for details.
+    pub fn changed_since_save(&self) -> Vec<u128> {
+        let mut result = changes(&self.saved(), &self.stored_digests());
+        result.retain(|key| *key >= RESERVED);
+        result
+    }
+
+    ///Writes entries, changed since last save, into `out`, returning number of written records.
+    ///
+    ///Delta is made of inserted, overwritten and removed entries, followed by manifest of whole store,
+    ///so that `Self::apply_incremental` can verify it is applied on top of the same state.
+    ///Delta is authenticated by MAC, using subkey of store.
+    ///Only digests of ciphertexts are kept to track changes, and each save, full or incremental, starts new delta.
+    ///Decoys are not written, refer to `Self::set_decoys`.
+    pub fn save_incremental<W: Write>(&self, out: W) -> io::Result<usize> {
+        let mut out = Signed::new(out, self);
+        let versions = self.pending_versions();
+        let current: Digests = self.stored_entries(versions.as_deref()).map(|(key, value)| (key, xxh3_128(value))).collect();
+        let mut saved = self.saved();
+        let changes = changes(&saved, &current);
+
+        out.write_all(MAGIC)?;
+        out.write_all(&[VERSION])?;
+        out.write_all(&(changes.len() as u64).to_le_bytes())?;
+        for key in changes.iter() {
+            let value = match versions.as_deref() {
+                Some(versions) if *key == VERSIONS_KEY => Some(versions),
+                _ => self.inner.get(*key),
+            };
+            match value {
+                Some(value) => {
+                    out.write_all(&[OP_INSERT])?;
+                    format::write_entry(&mut out, *key, value)?;
+                },
+                None => {
+                    out.write_all(&[OP_REMOVE])?;
+                    out.write_all(&key.to_le_bytes())?;
+                },
+            }
+        }
+
+        out.write_all(&(current.len() as u64).to_le_bytes())?;
+        for (key, digest) in current.iter() {
+            out.write_all(&key.to_le_bytes())?;
+            out.write_all(&digest.to_le_bytes())?;
+        }
+        let Signed { inner: mut out, context } = out;
+        out.write_all(context.sign().as_ref())?;
+        out.flush()?;
+
+        *saved = current;
+        Ok(changes.len())
+    }
+
+    ///Applies delta, written by `Self::save_incremental`, returning number of applied records.
+    ///
+    ///Delta is verified against its MAC and manifest before anything is modified,
+    ///so delta, written by different store or on top of different state, results in `InvalidData` error, leaving store untouched.
+    ///Applied content is considered persisted.
+    pub fn apply_incremental<R: Read>(&mut self, input: R) -> io::Result<usize> {
+        let mut input = Signed::new(input, self);
+        let mut magic = [0u8; 9];
+        input.read_exact(&mut magic)?;
+        if magic[..8] != MAGIC[..] {
+            return Err(invalid_data("Not a sec-store delta"));
+        } else if magic[8] != VERSION {
+            return Err(invalid_data("Unsupported delta version"));
+        }
+
+        let mut records = Vec::new();
+        for _ in 0..read_u64(&mut input)? {
+            let mut op = [0u8; 1];
+            input.read_exact(&mut op)?;
+            let key = format::read_key(&mut input)?;
+            let value = match op[0] {
+                OP_INSERT => Some(format::read_value(&mut input)?),
+                OP_REMOVE => None,
+                _ => return Err(invalid_data("Unknown delta record")),
+            };
+            records.push((key, value));
+        }
+
+        let mut manifest = Digests::new();
+        for _ in 0..read_u64(&mut input)? {
+            let key = format::read_key(&mut input)?;
+            manifest.insert(key, format::read_key(&mut input)?);
+        }
+
+        let Signed { inner: mut input, context } = input;
+        let mut mac = [0u8; enc::MAC_LEN];
+        input.read_exact(&mut mac)?;
+        if !enc::ct_eq(context.sign().as_ref(), &mac) {
+            return Err(invalid_data("Delta is not authentic"));
+        }
+
+        self.flush_versions();
+        let mut expected = digests(&self.inner);
+        for (key, value) in records.iter() {
+            match value {
+                Some(value) => expected.insert(*key, xxh3_128(value)),
+                None => expected.remove(key),
+            };
+        }
+        if expected != manifest {
+            return Err(invalid_data("Delta doesn't match store"));
+        }
+
+        //Internal entries go last, overriding ones, re-written as part of user's modifications.
+        let len = records.len();
+        records.sort_by_key(|(key, _)| *key < RESERVED);
+        self.suspend_autosave();
+        for (key, value) in records {
+            match (key >= RESERVED, value) {
+                (true, Some(value)) => {
+                    crate::discard(self.inner_put(key, value));
+                },
+                (true, None) => {
+                    crate::discard(self.inner_take(key));
+                },
+                (false, Some(value)) => {
+                    self.inner.insert(key, value);
+                },
+                (false, None) => {
+                    self.inner.remove(key);
+                },
+            }
+        }
+
+        self.names = names::load(&self.enc, &self.inner);
+        self.tags = tags::load(&self.enc, &self.inner);
+        self.versions = versions::load(&self.enc, &self.inner);
+        self.signatures = signing::load(&self.enc, &self.inner);
+        *self.saved() = manifest;
+        self.resume_autosave();
+        Ok(len)
+    }
+}
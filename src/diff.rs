@@ -0,0 +1,91 @@
+use crate::{enc, Backend, Store};
+
+use std::collections::BTreeMap;
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+///Difference between two stores, with keys represented by their hashes, or by their names, refer to `Store::diff_named`.
+pub struct Diff<K = u128> {
+    ///Keys present only in the other store.
+    pub added: Vec<K>,
+    ///Keys present only in the original store.
+    pub removed: Vec<K>,
+    ///Keys present in both stores, but with different values.
+    pub changed: Vec<K>,
+}
+
+impl<K> Diff<K> {
+    #[inline]
+    ///Returns whether stores are identical.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+impl<B: Backend> Store<B> {
+    ///Computes difference between `self` and `other`, treating `other` as newer snapshot.
+    ///
+    ///Values are compared by ciphertext first, and by decrypted value only when ciphertexts differ,
+    ///which allows comparing stores with different credentials.
+    ///Decrypted values are compared in constant time and wiped afterwards.
+    ///Value that cannot be decrypted by either store is considered changed.
+    pub fn diff<O: Backend>(&self, other: &Store<O>) -> Diff {
+        let mut result = Diff::default();
+
+        for (key, value) in self.entries() {
+            match other.inner.get(key) {
+                Some(other_value) => {
+                    if value == other_value {
+                        continue;
+                    }
+
+                    let local = self.decrypt_value(key, value);
+                    let remote = other.decrypt_value(key, other_value);
+                    let is_same = match (local.as_ref(), remote.as_ref()) {
+                        (Some(local), Some(remote)) => enc::ct_eq(local, remote),
+                        _ => false,
+                    };
+                    for mut value in local.into_iter().chain(remote) {
+                        enc::wipe(&mut value);
+                    }
+                    if !is_same {
+                        result.changed.push(key);
+                    }
+                },
+                None => result.removed.push(key),
+            }
+        }
+
+        for (key, _) in other.entries() {
+            if !self.inner.contains(key) {
+                result.added.push(key);
+            }
+        }
+
+        result
+    }
+
+    ///Computes difference between `self` and `other`, with keys represented by their names.
+    ///
+    ///Names of added keys are known by `other`, while rest of names are known by `self`,
+    ///and keys without known name are skipped, refer to `Self::enable_key_names`.
+    ///Otherwise it behaves as `Self::diff`.
+    pub fn diff_named<O: Backend>(&self, other: &Store<O>) -> Diff<Vec<u8>> {
+        let diff = self.diff(other);
+        let local = names_by_hash(self);
+        let remote = names_by_hash(other);
+        let resolve = |keys: Vec<u128>, names: &BTreeMap<u128, &[u8]>| -> Vec<Vec<u8>> {
+            keys.into_iter().filter_map(|key| names.get(&key).map(|name| name.to_vec())).collect()
+        };
+
+        Diff {
+            added: resolve(diff.added, &remote),
+            removed: resolve(diff.removed, &local),
+            changed: resolve(diff.changed, &local),
+        }
+    }
+}
+
+fn names_by_hash<B: Backend>(store: &Store<B>) -> BTreeMap<u128, &[u8]> {
+    let names = store.names.as_ref().map(|names| names.iter().map(|(name, key)| (*key, name.as_slice())));
+    names.into_iter().flatten().collect()
+}
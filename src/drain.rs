@@ -0,0 +1,124 @@
+use crate::{Backend, Error, Store};
+#[cfg(feature = "audit")]
+use crate::AuditOp;
+
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+///Key of entry, identified by its name when it is known, refer to `Store::enable_key_names`.
+pub enum EntryKey {
+    ///Name of key.
+    Name(Vec<u8>),
+    ///Hash of key, which name is not stored.
+    Hash(u128),
+}
+
+///Iterator, removing entries from store, created by `Store::drain`.
+pub struct Drain<'a, B: Backend = BTreeMap<u128, Vec<u8>>> {
+    store: &'a mut Store<B>,
+    keys: std::vec::IntoIter<u128>,
+    names: BTreeMap<u128, Vec<u8>>,
+}
+
+impl<'a, B: Backend> Iterator for Drain<'a, B> {
+    type Item = (EntryKey, Result<Vec<u8>, Error>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let key = self.keys.next()?;
+
+        let result = match self.store.locked {
+            true => Err(Error::Locked),
+            false => match self.store.inner.get(key).and_then(|value| self.store.decrypt_value(key, value)) {
+                Some(value) => {
+                    #[cfg(feature = "audit")]
+                    self.store.audit.record(AuditOp::Remove, key, true);
+                    #[cfg(feature = "tracing")]
+                    tracing::trace!(op = "remove", success = true);
+                    crate::discard(self.store.inner_take(key));
+                    Ok(value)
+                },
+                None => Err(Error::InvalidEntry(key)),
+            },
+        };
+
+        let key = match self.names.remove(&key) {
+            Some(name) => EntryKey::Name(name),
+            None => EntryKey::Hash(key),
+        };
+        Some((key, result))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.keys.size_hint()
+    }
+}
+
+impl<'a, B: Backend> ExactSizeIterator for Drain<'a, B> {
+}
+
+impl<'a, B: Backend> Drop for Drain<'a, B> {
+    #[inline]
+    fn drop(&mut self) {
+        self.store.resume_autosave();
+    }
+}
+
+impl<B: Backend> Store<B> {
+    ///Returns iterator, that removes entries one by one, yielding their decrypted values.
+    ///
+    ///Entry, that cannot be decrypted, is yielded with error, while being left in store, so nothing is lost silently.
+    ///It is either `Error::Locked` if store is locked, or `Error::InvalidEntry`, e.g. for values of namespaces.
+    ///Entries, that are not yet yielded when iterator is dropped, are left in store as well.
+    ///
+    ///Autosave, if any, is postponed until iterator is dropped.
+    pub fn drain(&mut self) -> Drain<'_, B> {
+        let keys: Vec<u128> = self.entries().map(|(key, _)| key).collect();
+        let names = match self.names.as_ref() {
+            Some(names) => names.iter().map(|(name, key)| (*key, name.clone())).collect(),
+            None => BTreeMap::new(),
+        };
+
+        self.suspend_autosave();
+        Drain {
+            store: self,
+            keys: keys.into_iter(),
+            names,
+        }
+    }
+}
+
+impl<B: Backend> Store<B> {
+    ///Decrypts all entries, returning them by hashes of keys.
+    ///
+    ///Values of namespaces cannot be decrypted by store's key, hence they are skipped, as well as corrupted entries.
+    ///
+    ///Returns `Error::Locked` if store is locked.
+    pub fn decrypt_all(&self) -> Result<BTreeMap<u128, Vec<u8>>, Error> {
+        if self.locked {
+            return Err(Error::Locked);
+        }
+
+        Ok(self.entries().filter_map(|(key, value)| self.decrypt_value(key, value).map(|value| (key, value))).collect())
+    }
+
+    ///Decrypts all entries with known names, returning them by names, refer to `Self::enable_key_names`.
+    ///
+    ///Entries without known name are skipped, hence result is empty unless names are stored.
+    ///Otherwise it behaves as `Self::decrypt_all`.
+    pub fn decrypt_all_named(&self) -> Result<BTreeMap<Vec<u8>, Vec<u8>>, Error> {
+        if self.locked {
+            return Err(Error::Locked);
+        }
+
+        let mut result = BTreeMap::new();
+        if let Some(names) = self.names.as_ref() {
+            for (name, key) in names.iter() {
+                if let Some(value) = self.inner.get(*key).and_then(|value| self.decrypt_value(*key, value)) {
+                    result.insert(name.clone(), value);
+                }
+            }
+        }
+        Ok(result)
+    }
+}
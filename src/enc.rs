@@ -1,10 +1,43 @@
+use core::ptr;
 use ring::aead::{UnboundKey, LessSafeKey, Nonce, Aad, CHACHA20_POLY1305};
+use ring::{hkdf, hmac};
+use ring::rand::{SecureRandom, SystemRandom};
+
+pub const MAC_LEN: usize = 32;
+pub const TAG_LEN: usize = 16;
+pub const NONCE_LEN: usize = 12;
+
+///Overwrites `buffer` with zeroes in a way that cannot be optimized out.
+pub fn wipe(buffer: &mut [u8]) {
+    for byte in buffer.iter_mut() {
+        unsafe {
+            ptr::write_volatile(byte, 0);
+        }
+    }
+    core::sync::atomic::compiler_fence(core::sync::atomic::Ordering::SeqCst);
+}
+
+///Compares slices in constant time, only leaking their length.
+pub fn ct_eq(left: &[u8], right: &[u8]) -> bool {
+    if left.len() != right.len() {
+        return false;
+    }
+
+    let diff = left.iter().zip(right.iter()).fold(0u8, |acc, (left, right)| acc | (left ^ right));
+    unsafe {
+        ptr::read_volatile(&diff) == 0
+    }
+}
+
+///Number of PBKDF2 iterations.
+pub const ITERATIONS: u32 = 1_000;
 
 pub fn generate_key(salt: &[u8], pass: &[u8]) -> [u8; 32] {
     use core::num::NonZeroU32;
 
-    const IT: NonZeroU32 = unsafe {
-        NonZeroU32::new_unchecked(1_000)
+    const IT: NonZeroU32 = match NonZeroU32::new(ITERATIONS) {
+        Some(it) => it,
+        None => unreachable!(),
     };
 
     use ring::pbkdf2::{self, PBKDF2_HMAC_SHA256};
@@ -14,6 +47,107 @@ pub fn generate_key(salt: &[u8], pass: &[u8]) -> [u8; 32] {
     out
 }
 
+///Same as `generate_key`, reporting number of completed iterations out of total via `progress`.
+///
+///Progress is reported on start and after every percent of iterations.
+pub fn generate_key_with_progress<F: FnMut(u32, u32)>(salt: &[u8], pass: &[u8], mut progress: F) -> [u8; 32] {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, pass);
+    let step = core::cmp::max(ITERATIONS / 100, 1);
+    progress(0, ITERATIONS);
+
+    //PBKDF2 with single block of output
+    let mut ctx = hmac::Context::with_key(&key);
+    ctx.update(salt);
+    ctx.update(&1u32.to_be_bytes());
+    let mut block = [0u8; 32];
+    block.copy_from_slice(ctx.sign().as_ref());
+    let mut out = block;
+
+    for done in 1..=ITERATIONS {
+        if done > 1 {
+            let tag = hmac::sign(&key, &block);
+            block.copy_from_slice(tag.as_ref());
+            for (out, byte) in out.iter_mut().zip(block.iter()) {
+                *out ^= byte;
+            }
+        }
+
+        if done % step == 0 {
+            progress(done, ITERATIONS);
+        }
+    }
+
+    wipe(&mut block);
+    out
+}
+
+///Derives key using scrypt (RFC 7914) with cost `2^log_n`, block size `r` and parallelism `p`, filling `out`.
+///
+///Parameters are expected to be validated by caller, panicking otherwise.
+pub fn scrypt(pass: &[u8], salt: &[u8], log_n: u8, r: u32, p: u32, out: &mut [u8]) {
+    let params = ::scrypt::Params::new(log_n, r, p, ::scrypt::Params::RECOMMENDED_LEN).expect("Invalid scrypt parameters");
+    ::scrypt::scrypt(pass, salt, &params, out).expect("Invalid scrypt output length");
+}
+
+///Length of X25519 keys.
+pub const X25519_LEN: usize = 32;
+
+///Mixes additional `secret` into `key`, producing new key.
+pub fn mix_key(key: &[u8; 32], secret: &[u8]) -> [u8; 32] {
+    let prk = hkdf::Salt::new(hkdf::HKDF_SHA256, secret).extract(key);
+    let mut out = [0u8; 32];
+    match prk.expand(&[b"sec-store:secret"], hkdf::HKDF_SHA256).and_then(|okm| okm.fill(&mut out)) {
+        Ok(()) => out,
+        Err(_) => unreachable!(),
+    }
+}
+
+#[inline]
+///Derives key from high entropy `secret`.
+pub fn expand_key(secret: &[u8], info: &[u8]) -> [u8; 32] {
+    derive_key(&[], secret, info)
+}
+
+///Derives key from high entropy `secret` via HKDF-SHA256 with `salt`.
+pub fn derive_key(salt: &[u8], secret: &[u8], info: &[u8]) -> [u8; 32] {
+    let prk = hkdf::Salt::new(hkdf::HKDF_SHA256, salt).extract(secret);
+    let mut out = [0u8; 32];
+    match prk.expand(&[info], hkdf::HKDF_SHA256).and_then(|okm| okm.fill(&mut out)) {
+        Ok(()) => out,
+        Err(_) => unreachable!(),
+    }
+}
+
+///Generates random nonce.
+pub fn random_nonce() -> Option<[u8; NONCE_LEN]> {
+    let mut nonce = [0u8; NONCE_LEN];
+    match SystemRandom::new().fill(&mut nonce) {
+        Ok(()) => Some(nonce),
+        Err(_) => None,
+    }
+}
+
+///MAC over entries, created by `Manager::mac_context`.
+pub struct Mac(hmac::Context);
+
+impl Mac {
+    #[inline]
+    ///Authenticates entry.
+    pub fn update(&mut self, key: u128, value: &[u8]) {
+        self.0.update(&key.to_le_bytes());
+        self.0.update(&(value.len() as u64).to_le_bytes());
+        self.0.update(value);
+    }
+
+    #[inline]
+    ///Returns MAC over all entries.
+    pub fn sign(self) -> [u8; MAC_LEN] {
+        let mut result = [0; MAC_LEN];
+        result.copy_from_slice(self.0.sign().as_ref());
+        result
+    }
+}
+
 pub struct Manager {
     key: [u8; 32],
     //Additional security if we use it
@@ -29,6 +163,17 @@ impl Manager {
         }
     }
 
+    #[inline]
+    pub fn key(&self) -> &[u8; 32] {
+        &self.key
+    }
+
+    #[inline]
+    ///Overwrites key with zeroes.
+    pub fn wipe(&mut self) {
+        wipe(&mut self.key);
+    }
+
     #[inline]
     fn get_nonce(&self, input: u128) -> Nonce {
         let input = input.to_ne_bytes();
@@ -43,6 +188,29 @@ impl Manager {
         Aad::empty()
     }
 
+    pub fn subkey(&self, info: &[u8]) -> hmac::Key {
+        let prk = hkdf::Salt::new(hkdf::HKDF_SHA256, &[]).extract(&self.key);
+        match prk.expand(&[info], hmac::HMAC_SHA256) {
+            Ok(okm) => okm.into(),
+            Err(_) => unreachable!(),
+        }
+    }
+
+    ///Computes MAC over sequence of entries.
+    pub fn mac<'a, I: Iterator<Item = (u128, &'a [u8])>>(&self, entries: I) -> [u8; MAC_LEN] {
+        let mut mac = self.mac_context();
+        for (key, value) in entries {
+            mac.update(key, value);
+        }
+        mac.sign()
+    }
+
+    #[inline]
+    ///Starts computing MAC over entries, that are fed one by one, refer to `Self::mac`.
+    pub fn mac_context(&self) -> Mac {
+        Mac(hmac::Context::with_key(&self.subkey(b"sec-store:mac")))
+    }
+
     pub fn encrypt(&self, nonce: u128, in_out: &'_ mut Vec<u8>) -> bool {
         let key = match UnboundKey::new(&CHACHA20_POLY1305, &self.key) {
             Ok(key) => LessSafeKey::new(key),
@@ -60,6 +228,154 @@ impl Manager {
 
         key.open_in_place(self.get_nonce(nonce), self.get_aad(), in_out).ok()
     }
+
+    ///Encrypts `in_out`, prepending `nonce` to ciphertext, and authenticating `aad` along with it.
+    pub fn encrypt_prefixed(&self, nonce: [u8; NONCE_LEN], aad: &[u8], in_out: &'_ mut Vec<u8>) -> bool {
+        let cipher = match UnboundKey::new(&CHACHA20_POLY1305, &self.key) {
+            Ok(cipher) => LessSafeKey::new(cipher),
+            Err(_) => return false,
+        };
+
+        in_out.reserve_exact(NONCE_LEN + TAG_LEN);
+        match cipher.seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::from(aad), in_out) {
+            Ok(()) => {
+                in_out.splice(..0, nonce.iter().copied());
+                true
+            },
+            Err(_) => false,
+        }
+    }
+
+    ///Decrypts `in_out`, produced by `encrypt_prefixed`, returning plaintext, which follows nonce.
+    pub fn decrypt_prefixed<'a>(&self, aad: &[u8], in_out: &'a mut [u8]) -> Option<&'a mut [u8]> {
+        if in_out.len() < NONCE_LEN + TAG_LEN {
+            return None;
+        }
+
+        let cipher = match UnboundKey::new(&CHACHA20_POLY1305, &self.key) {
+            Ok(cipher) => LessSafeKey::new(cipher),
+            Err(_) => return None,
+        };
+
+        let (nonce, in_out) = in_out.split_at_mut(NONCE_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(nonce).ok()?;
+        cipher.open_in_place(nonce, Aad::from(aad), in_out).ok()
+    }
+
+    ///Encrypts `in_out` in place using `nonce`, authenticating `aad` along with it, returning detached tag.
+    pub fn encrypt_detached(&self, nonce: [u8; NONCE_LEN], aad: &[u8], in_out: &mut [u8]) -> Option<[u8; TAG_LEN]> {
+        let cipher = match UnboundKey::new(&CHACHA20_POLY1305, &self.key) {
+            Ok(cipher) => LessSafeKey::new(cipher),
+            Err(_) => return None,
+        };
+
+        match cipher.seal_in_place_separate_tag(Nonce::assume_unique_for_key(nonce), Aad::from(aad), in_out) {
+            Ok(tag) => {
+                let mut result = [0u8; TAG_LEN];
+                result.copy_from_slice(tag.as_ref());
+                Some(result)
+            },
+            Err(_) => None,
+        }
+    }
+
+    ///Decrypts `in_out`, made of ciphertext followed by its tag, using `nonce`, authenticating `aad` along with it.
+    pub fn decrypt_with<'a>(&self, nonce: [u8; NONCE_LEN], aad: &[u8], in_out: &'a mut [u8]) -> Option<&'a mut [u8]> {
+        let cipher = match UnboundKey::new(&CHACHA20_POLY1305, &self.key) {
+            Ok(cipher) => LessSafeKey::new(cipher),
+            Err(_) => return None,
+        };
+
+        cipher.open_in_place(Nonce::assume_unique_for_key(nonce), Aad::from(aad), in_out).ok()
+    }
+
+    #[inline]
+    ///Derives nonce from `key` and `value` via keyed hash, so that only the same value gets the same nonce.
+    pub fn derive_nonce(&self, key: u128, value: &[u8]) -> [u8; NONCE_LEN] {
+        self.derive_nonce_parts(key, &[value])
+    }
+
+    ///Derives nonce from `key` and concatenation of `parts`, refer to `Self::derive_nonce`.
+    pub fn derive_nonce_parts(&self, key: u128, parts: &[&[u8]]) -> [u8; NONCE_LEN] {
+        let mut context = hmac::Context::with_key(&self.subkey(b"sec-store:nonce"));
+        context.update(&key.to_le_bytes());
+        for part in parts {
+            context.update(part);
+        }
+        let mut result = [0u8; NONCE_LEN];
+        result.copy_from_slice(&context.sign().as_ref()[..NONCE_LEN]);
+        result
+    }
+
+    #[inline]
+    ///Encrypts `in_out` using random nonce, which is prepended to ciphertext, authenticating `key` along with it.
+    pub fn encrypt_random(&self, key: u128, in_out: &'_ mut Vec<u8>) -> bool {
+        match random_nonce() {
+            Some(nonce) => self.encrypt_prefixed(nonce, &key.to_le_bytes(), in_out),
+            None => false,
+        }
+    }
+
+    #[inline]
+    ///Decrypts `in_out`, produced by `encrypt_random`, returning plaintext, which follows nonce.
+    pub fn decrypt_random<'a>(&self, key: u128, in_out: &'a mut [u8]) -> Option<&'a mut [u8]> {
+        self.decrypt_prefixed(&key.to_le_bytes(), in_out)
+    }
+
+    ///Encrypts `value` using random nonce, which is prepended to ciphertext.
+    pub fn seal_random(&self, value: &[u8]) -> Option<Vec<u8>> {
+        let key = match UnboundKey::new(&CHACHA20_POLY1305, &self.key) {
+            Ok(key) => LessSafeKey::new(key),
+            Err(_) => return None,
+        };
+
+        let mut nonce = [0u8; NONCE_LEN];
+        if SystemRandom::new().fill(&mut nonce).is_err() {
+            return None;
+        }
+
+        let mut result = Vec::with_capacity(NONCE_LEN + value.len() + TAG_LEN);
+        result.extend_from_slice(&nonce);
+        result.extend_from_slice(value);
+        match key.seal_in_place_separate_tag(Nonce::assume_unique_for_key(nonce), self.get_aad(), &mut result[NONCE_LEN..]) {
+            Ok(tag) => {
+                result.extend_from_slice(tag.as_ref());
+                Some(result)
+            },
+            Err(_) => None,
+        }
+    }
+
+    ///Decrypts `value`, produced by `seal_random`.
+    pub fn open_random(&self, value: &[u8]) -> Option<Vec<u8>> {
+        if value.len() < NONCE_LEN + TAG_LEN {
+            return None;
+        }
+
+        let key = match UnboundKey::new(&CHACHA20_POLY1305, &self.key) {
+            Ok(key) => LessSafeKey::new(key),
+            Err(_) => return None,
+        };
+
+        let mut nonce = [0u8; NONCE_LEN];
+        nonce.copy_from_slice(&value[..NONCE_LEN]);
+        let mut result = value[NONCE_LEN..].to_owned();
+        match key.open_in_place(Nonce::assume_unique_for_key(nonce), self.get_aad(), &mut result) {
+            Ok(written) => {
+                let len = written.len();
+                result.truncate(len);
+                Some(result)
+            },
+            Err(_) => None,
+        }
+    }
+}
+
+impl Drop for Manager {
+    #[inline]
+    fn drop(&mut self) {
+        self.wipe();
+    }
 }
 
 #[cfg(test)]
@@ -90,6 +406,88 @@ mod tests {
         assert!(manager2.decrypt(1, &mut value).is_none());
     }
 
+    #[test]
+    fn should_seal_with_random_nonce() {
+        const TEXT: &[u8] = b"lolka";
+
+        let manager = Manager::new([1; 32]);
+        let manager2 = Manager::new([2; 32]);
+
+        let sealed = manager.seal_random(TEXT).expect("To seal");
+        assert_eq!(sealed.len(), NONCE_LEN + TEXT.len() + TAG_LEN);
+        assert_ne!(sealed, manager.seal_random(TEXT).expect("To seal"));
+        assert_eq!(manager.open_random(&sealed).expect("To open"), TEXT);
+        assert!(manager2.open_random(&sealed).is_none());
+        assert!(manager.open_random(&sealed[1..]).is_none());
+    }
+
+    #[test]
+    fn should_encrypt_with_random_nonce() {
+        const TEXT: &[u8] = b"lolka";
+
+        let manager = Manager::new([1; 32]);
+        let manager2 = Manager::new([2; 32]);
+
+        let mut value = TEXT.to_owned();
+        assert!(manager.encrypt_random(1, &mut value));
+        assert_eq!(value.len(), NONCE_LEN + TEXT.len() + TAG_LEN);
+        let mut other = TEXT.to_owned();
+        assert!(manager.encrypt_random(1, &mut other));
+        assert_ne!(value, other);
+
+        assert!(manager.decrypt_random(2, &mut value.clone()).is_none());
+        assert!(manager2.decrypt_random(1, &mut value.clone()).is_none());
+        assert!(manager.decrypt(1, &mut value.clone()).is_none());
+        assert!(manager.decrypt_random(1, &mut value.clone()[1..]).is_none());
+        assert_eq!(manager.decrypt_random(1, &mut value).expect("To decrypt"), TEXT);
+    }
+
+    #[test]
+    fn should_compute_mac() {
+        let manager = Manager::new([1; 32]);
+        let manager2 = Manager::new([2; 32]);
+
+        let entries = [(1u128, &b"one"[..]), (2u128, &b"two"[..])];
+        let mac = manager.mac(entries.iter().cloned());
+        assert!(ct_eq(&mac, &manager.mac(entries.iter().cloned())));
+        assert!(!ct_eq(&mac, &manager2.mac(entries.iter().cloned())));
+        assert!(!ct_eq(&mac, &manager.mac(entries.iter().cloned().take(1))));
+        assert!(!ct_eq(&mac, &mac[1..]));
+    }
+
+    #[test]
+    fn should_derive_scrypt() {
+        //RFC 7914 test vectors
+        const EMPTY: [u8; 64] = [
+            0x77, 0xd6, 0x57, 0x62, 0x38, 0x65, 0x7b, 0x20, 0x3b, 0x19, 0xca, 0x42, 0xc1, 0x8a, 0x04, 0x97,
+            0xf1, 0x6b, 0x48, 0x44, 0xe3, 0x07, 0x4a, 0xe8, 0xdf, 0xdf, 0xfa, 0x3f, 0xed, 0xe2, 0x14, 0x42,
+            0xfc, 0xd0, 0x06, 0x9d, 0xed, 0x09, 0x48, 0xf8, 0x32, 0x6a, 0x75, 0x3a, 0x0f, 0xc8, 0x1f, 0x17,
+            0xe8, 0xd3, 0xe0, 0xfb, 0x2e, 0x0d, 0x36, 0x28, 0xcf, 0x35, 0xe2, 0x0c, 0x38, 0xd1, 0x89, 0x06,
+        ];
+        const PASSWORD: [u8; 64] = [
+            0xfd, 0xba, 0xbe, 0x1c, 0x9d, 0x34, 0x72, 0x00, 0x78, 0x56, 0xe7, 0x19, 0x0d, 0x01, 0xe9, 0xfe,
+            0x7c, 0x6a, 0xd7, 0xcb, 0xc8, 0x23, 0x78, 0x30, 0xe7, 0x73, 0x76, 0x63, 0x4b, 0x37, 0x31, 0x62,
+            0x2e, 0xaf, 0x30, 0xd9, 0x2e, 0x22, 0xa3, 0x88, 0x6f, 0xf1, 0x09, 0x27, 0x9d, 0x98, 0x30, 0xda,
+            0xc7, 0x27, 0xaf, 0xb9, 0x4a, 0x83, 0xee, 0x6d, 0x83, 0x60, 0xcb, 0xdf, 0xa2, 0xcc, 0x06, 0x40,
+        ];
+
+        let mut out = [0u8; 64];
+        scrypt(b"", b"", 4, 1, 1, &mut out);
+        assert_eq!(out, EMPTY);
+        scrypt(b"password", b"NaCl", 10, 8, 16, &mut out);
+        assert_eq!(out, PASSWORD);
+    }
+
+    #[test]
+    fn should_mix_key() {
+        let key = [1; 32];
+        let mixed = mix_key(&key, b"response");
+        assert_ne!(mixed, key);
+        assert_eq!(mixed, mix_key(&key, b"response"));
+        assert_ne!(mixed, mix_key(&key, b"other"));
+        assert_ne!(mixed, mix_key(&[2; 32], b"response"));
+    }
+
     #[test]
     fn should_generate_key() {
         const SALT: &[u8] = b"whatever";
@@ -98,5 +496,13 @@ mod tests {
 
         let result = generate_key(SALT, SECRET);
         assert_eq!(result, EXPECTED);
+
+        let mut reported = Vec::new();
+        let result = generate_key_with_progress(SALT, SECRET, |done, total| reported.push((done, total)));
+        assert_eq!(result, EXPECTED);
+        assert_eq!(reported.len(), 101);
+        assert_eq!(reported[0], (0, ITERATIONS));
+        assert_eq!(reported[100], (ITERATIONS, ITERATIONS));
+        assert!(reported.windows(2).all(|pair| pair[0].0 < pair[1].0));
     }
 }
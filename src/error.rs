@@ -0,0 +1,80 @@
+use core::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+///Storage error.
+pub enum Error {
+    ///User or password is empty.
+    InvalidCredentials,
+    ///Credentials do not match storage.
+    WrongCredentials,
+    ///Storage contains malformed entry under specified hash.
+    InvalidEntry(u128),
+    ///Storage integrity MAC doesn't match its content.
+    IntegrityMismatch,
+    ///Operation would exceed store's limits.
+    LimitExceeded,
+    ///Recovery shares are missing or malformed.
+    InvalidShares,
+    ///Too many failed unlock attempts, refer to `UnlockGuard`.
+    LockedOut,
+    ///Password's strength is below required one.
+    WeakPassword {
+        ///Estimated score of password.
+        score: u8,
+        ///Required score.
+        required: u8,
+    },
+    ///Store is locked, refer to `Store::lock`.
+    Locked,
+    ///Key doesn't exist within storage.
+    NotFound,
+    ///Buffer is insufficient, while specified number of bytes is required.
+    BufferTooSmall(usize),
+    ///Hasher of key names doesn't match one, recorded within storage.
+    KeyHasherMismatch,
+    ///System's random number generator failed.
+    RandomFailure,
+    ///Recipients of encrypted file are missing or invalid, refer to `age::Recipient`.
+    InvalidRecipient,
+    ///Storage cannot be serialized, due to error of specified kind.
+    Serialize(std::io::ErrorKind),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidCredentials => fmt.write_str("User and password must not be empty"),
+            Error::WrongCredentials => fmt.write_str("Credentials do not match storage"),
+            Error::InvalidEntry(key) => write!(fmt, "Malformed entry {:032x}", key),
+            Error::IntegrityMismatch => fmt.write_str("Storage integrity MAC mismatch"),
+            Error::LimitExceeded => fmt.write_str("Storage limit exceeded"),
+            Error::InvalidShares => fmt.write_str("Invalid recovery shares"),
+            Error::LockedOut => fmt.write_str("Too many failed unlock attempts"),
+            Error::Locked => fmt.write_str("Store is locked"),
+            Error::NotFound => fmt.write_str("Key not found"),
+            Error::BufferTooSmall(required) => write!(fmt, "Buffer is too small, {} bytes required", required),
+            Error::KeyHasherMismatch => fmt.write_str("Key hasher doesn't match storage"),
+            Error::RandomFailure => fmt.write_str("Unable to generate random bytes"),
+            Error::InvalidRecipient => fmt.write_str("Invalid recipients of encrypted file"),
+            Error::Serialize(kind) => write!(fmt, "Unable to serialize storage: {}", kind),
+            Error::WeakPassword { score, required } => write!(fmt, "Password is too weak: score {} out of 4, while at least {} is required", score, required),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<Error> for std::io::Error {
+    #[inline]
+    fn from(error: Error) -> Self {
+        let kind = match error {
+            Error::LimitExceeded | Error::WeakPassword { .. } | Error::BufferTooSmall(_) | Error::InvalidRecipient => std::io::ErrorKind::InvalidInput,
+            Error::NotFound => std::io::ErrorKind::NotFound,
+            Error::LockedOut | Error::Locked => std::io::ErrorKind::PermissionDenied,
+            Error::RandomFailure => std::io::ErrorKind::Other,
+            Error::Serialize(kind) => kind,
+            _ => std::io::ErrorKind::InvalidData,
+        };
+        std::io::Error::new(kind, error)
+    }
+}
@@ -0,0 +1,245 @@
+//!Advisory locking of store files.
+//!
+//!Store is saved by replacing its file, so lock is held on sibling file `<path>.lock`,
+//!which is left in place, as removing it would race with other processes.
+//!Lock is `flock` on unix and `LockFileEx` on Windows.
+//!
+//!Exclusive owner also increments generation, kept within `<path>.gen`, on every save,
+//!so that readers, opened via `Store::open_watched`, can detect changes without taking lock.
+
+use crate::{MasterKey, OwnedReadOnlyStore, Store};
+
+use core::ops::{Deref, DerefMut};
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+#[inline]
+fn sibling(path: &Path, extension: &str) -> PathBuf {
+    let mut result = path.as_os_str().to_owned();
+    result.push(extension);
+    PathBuf::from(result)
+}
+
+fn read_generation(path: &Path) -> io::Result<u64> {
+    match fs::read(sibling(path, ".gen")) {
+        Ok(data) => {
+            let mut generation = [0u8; 8];
+            let len = core::cmp::min(data.len(), generation.len());
+            generation[..len].copy_from_slice(&data[..len]);
+            Ok(u64::from_le_bytes(generation))
+        },
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(0),
+        Err(error) => Err(error),
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+///State of file on disk, that changes with every save.
+struct Stamp {
+    generation: u64,
+    len: u64,
+    modified: Option<SystemTime>,
+}
+
+impl Stamp {
+    fn read(path: &Path) -> io::Result<Self> {
+        let generation = read_generation(path)?;
+        let metadata = fs::metadata(path)?;
+        Ok(Self {
+            generation,
+            len: metadata.len(),
+            modified: metadata.modified().ok(),
+        })
+    }
+}
+
+fn lock_file(path: &Path, exclusive: bool) -> io::Result<File> {
+    let file = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(sibling(path, ".lock"))?;
+
+    let result = match exclusive {
+        true => file.try_lock(),
+        false => file.try_lock_shared(),
+    };
+    match result {
+        Ok(()) => Ok(file),
+        Err(TryLockError::WouldBlock) => Err(io::Error::new(io::ErrorKind::WouldBlock, "Store file is locked by another process")),
+        Err(TryLockError::Error(error)) => Err(error),
+    }
+}
+
+///Store, opened via `Store::open_exclusive`.
+///
+///It holds exclusive lock of file until dropped, so no other process can open it via
+///`Store::open_exclusive` or `Store::open_shared_read`.
+pub struct LockedStore {
+    store: Store,
+    path: PathBuf,
+    _lock: File,
+}
+
+impl LockedStore {
+    #[inline]
+    ///Returns path of locked file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    ///Saves store into locked file, refer to `Store::save`.
+    ///
+    ///Generation of file is incremented afterwards, notifying readers, opened via `Store::open_watched`.
+    pub fn save(&self) -> io::Result<()> {
+        self.store.save(&self.path)?;
+        let generation = read_generation(&self.path)?.wrapping_add(1);
+        fs::write(sibling(&self.path, ".gen"), generation.to_le_bytes())
+    }
+
+    #[inline]
+    ///Releases lock, returning store.
+    pub fn into_inner(self) -> Store {
+        self.store
+    }
+}
+
+impl Deref for LockedStore {
+    type Target = Store;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.store
+    }
+}
+
+impl DerefMut for LockedStore {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.store
+    }
+}
+
+///Read-only store, opened via `Store::open_shared_read`.
+///
+///It holds shared lock of file until dropped, so other processes can only open it via `Store::open_shared_read`.
+pub struct SharedStore {
+    store: OwnedReadOnlyStore,
+    _lock: File,
+}
+
+impl SharedStore {
+    #[inline]
+    ///Releases lock, returning store.
+    pub fn into_inner(self) -> OwnedReadOnlyStore {
+        self.store
+    }
+}
+
+impl Deref for SharedStore {
+    type Target = OwnedReadOnlyStore;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.store
+    }
+}
+
+///Read-only store, opened via `Store::open_watched`.
+///
+///It takes no lock, relying on saves replacing file atomically, so it can be used along with single writer,
+///holding `LockedStore`, and any number of other readers.
+pub struct WatchedStore {
+    store: OwnedReadOnlyStore,
+    path: PathBuf,
+    key: MasterKey,
+    stamp: Stamp,
+}
+
+impl WatchedStore {
+    #[inline]
+    ///Returns path of watched file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    #[inline]
+    ///Returns whether file has been saved since it was loaded.
+    pub fn has_changed(&self) -> io::Result<bool> {
+        Stamp::read(&self.path).map(|stamp| stamp != self.stamp)
+    }
+
+    ///Reloads store if file has been saved since it was loaded, returning whether it is reloaded.
+    ///
+    ///On error, previously loaded content is kept.
+    pub fn refresh(&mut self) -> io::Result<bool> {
+        let stamp = Stamp::read(&self.path)?;
+        if stamp == self.stamp {
+            return Ok(false);
+        }
+
+        self.store = Store::open_with_key(&self.path, &self.key)?.into_read_only();
+        self.stamp = stamp;
+        Ok(true)
+    }
+
+    #[inline]
+    ///Returns store, loaded last.
+    pub fn into_inner(self) -> OwnedReadOnlyStore {
+        self.store
+    }
+}
+
+impl Deref for WatchedStore {
+    type Target = OwnedReadOnlyStore;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.store
+    }
+}
+
+impl Store {
+    ///Opens storage as `Self::open` does, holding exclusive lock of file while result is alive.
+    ///
+    ///Lock is advisory, so it only guards against processes, that open store via this or `Self::open_shared_read`.
+    ///Returns `WouldBlock` error if file is already locked.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, err))]
+    pub fn open_exclusive<P: AsRef<Path>>(path: P, user: &[u8], pass: &[u8]) -> io::Result<LockedStore> {
+        let path = path.as_ref();
+        let lock = lock_file(path, true)?;
+        Ok(LockedStore {
+            store: Self::open(path, user, pass)?,
+            path: path.to_owned(),
+            _lock: lock,
+        })
+    }
+
+    ///Opens storage as `Self::open` does, holding shared lock of file while result is alive.
+    ///
+    ///Any number of processes can hold shared lock at the same time, while none holds exclusive one.
+    ///Returns `WouldBlock` error if file is exclusively locked.
+    pub fn open_shared_read<P: AsRef<Path>>(path: P, user: &[u8], pass: &[u8]) -> io::Result<SharedStore> {
+        let path = path.as_ref();
+        let lock = lock_file(path, false)?;
+        Ok(SharedStore {
+            store: Self::open(path, user, pass)?.into_read_only(),
+            _lock: lock,
+        })
+    }
+
+    ///Opens storage as `Self::open` does, watching file for changes without taking lock.
+    ///
+    ///It is intended for readers, sharing file with single writer, opened via `Self::open_exclusive`.
+    ///Changes are detected by generation, incremented by `LockedStore::save`, along with size and modification time of file,
+    ///and are loaded via `WatchedStore::refresh`, using encryption key of store, that is kept in memory.
+    pub fn open_watched<P: AsRef<Path>>(path: P, user: &[u8], pass: &[u8]) -> io::Result<WatchedStore> {
+        let path = path.as_ref();
+        let stamp = Stamp::read(path)?;
+        let store = Self::open(path, user, pass)?;
+        Ok(WatchedStore {
+            key: store.master_key()?,
+            store: store.into_read_only(),
+            path: path.to_owned(),
+            stamp,
+        })
+    }
+}
@@ -0,0 +1,478 @@
+//!Binary encoding of storage.
+//!
+//!Layout: `MAGIC | VERSION: u8 | count: u64 | entries`, where each entry is `key: u128 | len: u32 | value`.
+//!All integers are little endian.
+//!
+//!Versions:
+//!
+//!- `1` - values are bare ciphertexts, that are never chunked.
+//!- `2` - values, unless chunked, are enveloped with sealed length of plaintext, refer to `seal`.
+
+use crate::{decoy, enc, open_to_vec, seal, Backend, Error, Store};
+use crate::{AUDIT_KEY, HEADER_KEY, KDF_KEY, MAC_KEY, RECOVERY_KEY, RESERVED};
+
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::collections::BTreeMap;
+
+pub const MAGIC: &[u8; 8] = b"SECSTORE";
+pub const VERSION: u8 = 2;
+///Oldest version, that can be read.
+pub const MIN_VERSION: u8 = 1;
+
+#[inline]
+fn invalid_data(text: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, text)
+}
+
+pub fn write_entry<W: Write>(out: &mut W, key: u128, value: &[u8]) -> io::Result<()> {
+    if value.len() > u32::MAX as usize {
+        return Err(invalid_data("Value is too large"));
+    }
+
+    out.write_all(&key.to_le_bytes())?;
+    out.write_all(&(value.len() as u32).to_le_bytes())?;
+    out.write_all(value)
+}
+
+pub fn read_key<R: Read>(input: &mut R) -> io::Result<u128> {
+    let mut key = [0u8; 16];
+    input.read_exact(&mut key)?;
+    Ok(u128::from_le_bytes(key))
+}
+
+pub fn read_value<R: Read>(input: &mut R) -> io::Result<Vec<u8>> {
+    let mut len = [0u8; 4];
+    input.read_exact(&mut len)?;
+    let len = u32::from_le_bytes(len) as usize;
+
+    let mut value = Vec::new();
+    input.take(len as u64).read_to_end(&mut value)?;
+    if value.len() != len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+
+    Ok(value)
+}
+
+#[cfg_attr(not(feature = "danger-plaintext"), allow(dead_code))]
+#[inline]
+pub fn write_header<W: Write>(out: &mut W, count: usize) -> io::Result<()> {
+    write_header_version(out, VERSION, count)
+}
+
+pub fn write_header_version<W: Write>(out: &mut W, version: u8, count: usize) -> io::Result<()> {
+    out.write_all(MAGIC)?;
+    out.write_all(&[version])?;
+    out.write_all(&(count as u64).to_le_bytes())
+}
+
+pub fn read_header<R: Read>(input: &mut R) -> io::Result<u64> {
+    let mut magic = [0u8; 9];
+    input.read_exact(&mut magic)?;
+    if magic[..8] != MAGIC[..] {
+        return Err(invalid_data("Not a sec-store storage"));
+    } else if magic[8] < MIN_VERSION || magic[8] > VERSION {
+        return Err(invalid_data("Unsupported storage version"));
+    }
+
+    let mut count = [0u8; 8];
+    input.read_exact(&mut count)?;
+    Ok(u64::from_le_bytes(count))
+}
+
+#[cfg_attr(not(feature = "danger-plaintext"), allow(dead_code))]
+pub fn write_entries<'a, W: Write, I: Iterator<Item = (u128, &'a [u8])>>(out: &mut W, count: usize, entries: I) -> io::Result<()> {
+    write_header(out, count)?;
+    for (key, value) in entries {
+        write_entry(out, key, value)?;
+    }
+    out.flush()
+}
+
+#[cfg_attr(not(feature = "danger-plaintext"), allow(dead_code))]
+#[inline]
+pub fn write_map<W: Write, B: Backend>(out: &mut W, map: &B) -> io::Result<()> {
+    write_entries(out, map.len(), map.iter())
+}
+
+pub fn read_map<R: Read>(input: &mut R) -> io::Result<BTreeMap<u128, Vec<u8>>> {
+    let count = read_header(input)?;
+    let mut result = BTreeMap::new();
+    for _ in 0..count {
+        let key = read_key(input)?;
+        let value = read_value(input)?;
+        result.insert(key, value);
+    }
+
+    Ok(result)
+}
+
+///Builds index of entries within encoded storage, mapping key to `(offset, len)` of its value.
+#[cfg_attr(not(all(unix, feature = "mmap")), allow(dead_code))]
+pub fn read_index(data: &[u8]) -> io::Result<BTreeMap<u128, (usize, usize)>> {
+    let mut input = data;
+    let count = read_header(&mut input)?;
+    let mut result = BTreeMap::new();
+    for _ in 0..count {
+        let key = read_key(&mut input)?;
+        let mut len = [0u8; 4];
+        input.read_exact(&mut len)?;
+        let len = u32::from_le_bytes(len) as usize;
+        if input.len() < len {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+
+        result.insert(key, (data.len() - input.len(), len));
+        input = &input[len..];
+    }
+
+    Ok(result)
+}
+
+#[cfg_attr(not(feature = "danger-plaintext"), allow(dead_code))]
+#[inline]
+///Writes `map` into temporary file first, which then atomically replaces `path`.
+pub fn write_file<B: Backend>(path: &Path, map: &B) -> io::Result<()> {
+    write_file_with(path, |file| write_map(file, map))
+}
+
+///Writes content via `write` into temporary file first, which then atomically replaces `path`.
+pub fn write_file_with<F: FnOnce(&mut BufWriter<File>) -> io::Result<()>>(path: &Path, write: F) -> io::Result<()> {
+    let tmp = write_tmp_file(path, write)?;
+    fs::rename(&tmp, path)
+}
+
+///Writes content via `write` into temporary file next to `path`, returning its path.
+///
+///Temporary file is removed if writing fails.
+fn write_tmp_file<F: FnOnce(&mut BufWriter<File>) -> io::Result<()>>(path: &Path, write: F) -> io::Result<PathBuf> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+
+    let mut file = BufWriter::new(File::create(&tmp)?);
+    let result = match write(&mut file) {
+        Ok(()) => file.into_inner().map_err(|error| error.into_error()).and_then(|file| file.sync_all()),
+        Err(error) => Err(error),
+    };
+    match result {
+        Ok(()) => Ok(tmp),
+        Err(error) => {
+            let _ = fs::remove_file(&tmp);
+            Err(error)
+        },
+    }
+}
+
+///Rotates backups of `path`, keeping up to `keep` of them, with current file becoming the newest one.
+///
+///Current file is linked (or copied, if linking is not supported) rather than moved, so it stays in place.
+fn rotate_backups(path: &Path, keep: usize) -> io::Result<()> {
+    match fs::remove_file(backup_path(path, keep)) {
+        Ok(()) => (),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => (),
+        Err(error) => return Err(error),
+    }
+
+    for idx in (1..keep).rev() {
+        match fs::rename(backup_path(path, idx), backup_path(path, idx + 1)) {
+            Ok(()) => (),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => (),
+            Err(error) => return Err(error),
+        }
+    }
+
+    let backup = backup_path(path, 1);
+    match fs::hard_link(path, &backup) {
+        Ok(()) => Ok(()),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(_) => fs::copy(path, &backup).map(|_| ()),
+    }
+}
+
+pub fn read_file(path: &Path) -> io::Result<BTreeMap<u128, Vec<u8>>> {
+    let file = File::open(path)?;
+    read_map(&mut BufReader::new(file))
+}
+
+impl<B: Backend> Store<B> {
+    ///Saves storage into file at `path`.
+    ///
+    ///Storage is written into temporary file first, which then replaces `path`,
+    ///so interrupted save doesn't corrupt previous content.
+    ///Integrity MAC, once required, is computed anew over saved entries, refer to `Self::update_mac`.
+    ///Fresh decoys are written along with entries, if enabled via `Self::set_decoys`,
+    ///in which case `Error::Locked` is returned while store is locked, as decoys cannot be generated.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, err))]
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let decoys = self.generate_decoys()?;
+        write_file_with(path.as_ref(), |file| self.write_with_decoys(file, &decoys))?;
+        self.mark_saved();
+        Ok(())
+    }
+
+    ///Writes storage into `writer`, as `Self::save` does, entry by entry.
+    ///
+    ///Unlike `Self::save`, storage is not marked as saved, refer to `Self::set_autosave`.
+    ///As entries are written by small pieces, `writer` should be buffered.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, err))]
+    pub fn save_to_writer<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let decoys = self.generate_decoys()?;
+        self.write_with_decoys(&mut writer, &decoys)
+    }
+
+    ///Returns decoys to be written on save, if enabled, generating missing ones.
+    fn generate_decoys(&self) -> io::Result<Vec<(u128, Vec<u8>)>> {
+        let count = match self.decoys {
+            0 => return Ok(Vec::new()),
+            _ if self.locked => return Err(crate::Error::Locked.into()),
+            count => count,
+        };
+
+        let mut decoys = self.decoy_entries.lock().unwrap_or_else(|error| error.into_inner());
+        if decoys.len() < count {
+            //Values of format `1` don't record their length, so it is estimated out of ciphertext.
+            let lens: Vec<_> = self.entries().map(|(key, value)| match seal::plain_len(&self.enc, key, value) {
+                Some(len) => len,
+                None => core::cmp::max(value.len().saturating_sub(enc::TAG_LEN), 1),
+            }).collect();
+            match decoy::generate(&self.enc, &self.sealing, count - decoys.len(), &lens) {
+                Some(fresh) => decoys.extend(fresh),
+                None => return Err(io::Error::new(io::ErrorKind::Other, "Unable to generate decoys")),
+            }
+        }
+        Ok(decoys.iter().take(count).cloned().collect())
+    }
+
+    #[inline]
+    ///Forgets decoys, so that they are generated anew along with values being sealed anew.
+    pub(crate) fn forget_decoys(&mut self) {
+        self.decoy_entries.get_mut().unwrap_or_else(|error| error.into_inner()).clear();
+    }
+
+    ///Writes entries along with `decoys` into `out`, in format of `Self::format_version`.
+    ///
+    ///Entries are written as they are kept in memory, as they are already sealed according to format.
+    ///Integrity MAC, if required, is computed anew over written entries, refer to `Self::stored_mac`.
+    fn write_with_decoys<W: Write>(&self, out: &mut W, decoys: &[(u128, Vec<u8>)]) -> io::Result<()> {
+        let versions = self.pending_versions();
+        let mac = self.stored_mac(versions.as_deref());
+        let stored = self.stored_entries(versions.as_deref()).filter(|(key, _)| mac.is_none() || *key != MAC_KEY);
+        let stored = mac.as_ref().map(|mac| (MAC_KEY, &mac[..])).into_iter().chain(stored);
+        let count = match mac.is_some() && !self.inner.contains(MAC_KEY) {
+            true => self.inner.len() + 1,
+            false => self.inner.len(),
+        };
+        write_header_version(out, self.format, count + decoys.len())?;
+        match decoys.is_empty() {
+            true => for (key, value) in stored {
+                write_entry(out, key, value)?;
+            },
+            false => {
+                let mut entries: Vec<_> = stored.chain(decoys.iter().map(|(key, value)| (*key, value.as_slice()))).collect();
+                entries.sort_unstable_by_key(|(key, _)| *key);
+                for (key, value) in entries {
+                    write_entry(out, key, value)?;
+                }
+            },
+        }
+        out.flush()
+    }
+
+    ///Returns integrity MAC over entries as they are saved, with `versions`, if storage requires it.
+    ///
+    ///MAC is computed anew, so that modifications since `Self::update_mac` are covered by saved storage.
+    ///Locked store cannot be modified, so its MAC is saved as it is.
+    fn stored_mac(&self, versions: Option<&[u8]>) -> Option<[u8; enc::MAC_LEN]> {
+        if self.locked || !self.requires_mac() {
+            return None;
+        }
+
+        let mut entries: Vec<_> = self.stored_entries(versions).filter(|(key, _)| *key != MAC_KEY).collect();
+        entries.sort_unstable_by_key(|(key, _)| *key);
+        Some(self.enc.mac(entries.into_iter()))
+    }
+
+    #[inline]
+    ///Writes entries into `out`, in format of `Self::format_version`, without decoys.
+    pub(crate) fn write_to<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        self.write_with_decoys(out, &[])
+    }
+
+    #[inline]
+    ///Returns version of format, used by `Self::save`, refer to `Self::migrate_format`.
+    pub fn format_version(&self) -> u8 {
+        self.format
+    }
+
+    ///Rewrites entries into format of `version`, used by `Self::save` afterwards, returning number of rewritten entries.
+    ///
+    ///Storage of any supported version is read as it is, so migration is only required to produce file
+    ///for older readers, or to bring old entries up to date:
+    ///
+    ///- `1` - enveloped and chunked values are encrypted anew as bare ciphertexts, as well as values inserted afterwards.
+    ///- `2` - values without envelope are encrypted anew, using current settings of encryption.
+    ///
+    ///Entries are kept in memory in the same form as they are saved, so integrity MAC, if required, is updated to cover them.
+    ///Entries of namespaces cannot be decrypted, so they are left as they are.
+    ///
+    ///Returns `Error::Locked` if store is locked.
+    ///Panics if `version` is not supported.
+    pub fn migrate_format(&mut self, version: u8) -> Result<usize, Error> {
+        assert!((MIN_VERSION..=VERSION).contains(&version), "Unsupported format version {}", version);
+        if self.locked {
+            return Err(Error::Locked);
+        }
+
+        self.flush_versions();
+        let mut sealing = self.sealing;
+        sealing.legacy = version < VERSION;
+        let mut changes = Vec::new();
+        for (key, value) in self.inner.iter() {
+            if !is_sealed(key) || seal::is_current(&self.enc, key, value) != sealing.legacy {
+                continue;
+            }
+
+            let mut plain = Vec::new();
+            if open_to_vec(&self.enc, key, value, &mut plain).is_ok() {
+                let sealing = match key >= RESERVED {
+                    true => sealing,
+                    false => sealing.internal(),
+                };
+                match sealing.seal(&self.enc, key, &mut plain) {
+                    true => changes.push((key, plain)),
+                    false => enc::wipe(&mut plain),
+                }
+            }
+        }
+
+        let result = changes.len();
+        let requires_mac = self.requires_mac();
+        for (key, value) in changes {
+            crate::discard(self.inner.insert(key, value));
+        }
+        self.format = version;
+        self.sealing = sealing;
+        self.forget_decoys();
+        self.size = crate::entries_size(&self.inner);
+        if requires_mac {
+            self.update_mac();
+        }
+        self.autosave_changed();
+        Ok(result)
+    }
+
+    ///Saves storage into file at `path`, keeping up to `keep` previous versions of it as backups.
+    ///
+    ///Storage is written into temporary file first, and only once it succeeds, backups are rotated
+    ///as `<path>.bak.1` (the newest) up to `<path>.bak.<keep>`, with the oldest one removed,
+    ///and current file becoming `<path>.bak.1`, after which temporary file replaces `path`.
+    ///Hence failed save leaves both current file and its backups untouched.
+    ///Refer to `Self::save` for details.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, err))]
+    pub fn save_with_backups<P: AsRef<Path>>(&self, path: P, keep: usize) -> io::Result<()> {
+        let path = path.as_ref();
+        let decoys = self.generate_decoys()?;
+        let tmp = write_tmp_file(path, |file| self.write_with_decoys(file, &decoys))?;
+
+        let result = match keep {
+            0 => Ok(()),
+            keep => rotate_backups(path, keep),
+        };
+        match result.and_then(|()| fs::rename(&tmp, path)) {
+            Ok(()) => {
+                self.mark_saved();
+                Ok(())
+            },
+            Err(error) => {
+                let _ = fs::remove_file(&tmp);
+                Err(error)
+            },
+        }
+    }
+}
+
+#[inline]
+///Returns whether entry under `key` is sealed via `seal::Sealing`, hence follows format.
+fn is_sealed(key: u128) -> bool {
+    !matches!(key, MAC_KEY | HEADER_KEY | AUDIT_KEY | RECOVERY_KEY | KDF_KEY)
+}
+
+///Returns path of backup number `idx`, written by `Store::save_with_backups`.
+fn backup_path(path: &Path, idx: usize) -> PathBuf {
+    let mut result = path.as_os_str().to_owned();
+    result.push(format!(".bak.{}", idx));
+    PathBuf::from(result)
+}
+
+///Returns backups of `path`, written by `Store::save_with_backups`, from the newest to the oldest.
+fn backups(path: &Path) -> io::Result<Vec<PathBuf>> {
+    let name = match path.file_name() {
+        Some(name) => name.to_string_lossy().into_owned() + ".bak.",
+        None => return Ok(Vec::new()),
+    };
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+
+    let mut result = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let idx = entry.file_name().to_str().and_then(|file| file.strip_prefix(name.as_str())).and_then(|idx| idx.parse::<usize>().ok());
+        if let Some(idx) = idx {
+            result.push((idx, entry.path()));
+        }
+    }
+
+    result.sort_unstable_by_key(|(idx, _)| *idx);
+    Ok(result.into_iter().map(|(_, path)| path).collect())
+}
+
+impl Store {
+    #[inline]
+    ///Opens storage, previously saved via `Self::save`.
+    ///
+    ///Storage is validated as `Self::try_from_inner` does, with error reported as `InvalidData`.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, err))]
+    pub fn open<P: AsRef<Path>>(path: P, user: &[u8], pass: &[u8]) -> io::Result<Self> {
+        let inner = read_file(path.as_ref())?;
+        Self::try_from_inner(inner, user, pass).map_err(Into::into)
+    }
+
+    #[inline]
+    ///Reads storage, previously written via `Self::save_to_writer` or `Self::save`, from `reader`, entry by entry.
+    ///
+    ///As entries are read by small pieces, `reader` should be buffered.
+    ///Storage is validated as `Self::try_from_inner` does, with error reported as `InvalidData`.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, err))]
+    pub fn load_from_reader<R: Read>(mut reader: R, user: &[u8], pass: &[u8]) -> io::Result<Self> {
+        let inner = read_map(&mut reader)?;
+        Self::try_from_inner(inner, user, pass).map_err(Into::into)
+    }
+
+    ///Opens storage at `path`, falling back to the newest of its backups, written by `Self::save_with_backups`,
+    ///that can be opened.
+    ///
+    ///Each file is validated as `Self::open` does, including integrity MAC, if it is stored,
+    ///so corrupted or truncated save is skipped.
+    ///Credentials are verified by each file too, so fallback doesn't help against wrong ones.
+    ///Returns error of `path` itself if neither file can be opened.
+    pub fn open_latest_valid<P: AsRef<Path>>(path: P, user: &[u8], pass: &[u8]) -> io::Result<Self> {
+        let path = path.as_ref();
+        let error = match Self::open(path, user, pass) {
+            Ok(store) => return Ok(store),
+            Err(error) => error,
+        };
+
+        for backup in backups(path)? {
+            if let Ok(store) = Self::open(backup, user, pass) {
+                return Ok(store);
+            }
+        }
+
+        Err(error)
+    }
+}
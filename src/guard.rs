@@ -0,0 +1,122 @@
+use crate::{enc, open_into, Backend, Error, Store};
+
+use core::ops::Deref;
+use std::sync::MutexGuard;
+
+enum Buffer<'a> {
+    Scratch(MutexGuard<'a, Vec<u8>>),
+    Owned(Vec<u8>),
+}
+
+///Decrypted value, created by `Store::get_guarded`, which is wiped from memory on drop.
+pub struct PlainGuard<'a> {
+    buffer: Buffer<'a>,
+    len: usize,
+}
+
+impl<'a> PlainGuard<'a> {
+    #[inline]
+    fn buffer(&self) -> &Vec<u8> {
+        match &self.buffer {
+            Buffer::Scratch(buffer) => buffer,
+            Buffer::Owned(buffer) => buffer,
+        }
+    }
+
+    #[inline]
+    fn buffer_mut(&mut self) -> &mut Vec<u8> {
+        match &mut self.buffer {
+            Buffer::Scratch(buffer) => buffer,
+            Buffer::Owned(buffer) => buffer,
+        }
+    }
+}
+
+impl<'a> Deref for PlainGuard<'a> {
+    type Target = [u8];
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.buffer()[..self.len]
+    }
+}
+
+impl<'a> AsRef<[u8]> for PlainGuard<'a> {
+    #[inline]
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
+impl<'a> Drop for PlainGuard<'a> {
+    #[inline]
+    fn drop(&mut self) {
+        enc::wipe(self.buffer_mut());
+    }
+}
+
+impl<B: Backend> Store<B> {
+    ///Retrieves value for `key`, returning guard that wipes it once dropped.
+    ///
+    ///Value is decrypted into internal buffer, which is reused by subsequent calls, when there is no other guard alive.
+    ///
+    ///Returns `None` if decryption failed.
+    pub fn get_guarded(&self, key: &[u8]) -> Option<PlainGuard<'_>> {
+        let key = self.hash_key(key);
+
+        let result = match self.inner.get(key) {
+            Some(value) => {
+                self.touch(key);
+                let buffer = match self.scratch.try_lock() {
+                    Ok(scratch) => Buffer::Scratch(scratch),
+                    Err(_) => Buffer::Owned(Vec::new()),
+                };
+                let mut result = PlainGuard {
+                    buffer,
+                    len: 0,
+                };
+
+                let len = open_into(&self.enc, key, value, result.buffer_mut());
+                self.metrics.get(Some(len));
+                match len {
+                    Ok(len) => {
+                        result.len = len;
+                        Some(result)
+                    },
+                    Err(_) => None,
+                }
+            },
+            None => {
+                self.metrics.get(None);
+                None
+            },
+        };
+        self.record_get(key, result.is_some());
+        #[cfg(feature = "tracing")]
+        tracing::trace!(op = "get", success = result.is_some());
+        result
+    }
+
+    ///Checks whether value of `key` equals `candidate`, without handing out plaintext, e.g. to verify password.
+    ///
+    ///Value is decrypted as by `Self::get_guarded`, compared in constant time, only leaking its length, and wiped right away.
+    ///
+    ///Returns error when:
+    ///
+    ///- `Error::NotFound` - `key` doesn't exist.
+    ///- `Error::Locked` - store is locked.
+    ///- `Error::InvalidEntry` - value cannot be decrypted.
+    pub fn verify(&self, key: &[u8], candidate: &[u8]) -> Result<bool, Error> {
+        let hash = self.hash_key(key);
+        if self.locked {
+            return Err(Error::Locked);
+        } else if !self.inner.contains(hash) {
+            return Err(Error::NotFound);
+        }
+
+        match self.get_guarded(key) {
+            Some(value) => Ok(enc::ct_eq(&value, candidate)),
+            None => Err(Error::InvalidEntry(hash)),
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use crate::{Backend, Error, Store};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+///Pre-computed hash of key, created by `Store::key_handle`, allowing to access its value without hashing key on every call.
+///
+///Hash depends on hasher of store, refer to `StoreBuilder::key_hasher`,
+///so handle must be used only with store, that created it, or its replicas.
+pub struct KeyHandle(u128);
+
+impl KeyHandle {
+    #[inline]
+    ///Returns hash of key, under which its value is stored.
+    pub fn hash(&self) -> u128 {
+        self.0
+    }
+}
+
+impl<B: Backend> Store<B> {
+    #[inline]
+    ///Hashes `key`, returning handle to access its value via `*_by_handle` methods.
+    pub fn key_handle(&self, key: &[u8]) -> KeyHandle {
+        KeyHandle(self.hash_key(key))
+    }
+
+    ///Retrieves value by `handle` to store in `dest`, resulting in it being overwritten.
+    ///
+    ///Refer to `Self::get_to_vec` for details.
+    pub fn get_to_vec_by_handle(&self, handle: KeyHandle, dest: &mut Vec<u8>) -> Result<usize, ()> {
+        let result = self.inner_get_to_vec(handle.0, dest);
+        #[cfg(feature = "tracing")]
+        tracing::trace!(op = "get", success = result.is_ok());
+        result
+    }
+
+    #[inline]
+    ///Retrieves value by `handle`.
+    ///
+    ///Returns `None` if decryption failed.
+    pub fn get_by_handle(&self, handle: KeyHandle) -> Option<Vec<u8>> {
+        let mut result = Vec::new();
+        match self.get_to_vec_by_handle(handle, &mut result) {
+            Ok(_) => Some(result),
+            Err(_) => None,
+        }
+    }
+
+    #[inline]
+    ///Checks for presence of key by `handle` within storage.
+    pub fn contains_by_handle(&self, handle: KeyHandle) -> bool {
+        self.inner.contains(handle.0)
+    }
+
+    #[inline]
+    ///Inserts new `value` by `handle`, returning previous one, if any.
+    ///
+    ///Unlike insertion by key, name of key is unknown, hence it is not recorded, refer to `Self::enable_key_names`.
+    ///
+    ///Returns `Error::LimitExceeded` if value doesn't fit store's limits, leaving it untouched.
+    pub fn try_insert_by_handle(&mut self, handle: KeyHandle, value: &[u8]) -> Result<Option<Vec<u8>>, Error> {
+        self.try_insert_hash(handle.0, None, value.to_owned())
+    }
+
+    #[inline]
+    ///Inserts new `value` by `handle`, returning previous one, if any.
+    ///
+    ///Panics if value doesn't fit store's limits, refer to `Self::try_insert_by_handle`.
+    pub fn insert_by_handle(&mut self, handle: KeyHandle, value: &[u8]) -> Option<Vec<u8>> {
+        match self.try_insert_by_handle(handle, value) {
+            Ok(result) => result,
+            Err(error) => panic!("Cannot insert value: {}", error),
+        }
+    }
+
+    #[inline]
+    ///Removes key by `handle`, returning previous value, if any.
+    ///
+    ///Failing to decrypt, doesn't remove value.
+    pub fn remove_by_handle(&mut self, handle: KeyHandle) -> Option<Vec<u8>> {
+        let mut result = Vec::new();
+        match self.remove_hash_to_vec(handle.0, &mut result) {
+            Ok(_) => Some(result),
+            Err(_) => None,
+        }
+    }
+}
@@ -0,0 +1,99 @@
+//!Hashing of key names.
+//!
+//!Values are stored under hashes of their keys, so hasher must stay the same for whole lifetime of storage.
+//!Hasher, other than default, is recorded within header of storage, refer to `header`.
+
+use crate::{Backend, Error, Store};
+use crate::header::Header;
+
+use ring::digest;
+use xxhash_rust::xxh3::{xxh3_64, xxh3_128, xxh3_128_with_seed};
+
+///Hash function, mapping name of key to hash, under which its value is stored.
+///
+///Identifier of hasher is recorded within storage, so it can be opened only with the same hasher,
+///refer to `StoreBuilder::key_hasher`.
+pub trait KeyHasher: Send + Sync {
+    ///Returns identifier, that is unique for both algorithm and its parameters.
+    ///
+    ///It is stored encrypted, yet it should not be secret itself, e.g. key of keyed hash must not be part of it.
+    fn id(&self) -> &[u8];
+    ///Returns hash of `key`.
+    fn hash(&self, key: &[u8]) -> u128;
+    ///Returns hash of `key` within namespace, identified by `namespace`, refer to `Store::namespace`.
+    ///
+    ///By default it is hash of `namespace` length as `u64`, followed by `namespace` and `key`.
+    fn hash_namespaced(&self, namespace: &[u8], key: &[u8]) -> u128 {
+        let mut input = Vec::with_capacity(8 + namespace.len() + key.len());
+        input.extend_from_slice(&(namespace.len() as u64).to_le_bytes());
+        input.extend_from_slice(namespace);
+        input.extend_from_slice(key);
+        self.hash(&input)
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+///Default hasher, which is 128 bit XXH3.
+pub struct Xxh3;
+
+impl KeyHasher for Xxh3 {
+    #[inline]
+    fn id(&self) -> &[u8] {
+        b"xxh3-128"
+    }
+
+    #[inline]
+    fn hash(&self, key: &[u8]) -> u128 {
+        xxh3_128(key).to_le()
+    }
+
+    #[inline]
+    fn hash_namespaced(&self, namespace: &[u8], key: &[u8]) -> u128 {
+        xxh3_128_with_seed(key, xxh3_64(namespace)).to_le()
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+///SHA-256, truncated to 128 bits.
+pub struct Sha256;
+
+impl KeyHasher for Sha256 {
+    #[inline]
+    fn id(&self) -> &[u8] {
+        b"sha256-128"
+    }
+
+    fn hash(&self, key: &[u8]) -> u128 {
+        let digest = digest::digest(&digest::SHA256, key);
+        let mut result = [0u8; 16];
+        result.copy_from_slice(&digest.as_ref()[..16]);
+        u128::from_le_bytes(result)
+    }
+}
+
+impl<B: Backend> Store<B> {
+    #[inline]
+    ///Returns hash of `key`, under which its value is stored.
+    pub(crate) fn hash_key(&self, key: &[u8]) -> u128 {
+        self.hasher.hash(key)
+    }
+
+    #[inline]
+    ///Returns hasher of key names.
+    pub fn key_hasher(&self) -> &dyn KeyHasher {
+        &*self.hasher
+    }
+
+    ///Checks that hasher, recorded within header, is the same as store's one.
+    ///
+    ///Storage without header is assumed to use default hasher.
+    pub(crate) fn verify_key_hasher(&self) -> Result<(), Error> {
+        match self.decrypt_header() {
+            Some(header) => match Header::parse(&header) {
+                Some(header) if !header.is_hashed_by(&*self.hasher) => Err(Error::KeyHasherMismatch),
+                _ => Ok(()),
+            },
+            None => Ok(()),
+        }
+    }
+}
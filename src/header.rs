@@ -0,0 +1,119 @@
+//!Encrypted header of storage, used to verify credentials.
+//!
+//!Layout: `HEADER | ['+'] | ['/' | id]`, where `+` marks that storage requires integrity MAC,
+//!and `id` is identifier of hasher of key names, unless it is default one.
+
+use crate::{enc, Backend, Error, KeyHasher, Store, Xxh3, HEADER, HEADER_KEY, MAC_KEY};
+
+///Marker of storage, that requires integrity MAC.
+const MAC_MARKER: u8 = b'+';
+///Separator of hasher's identifier.
+const HASHER_MARKER: u8 = b'/';
+
+///Parsed header.
+pub(crate) struct Header<'a> {
+    ///Whether storage requires integrity MAC, refer to `Store::update_mac`.
+    pub(crate) requires_mac: bool,
+    ///Identifier of hasher of key names, which is empty for default one.
+    hasher: &'a [u8],
+}
+
+impl<'a> Header<'a> {
+    ///Parses decrypted `header`, returning `None` if it is not header of storage.
+    pub(crate) fn parse(header: &'a [u8]) -> Option<Self> {
+        let rest = header.strip_prefix(HEADER)?;
+        let (requires_mac, rest) = match rest.strip_prefix(&[MAC_MARKER]) {
+            Some(rest) => (true, rest),
+            None => (false, rest),
+        };
+        let hasher = match rest {
+            [] => rest,
+            [HASHER_MARKER, id @ ..] if !id.is_empty() => id,
+            _ => return None,
+        };
+
+        Some(Self {
+            requires_mac,
+            hasher,
+        })
+    }
+
+    #[inline]
+    ///Returns whether header records `hasher`.
+    pub(crate) fn is_hashed_by(&self, hasher: &dyn KeyHasher) -> bool {
+        match self.hasher.is_empty() {
+            true => hasher.id() == Xxh3.id(),
+            false => self.hasher == hasher.id(),
+        }
+    }
+}
+
+///Returns header, recording `hasher`, unless it is default one, and whether storage `requires_mac`.
+pub(crate) fn encode(hasher: &dyn KeyHasher, requires_mac: bool) -> Vec<u8> {
+    let mut result = HEADER.to_owned();
+    if requires_mac {
+        result.push(MAC_MARKER);
+    }
+    if hasher.id() != Xxh3.id() {
+        result.push(HASHER_MARKER);
+        result.extend_from_slice(hasher.id());
+    }
+    result
+}
+
+impl<B: Backend> Store<B> {
+    #[inline]
+    ///Decrypts header, returning `None` if there is none or it cannot be decrypted.
+    pub(crate) fn decrypt_header(&self) -> Option<Vec<u8>> {
+        self.inner.get(HEADER_KEY).and_then(|header| self.decrypt_value(HEADER_KEY, header))
+    }
+
+    ///Returns whether header records, that storage requires integrity MAC.
+    pub(crate) fn is_mac_recorded(&self) -> bool {
+        match self.decrypt_header() {
+            Some(header) => Header::parse(&header).map(|header| header.requires_mac).unwrap_or(false),
+            None => false,
+        }
+    }
+
+    #[inline]
+    ///Returns whether storage requires integrity MAC, which is either stored or recorded within header.
+    pub(crate) fn requires_mac(&self) -> bool {
+        self.inner.contains(MAC_KEY) || self.is_mac_recorded()
+    }
+
+    #[inline]
+    ///Writes encrypted header, used to verify credentials, preserving whether storage requires integrity MAC.
+    pub(crate) fn write_header(&mut self) {
+        let requires_mac = self.requires_mac();
+        self.write_header_with(requires_mac);
+    }
+
+    ///Writes encrypted header, recording hasher of key names and whether storage `requires_mac`.
+    ///
+    ///Header is encrypted using random nonce, as it is re-written once integrity MAC becomes required.
+    pub(crate) fn write_header_with(&mut self, requires_mac: bool) {
+        let mut header = encode(&*self.hasher, requires_mac);
+        assert!(self.enc.encrypt_random(HEADER_KEY, &mut header));
+        crate::discard(self.inner.insert(HEADER_KEY, header));
+    }
+}
+
+///Decrypts `value` of header, using `enc`, verifying that it records `hasher`.
+///
+///Returns whether storage requires integrity MAC, or error when:
+///
+///- `Error::WrongCredentials` - header cannot be decrypted.
+///- `Error::KeyHasherMismatch` - header records other hasher.
+pub(crate) fn verify(enc: &enc::Manager, value: &[u8], hasher: &dyn KeyHasher) -> Result<bool, Error> {
+    let mut header = Vec::new();
+    if crate::open_to_vec(enc, HEADER_KEY, value, &mut header).is_err() {
+        return Err(Error::WrongCredentials);
+    }
+
+    match Header::parse(&header) {
+        Some(header) if header.is_hashed_by(hasher) => Ok(header.requires_mac),
+        Some(_) => Err(Error::KeyHasherMismatch),
+        None => Err(Error::WrongCredentials),
+    }
+}
@@ -0,0 +1,238 @@
+use crate::{enc, Backend, Error, Store, HISTORY_KEY, RESERVED};
+use crate::tags::{pop_bytes, push_bytes};
+
+use std::collections::BTreeMap;
+
+const FORMAT: u8 = 1;
+
+#[derive(Default)]
+///Previous values of key.
+struct Record {
+    ///Number of next previous value.
+    next: u64,
+    ///Previous values along with their numbers, from the oldest.
+    values: Vec<(u64, Vec<u8>)>,
+}
+
+///Previous values of keys, keyed by their hashes.
+struct History {
+    ///Maximum number of previous values per key.
+    depth: usize,
+    entries: BTreeMap<u128, Record>,
+}
+
+impl History {
+    ///Removes the oldest values of `record` beyond depth.
+    fn trim(depth: usize, record: &mut Record) {
+        let excess = record.values.len().saturating_sub(depth);
+        for (_, mut value) in record.values.drain(..excess) {
+            enc::wipe(&mut value);
+        }
+    }
+}
+
+///Encodes `history` as `FORMAT: u8 | depth: u32 | (key: u128 | next: u64 | count: u32 | (number: u64 | value)..)..`,
+///where value is prefixed with length as `u32`.
+fn encode(history: &History) -> Vec<u8> {
+    let mut result = Vec::with_capacity(5 + history.entries.values().map(|record| 28 + record.values.iter().map(|(_, value)| 12 + value.len()).sum::<usize>()).sum::<usize>());
+    result.push(FORMAT);
+    result.extend_from_slice(&(history.depth as u32).to_le_bytes());
+    for (key, record) in history.entries.iter() {
+        result.extend_from_slice(&key.to_le_bytes());
+        result.extend_from_slice(&record.next.to_le_bytes());
+        result.extend_from_slice(&(record.values.len() as u32).to_le_bytes());
+        for (number, value) in record.values.iter() {
+            result.extend_from_slice(&number.to_le_bytes());
+            push_bytes(&mut result, value);
+        }
+    }
+    result
+}
+
+#[inline]
+fn pop_array<const N: usize>(input: &mut &[u8]) -> Option<[u8; N]> {
+    let mut result = [0u8; N];
+    result.copy_from_slice(input.get(..N)?);
+    *input = &input[N..];
+    Some(result)
+}
+
+fn decode(input: &[u8]) -> Option<History> {
+    let mut input = match input.split_first() {
+        Some((&FORMAT, input)) => input,
+        _ => return None,
+    };
+
+    let mut result = History {
+        depth: u32::from_le_bytes(pop_array(&mut input)?) as usize,
+        entries: BTreeMap::new(),
+    };
+    while !input.is_empty() {
+        let key = u128::from_le_bytes(pop_array(&mut input)?);
+        let mut record = Record {
+            next: u64::from_le_bytes(pop_array(&mut input)?),
+            values: Vec::new(),
+        };
+        for _ in 0..u32::from_le_bytes(pop_array(&mut input)?) {
+            let number = u64::from_le_bytes(pop_array(&mut input)?);
+            record.values.push((number, pop_bytes(&mut input)?.to_owned()));
+        }
+        result.entries.insert(key, record);
+    }
+
+    Some(result)
+}
+
+fn wipe(history: History) {
+    for (_, record) in history.entries {
+        for (_, mut value) in record.values {
+            enc::wipe(&mut value);
+        }
+    }
+}
+
+impl<B: Backend> Store<B> {
+    ///Decrypts history, returning `None` if it is not stored or cannot be decrypted.
+    fn load_history(&self) -> Option<History> {
+        let mut plain = self.decrypt_value(HISTORY_KEY, self.inner.get(HISTORY_KEY)?)?;
+        let result = decode(&plain);
+        enc::wipe(&mut plain);
+        result
+    }
+
+    fn write_history(&mut self, history: History) {
+        let mut value = encode(&history);
+        if self.sealing.internal().seal(&self.enc, HISTORY_KEY, &mut value) {
+            self.inner.insert(HISTORY_KEY, value);
+        }
+        wipe(history);
+    }
+
+    ///Remembers `previous` ciphertext of `key`, if history is enabled.
+    pub(crate) fn record_history(&mut self, key: u128, previous: &[u8]) {
+        if key < RESERVED || self.locked || !self.inner.contains(HISTORY_KEY) {
+            return;
+        }
+
+        //Values of namespaces cannot be decrypted, hence they have no history.
+        let value = match self.decrypt_value(key, previous) {
+            Some(value) => value,
+            None => return,
+        };
+        let mut history = match self.load_history() {
+            Some(history) => history,
+            None => return,
+        };
+
+        let record = history.entries.entry(key).or_default();
+        record.values.push((record.next, value));
+        record.next += 1;
+        History::trim(history.depth, record);
+        self.write_history(history);
+    }
+
+    ///Forgets previous values of `key`, if history is enabled.
+    pub(crate) fn forget_history(&mut self, key: u128) {
+        if self.locked || !self.inner.contains(HISTORY_KEY) {
+            return;
+        }
+
+        if let Some(mut history) = self.load_history() {
+            match history.entries.remove(&key) {
+                Some(record) => {
+                    for (_, mut value) in record.values {
+                        enc::wipe(&mut value);
+                    }
+                    self.write_history(history);
+                },
+                None => wipe(history),
+            }
+        }
+    }
+
+    ///Sets number of previous values, kept per key on every modification, with `0` disabling history.
+    ///
+    ///Previous values are kept within single encrypted entry, which is re-written on every modification,
+    ///hence it is only suitable for moderate number of modifications and small values.
+    ///History is not subject to store's limits, and values of namespaces have no history.
+    ///Removal of key discards its history, while disabling discards history of all keys.
+    ///
+    ///Returns `Error::Locked` if store is locked, or `Error::InvalidEntry` if existing history cannot be decrypted.
+    pub fn set_history_depth(&mut self, depth: usize) -> Result<(), Error> {
+        if self.locked {
+            return Err(Error::Locked);
+        } else if depth == 0 {
+            if self.inner.remove(HISTORY_KEY).is_some() {
+                self.autosave_changed();
+            }
+            return Ok(());
+        }
+
+        let mut history = match self.inner.contains(HISTORY_KEY) {
+            true => self.load_history().ok_or(Error::InvalidEntry(HISTORY_KEY))?,
+            false => History {
+                depth,
+                entries: BTreeMap::new(),
+            },
+        };
+        history.depth = depth.min(u32::MAX as usize);
+        for record in history.entries.values_mut() {
+            History::trim(history.depth, record);
+        }
+        self.write_history(history);
+        self.autosave_changed();
+        Ok(())
+    }
+
+    #[inline]
+    ///Returns whether previous values are kept, refer to `Self::set_history_depth`.
+    pub fn has_history(&self) -> bool {
+        self.inner.contains(HISTORY_KEY)
+    }
+
+    ///Returns previous values of `key` along with their numbers, from the newest.
+    ///
+    ///Numbers grow with each modification, and are to be passed to `Self::rollback`.
+    ///Refer to `Self::set_history_depth` for details.
+    ///
+    ///Returns `Error::Locked` if store is locked, or `Error::InvalidEntry` if history cannot be decrypted.
+    pub fn history(&self, key: &[u8]) -> Result<Vec<(u64, Vec<u8>)>, Error> {
+        if self.locked {
+            return Err(Error::Locked);
+        } else if !self.inner.contains(HISTORY_KEY) {
+            return Ok(Vec::new());
+        }
+
+        let mut history = self.load_history().ok_or(Error::InvalidEntry(HISTORY_KEY))?;
+        let mut result = history.entries.remove(&self.hash_key(key)).unwrap_or_default().values;
+        result.reverse();
+        wipe(history);
+        Ok(result)
+    }
+
+    ///Restores previous value number `version` of `key`, returned by `Self::history`.
+    ///
+    ///Restoration is regular modification, hence current value is kept within history as well.
+    ///
+    ///Returns error when:
+    ///
+    ///- `Error::NotFound` - there is no such previous value.
+    ///- `Error::Locked` - store is locked.
+    ///- `Error::InvalidEntry` - history cannot be decrypted.
+    ///- `Error::LimitExceeded` - value doesn't fit store's limits.
+    pub fn rollback(&mut self, key: &[u8], version: u64) -> Result<(), Error> {
+        let mut history = self.history(key)?;
+        let result = match history.iter().position(|(number, _)| *number == version) {
+            Some(idx) => self.try_insert(key, &history[idx].1).map(|previous| {
+                if let Some(mut previous) = previous {
+                    enc::wipe(&mut previous);
+                }
+            }),
+            None => Err(Error::NotFound),
+        };
+        for (_, value) in history.iter_mut() {
+            enc::wipe(value);
+        }
+        result
+    }
+}
@@ -0,0 +1,82 @@
+use crate::{enc, kdf, namespace, open_to_vec, parallel, Backend, Error, Store};
+use crate::{AUDIT_KEY, HEADER_KEY, KDF_KEY, MAC_KEY, RECOVERY_KEY, RESERVED};
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+///Result of `Store::check_integrity`.
+pub struct IntegrityReport {
+    ///Number of checked entries, including internal ones.
+    pub checked: usize,
+    ///Hashes of entries, that cannot be authenticated, in ascending order.
+    pub corrupt: Vec<u128>,
+    ///Whether integrity MAC matches content, or `None` if storage doesn't require MAC.
+    pub mac: Option<bool>,
+}
+
+impl IntegrityReport {
+    #[inline]
+    ///Returns whether every entry is authenticated, as well as integrity MAC, if any.
+    pub fn is_ok(&self) -> bool {
+        self.corrupt.is_empty() && self.mac != Some(false)
+    }
+}
+
+#[inline]
+fn authenticate(enc: &enc::Manager, key: u128, value: &[u8]) -> bool {
+    let mut plain = Vec::new();
+    let result = open_to_vec(enc, key, value, &mut plain).is_ok();
+    enc::wipe(&mut plain);
+    result
+}
+
+impl<B: Backend + Sync> Store<B> {
+    #[inline]
+    ///Authenticates every entry, reporting hashes of ones, that are corrupted or cannot be decrypted.
+    ///
+    ///Each value is decrypted in parallel and wiped immediately, so plaintext is never kept.
+    ///Entries of namespaces cannot be decrypted by store's key, so they are reported unless
+    ///namespaces are specified via `Self::check_integrity_namespaces`.
+    ///Wrapped recovery key can only be checked for its length, as it is encrypted by recovery key.
+    ///
+    ///Returns `Error::Locked` if store is locked.
+    pub fn check_integrity(&self) -> Result<IntegrityReport, Error> {
+        self.check_integrity_namespaces(&[])
+    }
+
+    ///Authenticates every entry as `Self::check_integrity` does, accepting entries of specified `namespaces`.
+    ///
+    ///Returns `Error::Locked` if store is locked.
+    pub fn check_integrity_namespaces(&self, namespaces: &[&[u8]]) -> Result<IntegrityReport, Error> {
+        if self.locked {
+            return Err(Error::Locked);
+        }
+
+        let namespaces: Vec<_> = namespaces.iter().map(|name| namespace::manager(&self.enc, name)).collect();
+        let entries: Vec<_> = self.inner.iter().collect();
+        let valid = parallel::map(&entries, |(key, value)| match *key {
+            MAC_KEY => value.len() == enc::MAC_LEN,
+            HEADER_KEY => crate::header::verify(&self.enc, value, &*self.hasher).is_ok(),
+            AUDIT_KEY => match self.enc.open_random(value) {
+                Some(mut log) => {
+                    enc::wipe(&mut log);
+                    true
+                },
+                None => false,
+            },
+            RECOVERY_KEY => value.len() > enc::TAG_LEN,
+            KDF_KEY => kdf::Kdf::from_entry(Some(value)).is_some(),
+            key if key < RESERVED => authenticate(&self.enc, key, value),
+            key => authenticate(&self.enc, key, value) || namespaces.iter().any(|enc| authenticate(enc, key, value)),
+        });
+
+        let mut corrupt: Vec<_> = entries.iter().zip(valid).filter(|(_, valid)| !valid).map(|((key, _), _)| *key).collect();
+        corrupt.sort_unstable();
+        Ok(IntegrityReport {
+            checked: entries.len(),
+            corrupt,
+            mac: match self.requires_mac() {
+                true => Some(self.verify_mac()),
+                false => None,
+            },
+        })
+    }
+}
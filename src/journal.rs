@@ -0,0 +1,326 @@
+//!Journaled persistence.
+//!
+//!Journal starts with `seed: [u8; MAC_LEN]`, which is integrity MAC of snapshot it follows (or zeroes, if there is none),
+//!followed by records.
+//!Record is either `REMOVE | key: u128 | mac`, `INSERT | key: u128 | len: u32 | value | mac` or `COMPACT | snapshot: [u8; MAC_LEN] | mac`,
+//!where `mac` is HMAC of previous record's `mac` (or seed, for the first record) and record itself.
+//!
+//!`COMPACT` record is appended before snapshot with integrity MAC `snapshot` replaces previous one,
+//!so journal, left by interrupted compaction, is recognized as included into new snapshot.
+
+use crate::{enc, format, Store, MAC_KEY};
+
+use ring::hmac;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+const OP_REMOVE: u8 = 0;
+const OP_INSERT: u8 = 1;
+const OP_COMPACT: u8 = 2;
+///Default number of journal records, after which journal is compacted into snapshot.
+pub const DEFAULT_COMPACT_THRESHOLD: usize = 1024;
+
+///Record of journal.
+enum Record {
+    Insert(u128, Vec<u8>),
+    Remove(u128),
+    Compact([u8; enc::MAC_LEN]),
+}
+
+///Store, persisting every modification into append-only journal.
+///
+///State is made of two files:
+///
+///- Snapshot at `path` - full storage, written on compaction.
+///- Journal at `path` with `.journal` extension - encrypted change records applied on top of snapshot.
+///
+///Each modification is appended to journal and synced to disk before it is applied to store.
+///Once journal reaches threshold, it is compacted into new snapshot.
+///
+///Records are authenticated as chain, starting from snapshot, so they cannot be modified, reordered or
+///replayed on top of other snapshot.
+pub struct Journaled {
+    store: Store,
+    path: PathBuf,
+    journal: File,
+    ///Size of journal in bytes, made of complete records only.
+    len: u64,
+    ///MAC of last record.
+    chain: [u8; enc::MAC_LEN],
+    records: usize,
+    threshold: usize,
+}
+
+impl Journaled {
+    ///Opens journaled store at `path`, creating new one if snapshot doesn't exist.
+    ///
+    ///Snapshot is validated as `Store::try_from_inner` does, and then journal is replayed on top.
+    ///
+    ///Incomplete trailing record, left by interrupted write, is truncated, so that new records follow complete ones.
+    ///Journal, which follows previous snapshot and ends with compaction into current one, is left by interrupted compaction,
+    ///hence it is discarded, as snapshot already includes its records.
+    ///Returns `InvalidData` error if any record cannot be authenticated, or journal doesn't follow snapshot otherwise.
+    pub fn open<P: AsRef<Path>>(path: P, user: &[u8], pass: &[u8]) -> io::Result<Self> {
+        let path = path.as_ref().to_owned();
+
+        let mut store = match format::read_file(&path) {
+            Ok(inner) => Store::try_from_inner(inner, user, pass)?,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Store::try_new(user, pass)?,
+            Err(error) => return Err(error),
+        };
+
+        let journal_path = Self::journal_path(&path);
+        let data = match fs::read(&journal_path) {
+            Ok(data) => data,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(error) => return Err(error),
+        };
+
+        let seed = Self::seed(&store);
+        //Journal without complete seed is either new or torn by interrupted reset, hence has no records.
+        let (mut chain, mut len) = match data.get(..enc::MAC_LEN) {
+            Some(header) => {
+                let mut chain = [0u8; enc::MAC_LEN];
+                chain.copy_from_slice(header);
+                (chain, enc::MAC_LEN)
+            },
+            None => (seed, 0),
+        };
+        let is_current = enc::ct_eq(&chain, &seed);
+
+        let mut changes = Vec::new();
+        let mut compacted = None;
+        while let Some((record_len, record)) = Self::read_record(&data[len..])? {
+            let (bytes, mac) = data[len..len + record_len].split_at(record_len - enc::MAC_LEN);
+            let expected = Self::sign(&store, &chain, bytes);
+            if !enc::ct_eq(&expected, mac) {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "Journal record cannot be authenticated"));
+            }
+
+            //Compaction is followed by records only if writing of snapshot failed, so it is only relevant as the last one.
+            compacted = match record {
+                Record::Compact(snapshot) => Some(snapshot),
+                record => {
+                    changes.push(record);
+                    None
+                },
+            };
+            chain = expected;
+            len += record_len;
+        }
+
+        let records = match (is_current, compacted) {
+            (true, _) => changes.len(),
+            (false, Some(snapshot)) if enc::ct_eq(&snapshot, &seed) => {
+                changes.clear();
+                len = 0;
+                0
+            },
+            (false, _) => return Err(io::Error::new(io::ErrorKind::InvalidData, "Journal doesn't follow snapshot")),
+        };
+        for change in changes {
+            match change {
+                Record::Insert(key, value) => crate::discard(store.inner_put(key, value)),
+                Record::Remove(key) => crate::discard(store.inner_take(key)),
+                Record::Compact(_) => (),
+            }
+        }
+
+        let journal = OpenOptions::new().create(true).append(true).open(&journal_path)?;
+        let mut result = Self {
+            store,
+            path,
+            journal,
+            len: len as u64,
+            chain,
+            records,
+            threshold: DEFAULT_COMPACT_THRESHOLD,
+        };
+        if len == 0 {
+            result.reset()?;
+        } else if len < data.len() {
+            result.journal.set_len(len as u64)?;
+            result.journal.sync_data()?;
+        }
+        Ok(result)
+    }
+
+    fn journal_path(path: &Path) -> PathBuf {
+        let mut result = path.as_os_str().to_owned();
+        result.push(".journal");
+        result.into()
+    }
+
+    ///Returns start of chain of records, which is integrity MAC of snapshot, if any.
+    fn seed(store: &Store) -> [u8; enc::MAC_LEN] {
+        let mut result = [0u8; enc::MAC_LEN];
+        if let Some(mac) = store.inner.get(&MAC_KEY).filter(|mac| mac.len() == enc::MAC_LEN) {
+            result.copy_from_slice(mac);
+        }
+        result
+    }
+
+    ///Computes MAC of `record`, following record with MAC `chain`.
+    fn sign(store: &Store, chain: &[u8; enc::MAC_LEN], record: &[u8]) -> [u8; enc::MAC_LEN] {
+        let mut context = hmac::Context::with_key(&store.enc.subkey(b"sec-store:journal"));
+        context.update(chain);
+        context.update(record);
+        let mut result = [0u8; enc::MAC_LEN];
+        result.copy_from_slice(context.sign().as_ref());
+        result
+    }
+
+    ///Reads record at start of `input`, returning its length, including MAC.
+    ///
+    ///Returns `None` if there is no complete record.
+    fn read_record(input: &[u8]) -> io::Result<Option<(usize, Record)>> {
+        let (op, mut rest) = match input.split_first() {
+            Some((op, rest)) => (*op, rest),
+            None => return Ok(None),
+        };
+
+        let record = match op {
+            OP_INSERT => format::read_key(&mut rest).and_then(|key| format::read_value(&mut rest).map(|value| Record::Insert(key, value))),
+            OP_REMOVE => format::read_key(&mut rest).map(Record::Remove),
+            OP_COMPACT => match rest.get(..enc::MAC_LEN) {
+                Some(mac) => {
+                    let mut snapshot = [0u8; enc::MAC_LEN];
+                    snapshot.copy_from_slice(mac);
+                    rest = &rest[enc::MAC_LEN..];
+                    Ok(Record::Compact(snapshot))
+                },
+                None => Err(io::ErrorKind::UnexpectedEof.into()),
+            },
+            _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "Unknown journal record")),
+        };
+
+        match record {
+            Ok(_) if rest.len() < enc::MAC_LEN => Ok(None),
+            Ok(record) => Ok(Some((input.len() - rest.len() + enc::MAC_LEN, record))),
+            Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+
+    #[inline]
+    ///Sets number of journal records after which journal is compacted.
+    pub fn set_compact_threshold(&mut self, threshold: usize) {
+        self.threshold = threshold;
+    }
+
+    #[inline]
+    ///Returns number of records in journal.
+    pub fn journal_len(&self) -> usize {
+        self.records
+    }
+
+    #[inline]
+    ///Accesses underlying store for reading.
+    pub fn store(&self) -> &Store {
+        &self.store
+    }
+
+    ///Appends `record`, followed by its MAC, to journal.
+    fn write_record(&mut self, mut record: Vec<u8>) -> io::Result<()> {
+        let mac = Self::sign(&self.store, &self.chain, &record);
+        record.extend_from_slice(&mac);
+
+        //Partially written record is truncated, so that next one doesn't follow it.
+        if let Err(error) = self.journal.write_all(&record).and_then(|()| self.journal.sync_data()) {
+            let _ = self.journal.set_len(self.len);
+            return Err(error);
+        }
+        self.len += record.len() as u64;
+        self.chain = mac;
+        Ok(())
+    }
+
+    ///Appends modification of `key`, setting its ciphertext to `value` or removing it.
+    fn append(&mut self, key: u128, value: Option<&[u8]>) -> io::Result<()> {
+        let mut record = Vec::new();
+        match value {
+            Some(value) => {
+                record.push(OP_INSERT);
+                format::write_entry(&mut record, key, value)?;
+            },
+            None => {
+                record.push(OP_REMOVE);
+                record.extend_from_slice(&key.to_le_bytes());
+            },
+        }
+        self.write_record(record)?;
+        self.records += 1;
+        Ok(())
+    }
+
+    #[inline]
+    fn compact_if_full(&mut self) -> io::Result<()> {
+        match self.records >= self.threshold {
+            true => self.compact(),
+            false => Ok(()),
+        }
+    }
+
+    ///Inserts new `value` for `key`, returning previous one, if any.
+    ///
+    ///Returns `InvalidInput` error if value doesn't fit store's limits.
+    pub fn insert(&mut self, key: &[u8], value: &[u8]) -> io::Result<Option<Vec<u8>>> {
+        let hash = self.store.hash_key(key);
+        let value = self.store.try_seal(hash, value.to_owned())?;
+        if let Err(error) = self.append(hash, Some(&value)) {
+            crate::discard(Some(value));
+            return Err(error);
+        }
+
+        self.store.record_name(hash, key);
+        let result = self.store.put_sealed(hash, value);
+        self.compact_if_full()?;
+        Ok(result)
+    }
+
+    ///Removes `key`, returning whether it was set previously.
+    pub fn remove_key(&mut self, key: &[u8]) -> io::Result<bool> {
+        let hash = self.store.hash_key(key);
+        if self.store.inner.contains_key(&hash) {
+            self.append(hash, None)?;
+        }
+
+        let result = self.store.remove_key(key);
+        self.compact_if_full()?;
+        Ok(result)
+    }
+
+    ///Writes full snapshot, updating its integrity MAC, and truncates journal.
+    ///
+    ///Snapshot is written into temporary file first, which then replaces previous snapshot.
+    pub fn compact(&mut self) -> io::Result<()> {
+        self.store.update_mac();
+        let mut record = vec![OP_COMPACT];
+        record.extend_from_slice(&Self::seed(&self.store));
+        self.write_record(record)?;
+
+        self.store.save(&self.path)?;
+        self.reset()
+    }
+
+    ///Truncates journal to seed of current snapshot.
+    fn reset(&mut self) -> io::Result<()> {
+        let seed = Self::seed(&self.store);
+        self.journal.set_len(0)?;
+        self.len = 0;
+        self.journal.write_all(&seed)?;
+        self.journal.sync_data()?;
+        self.len = enc::MAC_LEN as u64;
+        self.chain = seed;
+        self.records = 0;
+        Ok(())
+    }
+
+    #[inline]
+    ///Compacts journal and returns underlying store.
+    pub fn into_store(mut self) -> io::Result<Store> {
+        self.compact()?;
+        Ok(self.store)
+    }
+}
@@ -0,0 +1,267 @@
+//!Minimal JSON scanner, extracting fields without building whole document.
+//!
+//!Access to fields is enabled by `json` feature, while scanner itself is also used by `Store::import_plaintext_json`.
+#![cfg_attr(not(feature = "json"), allow(dead_code))]
+
+#[cfg(feature = "json")]
+use crate::{enc, Backend, Store};
+
+///Limit of nesting, protecting against stack exhaustion.
+const MAX_DEPTH: usize = 128;
+
+#[inline]
+pub(crate) fn skip_ws(input: &[u8], mut pos: usize) -> usize {
+    while matches!(input.get(pos), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+        pos += 1;
+    }
+    pos
+}
+
+#[inline]
+fn hex(input: &[u8], pos: usize) -> Option<u32> {
+    let digits = core::str::from_utf8(input.get(pos..pos + 4)?).ok()?;
+    u32::from_str_radix(digits, 16).ok()
+}
+
+///Parses string starting at `pos`, returning position after it and, if `out` is provided, unescaped content.
+pub(crate) fn parse_string(input: &[u8], mut pos: usize, mut out: Option<&mut Vec<u8>>) -> Option<usize> {
+    if input.get(pos) != Some(&b'"') {
+        return None;
+    }
+    pos += 1;
+
+    loop {
+        let byte = *input.get(pos)?;
+        pos += 1;
+        match byte {
+            b'"' => return Some(pos),
+            b'\\' => {
+                let escaped = *input.get(pos)?;
+                pos += 1;
+                let unescaped = match escaped {
+                    b'"' => b'"',
+                    b'\\' => b'\\',
+                    b'/' => b'/',
+                    b'b' => 8,
+                    b'f' => 12,
+                    b'n' => b'\n',
+                    b'r' => b'\r',
+                    b't' => b'\t',
+                    b'u' => {
+                        let mut code = hex(input, pos)?;
+                        pos += 4;
+                        if (0xD800..0xDC00).contains(&code) && input.get(pos..pos + 2) == Some(b"\\u") {
+                            let low = hex(input, pos + 2)?;
+                            if (0xDC00..0xE000).contains(&low) {
+                                code = 0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00);
+                                pos += 6;
+                            }
+                        }
+                        let ch = char::from_u32(code)?;
+                        if let Some(out) = out.as_mut() {
+                            let mut buffer = [0u8; 4];
+                            out.extend_from_slice(ch.encode_utf8(&mut buffer).as_bytes());
+                        }
+                        continue;
+                    },
+                    _ => return None,
+                };
+                if let Some(out) = out.as_mut() {
+                    out.push(unescaped);
+                }
+            },
+            0..=0x1f => return None,
+            byte => {
+                if let Some(out) = out.as_mut() {
+                    out.push(byte);
+                }
+            },
+        }
+    }
+}
+
+fn skip_number(input: &[u8], mut pos: usize) -> Option<usize> {
+    let start = pos;
+    if input.get(pos) == Some(&b'-') {
+        pos += 1;
+    }
+    let digits = pos;
+    while matches!(input.get(pos), Some(b'0'..=b'9')) {
+        pos += 1;
+    }
+    if pos == digits {
+        return None;
+    }
+    if input.get(pos) == Some(&b'.') {
+        pos += 1;
+        let fraction = pos;
+        while matches!(input.get(pos), Some(b'0'..=b'9')) {
+            pos += 1;
+        }
+        if pos == fraction {
+            return None;
+        }
+    }
+    if matches!(input.get(pos), Some(b'e' | b'E')) {
+        pos += 1;
+        if matches!(input.get(pos), Some(b'+' | b'-')) {
+            pos += 1;
+        }
+        let exponent = pos;
+        while matches!(input.get(pos), Some(b'0'..=b'9')) {
+            pos += 1;
+        }
+        if pos == exponent {
+            return None;
+        }
+    }
+    match pos > start {
+        true => Some(pos),
+        false => None,
+    }
+}
+
+///Iterates over members of object or elements of array at `pos`, invoking `member` with key and position of value.
+///
+///Callback returns position after value, or `Err` to stop iteration with specified result.
+pub(crate) fn for_each<F: FnMut(Option<&[u8]>, usize) -> Result<usize, Option<usize>>>(input: &[u8], pos: usize, mut member: F) -> Result<usize, Option<usize>> {
+    let (is_object, close) = match input.get(pos) {
+        Some(b'{') => (true, b'}'),
+        Some(b'[') => (false, b']'),
+        _ => return Err(None),
+    };
+
+    let mut pos = skip_ws(input, pos + 1);
+    if input.get(pos) == Some(&close) {
+        return Ok(pos + 1);
+    }
+
+    let mut key = Vec::new();
+    loop {
+        if is_object {
+            key.truncate(0);
+            pos = parse_string(input, pos, Some(&mut key)).ok_or(None)?;
+            pos = skip_ws(input, pos);
+            if input.get(pos) != Some(&b':') {
+                return Err(None);
+            }
+            pos = skip_ws(input, pos + 1);
+        }
+
+        pos = member(match is_object {
+            true => Some(&key),
+            false => None,
+        }, pos)?;
+        pos = skip_ws(input, pos);
+        match input.get(pos) {
+            Some(b',') => pos = skip_ws(input, pos + 1),
+            Some(byte) if *byte == close => return Ok(pos + 1),
+            _ => return Err(None),
+        }
+    }
+}
+
+///Skips value at `pos`, returning position after it.
+fn skip_value(input: &[u8], pos: usize, depth: usize) -> Option<usize> {
+    match input.get(pos)? {
+        b'"' => parse_string(input, pos, None),
+        b'{' | b'[' if depth < MAX_DEPTH => for_each(input, pos, |_, pos| skip_value(input, pos, depth + 1).ok_or(None)).ok(),
+        b't' if input[pos..].starts_with(b"true") => Some(pos + 4),
+        b'f' if input[pos..].starts_with(b"false") => Some(pos + 5),
+        b'n' if input[pos..].starts_with(b"null") => Some(pos + 4),
+        _ => skip_number(input, pos),
+    }
+}
+
+///Returns whether `input` is single valid JSON document.
+pub(crate) fn is_valid(input: &[u8]) -> bool {
+    let pos = skip_ws(input, 0);
+    match skip_value(input, pos, 0) {
+        Some(end) => skip_ws(input, end) == input.len(),
+        None => false,
+    }
+}
+
+///Finds value under `path` within `input`, returning its range.
+///
+///Path is made of object keys and array indexes, separated by `.`, while empty path refers to whole document.
+pub(crate) fn find(input: &[u8], path: &str) -> Option<core::ops::Range<usize>> {
+    if !is_valid(input) {
+        return None;
+    }
+
+    let mut pos = skip_ws(input, 0);
+    if !path.is_empty() {
+        for segment in path.split('.') {
+            let index = segment.parse::<usize>().ok();
+            let mut current = 0;
+            let found = for_each(input, pos, |key, value| {
+                let is_match = match key {
+                    Some(key) => key == segment.as_bytes(),
+                    None => {
+                        current += 1;
+                        index == Some(current - 1)
+                    },
+                };
+                match is_match {
+                    true => Err(Some(value)),
+                    false => skip_value(input, value, 0).ok_or(None),
+                }
+            });
+            pos = match found {
+                Err(Some(value)) => value,
+                _ => return None,
+            };
+        }
+    }
+
+    let end = skip_value(input, pos, 0)?;
+    Some(pos..end)
+}
+
+///Unescapes JSON string `value`, returning `None` if it is not a string.
+pub(crate) fn unescape(value: &[u8]) -> Option<Vec<u8>> {
+    let mut result = Vec::with_capacity(value.len());
+    match parse_string(value, 0, Some(&mut result)) {
+        Some(end) if end == value.len() => Some(result),
+        _ => None,
+    }
+}
+
+#[cfg(feature = "json")]
+impl<B: Backend> Store<B> {
+    ///Retrieves field under `path` of JSON document, stored under `key`, as JSON text.
+    ///
+    ///Path is made of object keys and array indexes, separated by `.` (e.g. `a.b.0`).
+    ///Only extracted field is copied out of decrypted document, which is wiped right away.
+    ///
+    ///Returns `None` if value cannot be decrypted, is not valid JSON or has no such field.
+    pub fn get_field(&self, key: &[u8], path: &str) -> Option<Vec<u8>> {
+        let mut document = Vec::new();
+        self.get_to_vec(key, &mut document).ok()?;
+        let result = find(&document, path).map(|range| document[range].to_owned());
+        enc::wipe(&mut document);
+        result
+    }
+
+    ///Retrieves string field under `path` of JSON document, stored under `key`, unescaping it.
+    ///
+    ///Returns `None` if field is not a string, refer to `Self::get_field` for details.
+    pub fn get_field_str(&self, key: &[u8], path: &str) -> Option<String> {
+        let mut field = self.get_field(key, path)?;
+        let result = unescape(&field).and_then(|value| String::from_utf8(value).ok());
+        enc::wipe(&mut field);
+        result
+    }
+
+    ///Inserts JSON `document` for `key`, returning previous value, if any.
+    ///
+    ///Returns `Err` if `document` is not valid JSON, leaving store untouched.
+    ///Panics if value doesn't fit store's limits, refer to `Self::try_insert`.
+    pub fn insert_json(&mut self, key: &[u8], document: &str) -> Result<Option<Vec<u8>>, ()> {
+        match is_valid(document.as_bytes()) {
+            true => Ok(self.insert(key, document.as_bytes())),
+            false => Err(()),
+        }
+    }
+}
@@ -0,0 +1,774 @@
+//!Importer of KeePass databases in KDBX 4 format.
+//!
+//!Composite key is expected to consist of password only, key files are not supported.
+
+use crate::{base64, enc, Backend, Error, Store};
+
+use std::io;
+use std::io::Read;
+use ring::{digest, hmac};
+use aes::cipher::block_padding::Pkcs7;
+use aes::cipher::generic_array::GenericArray;
+use aes::cipher::{BlockDecryptMut, BlockEncrypt, KeyInit, KeyIvInit, StreamCipher};
+use aes::Aes256;
+use chacha20::ChaCha20;
+use salsa20::Salsa20;
+
+const SIGNATURE: [u8; 8] = [0x03, 0xd9, 0xa2, 0x9a, 0x67, 0xfb, 0x4b, 0xb5];
+const MAJOR_VERSION: u16 = 4;
+
+const CIPHER_AES256: [u8; 16] = [0x31, 0xc1, 0xf2, 0xe6, 0xbf, 0x71, 0x43, 0x50, 0xbe, 0x58, 0x05, 0x21, 0x6a, 0xfc, 0x5a, 0xff];
+const CIPHER_CHACHA20: [u8; 16] = [0xd6, 0x03, 0x8a, 0x2b, 0x8b, 0x6f, 0x4c, 0xb5, 0xa5, 0x24, 0x33, 0x9a, 0x31, 0xdb, 0xb5, 0x9a];
+const KDF_AES: [u8; 16] = [0xc9, 0xd9, 0xf3, 0x9a, 0x62, 0x8a, 0x44, 0x60, 0xbf, 0x74, 0x0d, 0x08, 0xc1, 0x8a, 0x4f, 0xea];
+///AES-KDF, as identified by KeePassXC for KDBX 4.
+const KDF_AES_KDBX4: [u8; 16] = [0x7c, 0x02, 0xbb, 0x82, 0x79, 0xa7, 0x4a, 0xc0, 0x92, 0x7d, 0x11, 0x4a, 0x00, 0x64, 0x82, 0x38];
+const KDF_ARGON2D: [u8; 16] = [0xef, 0x63, 0x6d, 0xdf, 0x8c, 0x29, 0x44, 0x4b, 0x91, 0xf7, 0xa9, 0xa4, 0x03, 0xe3, 0x0a, 0x0c];
+const KDF_ARGON2ID: [u8; 16] = [0x9e, 0x29, 0x8b, 0x19, 0x56, 0xdb, 0x47, 0x73, 0xb2, 0x3d, 0xfc, 0x3e, 0xc6, 0xf0, 0xa1, 0xe6];
+
+const STREAM_SALSA20: u32 = 2;
+const STREAM_CHACHA20: u32 = 3;
+const SALSA20_NONCE: [u8; 8] = [0xe8, 0x30, 0x09, 0x4b, 0x97, 0x20, 0x5d, 0x2a];
+
+///Maximum number of AES-KDF rounds, well above what KeePass picks for a second of transformation.
+const MAX_AES_ROUNDS: u64 = 1 << 28;
+///Maximum memory of Argon2 in KiB (1 GiB).
+const MAX_ARGON2_MEMORY: u64 = 1 << 20;
+///Maximum memory of Argon2 in KiB, multiplied by number of iterations (16 passes over 1 GiB).
+const MAX_ARGON2_WORK: u64 = 1 << 24;
+///Maximum ratio of deflate compression, limiting size of decompressed payload.
+const MAX_DEFLATE_RATIO: usize = 1032;
+
+///Names of standard fields, mapped to keys within namespace.
+const FIELDS: [(&[u8], &[u8]); 5] = [
+    (b"Title", b"title"),
+    (b"UserName", b"username"),
+    (b"Password", b"password"),
+    (b"URL", b"url"),
+    (b"Notes", b"notes"),
+];
+
+#[inline]
+fn invalid(message: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[inline]
+fn read_u32(input: &[u8]) -> Option<u32> {
+    let mut value = [0u8; 4];
+    value.copy_from_slice(input.get(..4)?);
+    Some(u32::from_le_bytes(value))
+}
+
+#[inline]
+fn read_u64(input: &[u8]) -> Option<u64> {
+    let mut value = [0u8; 8];
+    value.copy_from_slice(input.get(..8)?);
+    Some(u64::from_le_bytes(value))
+}
+
+///Parses field of header as `id: u8 | len: u32 | value`, returning it along with rest of input.
+fn read_field(input: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (id, input) = input.split_first()?;
+    let len = read_u32(input)? as usize;
+    let input = &input[4..];
+    match input.len() >= len {
+        true => Some((*id, &input[..len], &input[len..])),
+        false => None,
+    }
+}
+
+///Looks up value of `name` within serialized `VariantDictionary`, returning its type and value.
+fn variant<'a>(mut dict: &'a [u8], name: &[u8]) -> Option<(u8, &'a [u8])> {
+    match dict.get(..2) {
+        //Only major version is relevant.
+        Some([_, 1]) => dict = &dict[2..],
+        _ => return None,
+    }
+
+    loop {
+        let (kind, rest) = dict.split_first()?;
+        if *kind == 0 {
+            return None;
+        }
+
+        let len = read_u32(rest)? as usize;
+        let key = rest.get(4..4 + len)?;
+        let rest = &rest[4 + len..];
+        let len = read_u32(rest)? as usize;
+        let value = rest.get(4..4 + len)?;
+        if key == name {
+            return Some((*kind, value));
+        }
+        dict = &rest[4 + len..];
+    }
+}
+
+#[inline]
+fn variant_u32(dict: &[u8], name: &[u8]) -> Option<u32> {
+    match variant(dict, name)? {
+        (0x04, value) if value.len() == 4 => read_u32(value),
+        _ => None,
+    }
+}
+
+#[inline]
+fn variant_u64(dict: &[u8], name: &[u8]) -> Option<u64> {
+    match variant(dict, name)? {
+        (0x05, value) if value.len() == 8 => read_u64(value),
+        _ => None,
+    }
+}
+
+#[inline]
+fn variant_bytes<'a>(dict: &'a [u8], name: &[u8]) -> Option<&'a [u8]> {
+    match variant(dict, name)? {
+        (0x42, value) => Some(value),
+        _ => None,
+    }
+}
+
+///Transforms composite key according to KDF parameters.
+fn transform_key(params: &[u8], key: &[u8; 32]) -> io::Result<[u8; 32]> {
+    let uuid = variant_bytes(params, b"$UUID").ok_or_else(|| invalid("KDBX lacks KDF"))?;
+    let mut result = [0u8; 32];
+
+    if uuid == KDF_AES || uuid == KDF_AES_KDBX4 {
+        let rounds = variant_u64(params, b"R").ok_or_else(|| invalid("KDBX has invalid AES-KDF parameters"))?;
+        if rounds > MAX_AES_ROUNDS {
+            return Err(invalid("KDBX has AES-KDF parameters above supported limits"));
+        }
+        let seed = match variant_bytes(params, b"S") {
+            Some(seed) if seed.len() == 32 => seed,
+            _ => return Err(invalid("KDBX has invalid AES-KDF parameters")),
+        };
+
+        let cipher = Aes256::new(GenericArray::from_slice(seed));
+        let mut blocks = [GenericArray::default(); 2];
+        blocks[0].copy_from_slice(&key[..16]);
+        blocks[1].copy_from_slice(&key[16..]);
+        for _ in 0..rounds {
+            cipher.encrypt_blocks(&mut blocks);
+        }
+
+        let mut hasher = digest::Context::new(&digest::SHA256);
+        hasher.update(&blocks[0]);
+        hasher.update(&blocks[1]);
+        result.copy_from_slice(hasher.finish().as_ref());
+        for block in blocks.iter_mut() {
+            enc::wipe(block);
+        }
+    } else if uuid == KDF_ARGON2D || uuid == KDF_ARGON2ID {
+        let kind = match uuid == KDF_ARGON2D {
+            true => argon2::Algorithm::Argon2d,
+            false => argon2::Algorithm::Argon2id,
+        };
+        let memory = variant_u64(params, b"M").map(|memory| memory / 1024);
+        match (memory, variant_u64(params, b"I")) {
+            (Some(memory), Some(iterations)) if memory > MAX_ARGON2_MEMORY || memory.saturating_mul(iterations) > MAX_ARGON2_WORK => {
+                return Err(invalid("KDBX has Argon2 parameters above supported limits"));
+            },
+            _ => (),
+        }
+        let salt = variant_bytes(params, b"S");
+        let argon = match (variant_u32(params, b"V"), memory, variant_u64(params, b"I"), variant_u32(params, b"P"), salt) {
+            (Some(version @ (0x10 | 0x13)), Some(memory), Some(iterations @ 1..=0xFFFF_FFFF), Some(lanes @ 1..=0xFF_FFFF), Some(salt)) if memory >= 8 * lanes as u64 && memory <= u32::MAX as u64 && salt.len() >= 8 => {
+                let version = match version {
+                    0x10 => argon2::Version::V0x10,
+                    _ => argon2::Version::V0x13,
+                };
+                let secret = variant_bytes(params, b"K").unwrap_or(&[]);
+                let data = variant_bytes(params, b"A").unwrap_or(&[]);
+                let argon = argon2::AssociatedData::new(data).and_then(|data| {
+                    argon2::ParamsBuilder::new().m_cost(memory as u32).t_cost(iterations as u32).p_cost(lanes).data(data).output_len(result.len()).build()
+                });
+                match argon.and_then(|argon| argon2::Argon2::new_with_secret(secret, kind, version, argon)) {
+                    Ok(argon) => (argon, salt),
+                    Err(_) => return Err(invalid("KDBX has invalid Argon2 parameters")),
+                }
+            },
+            _ => return Err(invalid("KDBX has invalid Argon2 parameters")),
+        };
+        if argon.0.hash_password_into(key, argon.1, &mut result).is_err() {
+            return Err(invalid("KDBX has invalid Argon2 parameters"));
+        }
+    } else {
+        return Err(invalid("KDBX uses unsupported KDF"));
+    }
+
+    Ok(result)
+}
+
+#[inline]
+///Returns HMAC key of block with `index`.
+fn block_key(base: &[u8], index: u64) -> hmac::Key {
+    let mut hasher = digest::Context::new(&digest::SHA512);
+    hasher.update(&index.to_le_bytes());
+    hasher.update(base);
+    hmac::Key::new(hmac::HMAC_SHA256, hasher.finish().as_ref())
+}
+
+///Decrypts payload of KDBX 4 database, returning inner header and XML document.
+fn decrypt(data: &[u8], password: &[u8]) -> io::Result<Vec<u8>> {
+    match (data.get(..8), data.get(10..12)) {
+        (Some(signature), Some(major)) if signature == SIGNATURE => match u16::from_le_bytes([major[0], major[1]]) {
+            MAJOR_VERSION => (),
+            _ => return Err(invalid("Unsupported version of KDBX")),
+        },
+        _ => return Err(invalid("Not a KDBX file")),
+    }
+
+    let mut cipher = None;
+    let mut compressed = false;
+    let mut seed = None;
+    let mut iv = None;
+    let mut params = None;
+    let mut rest = &data[12..];
+    loop {
+        let (id, value, next) = read_field(rest).ok_or_else(|| invalid("KDBX header is truncated"))?;
+        rest = next;
+        match id {
+            0 => break,
+            2 => cipher = Some(value),
+            3 => compressed = read_u32(value) == Some(1),
+            4 if value.len() == 32 => seed = Some(value),
+            7 => iv = Some(value),
+            11 => params = Some(value),
+            _ => (),
+        }
+    }
+
+    let header = &data[..data.len() - rest.len()];
+    let (hash, mac) = match rest.get(..64) {
+        Some(rest) => rest.split_at(32),
+        None => return Err(invalid("KDBX header is truncated")),
+    };
+    if digest::digest(&digest::SHA256, header).as_ref() != hash {
+        return Err(invalid("KDBX header is corrupted"));
+    }
+    let (cipher, seed, iv, params) = match (cipher, seed, iv, params) {
+        (Some(cipher), Some(seed), Some(iv), Some(params)) => (cipher, seed, iv, params),
+        _ => return Err(invalid("KDBX header lacks required fields")),
+    };
+
+    let mut key = [0u8; 32];
+    key.copy_from_slice(digest::digest(&digest::SHA256, digest::digest(&digest::SHA256, password).as_ref()).as_ref());
+    let mut transformed = transform_key(params, &key)?;
+    enc::wipe(&mut key);
+
+    let mut hasher = digest::Context::new(&digest::SHA256);
+    hasher.update(seed);
+    hasher.update(&transformed);
+    key.copy_from_slice(hasher.finish().as_ref());
+    let mut hasher = digest::Context::new(&digest::SHA512);
+    hasher.update(seed);
+    hasher.update(&transformed);
+    hasher.update(&[1]);
+    let mut mac_key = [0u8; 64];
+    mac_key.copy_from_slice(hasher.finish().as_ref());
+    enc::wipe(&mut transformed);
+
+    if hmac::verify(&block_key(&mac_key, u64::MAX), header, mac).is_err() {
+        enc::wipe(&mut key);
+        enc::wipe(&mut mac_key);
+        return Err(Error::WrongCredentials.into());
+    }
+
+    //Payload is split into blocks as `mac: [u8; 32] | len: u32 | data`, terminated by empty one.
+    let mut payload = Vec::new();
+    let mut rest = &rest[64..];
+    for index in 0.. {
+        let len = match rest.get(32..36).and_then(read_u32) {
+            Some(len) if rest.len() - 36 >= len as usize => len,
+            _ => {
+                payload.clear();
+                break;
+            },
+        };
+        let block = &rest[36..36 + len as usize];
+
+        let mut ctx = hmac::Context::with_key(&block_key(&mac_key, index));
+        ctx.update(&index.to_le_bytes());
+        ctx.update(&len.to_le_bytes());
+        ctx.update(block);
+        if !enc::ct_eq(ctx.sign().as_ref(), &rest[..32]) {
+            payload.clear();
+            break;
+        }
+
+        match len {
+            0 => break,
+            _ => payload.extend_from_slice(block),
+        }
+        rest = &rest[36 + len as usize..];
+    }
+    enc::wipe(&mut mac_key);
+
+    let result = if payload.is_empty() {
+        Err(invalid("KDBX payload is corrupted"))
+    } else if cipher == CIPHER_AES256 && iv.len() == 16 {
+        let cipher = cbc::Decryptor::<Aes256>::new(GenericArray::from_slice(&key), GenericArray::from_slice(iv));
+        match cipher.decrypt_padded_mut::<Pkcs7>(&mut payload) {
+            Ok(plain) => {
+                let len = plain.len();
+                enc::wipe(&mut payload[len..]);
+                payload.truncate(len);
+                Ok(())
+            },
+            Err(_) => Err(invalid("KDBX payload is corrupted")),
+        }
+    } else if cipher == CIPHER_CHACHA20 && iv.len() == 12 {
+        ChaCha20::new(GenericArray::from_slice(&key), GenericArray::from_slice(iv)).apply_keystream(&mut payload);
+        Ok(())
+    } else {
+        Err(invalid("KDBX uses unsupported cipher"))
+    };
+    enc::wipe(&mut key);
+
+    if let Err(error) = result {
+        enc::wipe(&mut payload);
+        return Err(error);
+    }
+
+    match compressed {
+        true => {
+            let result = gunzip(&payload).map_err(|_| invalid("KDBX payload is corrupted"));
+            enc::wipe(&mut payload);
+            result
+        },
+        false => Ok(payload),
+    }
+}
+
+///Decodes gzip member, verifying its checksum.
+///
+///Output is allocated upfront by size within trailer, so that decompressed secrets are never left within freed memory.
+fn gunzip(input: &[u8]) -> Result<Vec<u8>, ()> {
+    let size = match input.len().checked_sub(4).and_then(|start| input.get(start..)) {
+        Some(&[a, b, c, d]) => u32::from_le_bytes([a, b, c, d]) as usize,
+        _ => return Err(()),
+    };
+    if size > input.len().saturating_mul(MAX_DEFLATE_RATIO) {
+        return Err(());
+    }
+
+    let mut result = vec![0u8; size];
+    let mut decoder = flate2::bufread::GzDecoder::new(input);
+    //Reading past the end verifies checksum and that there is no more data than trailer states.
+    match decoder.read_exact(&mut result).and_then(|_| decoder.read(&mut [0u8])) {
+        Ok(0) => Ok(result),
+        _ => {
+            enc::wipe(&mut result);
+            Err(())
+        },
+    }
+}
+
+///Stream cipher, protecting values within XML document.
+enum InnerStream {
+    ChaCha20(ChaCha20),
+    Salsa20(Salsa20),
+}
+
+impl InnerStream {
+    #[inline]
+    ///Applies keystream to `data`, continuing from the end of previous data.
+    fn apply(&mut self, data: &mut [u8]) {
+        match self {
+            InnerStream::ChaCha20(cipher) => cipher.apply_keystream(data),
+            InnerStream::Salsa20(cipher) => cipher.apply_keystream(data),
+        }
+    }
+}
+
+///Creates stream cipher, protecting values within XML document, from inner header.
+fn inner_stream(id: u32, key: &[u8]) -> io::Result<InnerStream> {
+    match id {
+        STREAM_CHACHA20 => {
+            let mut hash = digest::digest(&digest::SHA512, key).as_ref().to_vec();
+            let result = ChaCha20::new(GenericArray::from_slice(&hash[..32]), GenericArray::from_slice(&hash[32..44]));
+            enc::wipe(&mut hash);
+            Ok(InnerStream::ChaCha20(result))
+        },
+        STREAM_SALSA20 => {
+            let mut hash = digest::digest(&digest::SHA256, key).as_ref().to_vec();
+            let result = Salsa20::new(GenericArray::from_slice(&hash), GenericArray::from_slice(&SALSA20_NONCE));
+            enc::wipe(&mut hash);
+            Ok(InnerStream::Salsa20(result))
+        },
+        _ => Err(invalid("KDBX uses unsupported protection of values")),
+    }
+}
+
+enum Event<'a> {
+    Start {
+        name: &'a [u8],
+        attrs: &'a [u8],
+        empty: bool,
+    },
+    End(&'a [u8]),
+    Text(&'a [u8]),
+    Data(&'a [u8]),
+}
+
+#[inline]
+fn find(input: &[u8], pos: usize, pattern: &[u8]) -> Option<usize> {
+    input.get(pos..)?.windows(pattern.len()).position(|window| window == pattern).map(|idx| pos + idx)
+}
+
+///Minimal pull parser of XML, skipping declarations and comments.
+struct Xml<'a> {
+    input: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Xml<'a> {
+    fn next(&mut self) -> Result<Option<Event<'a>>, ()> {
+        loop {
+            let input = self.input;
+            let rest = match input.get(self.pos..) {
+                Some(rest) if !rest.is_empty() => rest,
+                _ => return Ok(None),
+            };
+
+            if rest[0] != b'<' {
+                let end = find(input, self.pos, b"<").unwrap_or(input.len());
+                let text = &input[self.pos..end];
+                self.pos = end;
+                return Ok(Some(Event::Text(text)));
+            } else if rest.starts_with(b"<![CDATA[") {
+                let end = find(input, self.pos, b"]]>").ok_or(())?;
+                let data = &input[self.pos + 9..end];
+                self.pos = end + 3;
+                return Ok(Some(Event::Data(data)));
+            } else if rest.starts_with(b"<!--") {
+                self.pos = find(input, self.pos, b"-->").ok_or(())? + 3;
+                continue;
+            } else if rest.starts_with(b"<?") {
+                self.pos = find(input, self.pos, b"?>").ok_or(())? + 2;
+                continue;
+            } else if rest.starts_with(b"<!") {
+                self.pos = find(input, self.pos, b">").ok_or(())? + 1;
+                continue;
+            }
+
+            let mut quote = None;
+            let len = rest.iter().position(|byte| match quote {
+                Some(expected) => {
+                    if *byte == expected {
+                        quote = None;
+                    }
+                    false
+                },
+                None => match byte {
+                    b'"' | b'\'' => {
+                        quote = Some(*byte);
+                        false
+                    },
+                    byte => *byte == b'>',
+                },
+            }).ok_or(())?;
+            let mut tag = &rest[1..len];
+            self.pos += len + 1;
+
+            if let Some(name) = tag.strip_prefix(b"/") {
+                return Ok(Some(Event::End(trim(name))));
+            }
+
+            let empty = tag.last() == Some(&b'/');
+            if empty {
+                tag = &tag[..tag.len() - 1];
+            }
+            let split = tag.iter().position(|byte| byte.is_ascii_whitespace()).unwrap_or(tag.len());
+            return Ok(Some(Event::Start {
+                name: &tag[..split],
+                attrs: &tag[split..],
+                empty,
+            }));
+        }
+    }
+}
+
+#[inline]
+fn trim(input: &[u8]) -> &[u8] {
+    let start = input.iter().position(|byte| !byte.is_ascii_whitespace()).unwrap_or(input.len());
+    let end = input.iter().rposition(|byte| !byte.is_ascii_whitespace()).map(|end| end + 1).unwrap_or(start);
+    &input[start..end]
+}
+
+///Looks up value of attribute `name`, without unescaping it.
+fn attribute<'a>(mut attrs: &'a [u8], name: &[u8]) -> Option<&'a [u8]> {
+    loop {
+        attrs = trim(attrs);
+        let eq = attrs.iter().position(|byte| *byte == b'=')?;
+        let key = trim(&attrs[..eq]);
+        let rest = trim(&attrs[eq + 1..]);
+        let quote = *rest.first()?;
+        let end = rest[1..].iter().position(|byte| *byte == quote)? + 1;
+        if key == name {
+            return Some(&rest[1..end]);
+        }
+        attrs = &rest[end + 1..];
+    }
+}
+
+///Appends `text` to `out`, replacing references to characters.
+fn unescape(text: &[u8], out: &mut Vec<u8>) -> Result<(), ()> {
+    let mut rest = text;
+    while let Some(start) = rest.iter().position(|byte| *byte == b'&') {
+        out.extend_from_slice(&rest[..start]);
+        let end = rest[start..].iter().position(|byte| *byte == b';').ok_or(())? + start;
+        let ch = match &rest[start + 1..end] {
+            b"lt" => '<',
+            b"gt" => '>',
+            b"amp" => '&',
+            b"quot" => '"',
+            b"apos" => '\'',
+            reference => {
+                let code = match reference {
+                    [b'#', b'x', hex @ ..] => core::str::from_utf8(hex).ok().and_then(|hex| u32::from_str_radix(hex, 16).ok()),
+                    [b'#', dec @ ..] => core::str::from_utf8(dec).ok().and_then(|dec| dec.parse().ok()),
+                    _ => None,
+                };
+                code.and_then(char::from_u32).ok_or(())?
+            },
+        };
+        let mut buffer = [0u8; 4];
+        out.extend_from_slice(ch.encode_utf8(&mut buffer).as_bytes());
+        rest = &rest[end + 1..];
+    }
+    out.extend_from_slice(rest);
+    Ok(())
+}
+
+#[derive(Default)]
+struct Group {
+    name: Vec<u8>,
+    uuid: Vec<u8>,
+}
+
+///Entry of database, excluding its history.
+struct Entry {
+    ///Path of groups, excluding root one.
+    path: Vec<u8>,
+    uuid: Vec<u8>,
+    fields: Vec<(Vec<u8>, Vec<u8>)>,
+}
+
+impl Drop for Entry {
+    fn drop(&mut self) {
+        for (_, value) in self.fields.iter_mut() {
+            enc::wipe(value);
+        }
+    }
+}
+
+///Extracts entries from XML document, decrypting protected values via `stream`.
+///
+///Entries of recycle bin are skipped.
+fn parse_entries(xml: &[u8], stream: &mut InnerStream) -> Result<Vec<Entry>, ()> {
+    let mut parser = Xml {
+        input: xml,
+        pos: 0,
+    };
+    let mut result = Vec::new();
+    let mut stack: Vec<(&[u8], bool)> = Vec::new();
+    let mut groups: Vec<Group> = Vec::new();
+    let mut entry: Option<(usize, Entry)> = None;
+    let mut recycle_bin = (true, Vec::new());
+    let mut recycled = None;
+    let mut text = Vec::new();
+    let mut field = (Vec::new(), Vec::new());
+
+    while let Some(event) = parser.next()? {
+        let name = match event {
+            Event::Start { name, attrs, empty } => {
+                text.clear();
+                let parent = stack.last().map(|(name, _)| *name);
+                match (name, parent) {
+                    (b"Group", Some(b"Root" | b"Group")) => groups.push(Group::default()),
+                    (b"Entry", Some(b"Group")) => {
+                        let path = groups.iter().skip(1).map(|group| group.name.as_slice()).collect::<Vec<_>>().join(&b'/');
+                        entry = Some((stack.len() + 1, Entry {
+                            path,
+                            uuid: Vec::new(),
+                            fields: Vec::new(),
+                        }));
+                    },
+                    _ => (),
+                }
+
+                stack.push((name, attribute(attrs, b"Protected") == Some(b"True")));
+                match empty {
+                    true => name,
+                    false => continue,
+                }
+            },
+            Event::Text(value) => {
+                unescape(value, &mut text)?;
+                continue;
+            },
+            Event::Data(value) => {
+                text.extend_from_slice(value);
+                continue;
+            },
+            Event::End(name) => name,
+        };
+
+        let protected = match stack.pop() {
+            Some((start, protected)) if start == name => protected,
+            _ => return Err(()),
+        };
+        //Protected values are encrypted by single stream in order of appearance, including history.
+        if protected {
+            let mut value = base64::decode(&text).ok_or(())?;
+            stream.apply(&mut value);
+            enc::wipe(&mut text);
+            text = value;
+        }
+
+        let parent = stack.last().map(|(name, _)| *name);
+        let depth = stack.len();
+        match (name, parent) {
+            (b"RecycleBinEnabled", Some(b"Meta")) => recycle_bin.0 = trim(&text).eq_ignore_ascii_case(b"true"),
+            (b"RecycleBinUUID", Some(b"Meta")) => recycle_bin.1 = base64::decode(&text).ok_or(())?,
+            (b"Name", Some(b"Group")) => if let Some(group) = groups.last_mut() {
+                group.name = text.clone();
+            },
+            (b"UUID", Some(b"Group")) => if let Some(group) = groups.last_mut() {
+                group.uuid = base64::decode(&text).ok_or(())?;
+                if recycled.is_none() && recycle_bin.0 && !recycle_bin.1.is_empty() && group.uuid == recycle_bin.1 {
+                    recycled = Some(groups.len());
+                }
+            },
+            (b"Group", Some(b"Root" | b"Group")) => {
+                if recycled == Some(groups.len()) {
+                    recycled = None;
+                }
+                groups.pop();
+            },
+            (b"UUID", Some(b"Entry")) => if let Some((entry_depth, entry)) = entry.as_mut() {
+                if *entry_depth == depth {
+                    entry.uuid = base64::decode(&text).ok_or(())?;
+                }
+            },
+            (b"Key", Some(b"String")) => if matches!(entry, Some((entry_depth, _)) if entry_depth + 1 == depth) {
+                field.0 = text.clone();
+            },
+            (b"Value", Some(b"String")) => if matches!(entry, Some((entry_depth, _)) if entry_depth + 1 == depth) {
+                enc::wipe(&mut field.1);
+                field.1 = text.clone();
+            },
+            (b"String", Some(b"Entry")) => if let Some((entry_depth, entry)) = entry.as_mut() {
+                if *entry_depth == depth {
+                    entry.fields.push(core::mem::take(&mut field));
+                }
+            },
+            (b"Entry", Some(b"Group")) => if let Some((_, entry)) = entry.take() {
+                if recycled.is_none() {
+                    result.push(entry);
+                }
+            },
+            _ => (),
+        }
+        enc::wipe(&mut text);
+    }
+
+    match stack.is_empty() {
+        true => Ok(result),
+        false => Err(()),
+    }
+}
+
+#[inline]
+fn hex(bytes: &[u8]) -> Vec<u8> {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+    bytes.iter().flat_map(|byte| [DIGITS[(byte >> 4) as usize], DIGITS[(byte & 0xf) as usize]]).collect()
+}
+
+///Reads entries of KDBX 4 database.
+fn read(data: &[u8], password: &[u8]) -> io::Result<Vec<Entry>> {
+    let mut payload = decrypt(data, password)?;
+
+    let mut stream = None;
+    let mut rest = payload.as_slice();
+    let mut stream_id = None;
+    loop {
+        let (id, value, next) = read_field(rest).ok_or_else(|| invalid("KDBX inner header is truncated"))?;
+        rest = next;
+        match id {
+            0 => break,
+            1 => stream_id = read_u32(value),
+            2 => stream = Some(inner_stream(stream_id.ok_or_else(|| invalid("KDBX inner header is malformed"))?, value)?),
+            _ => (),
+        }
+    }
+
+    let result = match stream.as_mut() {
+        Some(stream) => parse_entries(rest, stream).map_err(|_| invalid("KDBX contains malformed XML")),
+        None => Err(invalid("KDBX inner header is malformed")),
+    };
+    enc::wipe(&mut payload);
+    result
+}
+
+impl<B: Backend + Clone> Store<B> {
+    ///Imports entries of KeePass database in KDBX 4 format, protected by `password`,
+    ///returning names of namespaces, which they are written into.
+    ///
+    ///Each entry is written into namespace, named by path of its groups, excluding root one, and its title, separated by `/`.
+    ///If several entries share the same name, all but first one get their UUID, as hex string, appended after `#`.
+    ///Standard fields are written under `title`, `username`, `password`, `url` and `notes` keys,
+    ///while custom ones are written under their own names.
+    ///Fields with empty value are skipped, as store doesn't allow empty values.
+    ///History, attachments and content of recycle bin are not imported.
+    ///
+    ///Database is decoded entirely before writing anything, and store is rolled back if any entry cannot be written.
+    ///
+    ///Parameters of key derivation are bounded, so that crafted database cannot exhaust memory or time:
+    ///Argon2 is limited to 1 GiB of memory and 16 passes over it, while AES-KDF is limited to `2^28` rounds.
+    ///
+    ///Returns error when:
+    ///
+    ///- `Error::WrongCredentials` - `password` doesn't match database.
+    ///- `Error::Locked` - store is locked.
+    ///- `Error::LimitExceeded` - entries don't fit store's limits.
+    ///- `io::ErrorKind::InvalidData` - database is corrupted, uses unsupported features or exceeds limits of key derivation.
+    pub fn import_kdbx<R: io::Read>(&mut self, mut reader: R, password: &[u8]) -> io::Result<Vec<Vec<u8>>> {
+        if self.locked {
+            return Err(Error::Locked.into());
+        }
+
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let entries = read(&data, password)?;
+
+        let mut result: Vec<Vec<u8>> = Vec::with_capacity(entries.len());
+        let snapshot = self.snapshot();
+        self.suspend_autosave();
+        for entry in entries.iter() {
+            let title = entry.fields.iter().find(|(key, _)| key == b"Title").map(|(_, value)| value.as_slice()).unwrap_or(&[]);
+            let mut name = match entry.path.is_empty() {
+                true => title.to_vec(),
+                false => [entry.path.as_slice(), title].join(&b'/'),
+            };
+            if result.contains(&name) {
+                name.push(b'#');
+                name.extend_from_slice(&hex(&entry.uuid));
+            }
+
+            let mut namespace = self.namespace(&name);
+            for (key, value) in entry.fields.iter().filter(|(_, value)| !value.is_empty()) {
+                let key = FIELDS.iter().find(|(field, _)| field == key).map(|(_, key)| *key).unwrap_or(key);
+                if let Err(error) = namespace.try_insert(key, value) {
+                    self.restore(snapshot);
+                    self.resume_autosave();
+                    return Err(error.into());
+                }
+            }
+            result.push(name);
+        }
+        self.resume_autosave();
+
+        Ok(result)
+    }
+}
@@ -0,0 +1,160 @@
+use crate::{enc, Backend, Error, MasterKey, Store, KDF_KEY};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+///Parameters of scrypt.
+pub struct ScryptParams {
+    log_n: u8,
+    r: u32,
+    p: u32,
+}
+
+impl ScryptParams {
+    ///Creates parameters with cost `2^log_n`, block size `r` and parallelism `p`.
+    ///
+    ///Derivation takes `128 * r * 2^log_n` bytes of memory.
+    ///Returns `None` if parameters are out of range, allowing `log_n` up to `30` and below `16 * r`.
+    pub fn new(log_n: u8, r: u32, p: u32) -> Option<Self> {
+        let is_valid = log_n > 0 && log_n <= 30 && r > 0 && p > 0
+                       && (log_n as u64) < 16 * r as u64
+                       && (r as u64) * (p as u64) < 1 << 30
+                       && (128 * r as usize).checked_mul(1 << log_n).is_some();
+        match is_valid {
+            true => Some(Self { log_n, r, p }),
+            false => None,
+        }
+    }
+
+    #[inline]
+    ///Returns binary logarithm of cost.
+    pub fn log_n(&self) -> u8 {
+        self.log_n
+    }
+
+    #[inline]
+    ///Returns block size.
+    pub fn r(&self) -> u32 {
+        self.r
+    }
+
+    #[inline]
+    ///Returns parallelism.
+    pub fn p(&self) -> u32 {
+        self.p
+    }
+}
+
+impl Default for ScryptParams {
+    #[inline]
+    ///Creates parameters recommended for interactive use: cost `2^15`, block size `8` and parallelism `1`.
+    fn default() -> Self {
+        Self {
+            log_n: 15,
+            r: 8,
+            p: 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+///Function deriving encryption key from credentials.
+///
+///Function, other than default, is persisted in storage alongside its header,
+///so it is picked automatically when storage is opened with credentials.
+pub enum Kdf {
+    ///PBKDF2-HMAC-SHA256 with `1000` iterations, salted with user.
+    Pbkdf2,
+    ///Memory-hard scrypt, salted with user.
+    Scrypt(ScryptParams),
+}
+
+impl Default for Kdf {
+    #[inline]
+    fn default() -> Self {
+        Kdf::Pbkdf2
+    }
+}
+
+const SCRYPT: u8 = 1;
+const SCRYPT_LEN: usize = 10;
+
+impl Kdf {
+    ///Derives key from credentials.
+    pub(crate) fn derive(&self, user: &[u8], pass: &[u8]) -> [u8; 32] {
+        match self {
+            Kdf::Pbkdf2 => enc::generate_key(user, pass),
+            Kdf::Scrypt(params) => {
+                let mut out = [0u8; 32];
+                enc::scrypt(pass, user, params.log_n, params.r, params.p, &mut out);
+                out
+            },
+        }
+    }
+
+    ///Encodes function as internal entry, with default one not being persisted.
+    pub(crate) fn to_entry(&self) -> Option<Vec<u8>> {
+        match self {
+            Kdf::Pbkdf2 => None,
+            Kdf::Scrypt(params) => {
+                let mut result = Vec::with_capacity(SCRYPT_LEN);
+                result.push(SCRYPT);
+                result.push(params.log_n);
+                result.extend_from_slice(&params.r.to_le_bytes());
+                result.extend_from_slice(&params.p.to_le_bytes());
+                Some(result)
+            },
+        }
+    }
+
+    ///Decodes function out of internal `entry`, returning `None` if it is malformed.
+    pub(crate) fn from_entry(entry: Option<&[u8]>) -> Option<Self> {
+        match entry {
+            None => Some(Kdf::Pbkdf2),
+            Some(entry) if entry.len() == SCRYPT_LEN && entry[0] == SCRYPT => {
+                let r = u32::from_le_bytes([entry[2], entry[3], entry[4], entry[5]]);
+                let p = u32::from_le_bytes([entry[6], entry[7], entry[8], entry[9]]);
+                ScryptParams::new(entry[1], r, p).map(Kdf::Scrypt)
+            },
+            Some(_) => None,
+        }
+    }
+
+    #[inline]
+    ///Decodes function of `backend`, falling back to default one if it is malformed.
+    pub(crate) fn of<B: Backend>(backend: &B) -> Self {
+        Self::from_entry(backend.get(KDF_KEY)).unwrap_or_default()
+    }
+}
+
+impl MasterKey {
+    ///Derives key from credentials using specified `kdf`.
+    ///
+    ///Returns `Error::InvalidCredentials` if `user` or `pass` is empty.
+    pub fn derive_with_kdf(user: &[u8], pass: &[u8], kdf: Kdf) -> Result<Self, Error> {
+        if user.is_empty() || pass.is_empty() {
+            return Err(Error::InvalidCredentials);
+        }
+
+        Ok(Self::from_bytes(kdf.derive(user, pass)))
+    }
+}
+
+impl<B: Backend> Store<B> {
+    ///Creates new instance within empty `backend` using creds, with key derived via `kdf`.
+    ///
+    ///Returns `Error::InvalidCredentials` if `user` or `pass` is empty.
+    pub fn try_new_in_with_kdf(backend: B, user: &[u8], pass: &[u8], kdf: Kdf) -> Result<Self, Error> {
+        let mut result = Self::new_in_with_key(backend, &MasterKey::derive_with_kdf(user, pass, kdf)?);
+        if let Some(entry) = kdf.to_entry() {
+            result.inner.insert(KDF_KEY, entry);
+        }
+        Ok(result)
+    }
+
+    #[inline]
+    ///Returns function, used to derive key from credentials.
+    ///
+    ///Note that store, created using master key, reports default function, regardless of how key was derived.
+    pub fn kdf(&self) -> Kdf {
+        Kdf::of(&self.inner)
+    }
+}
@@ -0,0 +1,209 @@
+use crate::{enc, format, Backend, Error, Store};
+
+use core::fmt;
+use std::collections::BTreeMap;
+use std::io;
+use std::path::Path;
+
+#[derive(Clone)]
+///Encryption key of store, derived from credentials.
+///
+///It allows to open store without credentials, by keeping key in secure location (e.g. platform keychain).
+///Anyone in possession of key has full access to store.
+///
+///Key is wiped from memory on drop.
+pub struct MasterKey([u8; 32]);
+
+impl MasterKey {
+    #[inline]
+    ///Creates key from raw bytes, previously obtained via `Self::as_bytes`.
+    pub fn from_bytes(bytes: [u8; 32]) -> Self {
+        Self(bytes)
+    }
+
+    ///Derives key from credentials, same as store does, using default `Kdf`.
+    ///
+    ///Key of store, created with other function, refer to `Store::kdf`, is derived via `Self::derive_with_kdf`.
+    ///
+    ///Returns `Error::InvalidCredentials` if `user` or `pass` is empty.
+    pub fn derive(user: &[u8], pass: &[u8]) -> Result<Self, Error> {
+        if user.is_empty() || pass.is_empty() {
+            return Err(Error::InvalidCredentials);
+        }
+
+        Ok(Self(enc::generate_key(user, pass)))
+    }
+
+    ///Derives key from credentials, reporting progress via `progress`.
+    ///
+    ///`progress` receives number of completed iterations of key derivation out of total,
+    ///being called on start and regularly until derivation is complete, so it can serve as heartbeat too.
+    ///Derived key can be used to open store via `Store::open_with_key`, as long as store uses default `Kdf`,
+    ///otherwise it is derived via `Self::derive_with_kdf`.
+    ///
+    ///Returns `Error::InvalidCredentials` if `user` or `pass` is empty.
+    pub fn derive_with_progress<F: FnMut(u32, u32)>(user: &[u8], pass: &[u8], progress: F) -> Result<Self, Error> {
+        if user.is_empty() || pass.is_empty() {
+            return Err(Error::InvalidCredentials);
+        }
+
+        Ok(Self(enc::generate_key_with_progress(user, pass, progress)))
+    }
+
+    ///Derives key from credentials, mixing in `secret` provided by hardware token.
+    ///
+    ///`secret` is expected to be token's response (e.g. YubiKey HMAC-SHA1 challenge-response or FIDO2 `hmac-secret`)
+    ///to the same challenge each time, so that store cannot be opened without both password and token.
+    ///Communication with YubiKey is provided by `YubiKey` with `yubikey` feature, otherwise it is up to the user.
+    ///
+    ///Returns `Error::InvalidCredentials` if `user`, `pass` or `secret` is empty.
+    pub fn derive_with_secret(user: &[u8], pass: &[u8], secret: &[u8]) -> Result<Self, Error> {
+        if secret.is_empty() {
+            return Err(Error::InvalidCredentials);
+        }
+
+        let key = Self::derive(user, pass)?;
+        Ok(Self(enc::mix_key(&key.0, secret)))
+    }
+
+    #[inline]
+    ///Accesses raw bytes of key.
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    #[inline]
+    ///Wraps key using external `wrapper`.
+    pub fn wrap<W: KeyWrap>(&self, wrapper: &W) -> Result<Vec<u8>, W::Error> {
+        wrapper.wrap(&self.0)
+    }
+
+    #[inline]
+    ///Unwraps key, previously wrapped via `Self::wrap`, using external `wrapper`.
+    pub fn unwrap<W: KeyWrap>(wrapped: &[u8], wrapper: &W) -> Result<Self, W::Error> {
+        wrapper.unwrap(wrapped).map(Self)
+    }
+}
+
+///External facility (e.g. HSM accessed via PKCS#11, refer to `Pkcs11Key`), that protects master key.
+///
+///Master key is only stored in wrapped form, and is unwrapped by facility when store is opened.
+///
+///Platform keystores, gated by biometrics, are integrated via `Biometric`.
+pub trait KeyWrap {
+    ///Error of facility.
+    type Error;
+
+    ///Wraps raw `key`, returning opaque bytes that can be stored alongside storage.
+    fn wrap(&self, key: &[u8; 32]) -> Result<Vec<u8>, Self::Error>;
+    ///Unwraps key, previously wrapped by `Self::wrap`.
+    fn unwrap(&self, wrapped: &[u8]) -> Result<[u8; 32], Self::Error>;
+}
+
+///Platform biometric authentication (e.g. Windows Hello, Touch ID or Android `BiometricPrompt`), that gates unlocking of store.
+///
+///It is implemented by application using platform SDK: key of platform keystore, that wraps master key,
+///is expected to be usable only after user is authenticated (e.g. Keychain item with biometric access control),
+///so that store can be opened without typing password, refer to `Store::open_biometric`.
+pub trait Biometric {
+    ///Error of authentication, e.g. user has cancelled prompt.
+    type Error;
+    ///Key of platform keystore, wrapping master key.
+    type Key: KeyWrap<Error = Self::Error>;
+
+    ///Prompts user to authenticate, displaying `reason`, and returns key of keystore once user is verified.
+    fn authenticate(&self, reason: &str) -> Result<Self::Key, Self::Error>;
+}
+
+impl fmt::Debug for MasterKey {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.write_str("MasterKey(..)")
+    }
+}
+
+impl Drop for MasterKey {
+    #[inline]
+    fn drop(&mut self) {
+        enc::wipe(&mut self.0);
+    }
+}
+
+impl<B: Backend> Store<B> {
+    #[inline]
+    ///Returns encryption key of store.
+    ///
+    ///Refer to `MasterKey` for details.
+    ///Returns `Error::Locked` if store is locked.
+    pub fn master_key(&self) -> Result<MasterKey, Error> {
+        match self.locked {
+            true => Err(Error::Locked),
+            false => Ok(MasterKey(*self.enc.key())),
+        }
+    }
+
+    ///Creates new instance within empty `backend` using encryption `key`.
+    pub fn new_in_with_key(backend: B, key: &MasterKey) -> Self {
+        Self::with_manager(backend, enc::Manager::new(key.0))
+    }
+
+    #[inline]
+    ///Creates new instance using provided storage and encryption `key`.
+    ///
+    ///Refer to `Self::from_backend` for details.
+    pub fn from_backend_with_key(inner: B, key: &MasterKey) -> Self {
+        Self::with_manager(inner, enc::Manager::new(key.0))
+    }
+
+    #[inline]
+    ///Creates new instance using provided storage and encryption `key`, validating it.
+    ///
+    ///Refer to `Self::try_from_backend` for details.
+    pub fn try_from_backend_with_key(inner: B, key: &MasterKey) -> Result<Self, Error> {
+        Self::validate_entries(&inner)?;
+        Self::with_manager(inner, enc::Manager::new(key.0)).validate()
+    }
+}
+
+impl<B: Backend> Store<B> {
+    ///Wraps encryption key of store by platform keystore, once user is authenticated via `biometric`.
+    ///
+    ///Wrapped key is expected to be kept alongside storage, for use with `Store::open_biometric`.
+    ///Returns `PermissionDenied` error if store is locked, refer to `Error::Locked`, or user is not authenticated.
+    pub fn wrap_biometric<A: Biometric>(&self, reason: &str, biometric: &A) -> io::Result<Vec<u8>> where A::Error: Into<Box<dyn std::error::Error + Send + Sync>> {
+        let master = self.master_key()?;
+        let wrapped = biometric.authenticate(reason).and_then(|key| master.wrap(&key));
+        wrapped.map_err(|error| io::Error::new(io::ErrorKind::PermissionDenied, error))
+    }
+}
+
+impl Store {
+    #[inline]
+    ///Creates new instance using encryption `key`.
+    ///
+    ///Refer to `MasterKey` for details.
+    pub fn with_key(key: &MasterKey) -> Self {
+        Self::new_in_with_key(BTreeMap::new(), key)
+    }
+
+    #[inline]
+    ///Opens storage, previously saved via `Self::save`, using encryption `key`.
+    ///
+    ///Refer to `Self::open` for details.
+    pub fn open_with_key<P: AsRef<Path>>(path: P, key: &MasterKey) -> io::Result<Self> {
+        let inner = format::read_file(path.as_ref())?;
+        Self::try_from_backend_with_key(inner, key).map_err(Into::into)
+    }
+
+    ///Opens storage, previously saved via `Self::save`, once user is authenticated via `biometric`.
+    ///
+    ///Encryption key is unwrapped from `wrapped`, produced by `Self::wrap_biometric`, without typing password.
+    ///Returns `PermissionDenied` error if user is not authenticated or key cannot be unwrapped.
+    ///Refer to `Self::open_with_key` for details.
+    pub fn open_biometric<P: AsRef<Path>, A: Biometric>(path: P, wrapped: &[u8], reason: &str, biometric: &A) -> io::Result<Self> where A::Error: Into<Box<dyn std::error::Error + Send + Sync>> {
+        let key = biometric.authenticate(reason).and_then(|key| MasterKey::unwrap(wrapped, &key));
+        let key = key.map_err(|error| io::Error::new(io::ErrorKind::PermissionDenied, error))?;
+        let inner = format::read_file(path.as_ref())?;
+        Self::try_from_backend_with_key(inner, &key).map_err(Into::into)
+    }
+}
@@ -0,0 +1,92 @@
+use crate::{enc, format, MasterKey, Store};
+
+use core::fmt;
+use std::io;
+use std::path::Path;
+
+///Entry of platform keychain, that keeps master key of store.
+///
+///Keychain is provided by platform:
+///
+///- macOS/iOS - Keychain;
+///- Windows - Credential Manager;
+///- Linux and BSD - Secret Service (e.g. GNOME Keyring or KWallet) over D-Bus.
+///
+///It protects key at rest by means of user's session, allowing desktop application to open store on every launch
+///without prompting for password, refer to `Store::open_with_keychain`.
+pub struct Keychain {
+    entry: keyring::Entry,
+}
+
+impl Keychain {
+    #[inline]
+    ///Creates entry of keychain, identified by name of `service` (e.g. application) and its `user`.
+    ///
+    ///Nothing is accessed until key is stored or loaded.
+    pub fn new(service: &str, user: &str) -> Result<Self, keyring::Error> {
+        keyring::Entry::new(service, user).map(|entry| Self {
+            entry,
+        })
+    }
+
+    #[inline]
+    ///Stores `key`, overwriting one, that is already kept by entry.
+    pub fn store_key(&self, key: &MasterKey) -> Result<(), keyring::Error> {
+        self.entry.set_secret(key.as_bytes())
+    }
+
+    ///Loads key, previously stored via `Self::store_key`.
+    ///
+    ///Returns `keyring::Error::NoEntry` if there is no key, or `keyring::Error::BadEncoding` if it is malformed.
+    pub fn load_key(&self) -> Result<MasterKey, keyring::Error> {
+        let mut secret = self.entry.get_secret()?;
+        let mut key = [0u8; 32];
+        let result = match secret.len() == key.len() {
+            true => {
+                key.copy_from_slice(&secret);
+                Ok(MasterKey::from_bytes(key))
+            },
+            false => Err(keyring::Error::BadEncoding(Vec::new())),
+        };
+        enc::wipe(&mut secret);
+        enc::wipe(&mut key);
+        result
+    }
+
+    #[inline]
+    ///Removes key from keychain.
+    ///
+    ///Returns `keyring::Error::NoEntry` if there is no key.
+    pub fn remove_key(&self) -> Result<(), keyring::Error> {
+        self.entry.delete_credential()
+    }
+}
+
+impl fmt::Debug for Keychain {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_tuple("Keychain").field(&self.entry).finish()
+    }
+}
+
+///Converts error of keychain, with missing key being `NotFound`.
+fn keychain_error(error: keyring::Error) -> io::Error {
+    match error {
+        keyring::Error::NoEntry => io::Error::new(io::ErrorKind::NotFound, error),
+        keyring::Error::NoStorageAccess(_) => io::Error::new(io::ErrorKind::PermissionDenied, error),
+        error => io::Error::other(error),
+    }
+}
+
+impl Store {
+    ///Opens storage, previously saved via `Self::save`, using encryption key from `keychain`.
+    ///
+    ///Key is expected to be stored via `Keychain::store_key`, e.g. on first launch, after store is opened using credentials.
+    ///Returns `NotFound` error if there is no key within keychain.
+    ///Refer to `Self::open_with_key` for details.
+    pub fn open_with_keychain<P: AsRef<Path>>(path: P, keychain: &Keychain) -> io::Result<Self> {
+        let key = keychain.load_key().map_err(keychain_error)?;
+        let inner = format::read_file(path.as_ref())?;
+        Self::try_from_backend_with_key(inner, &key).map_err(Into::into)
+    }
+}
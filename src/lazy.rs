@@ -0,0 +1,158 @@
+//!Lazily loaded storage.
+
+use crate::{format, hasher, KeyHasher, Store, KDF_KEY};
+use crate::reader::{Reader, Source};
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::{Arc, Mutex, OnceLock};
+
+///File, which ciphertexts are cached once they are loaded.
+struct Lazy {
+    file: Mutex<File>,
+    cache: BTreeMap<u128, OnceLock<Option<Vec<u8>>>>,
+}
+
+impl Lazy {
+    fn load(&self, offset: usize, len: usize) -> Option<Vec<u8>> {
+        let mut file = self.file.lock().unwrap_or_else(|error| error.into_inner());
+        let mut result = vec![0u8; len];
+        match file.seek(SeekFrom::Start(offset as u64)).and_then(|_| file.read_exact(&mut result)) {
+            Ok(()) => Some(result),
+            Err(_) => None,
+        }
+    }
+}
+
+impl Source for Lazy {
+    fn get(&self, key: u128, offset: usize, len: usize) -> Option<&[u8]> {
+        self.cache.get(&key)?.get_or_init(|| self.load(offset, len)).as_deref()
+    }
+
+    #[inline]
+    fn read(&self, offset: usize, len: usize) -> Option<Cow<'_, [u8]>> {
+        self.load(offset, len).map(Cow::Owned)
+    }
+}
+
+///Read-only storage, saved by `Store::save`, that loads each ciphertext from file only once it is accessed.
+///
+///Only index of entries is read on open, so opening time doesn't depend on size of values,
+///unless storage requires integrity MAC, which is verified by reading whole file once.
+///Loaded ciphertexts are cached in memory.
+///
+///File must not be modified while storage is open.
+pub struct LazyStore {
+    reader: Reader<Lazy>,
+}
+
+impl Store {
+    ///Opens storage file at `path`, reading only index of its entries.
+    ///
+    ///Refer to `LazyStore` for details.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, err))]
+    pub fn open_lazy<P: AsRef<Path>>(path: P, user: &[u8], pass: &[u8]) -> io::Result<LazyStore> {
+        LazyStore::open(path, user, pass)
+    }
+}
+
+impl LazyStore {
+    #[inline]
+    ///Opens storage file at `path`, reading only index of its entries.
+    ///
+    ///Returns `Error::InvalidCredentials` or `Error::WrongCredentials` as `InvalidData`, if credentials are empty or incorrect.
+    ///Storage must use default hasher of key names, refer to `Self::open_with_hasher`.
+    pub fn open<P: AsRef<Path>>(path: P, user: &[u8], pass: &[u8]) -> io::Result<Self> {
+        Self::open_with_hasher(path, user, pass, hasher::Xxh3)
+    }
+
+    ///Opens storage file at `path`, which keys are hashed by `hasher`, reading only index of its entries.
+    ///
+    ///Returns `Error::KeyHasherMismatch` as `InvalidData`, if storage uses other hasher, refer to `StoreBuilder::key_hasher`,
+    ///or `Error::IntegrityMismatch`, if storage requires integrity MAC, which doesn't match its content.
+    ///Otherwise it behaves as `Self::open`.
+    pub fn open_with_hasher<P: AsRef<Path>, H: KeyHasher + 'static>(path: P, user: &[u8], pass: &[u8], hasher: H) -> io::Result<Self> {
+        let mut file = BufReader::new(File::open(path)?);
+        let count = format::read_header(&mut file)?;
+        let mut index = BTreeMap::new();
+        let mut cache = BTreeMap::new();
+        for _ in 0..count {
+            let key = format::read_key(&mut file)?;
+            let mut len = [0u8; 4];
+            file.read_exact(&mut len)?;
+            let len = u32::from_le_bytes(len);
+            let offset = file.stream_position()?;
+            let value = OnceLock::new();
+            //Parameters of key derivation are needed right away.
+            if key == KDF_KEY {
+                let mut kdf = vec![0u8; len as usize];
+                file.read_exact(&mut kdf)?;
+                let _ = value.set(Some(kdf));
+            } else {
+                file.seek_relative(len as i64)?;
+            }
+
+            index.insert(key, (offset as usize, len as usize));
+            cache.insert(key, value);
+        }
+
+        let source = Lazy {
+            file: Mutex::new(file.into_inner()),
+            cache,
+        };
+        Ok(Self {
+            reader: Reader::open(index, source, user, pass, Arc::new(hasher))?,
+        })
+    }
+
+    #[inline]
+    ///Returns number of key-value pairs
+    pub fn len(&self) -> usize {
+        self.reader.len()
+    }
+
+    #[inline]
+    ///Returns number of ciphertexts, loaded from file so far.
+    pub fn loaded_len(&self) -> usize {
+        self.reader.source.cache.values().filter(|value| value.get().is_some()).count()
+    }
+
+    #[inline]
+    ///Checks for `key` presence within storage, without loading it.
+    pub fn contains(&self, key: &[u8]) -> bool {
+        self.reader.contains(key)
+    }
+
+    #[inline]
+    ///Retrieves value for `key`, storing decrypted value in `dest`.
+    ///
+    ///Returns `Err` when key doesn't exist, cannot be loaded or user has no permission to read it.
+    ///Otherwise returns number of bytes written, or '0' in case of insufficient storage.
+    pub fn get_to(&self, key: &[u8], dest: &mut [u8]) -> Result<usize, ()> {
+        self.reader.get_to(key, dest)
+    }
+
+    #[inline]
+    ///Retrieves value for `key` to store in `dest`, resulting in it being overwritten.
+    ///
+    ///Returns `Err` when key doesn't exist, cannot be loaded or user has no permission to read it.
+    ///Otherwise returns number of bytes written.
+    pub fn get_to_vec(&self, key: &[u8], dest: &mut Vec<u8>) -> Result<usize, ()> {
+        self.reader.get_to_vec(key, dest)
+    }
+
+    #[inline]
+    ///Retrieves value for `key`
+    ///
+    ///Returns `None` if decryption failed.
+    pub fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        let mut result = Vec::new();
+        match self.get_to_vec(key, &mut result) {
+            Ok(_) => Some(result),
+            Err(_) => None,
+        }
+    }
+}
@@ -1,14 +1,431 @@
 //!Minimalistic encrypted storage for arbitrary values.
 
 #![warn(missing_docs)]
-#![cfg_attr(feature = "cargo-clippy", allow(clippy::style))]
+#![allow(clippy::style)]
 
 use std::collections::BTreeMap;
-use xxhash_rust::xxh3::xxh3_128;
 
-use core::ptr;
+use core::mem::MaybeUninit;
+use std::sync::{mpsc, Arc, Mutex};
 
 mod enc;
+pub mod backend;
+pub use backend::Backend;
+mod error;
+pub use error::Error;
+mod builder;
+pub use builder::{Limits, StoreBuilder};
+mod lru;
+pub use lru::EvictFn;
+mod key;
+pub use key::{MasterKey, KeyWrap, Biometric};
+#[cfg(feature = "keychain")]
+mod keychain;
+#[cfg(feature = "keychain")]
+pub use keychain::Keychain;
+#[cfg(feature = "pkcs11")]
+mod pkcs11;
+#[cfg(feature = "pkcs11")]
+pub use pkcs11::Pkcs11Key;
+#[cfg(all(target_os = "linux", feature = "tpm"))]
+mod tpm;
+#[cfg(all(target_os = "linux", feature = "tpm"))]
+pub use tpm::Tpm;
+#[cfg(feature = "yubikey")]
+mod yubikey;
+#[cfg(feature = "yubikey")]
+pub use yubikey::YubiKey;
+mod credentials;
+pub use credentials::Credentials;
+mod machine;
+pub use machine::machine_id;
+mod kdf;
+pub use kdf::{Kdf, ScryptParams};
+mod lock;
+#[cfg(feature = "auto-lock")]
+mod auto_lock;
+#[cfg(feature = "auto-lock")]
+pub use auto_lock::AutoLock;
+mod shamir;
+pub use shamir::{Share, SHARE_LEN};
+mod recovery;
+pub use recovery::RecoveryKey;
+mod read_only;
+pub use read_only::{ReadOnlyStore, OwnedReadOnlyStore};
+mod namespace;
+pub use namespace::Namespace;
+mod parallel;
+mod rekey;
+mod guard;
+pub use guard::PlainGuard;
+mod concurrent;
+pub use concurrent::ConcurrentStore;
+mod api;
+pub use api::SecStore;
+#[cfg(feature = "danger-plaintext")]
+pub mod plaintext;
+mod merge;
+pub use merge::{MergePolicy, ConflictFn};
+mod diff;
+pub use diff::Diff;
+mod transaction;
+pub use transaction::Transaction;
+mod format;
+#[cfg(feature = "compact")]
+mod compact;
+mod file_lock;
+pub use file_lock::{LockedStore, SharedStore, WatchedStore};
+pub mod journal;
+#[cfg(all(unix, feature = "mmap"))]
+pub mod mmap;
+mod reader;
+pub mod lazy;
+mod snapshot;
+pub use snapshot::Snapshot;
+mod notify;
+pub use notify::ChangeEvent;
+mod metrics;
+pub use metrics::Stats;
+mod seal;
+pub use seal::Padding;
+mod decoy;
+mod throttle;
+pub use throttle::UnlockGuard;
+mod strength;
+pub use strength::password_strength;
+mod chunk;
+mod hasher;
+mod header;
+pub use hasher::{KeyHasher, Sha256, Xxh3};
+mod handle;
+pub use handle::KeyHandle;
+mod names;
+mod modify;
+mod drain;
+pub use drain::{Drain, EntryKey};
+mod delta;
+pub mod sync;
+pub mod crypto;
+pub mod age;
+mod base64;
+mod armor;
+mod plain_json;
+mod autosave;
+pub use autosave::{AutosaveFn, AutosavePolicy};
+mod tags;
+mod versions;
+mod signing;
+mod meta;
+mod history;
+mod schema;
+pub mod record;
+mod integrity;
+mod lossy;
+pub use integrity::IntegrityReport;
+#[cfg(feature = "derive")]
+pub use sec_store_derive::SecRecord;
+pub use schema::{MigrationFn, Migrations};
+pub use signing::{Signer, SignatureStatus, Metadata, PUBLIC_KEY_LEN};
+mod json;
+#[cfg(feature = "kdbx")]
+mod kdbx;
+mod stream;
+pub use stream::ValueReader;
+#[cfg(feature = "audit")]
+mod audit;
+#[cfg(feature = "audit")]
+pub use audit::{AuditOp, AuditRecord};
+
+///Hashes below this value are reserved for internal entries.
+///
+///As keys are hashed using 128bit hash, user's key is not going to hit this range in practice.
+const RESERVED: u128 = 0x1_0000;
+///Integrity MAC over all other entries.
+const MAC_KEY: u128 = 1;
+///Encrypted header, written on creation to verify credentials.
+const HEADER_KEY: u128 = 2;
+const HEADER: &[u8] = b"sec-store";
+///Encrypted audit log.
+#[cfg_attr(not(feature = "audit"), allow(dead_code))]
+const AUDIT_KEY: u128 = 3;
+///Master key, wrapped by recovery key.
+const RECOVERY_KEY: u128 = 4;
+///Parameters of key derivation, unless default one is used.
+const KDF_KEY: u128 = 5;
+///Hash of internal entry with names of keys.
+const NAMES_KEY: u128 = 6;
+///Hash of internal entry with tags of keys.
+const TAGS_KEY: u128 = 7;
+///Hash of internal entry with versions of keys.
+const VERSIONS_KEY: u128 = 8;
+///Hash of internal entry with signers and signatures of keys.
+const SIGNATURES_KEY: u128 = 9;
+///Hash of internal entry with application-defined metadata of store.
+const META_KEY: u128 = 10;
+///Hash of internal entry with previous values of keys.
+const HISTORY_KEY: u128 = 11;
+///All internal entries in use.
+const RESERVED_KEYS: [u128; 11] = [MAC_KEY, HEADER_KEY, AUDIT_KEY, RECOVERY_KEY, KDF_KEY, NAMES_KEY, TAGS_KEY, VERSIONS_KEY, SIGNATURES_KEY, META_KEY, HISTORY_KEY];
+
+#[inline]
+///Returns number of internal entries within `backend`.
+fn reserved_len<B: Backend>(backend: &B) -> usize {
+    RESERVED_KEYS.iter().filter(|key| backend.contains(**key)).count()
+}
+
+///Checks that none of user's `entries` of storage without header is sealed in the current format, using `enc`.
+///
+///Earlier versions sealed values only as bare ciphertexts of format `1`, so the current format means that header is stripped.
+///Stripped storage of format `1` cannot be told apart from them, refer to `Store::migrate_format`.
+fn is_legacy<V: AsRef<[u8]>>(enc: &enc::Manager, mut entries: impl Iterator<Item = (u128, V)>) -> bool {
+    entries.all(|(key, value)| !seal::is_current(enc, key, value.as_ref()))
+}
+
+#[inline]
+///Returns size of user's ciphertexts within `backend`.
+fn entries_size<B: Backend>(backend: &B) -> usize {
+    backend.iter().filter(|(key, _)| *key >= RESERVED).map(|(_, value)| value.len()).sum()
+}
+
+#[inline]
+///Wipes ciphertext, that is no longer stored, before it gets freed.
+pub(crate) fn discard(value: Option<Vec<u8>>) {
+    if let Some(mut value) = value {
+        enc::wipe(&mut value);
+    }
+}
+
+///Decrypts `value` into `dest`, returning `Ok(0)` if it doesn't fit.
+fn open_to(enc: &enc::Manager, key: u128, value: &[u8], dest: &mut [u8]) -> Result<usize, ()> {
+    if let Some(envelope) = seal::Envelope::open(enc, key, value) {
+        let body = envelope.body();
+        if body.len() > dest.len() {
+            return Ok(0);
+        }
+
+        dest[..body.len()].copy_from_slice(body);
+        return match envelope.decrypt(enc, key, &mut dest[..body.len()]) {
+            true => Ok(envelope.plain_len()),
+            false => Err(()),
+        };
+    }
+
+    open_bare_to(enc, key, value, dest)
+}
+
+///Decrypts `value` without envelope into `dest`, returning `Ok(0)` if it doesn't fit.
+fn open_bare_to(enc: &enc::Manager, key: u128, value: &[u8], dest: &mut [u8]) -> Result<usize, ()> {
+    if let Some(chunks) = chunk::Chunks::parse(enc, key, value) {
+        let len = chunks.plain_len();
+        if len > dest.len() {
+            return Ok(0);
+        }
+
+        return match chunks.decrypt_to_slice(enc, &mut dest[..len]) {
+            true => Ok(len),
+            false => {
+                enc::wipe(&mut dest[..len]);
+                Err(())
+            },
+        };
+    }
+
+    if value.len() > dest.len() {
+        return Ok(0);
+    }
+
+    dest[..value.len()].copy_from_slice(value);
+    match enc.decrypt(key, &mut dest[..value.len()]) {
+        Some(written) => {
+            Ok(written.len())
+        },
+        None => open_prefixed(enc, key, value, dest),
+    }
+}
+
+#[inline]
+///Returns size of buffer, required to decrypt `value` in place.
+fn required_len(enc: &enc::Manager, key: u128, value: &[u8]) -> usize {
+    match chunk::Chunks::parse(enc, key, value) {
+        Some(chunks) => chunks.plain_len(),
+        None => match seal::Envelope::open(enc, key, value) {
+            Some(envelope) => envelope.body().len(),
+            None => value.len(),
+        },
+    }
+}
+
+#[inline]
+///Returns `dest` as initialized bytes.
+///
+///# Safety
+///
+///Every byte of `dest` must be written, e.g. via `MaybeUninit::write`.
+unsafe fn assume_init(dest: &mut [MaybeUninit<u8>]) -> &mut [u8] {
+    //`MaybeUninit<u8>` has the same layout as `u8`.
+    &mut *(dest as *mut [MaybeUninit<u8>] as *mut [u8])
+}
+
+#[inline]
+///Initializes start of `dest` with `value`, returning initialized part.
+fn init_with<'a>(dest: &'a mut [MaybeUninit<u8>], value: &[u8]) -> &'a mut [u8] {
+    let dest = &mut dest[..value.len()];
+    for (dest, byte) in dest.iter_mut().zip(value) {
+        dest.write(*byte);
+    }
+    //Every byte has been written above.
+    unsafe {
+        assume_init(dest)
+    }
+}
+
+#[inline]
+///Initializes first `len` bytes of `dest` with zeroes, returning initialized part.
+fn init_zeroed(dest: &mut [MaybeUninit<u8>], len: usize) -> &mut [u8] {
+    let dest = &mut dest[..len];
+    for dest in dest.iter_mut() {
+        dest.write(0);
+    }
+    //Every byte has been written above.
+    unsafe {
+        assume_init(dest)
+    }
+}
+
+///Decrypts `value` into uninitialized `dest`, returning `Error::BufferTooSmall` if it doesn't fit.
+///
+///Only part of `dest`, that is necessary for decryption, gets initialized, and only plaintext within it is returned.
+///On error initialized part is wiped, so neither plaintext nor uninitialized bytes are ever exposed.
+fn open_to_uninit<'a>(enc: &enc::Manager, key: u128, value: &[u8], dest: &'a mut [MaybeUninit<u8>]) -> Result<&'a mut [u8], Error> {
+    let required = required_len(enc, key, value);
+    if required > dest.len() {
+        return Err(Error::BufferTooSmall(required));
+    }
+
+    match seal::Envelope::open(enc, key, value) {
+        Some(envelope) => {
+            let dest = init_with(dest, envelope.body());
+            match envelope.decrypt(enc, key, dest) {
+                true => Ok(&mut dest[..envelope.plain_len()]),
+                false => Err(Error::InvalidEntry(key)),
+            }
+        },
+        None => open_bare_uninit(enc, key, value, dest),
+    }
+}
+
+///Decrypts `value` without envelope into uninitialized `dest`, which must fit it, returning plaintext.
+fn open_bare_uninit<'a>(enc: &enc::Manager, key: u128, value: &[u8], dest: &'a mut [MaybeUninit<u8>]) -> Result<&'a mut [u8], Error> {
+    if let Some(chunks) = chunk::Chunks::parse(enc, key, value) {
+        let dest = init_zeroed(dest, chunks.plain_len());
+        return match chunks.decrypt_to_slice(enc, dest) {
+            true => Ok(dest),
+            false => {
+                enc::wipe(dest);
+                Err(Error::InvalidEntry(key))
+            },
+        };
+    }
+
+    let dest = init_with(dest, value);
+    let len = match enc.decrypt(key, dest) {
+        Some(written) => written.len(),
+        None => open_prefixed(enc, key, value, dest).map_err(|_| Error::InvalidEntry(key))?,
+    };
+    //Decrypted value is at the start of initialized part
+    Ok(&mut dest[..len])
+}
+
+///Decrypts `value`, prefixed with its nonce, into `dest`, which must fit it.
+///
+///Such value is either encrypted using random nonce, or padded.
+fn open_prefixed(enc: &enc::Manager, key: u128, value: &[u8], dest: &mut [u8]) -> Result<usize, ()> {
+    let dest = &mut dest[..value.len()];
+    dest.copy_from_slice(value);
+    let len = match enc.decrypt_random(key, dest) {
+        Some(written) => written.len(),
+        None => {
+            dest.copy_from_slice(value);
+            match enc.decrypt_prefixed(&seal::padded_aad(key), dest).and_then(|written| seal::unpad(written)) {
+                Some(len) => len,
+                None => {
+                    enc::wipe(dest);
+                    return Err(());
+                },
+            }
+        },
+    };
+
+    dest.copy_within(enc::NONCE_LEN..enc::NONCE_LEN + len, 0);
+    //Plaintext is moved to the start, leaving its copy behind.
+    enc::wipe(&mut dest[len..]);
+    Ok(len)
+}
+
+///Decrypts `value`, overwriting `dest`.
+fn open_to_vec(enc: &enc::Manager, key: u128, value: &[u8], dest: &mut Vec<u8>) -> Result<usize, ()> {
+    let result = open_into(enc, key, value, dest);
+    if result.is_ok() && dest.capacity() > dest.len() {
+        //Shrinking re-allocates, so plaintext is moved manually to wipe the old buffer.
+        let mut old = core::mem::replace(dest, Vec::with_capacity(dest.len()));
+        dest.extend_from_slice(&old);
+        let capacity = old.capacity();
+        old.resize(capacity, 0);
+        enc::wipe(&mut old);
+    }
+    result
+}
+
+///Decrypts `value`, overwriting `dest` while keeping its capacity.
+fn open_into(enc: &enc::Manager, key: u128, value: &[u8], dest: &mut Vec<u8>) -> Result<usize, ()> {
+    if let Some(envelope) = seal::Envelope::open(enc, key, value) {
+        //Growing re-allocates, so previous content must not be left behind.
+        enc::wipe(dest);
+        dest.truncate(0);
+        dest.extend_from_slice(envelope.body());
+        return match envelope.decrypt(enc, key, dest) {
+            true => {
+                dest.truncate(envelope.plain_len());
+                Ok(dest.len())
+            },
+            false => Err(()),
+        };
+    }
+
+    open_bare_into(enc, key, value, dest)
+}
+
+///Decrypts `value` without envelope, overwriting `dest` while keeping its capacity.
+fn open_bare_into(enc: &enc::Manager, key: u128, value: &[u8], dest: &mut Vec<u8>) -> Result<usize, ()> {
+    //Growing re-allocates, so previous content must not be left behind.
+    enc::wipe(dest);
+    dest.truncate(0);
+
+    if let Some(chunks) = chunk::Chunks::parse(enc, key, value) {
+        dest.reserve_exact(chunks.plain_len());
+        return match chunks.decrypt_to_vec(enc, dest) {
+            true => Ok(dest.len()),
+            false => {
+                enc::wipe(dest);
+                Err(())
+            },
+        };
+    }
+
+    dest.extend_from_slice(value);
+    let result = match enc.decrypt(key, dest) {
+        Some(written) => Ok(written.len()),
+        None => open_prefixed(enc, key, value, dest),
+    };
+    match result {
+        Ok(len) => {
+            enc::wipe(&mut dest[len..]);
+            dest.truncate(len);
+        },
+        Err(()) => enc::wipe(dest),
+    }
+    result
+}
 
 ///Secure storage API
 ///
@@ -16,13 +433,50 @@ mod enc;
 ///and lately restore it using `serde`
 ///
 ///`Key` is stored as hash, while `Value` is stored as encrypted bytes.
-pub struct Store {
+///
+///Entries are kept within `Backend`, which is `BTreeMap` by default.
+pub struct Store<B = BTreeMap<u128, Vec<u8>>> {
     ///Values are stored as hash(key), encrypted data(value)
     ///
     ///Technically it is possible to reverse hash, but in practice it is unlikely to happen.
     ///Only value itself is supposed to be sensitive in our case
-    inner: BTreeMap<u128, Vec<u8>>,
+    inner: B,
     enc: enc::Manager,
+    limits: Limits,
+    ///Size of user's ciphertexts.
+    size: usize,
+    eviction: Option<lru::Lru>,
+    ///Buffer, reused by `Self::get_with`.
+    scratch: Mutex<Vec<u8>>,
+    ///Subscribers along with hash of watched key, if any.
+    subscribers: Vec<(Option<u128>, mpsc::Sender<ChangeEvent>)>,
+    metrics: metrics::Metrics,
+    sealing: seal::Sealing,
+    ///Number of decoys, written on save.
+    decoys: usize,
+    ///Decoys, written by previous save or loaded along with storage, so that they only change once values are sealed anew.
+    decoy_entries: Mutex<Vec<(u128, Vec<u8>)>>,
+    ///Whether key is wiped, refer to `Self::lock`.
+    locked: bool,
+    ///Names of keys, if enabled via `Self::enable_key_names`.
+    names: Option<names::Names>,
+    ///Tags of keys, refer to `Self::insert_with_meta`.
+    tags: tags::Tags,
+    ///Versions of keys, if enabled via `Self::enable_versioning`.
+    versions: Option<versions::Versions>,
+    ///Signers and signatures of keys, refer to `Self::insert_signed`.
+    signatures: signing::Signatures,
+    ///Digests of entries at the moment of last save.
+    saved: Mutex<delta::Digests>,
+    autosave: Option<autosave::Autosave<B>>,
+    ///Version of format, written on save.
+    format: u8,
+    ///Entries, skipped by `Store::open_lossy`.
+    quarantine: Vec<(u128, Vec<u8>)>,
+    ///Hasher of key names, refer to `StoreBuilder::key_hasher`.
+    hasher: Arc<dyn KeyHasher>,
+    #[cfg(feature = "audit")]
+    audit: audit::AuditLog,
 }
 
 impl Store {
@@ -34,7 +488,131 @@ impl Store {
     ///- `user` - user specific information that can distinguish him from others.
     ///- `pass` - can be any number of arbitrary bytes except it MUST NOT be zero length.
     pub fn new(user: &[u8], pass: &[u8]) -> Self {
-        Self::from_inner(Default::default(), user, pass)
+        Self::new_in(BTreeMap::new(), user, pass)
+    }
+
+    #[inline]
+    ///Creates new instance using creds, returning error instead of panicking on invalid input.
+    ///
+    ///Returns `Error::InvalidCredentials` if `user` or `pass` is empty.
+    pub fn try_new(user: &[u8], pass: &[u8]) -> Result<Self, Error> {
+        Self::try_new_in(BTreeMap::new(), user, pass)
+    }
+
+    #[inline]
+    ///Creates new instance using provided storage and pass, validating it.
+    ///
+    ///Refer to `Self::try_from_backend` for details.
+    pub fn try_from_inner(inner: BTreeMap<u128, Vec<u8>>, user: &[u8], pass: &[u8]) -> Result<Self, Error> {
+        Self::try_from_backend(inner, user, pass)
+    }
+
+    #[inline]
+    ///Creates new instance using provided storage and pass.
+    ///
+    ///Refer to `Self::from_backend` for details.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub fn from_inner(inner: BTreeMap<u128, Vec<u8>>, user: &[u8], pass: &[u8]) -> Self {
+        Self::from_backend(inner, user, pass)
+    }
+
+    #[inline]
+    ///Creates new instance using provided storage and pass, verifying its integrity MAC.
+    ///
+    ///Refer to `Self::from_backend_verified` for details.
+    pub fn from_inner_verified(inner: BTreeMap<u128, Vec<u8>>, user: &[u8], pass: &[u8]) -> Option<Self> {
+        Self::from_backend_verified(inner, user, pass)
+    }
+}
+
+impl<B: Backend> Store<B> {
+    ///Creates new instance within empty `backend` using creds.
+    ///
+    ///Refer to `Store::new` for details.
+    pub fn new_in(backend: B, user: &[u8], pass: &[u8]) -> Self {
+        Self::from_backend(backend, user, pass)
+    }
+
+    #[inline]
+    ///Creates new instance within empty `backend` using creds, returning error instead of panicking on invalid input.
+    ///
+    ///Returns `Error::InvalidCredentials` if `user` or `pass` is empty.
+    pub fn try_new_in(backend: B, user: &[u8], pass: &[u8]) -> Result<Self, Error> {
+        if user.is_empty() || pass.is_empty() {
+            return Err(Error::InvalidCredentials);
+        }
+
+        Ok(Self::new_in(backend, user, pass))
+    }
+
+    ///Creates new instance using provided storage and pass, validating it.
+    ///
+    ///Returns error when:
+    ///
+    ///- `Error::InvalidCredentials` - `user` or `pass` is empty.
+    ///- `Error::InvalidEntry` - storage contains value that cannot be valid ciphertext.
+    ///- `Error::WrongCredentials` - credentials do not match storage, refer to `Self::verify_credentials`.
+    ///- `Error::IntegrityMismatch` - storage requires integrity MAC, which is missing or doesn't match its content,
+    ///  or storage has no header, while it is not created by earlier versions, refer to `Self::verify_credentials`.
+    ///- `Error::KeyHasherMismatch` - storage uses hasher other than default, refer to `StoreBuilder::key_hasher`.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, err))]
+    pub fn try_from_backend(inner: B, user: &[u8], pass: &[u8]) -> Result<Self, Error> {
+        Self::try_from_backend_with_hasher(inner, user, pass, Arc::new(hasher::Xxh3))
+    }
+
+    ///Creates new instance using provided storage, pass and `hasher` of key names, validating it.
+    pub(crate) fn try_from_backend_with_hasher(inner: B, user: &[u8], pass: &[u8], hasher: Arc<dyn KeyHasher>) -> Result<Self, Error> {
+        if user.is_empty() || pass.is_empty() {
+            return Err(Error::InvalidCredentials);
+        }
+
+        Self::validate_entries(&inner)?;
+        let mut result = Self::from_backend(inner, user, pass);
+        result.hasher = hasher;
+        result.validate()
+    }
+
+    ///Checks that every entry of `inner` can be valid ciphertext, and that `inner` without header has no internal entries.
+    ///
+    ///Header records whether integrity MAC is required, so storage without it would let MAC be stripped along with header,
+    ///while storage of earlier versions has neither header nor internal entries.
+    fn validate_entries(inner: &B) -> Result<(), Error> {
+        if !inner.contains(HEADER_KEY) && reserved_len(inner) != 0 {
+            return Err(Error::IntegrityMismatch);
+        }
+
+        for (key, value) in inner.iter() {
+            let is_valid = match key {
+                MAC_KEY => value.len() == enc::MAC_LEN,
+                KDF_KEY => kdf::Kdf::from_entry(Some(value)).is_some(),
+                _ => value.len() > enc::TAG_LEN,
+            };
+
+            if !is_valid {
+                return Err(Error::InvalidEntry(key));
+            }
+        }
+
+        Ok(())
+    }
+
+    ///Checks that credentials and integrity MAC, if required, match storage.
+    ///
+    ///MAC is required once it is stored, which is recorded within header,
+    ///while header itself is required, unless storage is created by earlier versions, in which case `Self::with_manager` writes it.
+    ///So removing either of them doesn't help.
+    fn validate(self) -> Result<Self, Error> {
+        if !self.verify_credentials() {
+            Err(Error::WrongCredentials)
+        } else if !self.inner.contains(HEADER_KEY) {
+            Err(Error::IntegrityMismatch)
+        } else if let Err(error) = self.verify_key_hasher() {
+            Err(error)
+        } else if self.requires_mac() && !self.verify_mac() {
+            Err(Error::IntegrityMismatch)
+        } else {
+            Ok(self)
+        }
     }
 
     #[inline]
@@ -45,73 +623,279 @@ impl Store {
     ///- `storage` - already initialized storage, only can work with storage that is returned by `Self::inner`.
     ///- `user`    - user specific information that can distinguish him from others.
     ///- `pass`    - can be any number of arbitrary bytes except it MUST NOT be zero length.
-    pub fn from_inner(inner: BTreeMap<u128, Vec<u8>>, user: &[u8], pass: &[u8]) -> Self {
+    ///
+    ///Integrity MAC is not verified, use `Self::try_from_backend` or `Self::verify_mac` to verify it.
+    pub fn from_backend(inner: B, user: &[u8], pass: &[u8]) -> Self {
         assert_ne!(user.len(), 0);
         assert_ne!(pass.len(), 0);
 
-        Self {
+        let key = kdf::Kdf::of(&inner).derive(user, pass);
+        Self::with_manager(inner, enc::Manager::new(key))
+    }
+
+    ///Creates instance out of `inner`, using `enc`.
+    ///
+    ///Storage without header, created by earlier versions, gets one, once its first value verifies credentials,
+    ///unless it has internal entries or values sealed in the current format, which means that its header is stripped.
+    fn with_manager(mut inner: B, enc: enc::Manager) -> Self {
+        let decoys = decoy::strip(&enc, &mut inner);
+        let mut result = Self {
+            #[cfg(feature = "audit")]
+            audit: audit::AuditLog::load(&enc, &inner),
+            size: entries_size(&inner),
+            names: names::load(&enc, &inner),
+            tags: tags::load(&enc, &inner),
+            versions: versions::load(&enc, &inner),
+            signatures: signing::load(&enc, &inner),
+            saved: Mutex::new(delta::digests(&inner)),
+            autosave: None,
+            quarantine: Vec::new(),
+            format: format::VERSION,
+            hasher: Arc::new(hasher::Xxh3),
             inner,
-            enc: enc::Manager::new(enc::generate_key(user, pass))
+            enc,
+            limits: Limits::default(),
+            eviction: None,
+            scratch: Mutex::new(Vec::new()),
+            subscribers: Vec::new(),
+            metrics: metrics::Metrics::default(),
+            sealing: seal::Sealing::default(),
+            decoys: 0,
+            decoy_entries: Mutex::new(decoys),
+            locked: false,
+        };
+        if !result.inner.contains(HEADER_KEY) && reserved_len(&result.inner) == 0 && result.verify_credentials() && is_legacy(&result.enc, result.entries()) {
+            result.write_header();
         }
+        result
     }
 
     #[inline]
     ///Accesses inner representation of storage, allowing to serialize it.
-    pub fn inner(&self) -> &BTreeMap<u128, Vec<u8>> {
+    pub fn inner(&self) -> &B {
         &self.inner
     }
 
     #[inline]
     ///Consumes self, returning underlying storage.
-    pub fn into_inner(self) -> BTreeMap<u128, Vec<u8>> {
+    pub fn into_inner(mut self) -> B {
+        self.flush_versions();
+        #[cfg(feature = "audit")]
+        match self.verify_mac() {
+            true => self.update_mac(),
+            false => self.flush_audit(),
+        }
         self.inner
     }
 
+    #[inline]
+    ///Creates new instance using provided storage and pass, verifying its integrity MAC.
+    ///
+    ///Returns `None` if storage has no MAC or it doesn't match its content.
+    ///
+    ///Refer to `Self::update_mac` for details.
+    pub fn from_backend_verified(inner: B, user: &[u8], pass: &[u8]) -> Option<Self> {
+        let result = Self::from_backend(inner, user, pass);
+        match result.verify_mac() {
+            true => Some(result),
+            false => None,
+        }
+    }
+
+    ///Checks whether credentials, used to create instance, are correct.
+    ///
+    ///This is done by decrypting header, written at creation, without touching any value.
+    ///
+    ///Storages created by earlier versions do not have header, in which case first value is used for this purpose.
+    ///Such storage gets header, once credentials are verified, which is written by the next save.
+    ///Empty storage without header accepts any credentials.
+    pub fn verify_credentials(&self) -> bool {
+        match self.inner.get(HEADER_KEY) {
+            Some(header) => match self.decrypt_value(HEADER_KEY, header) {
+                Some(header) => header.starts_with(HEADER),
+                None => false,
+            },
+            None => match self.entries().next() {
+                Some((key, value)) => self.decrypt_value(key, value).is_some(),
+                None => true,
+            }
+        }
+    }
+
     #[inline]
     ///Returns number of key-value pairs
     pub fn len(&self) -> usize {
-        self.inner.len()
+        self.inner.len() - reserved_len(&self.inner)
+    }
+
+    #[inline]
+    ///Iterates over user's entries, skipping internal ones.
+    fn entries(&self) -> impl Iterator<Item = (u128, &[u8])> + '_ {
+        self.inner.iter().filter(|(key, _)| *key >= RESERVED)
+    }
+
+    fn compute_mac(&self) -> [u8; enc::MAC_LEN] {
+        let mut entries: Vec<_> = self.inner.iter().filter(|(key, _)| *key != MAC_KEY).collect();
+        entries.sort_unstable_by_key(|(key, _)| *key);
+        self.enc.mac(entries.into_iter())
+    }
+
+    ///Recomputes integrity MAC over all entries, storing it within storage.
+    ///
+    ///Each value is authenticated on its own, but it doesn't prevent removal or rolling back of individual entries.
+    ///MAC covers whole storage, making such modifications detectable via `Self::verify_mac`.
+    ///
+    ///Any modification invalidates MAC, so it should be called before taking storage via `Self::inner` or `Self::into_inner`,
+    ///while `Self::save` and `Self::save_to_writer` write MAC, computed anew over saved entries.
+    ///Once MAC is stored, it is recorded within header, that storage requires it,
+    ///so storage without MAC fails to open with `Error::IntegrityMismatch`, refer to `Self::try_from_backend`.
+    ///Does nothing while store is locked.
+    pub fn update_mac(&mut self) {
+        if self.locked {
+            return;
+        }
+
+        #[cfg(feature = "audit")]
+        self.flush_audit();
+        self.flush_versions();
+        if !self.is_mac_recorded() {
+            self.write_header_with(true);
+        }
+        let mac = self.compute_mac();
+        self.inner.insert(MAC_KEY, mac.to_vec());
+    }
+
+    ///Verifies integrity MAC stored within storage.
+    ///
+    ///Returns `false` if MAC is missing or storage has been modified since last `Self::update_mac`.
+    pub fn verify_mac(&self) -> bool {
+        match self.inner.get(MAC_KEY) {
+            Some(mac) => enc::ct_eq(&self.compute_mac(), mac),
+            None => false,
+        }
     }
 
     fn inner_get_to(&self, key: u128, dest: &mut [u8]) -> Result<usize, ()> {
-        match self.inner.get(&key) {
+        let result = match self.inner.get(key) {
             Some(value) => {
-                if value.len() > dest.len() {
-                    return Ok(0);
-                }
-
-                unsafe {
-                    ptr::copy_nonoverlapping(value.as_ptr(), dest.as_mut_ptr(), value.len());
-                }
-
-                match self.enc.decrypt(key, &mut dest[..value.len()]) {
-                    Some(written) => {
-                        Ok(written.len())
-                    },
-                    None => Err(())
-                }
+                self.touch(key);
+                Some(self.decrypt_to(key, value, dest))
             },
-            None => Err(())
-        }
+            None => {
+                self.record_get(key, false);
+                None
+            },
+        };
+        self.metrics.get(result);
+        result.unwrap_or(Err(()))
     }
 
     fn inner_get_to_vec(&self, key: u128, dest: &mut Vec<u8>) -> Result<usize, ()> {
-        match self.inner.get(&key) {
+        let result = match self.inner.get(key) {
             Some(value) => {
-                dest.truncate(0);
-                dest.extend_from_slice(&value);
-                match self.enc.decrypt(key, dest) {
-                    Some(written) => {
-                        let len = written.len();
-                        dest.truncate(len);
-                        dest.shrink_to_fit();
-                        Ok(dest.len())
-                    },
-                    None => Err(())
-                }
+                self.touch(key);
+                Some(self.decrypt_to_vec(key, value, dest))
+            },
+            None => {
+                self.record_get(key, false);
+                None
             },
-            None => Err(()),
+        };
+        self.metrics.get(result);
+        result.unwrap_or(Err(()))
+    }
+
+    #[inline]
+    #[cfg_attr(not(feature = "audit"), allow(unused_variables))]
+    ///Records reading of value under `key` within audit log, if enabled.
+    ///
+    ///Every decryption of user's value, handing out plaintext, must be recorded, hence it is done by `Self::decrypt_to` and co.
+    pub(crate) fn record_get(&self, key: u128, success: bool) {
+        #[cfg(feature = "audit")]
+        if key >= RESERVED {
+            self.audit.record(AuditOp::Get, key, success);
+        }
+    }
+
+    ///Decrypts `value` of `key` into `dest`, returning `Ok(0)` if it doesn't fit.
+    pub(crate) fn decrypt_to(&self, key: u128, value: &[u8], dest: &mut [u8]) -> Result<usize, ()> {
+        let result = open_to(&self.enc, key, value, dest);
+        self.record_get(key, result.is_ok());
+        result
+    }
+
+    ///Decrypts `value` of `key` into `dest`, overwriting it.
+    pub(crate) fn decrypt_to_vec(&self, key: u128, value: &[u8], dest: &mut Vec<u8>) -> Result<usize, ()> {
+        let result = open_to_vec(&self.enc, key, value, dest);
+        self.record_get(key, result.is_ok());
+        result
+    }
+
+    fn decrypt_value(&self, key: u128, value: &[u8]) -> Option<Vec<u8>> {
+        let mut result = Vec::new();
+        match self.decrypt_to_vec(key, value, &mut result) {
+            Ok(_) => Some(result),
+            Err(_) => None,
+        }
+    }
+
+    ///Stores encrypted `value`, returning previous ciphertext.
+    ///
+    ///All modifications of user's entries must go through it.
+    fn inner_put(&mut self, key: u128, value: Vec<u8>) -> Option<Vec<u8>> {
+        self.suspend_autosave();
+        self.size += value.len();
+        self.metrics.insert(value.len());
+        let result = self.inner.insert(key, value);
+        if let Some(previous) = result.as_ref() {
+            self.size -= previous.len();
+            self.record_history(key, previous);
+        }
+        self.notify(ChangeEvent::Insert(key));
+        self.forget_signature(key);
+        self.record_version(key);
+        self.touch(key);
+        self.evict();
+        self.autosave_changed();
+        self.resume_autosave();
+        result
+    }
+
+    ///Removes ciphertext under `key`.
+    ///
+    ///All removals of user's entries must go through it.
+    fn inner_take(&mut self, key: u128) -> Option<Vec<u8>> {
+        if let Some(eviction) = self.eviction.as_ref() {
+            eviction.forget(key);
         }
+        let result = self.inner.remove(key);
+        if let Some(previous) = result.as_ref() {
+            self.size -= previous.len();
+            self.metrics.remove();
+            self.forget_name(key);
+            self.forget_tags(key);
+            self.record_removal(key);
+            self.forget_signature(key);
+            self.forget_history(key);
+            self.notify(ChangeEvent::Remove(key));
+            self.autosave_changed();
+        }
+        result
+    }
+
+    fn inner_insert(&mut self, key: u128, mut value: Vec<u8>) -> Option<Vec<u8>> {
+        assert_ne!(value.len(), 0);
+        assert!(self.sealing.seal(&self.enc, key, &mut value));
+        self.put_sealed(key, value)
+    }
+
+    ///Stores sealed `value` under `key`, returning previous value, if any.
+    pub(crate) fn put_sealed(&mut self, key: u128, value: Vec<u8>) -> Option<Vec<u8>> {
+        self.inner_put(key, value).and_then(|mut previous| {
+            let result = self.decrypt_value(key, &previous);
+            enc::wipe(&mut previous);
+            result
+        })
     }
 
     #[inline]
@@ -119,10 +903,101 @@ impl Store {
     ///
     ///Returns `Err` when key doesn't exist or user has no permission to read it.
     ///Otherwise returns number of bytes written, or '0' in case of insufficient storage.
+    ///
+    ///Use `Self::get_len` to determine required size of `dest`.
     pub fn get_to(&self, key: &[u8], dest: &mut [u8]) -> Result<usize, ()> {
-        let key = xxh3_128(key).to_le();
+        let key = self.hash_key(key);
+
+        let result = self.inner_get_to(key, dest);
+        #[cfg(feature = "tracing")]
+        tracing::trace!(op = "get", success = result.is_ok());
+        result
+    }
+
+    ///Returns size of buffer, that is required to retrieve value for `key` via `Self::get_to` or `Self::get_to_uninit`.
+    ///
+    ///As values are decrypted in place, required size includes encryption overhead, unless value is chunked.
+    ///Value is not decrypted, hence it is not verified that user has permission to read it.
+    ///
+    ///Returns `Error::NotFound` if key doesn't exist.
+    pub fn get_len(&self, key: &[u8]) -> Result<usize, Error> {
+        let key = self.hash_key(key);
+
+        match self.inner.get(key) {
+            Some(value) => Ok(required_len(&self.enc, key, value)),
+            None => Err(Error::NotFound),
+        }
+    }
+
+    ///Returns length of value for `key` without decrypting it, e.g. to allocate exact buffer.
+    ///
+    ///Length is sealed within envelope of ciphertext, refer to `Self::set_padding`, or within header of chunked value,
+    ///so only either of them is decrypted.
+    ///Value of format `1`, refer to `Self::migrate_format`, is decrypted whole to find out its length.
+    ///
+    ///Returns `Error::NotFound` if key doesn't exist, or `Error::InvalidEntry` if value cannot be decrypted.
+    pub fn value_len(&self, key: &[u8]) -> Result<usize, Error> {
+        let key = self.hash_key(key);
+
+        let value = match self.inner.get(key) {
+            Some(value) => value,
+            None => return Err(Error::NotFound),
+        };
+        if let Some(len) = seal::plain_len(&self.enc, key, value) {
+            return Ok(len);
+        }
+
+        match self.decrypt_value(key, value) {
+            Some(mut plain) => {
+                enc::wipe(&mut plain);
+                Ok(plain.len())
+            },
+            None => Err(Error::InvalidEntry(key)),
+        }
+    }
+
+    #[inline]
+    ///Returns ciphertext of value for `key` along with hash, it is stored under, without decrypting it.
+    ///
+    ///Ciphertext is bound to hash and encryption key of store, so it can be moved verbatim
+    ///into other replica of the same store (e.g. via `Self::insert_encrypted`), but nowhere else.
+    pub fn get_encrypted(&self, key: &[u8]) -> Option<(u128, &[u8])> {
+        let key = self.hash_key(key);
+        self.inner.get(key).map(|value| (key, value))
+    }
+
+    ///Retrieves value for `key`, decrypting it straight into uninitialized `dest`.
+    ///
+    ///Only part of `dest`, that is necessary for decryption, gets initialized,
+    ///which is slightly more than value itself, unless it is chunked.
+    ///Returned value is the only part of `dest`, that is exposed, while on error initialized part is wiped,
+    ///so neither plaintext nor uninitialized bytes are ever exposed.
+    ///
+    ///Returns decrypted value on success, otherwise:
+    ///
+    ///- `Error::NotFound` if key doesn't exist;
+    ///- `Error::BufferTooSmall` with required size of `dest`, if value doesn't fit;
+    ///- `Error::InvalidEntry` if user has no permission to read it.
+    pub fn get_to_uninit<'a>(&self, key: &[u8], dest: &'a mut [MaybeUninit<u8>]) -> Result<&'a mut [u8], Error> {
+        let key = self.hash_key(key);
 
-        self.inner_get_to(key, dest)
+        let result = match self.inner.get(key) {
+            Some(value) => {
+                self.touch(key);
+                open_to_uninit(&self.enc, key, value, dest)
+            },
+            None => Err(Error::NotFound),
+        };
+        self.metrics.get(match result {
+            Ok(ref value) => Some(Ok(value.len())),
+            Err(Error::BufferTooSmall(_)) => Some(Ok(0)),
+            Err(Error::NotFound) => None,
+            Err(_) => Some(Err(())),
+        });
+        self.record_get(key, result.is_ok());
+        #[cfg(feature = "tracing")]
+        tracing::trace!(op = "get", success = result.is_ok());
+        result
     }
 
     #[inline]
@@ -131,9 +1006,12 @@ impl Store {
     ///Returns `Err` when key doesn't exist or user has no permission to read it.
     ///Otherwise returns number of bytes written.
     pub fn get_to_vec(&self, key: &[u8], dest: &mut Vec<u8>) -> Result<usize, ()> {
-        let key = xxh3_128(key).to_le();
+        let key = self.hash_key(key);
 
-        self.inner_get_to_vec(key, dest)
+        let result = self.inner_get_to_vec(key, dest);
+        #[cfg(feature = "tracing")]
+        tracing::trace!(op = "get", success = result.is_ok());
+        result
     }
 
     #[inline]
@@ -148,37 +1026,100 @@ impl Store {
         }
     }
 
+    ///Retrieves value for `key`, passing it to `cb`, and returning its result.
+    ///
+    ///Value is decrypted into internal buffer, which is reused by subsequent calls and wiped once `cb` returns,
+    ///so repeated reads do not allocate. Refer to `Self::get_guarded` for details.
+    ///
+    ///Returns `None` if decryption failed.
+    pub fn get_with<R, F: FnOnce(&[u8]) -> R>(&self, key: &[u8], cb: F) -> Option<R> {
+        self.get_guarded(key).map(|value| cb(&value))
+    }
+
     #[inline]
     ///Checks for `key` presence within storage
     pub fn contains(&self, key: &[u8]) -> bool {
-        let key = xxh3_128(key).to_le();
+        let key = self.hash_key(key);
 
-        self.inner().contains_key(&key)
+        self.inner.contains(key)
     }
 
     ///Inserts new owned `value` for `key`, returning previous one, if any.
-    pub fn insert_owned(&mut self, key: &[u8], mut value: Vec<u8>) -> Option<Vec<u8>> {
+    ///
+    ///Returns `Error::LimitExceeded` if value doesn't fit store's limits, leaving it untouched.
+    pub fn try_insert_owned(&mut self, name: &[u8], value: Vec<u8>) -> Result<Option<Vec<u8>>, Error> {
+        let key = self.hash_key(name);
+        self.try_insert_hash(key, Some(name), value)
+    }
+
+    ///Inserts `value` under hash `key`, recording its `name`, if known.
+    fn try_insert_hash(&mut self, key: u128, name: Option<&[u8]>, value: Vec<u8>) -> Result<Option<Vec<u8>>, Error> {
+        let value = self.try_seal(key, value)?;
+        if let Some(name) = name {
+            self.record_name(key, name);
+        }
+        Ok(self.put_sealed(key, value))
+    }
+
+    ///Seals `value` for hash `key`, once it is checked to fit store's limits.
+    pub(crate) fn try_seal(&self, key: u128, mut value: Vec<u8>) -> Result<Vec<u8>, Error> {
+        let result = self.check_limits(key, value.len(), self.sealing.sealed_len(value.len()));
+        #[cfg(feature = "audit")]
+        self.audit.record(AuditOp::Insert, key, result.is_ok());
+        #[cfg(feature = "tracing")]
+        tracing::trace!(op = "insert", success = result.is_ok());
+        result?;
+
         assert_ne!(value.len(), 0);
+        assert!(self.sealing.seal(&self.enc, key, &mut value));
+        Ok(value)
+    }
 
-        let key = xxh3_128(key).to_le();
+    ///Inserts ciphertext `value` under hash `key`, as returned by `Self::get_encrypted`, returning previous ciphertext, if any.
+    ///
+    ///It is intended to move entries between replicas of the same store without decrypting and re-encrypting them,
+    ///so value is not verified until it is read, and one of another store cannot be decrypted.
+    ///
+    ///Returns `Error::InvalidEntry` if `key` is reserved for internal entries or `value` is too short to be ciphertext,
+    ///or `Error::LimitExceeded` if value doesn't fit store's limits, leaving it untouched.
+    pub fn insert_encrypted(&mut self, key: u128, value: Vec<u8>) -> Result<Option<Vec<u8>>, Error> {
+        if key < RESERVED || value.len() <= enc::TAG_LEN {
+            return Err(Error::InvalidEntry(key));
+        }
 
-        assert!(self.enc.encrypt(key, &mut value));
+        let plain_len = seal::plain_len(&self.enc, key, &value).unwrap_or(value.len());
+        let result = self.check_limits(key, plain_len, value.len());
+        #[cfg(feature = "audit")]
+        self.audit.record(AuditOp::Insert, key, result.is_ok());
+        #[cfg(feature = "tracing")]
+        tracing::trace!(op = "insert", success = result.is_ok());
+        result?;
+        Ok(self.inner_put(key, value))
+    }
 
-        self.inner.insert(key, value).and_then(|mut value| {
-            match self.enc.decrypt(key, &mut value) {
-                Some(written) => {
-                    let len = written.len();
-                    value.truncate(len);
-                    value.shrink_to_fit();
-                    Some(value)
-                },
-                None => None
-            }
-        })
+    #[inline]
+    ///Inserts new `value` for `key`, returning previous one, if any.
+    ///
+    ///Returns `Error::LimitExceeded` if value doesn't fit store's limits, leaving it untouched.
+    pub fn try_insert(&mut self, key: &[u8], value: &[u8]) -> Result<Option<Vec<u8>>, Error> {
+        self.try_insert_owned(key, value.to_owned())
+    }
+
+    #[inline]
+    ///Inserts new owned `value` for `key`, returning previous one, if any.
+    ///
+    ///Panics if value doesn't fit store's limits, refer to `Self::try_insert_owned`.
+    pub fn insert_owned(&mut self, key: &[u8], value: Vec<u8>) -> Option<Vec<u8>> {
+        match self.try_insert_owned(key, value) {
+            Ok(result) => result,
+            Err(error) => panic!("Cannot insert value: {}", error),
+        }
     }
 
     #[inline]
     ///Inserts new `value` for `key`, returning previous one, if any.
+    ///
+    ///Panics if value doesn't fit store's limits, refer to `Self::try_insert`.
     pub fn insert(&mut self, key: &[u8], value: &[u8]) -> Option<Vec<u8>> {
         self.insert_owned(key, value.to_owned())
     }
@@ -190,16 +1131,21 @@ impl Store {
     ///
     ///Note that value is removed only if `Ok(size) >= Ok(1)`
     pub fn remove_to(&mut self, key: &[u8], dest: &mut [u8]) -> Result<usize, ()> {
-        let key = xxh3_128(key).to_le();
+        let key = self.hash_key(key);
 
-        match self.inner_get_to(key, dest) {
+        let result = match self.inner_get_to(key, dest) {
             Ok(0) => Ok(0),
             Ok(result) => {
-                let _ = self.inner.remove(&key);
+                discard(self.inner_take(key));
                 Ok(result)
             },
             Err(_) => Err(()),
-        }
+        };
+        #[cfg(feature = "audit")]
+        self.audit.record(AuditOp::Remove, key, result.map(|size| size > 0).unwrap_or(false));
+        #[cfg(feature = "tracing")]
+        tracing::trace!(op = "remove", success = result.map(|size| size > 0).unwrap_or(false));
+        result
     }
 
     ///Extracts value under `key` to specified `dest`
@@ -207,15 +1153,24 @@ impl Store {
     ///Returns `Err` when key doesn't exist, or user has no permission to read it.
     ///Otherwise returns number of bytes written.
     pub fn remove_to_vec(&mut self, key: &[u8], dest: &mut Vec<u8>) -> Result<usize, ()> {
-        let key = xxh3_128(key).to_le();
+        let key = self.hash_key(key);
+        self.remove_hash_to_vec(key, dest)
+    }
 
-        match self.inner_get_to_vec(key, dest) {
+    ///Extracts value under hash `key` to specified `dest`, refer to `Self::remove_to_vec`.
+    fn remove_hash_to_vec(&mut self, key: u128, dest: &mut Vec<u8>) -> Result<usize, ()> {
+        let result = match self.inner_get_to_vec(key, dest) {
             Ok(result) => {
-                let _ = self.inner.remove(&key);
+                discard(self.inner_take(key));
                 Ok(result)
             },
             Err(_) => Err(()),
-        }
+        };
+        #[cfg(feature = "audit")]
+        self.audit.record(AuditOp::Remove, key, result.is_ok());
+        #[cfg(feature = "tracing")]
+        tracing::trace!(op = "remove", success = result.is_ok());
+        result
     }
 
     #[inline]
@@ -235,7 +1190,18 @@ impl Store {
     ///
     ///Note that it only removes value, without checking if you can read it.
     pub fn remove_key(&mut self, key: &[u8]) -> bool {
-        let key = xxh3_128(key).to_le();
-        self.inner.remove(&key).is_some()
+        let key = self.hash_key(key);
+        let result = match self.inner_take(key) {
+            Some(mut value) => {
+                enc::wipe(&mut value);
+                true
+            },
+            None => false,
+        };
+        #[cfg(feature = "audit")]
+        self.audit.record(AuditOp::Remove, key, result);
+        #[cfg(feature = "tracing")]
+        tracing::trace!(op = "remove", success = result);
+        result
     }
 }
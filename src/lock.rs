@@ -0,0 +1,73 @@
+use crate::{enc, Backend, Error, Kdf, MasterKey, Store, HEADER_KEY};
+
+impl<B: Backend> Store<B> {
+    ///Locks store, wiping encryption key from memory, while keeping ciphertexts.
+    ///
+    ///While locked, values cannot be read, and operations that need key fail with `Error::Locked`
+    ///(or panic, for their infallible variants), until store is unlocked via `Self::unlock`.
+    ///Integrity MAC and audit log, if any, are not updated while locked.
+    pub fn lock(&mut self) {
+        if self.locked {
+            return;
+        }
+
+        self.flush_versions();
+        #[cfg(feature = "audit")]
+        match self.verify_mac() {
+            true => self.update_mac(),
+            false => self.flush_audit(),
+        }
+        self.enc.wipe();
+        let mut scratch = self.scratch.lock().unwrap_or_else(|error| error.into_inner());
+        enc::wipe(&mut scratch);
+        scratch.truncate(0);
+        drop(scratch);
+        if let Some(names) = self.names.take() {
+            for (mut name, _) in names {
+                enc::wipe(&mut name);
+            }
+        }
+        crate::tags::wipe(&mut self.tags);
+        self.locked = true;
+    }
+
+    ///Unlocks store, re-deriving encryption key from credentials.
+    ///
+    ///Returns error when:
+    ///
+    ///- `Error::InvalidCredentials` - `user` or `pass` is empty.
+    ///- `Error::WrongCredentials` - credentials do not match storage, in which case it stays locked.
+    pub fn unlock(&mut self, user: &[u8], pass: &[u8]) -> Result<(), Error> {
+        let key = MasterKey::derive_with_kdf(user, pass, Kdf::of(&self.inner))?;
+        self.unlock_with_key(&key)
+    }
+
+    ///Unlocks store using encryption `key`.
+    ///
+    ///Returns `Error::WrongCredentials` if `key` doesn't match storage, in which case it stays locked.
+    pub fn unlock_with_key(&mut self, key: &MasterKey) -> Result<(), Error> {
+        let enc = enc::Manager::new(*key.as_bytes());
+        let is_valid = match self.inner.get(HEADER_KEY) {
+            Some(header) => crate::header::verify(&enc, header, &*self.hasher).is_ok(),
+            None => true,
+        };
+
+        if !is_valid {
+            return Err(Error::WrongCredentials);
+        }
+
+        self.names = crate::names::load(&enc, &self.inner);
+        self.tags = crate::tags::load(&enc, &self.inner);
+        self.versions = crate::versions::load(&enc, &self.inner);
+        self.signatures = crate::signing::load(&enc, &self.inner);
+        self.enc = enc;
+        self.locked = false;
+        Ok(())
+    }
+
+    #[inline]
+    ///Returns whether store is locked.
+    pub fn is_locked(&self) -> bool {
+        self.locked
+    }
+}
@@ -0,0 +1,116 @@
+use crate::{enc, format, kdf, Backend, Error, Store, KDF_KEY, MAC_KEY};
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::Path;
+
+///Reads entries until the first malformed one, which is pushed into `quarantine` along with what is read of it.
+fn read_map_lossy<R: Read>(input: &mut R, quarantine: &mut Vec<(u128, Vec<u8>)>) -> io::Result<BTreeMap<u128, Vec<u8>>> {
+    let count = format::read_header(input)?;
+    let mut result = BTreeMap::new();
+    for _ in 0..count {
+        let key = match format::read_key(input) {
+            Ok(key) => key,
+            Err(_) => break,
+        };
+
+        let mut len = [0u8; 4];
+        if input.read_exact(&mut len).is_err() {
+            quarantine.push((key, Vec::new()));
+            break;
+        }
+        let len = u32::from_le_bytes(len) as usize;
+        let mut value = Vec::new();
+        input.take(len as u64).read_to_end(&mut value)?;
+        if value.len() != len {
+            quarantine.push((key, value));
+            break;
+        }
+        result.insert(key, value);
+    }
+
+    Ok(result)
+}
+
+impl<B: Backend> Store<B> {
+    #[inline]
+    ///Returns entries, that are quarantined by `Store::open_lossy`, as hashes of keys along with ciphertexts.
+    ///
+    ///Ciphertext can be put back via `Self::insert_encrypted`, e.g. if it belongs to namespace.
+    pub fn quarantined(&self) -> &[(u128, Vec<u8>)] {
+        &self.quarantine
+    }
+
+    #[inline]
+    ///Takes entries, that are quarantined by `Store::open_lossy`, leaving quarantine empty.
+    pub fn take_quarantined(&mut self) -> Vec<(u128, Vec<u8>)> {
+        core::mem::take(&mut self.quarantine)
+    }
+}
+
+impl Store {
+    ///Opens storage, previously saved via `Self::save`, skipping entries, that cannot be read.
+    ///
+    ///Unlike `Self::open`, it loads everything it can out of partially corrupted storage:
+    ///
+    ///- Truncated file is read up to the first incomplete entry.
+    ///- Value, that cannot be decrypted, is removed.
+    ///- Integrity MAC, that doesn't match content, is removed, while MAC, required by storage, is recomputed over loaded entries.
+    ///
+    ///Skipped entries are kept within `Self::quarantined`.
+    ///Note that entries of namespaces cannot be decrypted by store's key, so they are quarantined as well.
+    ///
+    ///Fails if file is not storage at all, or credentials do not match it, as nothing can be decrypted.
+    pub fn open_lossy<P: AsRef<Path>>(path: P, user: &[u8], pass: &[u8]) -> io::Result<Self> {
+        if user.is_empty() || pass.is_empty() {
+            return Err(Error::InvalidCredentials.into());
+        }
+
+        let mut quarantine = Vec::new();
+        let mut inner = read_map_lossy(&mut BufReader::new(File::open(path)?), &mut quarantine)?;
+        if matches!(inner.get(&KDF_KEY), Some(value) if kdf::Kdf::from_entry(Some(value)).is_none()) {
+            if let Some(value) = inner.remove(&KDF_KEY) {
+                quarantine.push((KDF_KEY, value));
+            }
+        }
+        if matches!(inner.get(&MAC_KEY), Some(value) if value.len() != enc::MAC_LEN) {
+            if let Some(value) = inner.remove(&MAC_KEY) {
+                quarantine.push((MAC_KEY, value));
+            }
+        }
+
+        let mut result = Self::from_backend(inner, user, pass);
+        if !result.verify_credentials() {
+            return Err(Error::WrongCredentials.into());
+        }
+
+        let requires_mac = result.requires_mac();
+        if requires_mac && !result.verify_mac() {
+            if let Some(value) = result.inner.remove(&MAC_KEY) {
+                quarantine.push((MAC_KEY, value));
+            }
+        }
+
+        let corrupted: Vec<_> = result.entries().filter(|(key, value)| match result.decrypt_value(*key, value) {
+            Some(mut value) => {
+                enc::wipe(&mut value);
+                false
+            },
+            None => true,
+        }).map(|(key, _)| key).collect();
+        for key in corrupted {
+            if let Some(value) = result.inner_take(key) {
+                quarantine.push((key, value));
+            }
+        }
+
+        if requires_mac && !result.verify_mac() {
+            result.update_mac();
+        }
+
+        quarantine.sort_unstable_by_key(|(key, _)| *key);
+        result.quarantine = quarantine;
+        Ok(result)
+    }
+}
@@ -0,0 +1,126 @@
+use crate::{enc, Backend, Store};
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+///Eviction callback, receiving hash of evicted key and its decrypted value.
+///
+///Value is `None` if it cannot be decrypted.
+pub type EvictFn = dyn FnMut(u128, Option<Vec<u8>>) + Send;
+
+#[derive(Default)]
+struct Order {
+    tick: u64,
+    //tick -> key
+    keys: BTreeMap<u64, u128>,
+    //key -> tick
+    ticks: HashMap<u128, u64>,
+}
+
+impl Order {
+    fn touch(&mut self, key: u128) {
+        self.tick += 1;
+        if let Some(tick) = self.ticks.insert(key, self.tick) {
+            self.keys.remove(&tick);
+        }
+        self.keys.insert(self.tick, key);
+    }
+
+    fn forget(&mut self, key: u128) {
+        if let Some(tick) = self.ticks.remove(&key) {
+            self.keys.remove(&tick);
+        }
+    }
+}
+
+///Tracks access order of entries, evicting least recently used one once capacity is exceeded.
+pub(crate) struct Lru {
+    capacity: usize,
+    order: Mutex<Order>,
+    //Mutex only to make store `Sync`, as callback is invoked with exclusive access.
+    on_evict: Mutex<Box<EvictFn>>,
+}
+
+impl Lru {
+    pub(crate) fn new(capacity: usize, on_evict: Box<EvictFn>) -> Self {
+        Self {
+            capacity,
+            order: Mutex::new(Order::default()),
+            on_evict: Mutex::new(on_evict),
+        }
+    }
+
+    #[inline]
+    fn order(&self) -> std::sync::MutexGuard<'_, Order> {
+        self.order.lock().unwrap_or_else(|error| error.into_inner())
+    }
+
+    #[inline]
+    pub(crate) fn touch(&self, key: u128) {
+        self.order().touch(key)
+    }
+
+    #[inline]
+    pub(crate) fn forget(&self, key: u128) {
+        self.order().forget(key)
+    }
+
+    ///Starts tracking `keys` anew, in order of iteration.
+    pub(crate) fn reset<I: Iterator<Item = u128>>(&self, keys: I) {
+        let mut order = self.order();
+        *order = Order::default();
+        for key in keys {
+            order.touch(key);
+        }
+    }
+
+    #[inline]
+    fn oldest(&self) -> Option<u128> {
+        self.order().keys.values().next().copied()
+    }
+}
+
+impl<B: Backend> Store<B> {
+    #[inline]
+    ///Marks `key` as recently used, if eviction is enabled.
+    pub(crate) fn touch(&self, key: u128) {
+        if let Some(eviction) = self.eviction.as_ref() {
+            eviction.touch(key);
+        }
+    }
+
+    ///Evicts least recently used entries until store fits capacity.
+    pub(crate) fn evict(&mut self) {
+        loop {
+            let key = match self.eviction.as_ref() {
+                Some(eviction) if self.len() > eviction.capacity => match eviction.oldest() {
+                    Some(key) => key,
+                    None => break,
+                },
+                _ => break,
+            };
+
+            let value = match self.inner_take(key) {
+                Some(mut value) => {
+                    let result = self.decrypt_value(key, &value);
+                    enc::wipe(&mut value);
+                    result
+                },
+                None => continue,
+            };
+
+            if let Some(eviction) = self.eviction.as_mut() {
+                let on_evict = eviction.on_evict.get_mut().unwrap_or_else(|error| error.into_inner());
+                on_evict(key, value);
+            }
+        }
+    }
+
+    #[inline]
+    ///Returns capacity, after which least recently used entries are evicted.
+    ///
+    ///Refer to `StoreBuilder::evict_lru` for details.
+    pub fn capacity(&self) -> Option<usize> {
+        self.eviction.as_ref().map(|eviction| eviction.capacity)
+    }
+}
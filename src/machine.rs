@@ -0,0 +1,90 @@
+use crate::{enc, Credentials};
+
+use std::io;
+
+#[cfg(any(target_os = "linux", target_os = "android", target_os = "freebsd", target_os = "openbsd", target_os = "netbsd"))]
+fn read_id() -> io::Result<Vec<u8>> {
+    const PATHS: [&str; 3] = ["/etc/machine-id", "/var/lib/dbus/machine-id", "/etc/hostid"];
+
+    let mut error = io::Error::new(io::ErrorKind::NotFound, "No machine identifier");
+    for path in PATHS {
+        match std::fs::read(path) {
+            Ok(id) => return Ok(id),
+            Err(err) => error = err,
+        }
+    }
+    Err(error)
+}
+
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+fn read_id() -> io::Result<Vec<u8>> {
+    use std::process::Command;
+
+    #[cfg(target_os = "macos")]
+    let (output, name) = (Command::new("ioreg").args(["-rd1", "-c", "IOPlatformExpertDevice"]).output()?, "\"IOPlatformUUID\"");
+    #[cfg(target_os = "windows")]
+    let (output, name) = (Command::new("reg").args(["query", "HKLM\\SOFTWARE\\Microsoft\\Cryptography", "/v", "MachineGuid"]).output()?, "MachineGuid");
+
+    let output = String::from_utf8_lossy(&output.stdout);
+    match output.lines().find_map(|line| line.trim().strip_prefix(name)) {
+        Some(id) => Ok(id.trim_start_matches([' ', '=', '\t']).trim_start_matches("REG_SZ").trim().trim_matches('"').as_bytes().to_owned()),
+        None => Err(io::Error::new(io::ErrorKind::NotFound, "No machine identifier")),
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android", target_os = "freebsd", target_os = "openbsd", target_os = "netbsd", target_os = "macos", target_os = "windows")))]
+fn read_id() -> io::Result<Vec<u8>> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "Machine identifier is not supported"))
+}
+
+///Returns stable identifier of current machine.
+///
+///Identifier is taken from:
+///
+///- Linux and BSDs - `/etc/machine-id`, `/var/lib/dbus/machine-id` or `/etc/hostid`;
+///- macOS - `IOPlatformUUID`;
+///- Windows - `MachineGuid` of registry.
+///
+///Returns `io::ErrorKind::NotFound` if identifier is empty or missing, and `io::ErrorKind::Unsupported` on other platforms.
+pub fn machine_id() -> io::Result<Vec<u8>> {
+    let mut id = read_id()?;
+    while let Some(last) = id.last() {
+        match last.is_ascii_whitespace() {
+            true => id.pop(),
+            false => break,
+        };
+    }
+
+    match id.is_empty() {
+        true => Err(io::Error::new(io::ErrorKind::NotFound, "No machine identifier")),
+        false => Ok(id),
+    }
+}
+
+impl Credentials {
+    ///Binds credentials to current machine, using `machine_id`.
+    ///
+    ///Identifier of machine is mixed into derived key, so store cannot be opened on other machine,
+    ///even with correct password.
+    ///Note that store becomes inaccessible if identifier changes (e.g. after re-installation of OS),
+    ///hence it is only suitable for data that can be restored otherwise (e.g. cached secrets).
+    pub fn bind_to_machine(self) -> io::Result<Self> {
+        let id = machine_id()?;
+        Ok(self.bind_to(id))
+    }
+
+    ///Binds credentials to secret `id`, that identifies machine.
+    ///
+    ///Allows to use identifier, not provided by `machine_id` (e.g. key stored within OS keystore).
+    ///Refer to `Self::bind_to_machine` for details.
+    ///
+    ///Panics if `id` is empty.
+    pub fn bind_to(mut self, id: Vec<u8>) -> Self {
+        assert!(!id.is_empty());
+        if let Some(machine) = self.machine.as_mut() {
+            enc::wipe(machine);
+        }
+        self.machine = Some(id);
+        self
+    }
+}
@@ -0,0 +1,131 @@
+use crate::{enc, Backend, Error, Store};
+
+///Conflict resolution callback, receiving `(key, local, remote)` and returning value to store.
+pub type ConflictFn<'a> = dyn FnMut(u128, &[u8], &[u8]) -> Vec<u8> + 'a;
+
+///Describes how to resolve keys that are present in both stores with different values.
+pub enum MergePolicy<'a> {
+    ///Keeps value of the local store.
+    KeepLocal,
+    ///Replaces local value with the one from the other store.
+    KeepRemote,
+    ///Invokes callback, storing returned value.
+    Resolve(&'a mut ConflictFn<'a>),
+}
+
+///Wipes decrypted values of pending changes.
+fn wipe_changes(changes: Vec<(u128, Vec<u8>)>) {
+    for (_, mut value) in changes {
+        enc::wipe(&mut value);
+    }
+}
+
+impl<B: Backend> Store<B> {
+    ///Merges `other` store into `self`, re-encrypting its values with own key.
+    ///
+    ///Conflicts are keys present in both stores with different values, which are resolved using `policy`.
+    ///All decrypted values are wiped once they are no longer needed.
+    ///
+    ///Returns error, leaving `self` untouched, when:
+    ///
+    ///- `Error::Locked` - either store is locked.
+    ///- `Error::InvalidEntry` - value needed to perform merge cannot be decrypted, or resolved value is empty.
+    ///- `Error::LimitExceeded` - result doesn't fit limits.
+    ///
+    ///Otherwise returns number of keys that were added or updated.
+    pub fn merge<O: Backend>(&mut self, other: &Store<O>, mut policy: MergePolicy<'_>) -> Result<usize, Error> {
+        if self.locked || other.locked {
+            return Err(Error::Locked);
+        }
+
+        let mut changes = Vec::new();
+        let result = match self.merge_changes(other, &mut policy, &mut changes) {
+            Ok(()) => self.check_changes(&changes),
+            Err(error) => Err(error),
+        };
+        if let Err(error) = result {
+            wipe_changes(changes);
+            return Err(error);
+        }
+
+        let len = changes.len();
+        self.suspend_autosave();
+        for (key, value) in changes {
+            crate::discard(self.inner_insert(key, value));
+        }
+        self.resume_autosave();
+
+        Ok(len)
+    }
+
+    ///Collects decrypted values of `other`, that are to be stored according to `policy`, into `changes`.
+    fn merge_changes<O: Backend>(&self, other: &Store<O>, policy: &mut MergePolicy<'_>, changes: &mut Vec<(u128, Vec<u8>)>) -> Result<(), Error> {
+        for (key, value) in other.entries() {
+            let mut remote = match other.decrypt_value(key, value) {
+                Some(remote) => remote,
+                None => return Err(Error::InvalidEntry(key)),
+            };
+
+            let mut local = match self.inner.get(key) {
+                Some(local) => match self.decrypt_value(key, local) {
+                    Some(local) => local,
+                    None => {
+                        enc::wipe(&mut remote);
+                        match policy {
+                            MergePolicy::KeepLocal => continue,
+                            _ => return Err(Error::InvalidEntry(key)),
+                        }
+                    },
+                },
+                None => {
+                    changes.push((key, remote));
+                    continue;
+                }
+            };
+
+            let result = match policy {
+                _ if local == remote => Ok(()),
+                MergePolicy::KeepLocal => Ok(()),
+                MergePolicy::KeepRemote => {
+                    changes.push((key, core::mem::take(&mut remote)));
+                    Ok(())
+                },
+                MergePolicy::Resolve(ref mut resolve) => {
+                    let mut value = resolve(key, &local, &remote);
+                    match value.is_empty() {
+                        true => Err(Error::InvalidEntry(key)),
+                        false if value == local => {
+                            enc::wipe(&mut value);
+                            Ok(())
+                        },
+                        false => {
+                            changes.push((key, value));
+                            Ok(())
+                        },
+                    }
+                },
+            };
+            enc::wipe(&mut local);
+            enc::wipe(&mut remote);
+            result?;
+        }
+
+        Ok(())
+    }
+
+    ///Checks whether all `changes` fit limits together.
+    fn check_changes(&self, changes: &[(u128, Vec<u8>)]) -> Result<(), Error> {
+        let mut entries = self.len();
+        let mut size = self.size;
+        for (key, value) in changes.iter() {
+            let previous = self.inner.get(*key).map(<[u8]>::len);
+            let len = self.sealing.sealed_len(value.len());
+            self.limits.check(entries, size, previous, value.len(), len)?;
+
+            entries += previous.is_none() as usize;
+            size = size - previous.unwrap_or(0) + len;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,117 @@
+use crate::{enc, Backend, Error, Store, META_KEY};
+use crate::tags::{pop_bytes, push_bytes};
+
+use std::collections::BTreeMap;
+
+///Application-defined metadata of store.
+type Meta = BTreeMap<Vec<u8>, Vec<u8>>;
+
+///Encodes `meta` as sequence of `name | value`, each prefixed with length as `u32`.
+fn encode(meta: &Meta) -> Vec<u8> {
+    let mut result = Vec::with_capacity(meta.iter().map(|(name, value)| 8 + name.len() + value.len()).sum());
+    for (name, value) in meta.iter() {
+        push_bytes(&mut result, name);
+        push_bytes(&mut result, value);
+    }
+    result
+}
+
+fn decode(mut input: &[u8]) -> Option<Meta> {
+    let mut result = Meta::new();
+    while !input.is_empty() {
+        let name = pop_bytes(&mut input)?.to_owned();
+        let value = pop_bytes(&mut input)?.to_owned();
+        result.insert(name, value);
+    }
+
+    Some(result)
+}
+
+fn wipe(meta: Meta) {
+    for (mut name, mut value) in meta {
+        enc::wipe(&mut name);
+        enc::wipe(&mut value);
+    }
+}
+
+impl<B: Backend> Store<B> {
+    ///Decrypts metadata, returning empty one if it is not stored, or `None` if it cannot be decrypted.
+    fn load_meta(&self) -> Option<Meta> {
+        let value = match self.inner.get(META_KEY) {
+            Some(value) => value,
+            None => return Some(Meta::new()),
+        };
+
+        let mut plain = self.decrypt_value(META_KEY, value)?;
+        let result = decode(&plain);
+        enc::wipe(&mut plain);
+        result
+    }
+
+    fn write_meta(&mut self, meta: Meta) {
+        match meta.is_empty() {
+            true => {
+                self.inner.remove(META_KEY);
+            },
+            false => {
+                let mut value = encode(&meta);
+                if self.sealing.internal().seal(&self.enc, META_KEY, &mut value) {
+                    self.inner.insert(META_KEY, value);
+                }
+            },
+        }
+        wipe(meta);
+        self.autosave_changed();
+    }
+
+    ///Sets metadata `name` to `value`, returning previous value, if any.
+    ///
+    ///Metadata is application-defined data of store itself (e.g. display name or schema version),
+    ///kept within single encrypted entry, that is separate from regular entries and is not subject to store's limits.
+    ///It is decrypted on every access, so it is expected to be small.
+    ///Names, starting with `sec-store:`, are reserved, e.g. for `Self::schema_version`.
+    ///
+    ///Returns `Error::Locked` if store is locked, or `Error::InvalidEntry` if existing metadata cannot be decrypted.
+    pub fn set_meta(&mut self, name: &[u8], value: &[u8]) -> Result<Option<Vec<u8>>, Error> {
+        if self.locked {
+            return Err(Error::Locked);
+        }
+
+        let mut meta = self.load_meta().ok_or(Error::InvalidEntry(META_KEY))?;
+        let result = meta.insert(name.to_owned(), value.to_owned());
+        self.write_meta(meta);
+        Ok(result)
+    }
+
+    ///Removes metadata `name`, returning its value, if any.
+    ///
+    ///Returns `Error::Locked` if store is locked, or `Error::InvalidEntry` if existing metadata cannot be decrypted.
+    pub fn remove_meta(&mut self, name: &[u8]) -> Result<Option<Vec<u8>>, Error> {
+        if self.locked {
+            return Err(Error::Locked);
+        }
+
+        let mut meta = self.load_meta().ok_or(Error::InvalidEntry(META_KEY))?;
+        let result = meta.remove(name);
+        if result.is_some() {
+            self.write_meta(meta);
+        } else {
+            wipe(meta);
+        }
+        Ok(result)
+    }
+
+    ///Returns value of metadata `name`, if any.
+    ///
+    ///Returns `None` if store is locked, refer to `Self::set_meta`.
+    pub fn meta(&self, name: &[u8]) -> Option<Vec<u8>> {
+        if self.locked {
+            return None;
+        }
+
+        let mut meta = self.load_meta()?;
+        let result = meta.remove(name);
+        wipe(meta);
+        result
+    }
+}
@@ -0,0 +1,92 @@
+use crate::{Backend, Store};
+
+use core::sync::atomic::{AtomicU64, Ordering};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+///Counters of store's operations, returned by `Store::stats`.
+pub struct Stats {
+    ///Number of attempts to read value.
+    pub gets: u64,
+    ///Number of reads, that found and decrypted value.
+    pub hits: u64,
+    ///Number of reads of absent key.
+    pub misses: u64,
+    ///Number of reads, that found value but failed to decrypt it.
+    pub decrypt_failures: u64,
+    ///Number of stored values.
+    pub inserts: u64,
+    ///Number of removed values.
+    pub removes: u64,
+    ///Total size of stored ciphertexts.
+    pub bytes_encrypted: u64,
+    ///Total size of decrypted values.
+    pub bytes_decrypted: u64,
+}
+
+#[derive(Default)]
+pub(crate) struct Metrics {
+    gets: AtomicU64,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    decrypt_failures: AtomicU64,
+    inserts: AtomicU64,
+    removes: AtomicU64,
+    bytes_encrypted: AtomicU64,
+    bytes_decrypted: AtomicU64,
+}
+
+impl Metrics {
+    #[inline]
+    fn add(counter: &AtomicU64, value: u64) {
+        counter.fetch_add(value, Ordering::Relaxed);
+    }
+
+    ///Records read, with `None` meaning absent key, and otherwise result of decryption.
+    pub(crate) fn get(&self, result: Option<Result<usize, ()>>) {
+        Self::add(&self.gets, 1);
+        match result {
+            Some(Ok(len)) => {
+                Self::add(&self.hits, 1);
+                Self::add(&self.bytes_decrypted, len as u64);
+            },
+            Some(Err(())) => Self::add(&self.decrypt_failures, 1),
+            None => Self::add(&self.misses, 1),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn insert(&self, len: usize) {
+        Self::add(&self.inserts, 1);
+        Self::add(&self.bytes_encrypted, len as u64);
+    }
+
+    #[inline]
+    pub(crate) fn remove(&self) {
+        Self::add(&self.removes, 1);
+    }
+}
+
+impl<B: Backend> Store<B> {
+    ///Returns counters of operations, performed since creation or last `Self::reset_stats`.
+    pub fn stats(&self) -> Stats {
+        let metrics = &self.metrics;
+        Stats {
+            gets: metrics.gets.load(Ordering::Relaxed),
+            hits: metrics.hits.load(Ordering::Relaxed),
+            misses: metrics.misses.load(Ordering::Relaxed),
+            decrypt_failures: metrics.decrypt_failures.load(Ordering::Relaxed),
+            inserts: metrics.inserts.load(Ordering::Relaxed),
+            removes: metrics.removes.load(Ordering::Relaxed),
+            bytes_encrypted: metrics.bytes_encrypted.load(Ordering::Relaxed),
+            bytes_decrypted: metrics.bytes_decrypted.load(Ordering::Relaxed),
+        }
+    }
+
+    ///Resets all counters to zero.
+    pub fn reset_stats(&self) {
+        let metrics = &self.metrics;
+        for counter in [&metrics.gets, &metrics.hits, &metrics.misses, &metrics.decrypt_failures, &metrics.inserts, &metrics.removes, &metrics.bytes_encrypted, &metrics.bytes_decrypted] {
+            counter.store(0, Ordering::Relaxed);
+        }
+    }
+}
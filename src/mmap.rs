@@ -0,0 +1,144 @@
+//!Memory-mapped storage.
+
+use crate::{format, hasher, KeyHasher};
+use crate::reader::{Reader, Source};
+
+use core::{ptr, slice};
+use std::borrow::Cow;
+use std::fs::File;
+use std::io;
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+use std::sync::Arc;
+
+struct Mmap {
+    ptr: *mut libc::c_void,
+    len: usize,
+}
+
+unsafe impl Send for Mmap {}
+unsafe impl Sync for Mmap {}
+
+impl Mmap {
+    fn map(file: &File) -> io::Result<Self> {
+        let len = file.metadata()?.len() as usize;
+        if len == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+
+        let ptr = unsafe {
+            libc::mmap(ptr::null_mut(), len, libc::PROT_READ, libc::MAP_PRIVATE, file.as_raw_fd(), 0)
+        };
+
+        if ptr == libc::MAP_FAILED {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(Self {
+                ptr,
+                len,
+            })
+        }
+    }
+
+    #[inline]
+    fn as_slice(&self) -> &[u8] {
+        unsafe {
+            slice::from_raw_parts(self.ptr as *const u8, self.len)
+        }
+    }
+}
+
+impl Source for Mmap {
+    #[inline]
+    fn get(&self, _: u128, offset: usize, len: usize) -> Option<&[u8]> {
+        self.as_slice().get(offset..offset + len)
+    }
+
+    #[inline]
+    fn read(&self, offset: usize, len: usize) -> Option<Cow<'_, [u8]>> {
+        self.as_slice().get(offset..offset + len).map(Cow::Borrowed)
+    }
+}
+
+impl Drop for Mmap {
+    fn drop(&mut self) {
+        unsafe {
+            libc::munmap(self.ptr, self.len);
+        }
+    }
+}
+
+///Read-only storage, saved by `Store::save`, with ciphertexts accessed from memory-mapped file.
+///
+///Only index of entries is kept in memory, while values are decrypted by copying them out of the map on demand.
+///
+///File must not be modified while it is mapped.
+pub struct MappedStore {
+    reader: Reader<Mmap>,
+}
+
+impl MappedStore {
+    #[inline]
+    ///Maps storage file at `path`.
+    ///
+    ///Returns `Error::InvalidCredentials` or `Error::WrongCredentials` as `InvalidData`, if credentials are empty or incorrect.
+    ///Storage must use default hasher of key names, refer to `Self::open_with_hasher`.
+    pub fn open<P: AsRef<Path>>(path: P, user: &[u8], pass: &[u8]) -> io::Result<Self> {
+        Self::open_with_hasher(path, user, pass, hasher::Xxh3)
+    }
+
+    ///Maps storage file at `path`, which keys are hashed by `hasher`.
+    ///
+    ///Returns `Error::KeyHasherMismatch` as `InvalidData`, if storage uses other hasher, refer to `StoreBuilder::key_hasher`,
+    ///or `Error::IntegrityMismatch`, if storage requires integrity MAC, which doesn't match its content.
+    ///Otherwise it behaves as `Self::open`.
+    pub fn open_with_hasher<P: AsRef<Path>, H: KeyHasher + 'static>(path: P, user: &[u8], pass: &[u8], hasher: H) -> io::Result<Self> {
+        let map = Mmap::map(&File::open(path)?)?;
+        let index = format::read_index(map.as_slice())?;
+        Ok(Self {
+            reader: Reader::open(index, map, user, pass, Arc::new(hasher))?,
+        })
+    }
+
+    #[inline]
+    ///Returns number of key-value pairs
+    pub fn len(&self) -> usize {
+        self.reader.len()
+    }
+
+    #[inline]
+    ///Checks for `key` presence within storage
+    pub fn contains(&self, key: &[u8]) -> bool {
+        self.reader.contains(key)
+    }
+
+    #[inline]
+    ///Retrieves value for `key`, storing decrypted value in `dest`.
+    ///
+    ///Returns `Err` when key doesn't exist or user has no permission to read it.
+    ///Otherwise returns number of bytes written, or '0' in case of insufficient storage.
+    pub fn get_to(&self, key: &[u8], dest: &mut [u8]) -> Result<usize, ()> {
+        self.reader.get_to(key, dest)
+    }
+
+    #[inline]
+    ///Retrieves value for `key` to store in `dest`, resulting in it being overwritten.
+    ///
+    ///Returns `Err` when key doesn't exist or user has no permission to read it.
+    ///Otherwise returns number of bytes written.
+    pub fn get_to_vec(&self, key: &[u8], dest: &mut Vec<u8>) -> Result<usize, ()> {
+        self.reader.get_to_vec(key, dest)
+    }
+
+    #[inline]
+    ///Retrieves value for `key`
+    ///
+    ///Returns `None` if decryption failed.
+    pub fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        let mut result = Vec::new();
+        match self.get_to_vec(key, &mut result) {
+            Ok(_) => Some(result),
+            Err(_) => None,
+        }
+    }
+}
@@ -0,0 +1,334 @@
+use crate::{chunk, enc, Backend, Error, Store};
+#[cfg(feature = "audit")]
+use crate::AuditOp;
+
+
+impl<B: Backend> Store<B> {
+    ///Modifies value of `key` in place, passing decrypted value to `cb` and encrypting result back.
+    ///
+    ///Value is decrypted into single buffer, which is encrypted as new value once `cb` returns.
+    ///
+    ///Returns error when:
+    ///
+    ///- `Error::NotFound` - `key` doesn't exist.
+    ///- `Error::Locked` - store is locked.
+    ///- `Error::InvalidEntry` - value cannot be decrypted.
+    ///- `Error::LimitExceeded` - modified value doesn't fit store's limits.
+    ///
+    ///Store is left untouched on error, while modified value is wiped.
+    ///Panics if `cb` leaves value empty, as empty values cannot be stored.
+    pub fn update<F: FnOnce(&mut Vec<u8>)>(&mut self, key: &[u8], cb: F) -> Result<(), Error> {
+        let key = self.hash_key(key);
+        if self.locked {
+            return Err(Error::Locked);
+        } else if !self.inner.contains(key) {
+            return Err(Error::NotFound);
+        }
+
+        let mut value = Vec::new();
+        if self.inner_get_to_vec(key, &mut value).is_err() {
+            return Err(Error::InvalidEntry(key));
+        }
+        cb(&mut value);
+        assert_ne!(value.len(), 0, "Value cannot be empty");
+
+        let result = self.check_limits(key, value.len(), self.sealing.sealed_len(value.len()));
+        #[cfg(feature = "audit")]
+        self.audit.record(AuditOp::Insert, key, result.is_ok());
+        #[cfg(feature = "tracing")]
+        tracing::trace!(op = "insert", success = result.is_ok());
+        match result {
+            Ok(()) => {
+                if let Some(mut previous) = self.inner_insert(key, value) {
+                    enc::wipe(&mut previous);
+                }
+                Ok(())
+            },
+            Err(error) => {
+                enc::wipe(&mut value);
+                Err(error)
+            },
+        }
+    }
+
+    ///Retrieves value for `key`, inserting one, returned by `default`, if `key` doesn't exist.
+    ///
+    ///`default` is only invoked when value is missing, so it can generate fresh secret, e.g. API token.
+    ///
+    ///Returns error when:
+    ///
+    ///- `Error::Locked` - store is locked.
+    ///- `Error::InvalidEntry` - existing value cannot be decrypted, in which case it is not replaced.
+    ///- `Error::LimitExceeded` - default value doesn't fit store's limits.
+    pub fn try_get_or_insert_with<F: FnOnce() -> Vec<u8>>(&mut self, key: &[u8], default: F) -> Result<Vec<u8>, Error> {
+        let hash = self.hash_key(key);
+        if self.locked {
+            return Err(Error::Locked);
+        } else if self.inner.contains(hash) {
+            let mut value = Vec::new();
+            return match self.get_to_vec(key, &mut value) {
+                Ok(_) => Ok(value),
+                Err(()) => Err(Error::InvalidEntry(hash)),
+            };
+        }
+
+        let mut value = default();
+        match self.try_insert_owned(key, value.clone()) {
+            Ok(_) => Ok(value),
+            Err(error) => {
+                enc::wipe(&mut value);
+                Err(error)
+            },
+        }
+    }
+
+    #[inline]
+    ///Retrieves value for `key`, inserting one, returned by `default`, if `key` doesn't exist.
+    ///
+    ///Panics on error, refer to `Self::try_get_or_insert_with`.
+    pub fn get_or_insert_with<F: FnOnce() -> Vec<u8>>(&mut self, key: &[u8], default: F) -> Vec<u8> {
+        match self.try_get_or_insert_with(key, default) {
+            Ok(result) => result,
+            Err(error) => panic!("Cannot insert value: {}", error),
+        }
+    }
+
+    ///Inserts `value` for `key` only if `key` doesn't exist, returning whether it is inserted.
+    ///
+    ///Existing value is never overwritten, so provisioned secret cannot be clobbered by repeated initialization.
+    ///
+    ///Returns `Error::LimitExceeded` if value doesn't fit store's limits, or `Error::Locked` if store is locked.
+    pub fn try_insert_if_absent(&mut self, key: &[u8], value: &[u8]) -> Result<bool, Error> {
+        if self.locked {
+            return Err(Error::Locked);
+        }
+
+        match self.contains(key) {
+            true => Ok(false),
+            false => self.try_insert(key, value).map(|_| true),
+        }
+    }
+
+    #[inline]
+    ///Inserts `value` for `key` only if `key` doesn't exist, returning whether it is inserted.
+    ///
+    ///Panics on error, refer to `Self::try_insert_if_absent`.
+    pub fn insert_if_absent(&mut self, key: &[u8], value: &[u8]) -> bool {
+        match self.try_insert_if_absent(key, value) {
+            Ok(result) => result,
+            Err(error) => panic!("Cannot insert value: {}", error),
+        }
+    }
+
+    ///Replaces value of `key` with `new` only if it is equal to `expected`, returning whether it is replaced.
+    ///
+    ///Decrypted value is compared in constant time, and wiped right away,
+    ///so several components can rotate the same credential, retrying on mismatch.
+    ///
+    ///Returns error when:
+    ///
+    ///- `Error::NotFound` - `key` doesn't exist.
+    ///- `Error::Locked` - store is locked.
+    ///- `Error::InvalidEntry` - value cannot be decrypted.
+    ///- `Error::LimitExceeded` - `new` value doesn't fit store's limits.
+    ///
+    ///Store is left untouched on error.
+    pub fn compare_and_swap(&mut self, key: &[u8], expected: &[u8], new: &[u8]) -> Result<bool, Error> {
+        let hash = self.hash_key(key);
+        if self.locked {
+            return Err(Error::Locked);
+        } else if !self.inner.contains(hash) {
+            return Err(Error::NotFound);
+        }
+
+        let mut current = Vec::new();
+        if self.inner_get_to_vec(hash, &mut current).is_err() {
+            return Err(Error::InvalidEntry(hash));
+        }
+        let is_expected = enc::ct_eq(&current, expected);
+        enc::wipe(&mut current);
+
+        match is_expected {
+            true => self.try_insert(key, new).map(|previous| {
+                if let Some(mut previous) = previous {
+                    enc::wipe(&mut previous);
+                }
+                true
+            }),
+            false => Ok(false),
+        }
+    }
+
+    ///Moves value of `old_key` to `new_key`, overwriting value of `new_key`, if any.
+    ///
+    ///Ciphertext is bound to its key, hence value is decrypted and encrypted anew under `new_key`, with plaintext wiped right away.
+    ///Moved value is new entry, so tags, signature and history of `old_key` are discarded.
+    ///
+    ///Returns error when:
+    ///
+    ///- `Error::NotFound` - `old_key` doesn't exist.
+    ///- `Error::Locked` - store is locked.
+    ///- `Error::InvalidEntry` - value cannot be decrypted.
+    ///- `Error::LimitExceeded` - value doesn't fit store's limits once moved.
+    ///
+    ///Store is left untouched on error.
+    pub fn rename(&mut self, old_key: &[u8], new_key: &[u8]) -> Result<(), Error> {
+        let old = self.hash_key(old_key);
+        let new = self.hash_key(new_key);
+        if self.locked {
+            return Err(Error::Locked);
+        }
+        let old_len = match self.inner.get(old) {
+            Some(value) => value.len(),
+            None => return Err(Error::NotFound),
+        };
+        if old == new {
+            return Ok(());
+        }
+
+        let mut value = Vec::new();
+        if self.inner_get_to_vec(old, &mut value).is_err() {
+            return Err(Error::InvalidEntry(old));
+        }
+
+        //Limits are checked as if value is already removed from `old_key`.
+        let previous = self.inner.get(new).map(<[u8]>::len);
+        let result = self.limits.check(self.len() - 1, self.size - old_len, previous, value.len(), self.sealing.sealed_len(value.len()));
+        #[cfg(feature = "audit")]
+        self.audit.record(AuditOp::Insert, new, result.is_ok());
+        #[cfg(feature = "tracing")]
+        tracing::trace!(op = "insert", success = result.is_ok());
+        if let Err(error) = result {
+            enc::wipe(&mut value);
+            return Err(error);
+        }
+
+        self.suspend_autosave();
+        #[cfg(feature = "audit")]
+        self.audit.record(AuditOp::Remove, old, true);
+        #[cfg(feature = "tracing")]
+        tracing::trace!(op = "remove", success = true);
+        crate::discard(self.inner_take(old));
+        self.record_name(new, new_key);
+        if let Some(mut previous) = self.inner_insert(new, value) {
+            enc::wipe(&mut previous);
+        }
+        self.resume_autosave();
+        Ok(())
+    }
+
+    ///Appends `bytes` to value of `key`, e.g. to extend list of recovery codes.
+    ///
+    ///Chunked value, refer to `Self::set_chunking`, is extended by re-sealing only its last chunk,
+    ///while any other value is decrypted and encrypted anew, with plaintext wiped right away.
+    ///
+    ///Returns error when:
+    ///
+    ///- `Error::NotFound` - `key` doesn't exist.
+    ///- `Error::Locked` - store is locked.
+    ///- `Error::InvalidEntry` - value cannot be decrypted.
+    ///- `Error::LimitExceeded` - extended value doesn't fit store's limits.
+    ///
+    ///Store is left untouched on error.
+    pub fn append(&mut self, key: &[u8], bytes: &[u8]) -> Result<(), Error> {
+        let hash = self.hash_key(key);
+        if self.locked {
+            return Err(Error::Locked);
+        }
+        let chunked = match self.inner.get(hash) {
+            Some(value) => match self.sealing.is_chunked() {
+                true => chunk::Chunks::parse(&self.enc, hash, value).map(|chunks| (chunks.plain_len(), value.to_vec())),
+                false => None,
+            },
+            None => return Err(Error::NotFound),
+        };
+
+        if let Some((plain_len, mut value)) = chunked {
+            if !chunk::append(&self.enc, hash, &mut value, bytes) {
+                return Err(Error::InvalidEntry(hash));
+            }
+
+            let result = self.check_limits(hash, plain_len + bytes.len(), value.len());
+            #[cfg(feature = "audit")]
+            self.audit.record(AuditOp::Insert, hash, result.is_ok());
+            #[cfg(feature = "tracing")]
+            tracing::trace!(op = "insert", success = result.is_ok());
+            return result.map(|()| crate::discard(self.inner_put(hash, value)));
+        }
+
+        let mut current = Vec::new();
+        if self.inner_get_to_vec(hash, &mut current).is_err() {
+            return Err(Error::InvalidEntry(hash));
+        }
+        //Extended value is allocated upfront, so that growing it doesn't leave plaintext in freed memory.
+        let mut value = Vec::with_capacity(current.len() + bytes.len());
+        value.extend_from_slice(&current);
+        value.extend_from_slice(bytes);
+        enc::wipe(&mut current);
+
+        let result = self.check_limits(hash, value.len(), self.sealing.sealed_len(value.len()));
+        #[cfg(feature = "audit")]
+        self.audit.record(AuditOp::Insert, hash, result.is_ok());
+        #[cfg(feature = "tracing")]
+        tracing::trace!(op = "insert", success = result.is_ok());
+        match result {
+            Ok(()) => {
+                if let Some(mut previous) = self.inner_insert(hash, value) {
+                    enc::wipe(&mut previous);
+                }
+                Ok(())
+            },
+            Err(error) => {
+                enc::wipe(&mut value);
+                Err(error)
+            },
+        }
+    }
+}
+
+impl<B: Backend + Clone> Store<B> {
+    ///Encrypts batch of plaintext `entries`, e.g. provisioned from environment, returning number of inserted entries.
+    ///
+    ///Existing values are overwritten.
+    ///Autosave is postponed until whole batch is inserted, while names of keys, if stored, are written once.
+    ///
+    ///Returns `Error::Locked` if store is locked, `Error::InvalidEntry` if any value is empty,
+    ///or `Error::LimitExceeded` if entries don't fit store's limits, in which case store is rolled back.
+    pub fn extend_from_plaintext<K: AsRef<[u8]>, V: AsRef<[u8]>, I: IntoIterator<Item = (K, V)>>(&mut self, entries: I) -> Result<usize, Error> {
+        if self.locked {
+            return Err(Error::Locked);
+        }
+
+        let snapshot = self.snapshot();
+        self.suspend_autosave();
+        let mut result = Ok(0);
+        for (name, value) in entries {
+            let (name, value) = (name.as_ref(), value.as_ref());
+            let key = self.hash_key(name);
+            let inserted = self.check_limits(key, value.len(), self.sealing.sealed_len(value.len()));
+            #[cfg(feature = "audit")]
+            self.audit.record(AuditOp::Insert, key, inserted.is_ok());
+            #[cfg(feature = "tracing")]
+            tracing::trace!(op = "insert", success = inserted.is_ok());
+            if let Err(error) = inserted {
+                result = Err(error);
+                break;
+            }
+
+            if let Some(names) = self.names.as_mut() {
+                names.insert(name.to_owned(), key);
+            }
+            if let Some(mut previous) = self.inner_insert(key, value.to_owned()) {
+                enc::wipe(&mut previous);
+            }
+            result = result.map(|count| count + 1);
+        }
+
+        match result {
+            Ok(_) => self.write_names(),
+            Err(_) => self.restore(snapshot),
+        }
+        self.resume_autosave();
+        result
+    }
+}
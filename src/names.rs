@@ -0,0 +1,131 @@
+use crate::{enc, Backend, Store, NAMES_KEY};
+
+use core::ops::{Bound, RangeBounds};
+use std::collections::BTreeMap;
+
+///Names of keys, mapped to their hashes.
+pub(crate) type Names = BTreeMap<Vec<u8>, u128>;
+
+///Encodes `names` as sequence of `hash | length: u32 | name`.
+fn encode(names: &Names) -> Vec<u8> {
+    let mut result = Vec::with_capacity(names.keys().map(|name| 16 + 4 + name.len()).sum());
+    for (name, key) in names.iter() {
+        result.extend_from_slice(&key.to_le_bytes());
+        result.extend_from_slice(&(name.len() as u32).to_le_bytes());
+        result.extend_from_slice(name);
+    }
+    result
+}
+
+fn decode(mut input: &[u8]) -> Option<Names> {
+    let mut result = Names::new();
+    while !input.is_empty() {
+        if input.len() < 16 + 4 {
+            return None;
+        }
+
+        let mut key = [0u8; 16];
+        key.copy_from_slice(&input[..16]);
+        let mut len = [0u8; 4];
+        len.copy_from_slice(&input[16..20]);
+        let len = u32::from_le_bytes(len) as usize;
+        input = &input[20..];
+        if input.len() < len {
+            return None;
+        }
+
+        result.insert(input[..len].to_owned(), u128::from_le_bytes(key));
+        input = &input[len..];
+    }
+
+    Some(result)
+}
+
+///Loads names of keys from `inner`, returning `None` if they are not stored or cannot be decrypted.
+pub(crate) fn load<B: Backend>(enc: &enc::Manager, inner: &B) -> Option<Names> {
+    let value = inner.get(NAMES_KEY)?;
+    let mut plain = Vec::new();
+    crate::open_to_vec(enc, NAMES_KEY, value, &mut plain).ok()?;
+    let result = decode(&plain);
+    enc::wipe(&mut plain);
+    result
+}
+
+impl<B: Backend> Store<B> {
+    pub(crate) fn write_names(&mut self) {
+        if self.locked {
+            return;
+        }
+
+        if let Some(names) = self.names.as_ref() {
+            let mut value = encode(names);
+            if self.sealing.internal().seal(&self.enc, NAMES_KEY, &mut value) {
+                self.inner.insert(NAMES_KEY, value);
+            }
+        }
+    }
+
+    ///Remembers `name` of `key`, if names are stored.
+    pub(crate) fn record_name(&mut self, key: u128, name: &[u8]) {
+        if let Some(names) = self.names.as_mut() {
+            if names.get(name) != Some(&key) {
+                names.insert(name.to_owned(), key);
+                self.write_names();
+            }
+        }
+    }
+
+    ///Forgets name of `key`, if names are stored.
+    pub(crate) fn forget_name(&mut self, key: u128) {
+        if let Some(names) = self.names.as_mut() {
+            let len = names.len();
+            names.retain(|_, hash| *hash != key);
+            if names.len() != len {
+                self.write_names();
+            }
+        }
+    }
+
+    ///Enables storing of key names, allowing to query them.
+    ///
+    ///Names are kept within single encrypted entry, which is re-written on every insertion of new key and removal,
+    ///hence it is only suitable for moderate number of keys.
+    ///Only keys, inserted by name after enabling it, are known, while values of namespaces are never named.
+    ///
+    ///Does nothing if it is already enabled.
+    pub fn enable_key_names(&mut self) {
+        if self.names.is_none() && !self.locked {
+            self.names = Some(Names::new());
+            self.write_names();
+        }
+    }
+
+    #[inline]
+    ///Returns whether key names are stored, refer to `Self::enable_key_names`.
+    pub fn has_key_names(&self) -> bool {
+        self.names.is_some()
+    }
+
+    ///Returns known key names, present within store, in ascending order.
+    ///
+    ///Refer to `Self::enable_key_names` for details.
+    pub fn keys(&self) -> impl Iterator<Item = &[u8]> + '_ {
+        self.keys_range(..)
+    }
+
+    ///Returns known key names, starting with `prefix`, in ascending order.
+    ///
+    ///Refer to `Self::enable_key_names` for details.
+    pub fn keys_with_prefix<'a>(&'a self, prefix: &'a [u8]) -> impl Iterator<Item = &'a [u8]> + 'a {
+        self.keys_range((Bound::Included(prefix), Bound::Unbounded)).take_while(move |name| name.starts_with(prefix))
+    }
+
+    ///Returns known key names within `range`, in ascending order.
+    ///
+    ///Range is specified over byte slices, e.g. `(Bound::Included(&b"a"[..]), Bound::Excluded(&b"c"[..]))`.
+    ///Refer to `Self::enable_key_names` for details.
+    pub fn keys_range<'a, R: RangeBounds<[u8]> + 'a>(&'a self, range: R) -> impl Iterator<Item = &'a [u8]> + 'a {
+        let names = self.names.as_ref().map(move |names| names.range::<[u8], R>(range));
+        names.into_iter().flatten().filter(move |(_, key)| self.inner.contains(**key)).map(|(name, _)| name.as_slice())
+    }
+}
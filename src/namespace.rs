@@ -0,0 +1,191 @@
+use crate::{enc, open_to_vec, Backend, Store};
+#[cfg(feature = "audit")]
+use crate::AuditOp;
+
+use std::collections::BTreeMap;
+
+///View of store's namespace, created by `Store::namespace`.
+///
+///Each namespace has own key space, and its values are encrypted using distinct subkey,
+///derived from master key and namespace's name.
+///Hence namespaced values cannot be accessed via store directly, nor via other namespaces.
+pub struct Namespace<'a, B = BTreeMap<u128, Vec<u8>>> {
+    store: &'a mut Store<B>,
+    enc: enc::Manager,
+    ///Identifier of namespace, used to hash its keys.
+    info: Vec<u8>,
+}
+
+impl<'a, B: Backend> Namespace<'a, B> {
+    #[inline]
+    pub(crate) fn hash(&self, key: &[u8]) -> u128 {
+        self.store.hasher.hash_namespaced(&self.info, key)
+    }
+
+    ///Decrypts `value` of `key` into `dest`, overwriting it, refer to `Store::record_get`.
+    fn decrypt_to_vec(&self, key: u128, value: &[u8], dest: &mut Vec<u8>) -> Result<usize, ()> {
+        let result = open_to_vec(&self.enc, key, value, dest);
+        self.store.record_get(key, result.is_ok());
+        result
+    }
+
+    fn decrypt_value(&self, key: u128, value: &[u8]) -> Option<Vec<u8>> {
+        let mut result = Vec::new();
+        match self.decrypt_to_vec(key, value, &mut result) {
+            Ok(_) => Some(result),
+            Err(_) => None,
+        }
+    }
+
+    ///Retrieves value for `key` to store in `dest`, resulting in it being overwritten.
+    ///
+    ///Returns `Err` when key doesn't exist within namespace or user has no permission to read it.
+    ///Otherwise returns number of bytes written.
+    pub fn get_to_vec(&self, key: &[u8], dest: &mut Vec<u8>) -> Result<usize, ()> {
+        let key = self.hash(key);
+
+        let result = match self.store.inner.get(key) {
+            Some(value) => {
+                self.store.touch(key);
+                Some(self.decrypt_to_vec(key, value, dest))
+            },
+            None => {
+                self.store.record_get(key, false);
+                None
+            },
+        };
+        self.store.metrics.get(result);
+        let result = result.unwrap_or(Err(()));
+        #[cfg(feature = "tracing")]
+        tracing::trace!(op = "get", success = result.is_ok());
+        result
+    }
+
+    #[inline]
+    ///Retrieves value for `key`
+    ///
+    ///Returns `None` if decryption failed.
+    pub fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        let mut result = Vec::new();
+        match self.get_to_vec(key, &mut result) {
+            Ok(_) => Some(result),
+            Err(_) => None,
+        }
+    }
+
+    #[inline]
+    ///Checks for `key` presence within namespace
+    pub fn contains(&self, key: &[u8]) -> bool {
+        self.store.inner.contains(self.hash(key))
+    }
+
+    ///Inserts new `value` for `key`, returning previous one, if any.
+    ///
+    ///Returns `Error::InvalidEntry` if value is empty, or `Error::LimitExceeded` if it doesn't fit store's limits,
+    ///leaving store untouched.
+    pub fn try_insert(&mut self, key: &[u8], value: &[u8]) -> Result<Option<Vec<u8>>, crate::Error> {
+        let key = self.hash(key);
+
+        let result = self.store.check_limits(key, value.len(), self.store.sealing.sealed_len(value.len()));
+        #[cfg(feature = "audit")]
+        self.store.audit.record(AuditOp::Insert, key, result.is_ok());
+        #[cfg(feature = "tracing")]
+        tracing::trace!(op = "insert", success = result.is_ok());
+        result?;
+
+        let mut value = value.to_owned();
+        assert!(self.store.sealing.seal(&self.enc, key, &mut value));
+        Ok(self.store.inner_put(key, value).and_then(|mut previous| {
+            let result = self.decrypt_value(key, &previous);
+            enc::wipe(&mut previous);
+            result
+        }))
+    }
+
+    #[inline]
+    ///Inserts new `value` for `key`, returning previous one, if any.
+    ///
+    ///Panics if value doesn't fit store's limits, refer to `Self::try_insert`.
+    pub fn insert(&mut self, key: &[u8], value: &[u8]) -> Option<Vec<u8>> {
+        match self.try_insert(key, value) {
+            Ok(result) => result,
+            Err(error) => panic!("Cannot insert value: {}", error),
+        }
+    }
+
+    ///Removes value under `key`, returning it.
+    ///
+    ///Returns `None` if key doesn't exist or user has no permission to read it, in which case value is not removed.
+    pub fn remove(&mut self, key: &[u8]) -> Option<Vec<u8>> {
+        let key = self.hash(key);
+
+        let result = match self.store.inner.get(key).and_then(|value| self.decrypt_value(key, value)) {
+            Some(value) => {
+                crate::discard(self.store.inner_take(key));
+                Some(value)
+            },
+            None => None,
+        };
+        #[cfg(feature = "audit")]
+        self.store.audit.record(AuditOp::Remove, key, result.is_some());
+        #[cfg(feature = "tracing")]
+        tracing::trace!(op = "remove", success = result.is_some());
+        result
+    }
+
+    #[inline]
+    ///Returns whether `value` under `key` belongs to namespace.
+    fn owns(&self, key: u128, value: &[u8]) -> bool {
+        key >= crate::RESERVED && self.decrypt_value(key, value).is_some()
+    }
+
+    ///Returns number of key-value pairs within namespace.
+    ///
+    ///As entries of namespace are not distinguishable from others without decryption,
+    ///this requires decrypting every value of store.
+    pub fn len(&self) -> usize {
+        self.store.entries().filter(|(key, value)| self.owns(*key, value)).count()
+    }
+
+    ///Exports namespace as new store, holding only its entries.
+    ///
+    ///Exported store uses the same master key, and its entries are accessible via namespace of the same name.
+    ///Returns `Error::Locked` if store is locked.
+    pub fn export(&self) -> Result<Store, crate::Error> {
+        let mut result = Store::from_backend_with_key(BTreeMap::new(), &self.store.master_key()?);
+        result.write_header();
+        if let Some(kdf) = self.store.inner.get(crate::KDF_KEY) {
+            result.inner.insert(crate::KDF_KEY, kdf.to_owned());
+        }
+        for (key, value) in self.store.entries().filter(|(key, value)| self.owns(*key, value)) {
+            result.inner_put(key, value.to_owned());
+        }
+        Ok(result)
+    }
+}
+
+#[inline]
+fn info(name: &[u8]) -> Vec<u8> {
+    let mut info = b"sec-store:namespace:".to_vec();
+    info.extend_from_slice(name);
+    info
+}
+
+#[inline]
+///Derives encryption of namespace `name` from master encryption.
+pub(crate) fn manager(enc: &enc::Manager, name: &[u8]) -> enc::Manager {
+    enc::Manager::new(enc::expand_key(enc.key(), &info(name)))
+}
+
+impl<B: Backend> Store<B> {
+    ///Accesses namespace `name`.
+    ///
+    ///Refer to `Namespace` for details.
+    pub fn namespace(&mut self, name: &[u8]) -> Namespace<'_, B> {
+        Namespace {
+            enc: manager(&self.enc, name),
+            info: info(name),
+            store: self,
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use crate::{Backend, Store};
+
+use std::sync::mpsc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+///Modification of store, delivered to subscribers.
+pub enum ChangeEvent {
+    ///Value has been inserted or overwritten under specified key hash.
+    Insert(u128),
+    ///Value has been removed under specified key hash.
+    Remove(u128),
+    ///Whole content of store has been replaced.
+    Restore,
+    ///Store has been re-encrypted with new key.
+    Rekey,
+}
+
+impl<B: Backend> Store<B> {
+    ///Subscribes to modifications of store.
+    ///
+    ///Events are delivered synchronously as part of modification.
+    ///Subscription is dropped once receiver is dropped.
+    pub fn subscribe(&mut self) -> mpsc::Receiver<ChangeEvent> {
+        let (sender, receiver) = mpsc::channel();
+        self.subscribers.push((None, sender));
+        receiver
+    }
+
+    ///Subscribes to modifications of value under `key`.
+    ///
+    ///Besides modifications of `key` itself, events replacing whole content of store are delivered too.
+    ///Refer to `Self::subscribe` for details.
+    pub fn watch(&mut self, key: &[u8]) -> mpsc::Receiver<ChangeEvent> {
+        let (sender, receiver) = mpsc::channel();
+        self.subscribers.push((Some(self.hash_key(key)), sender));
+        receiver
+    }
+
+    pub(crate) fn notify(&mut self, event: ChangeEvent) {
+        let key = match event {
+            ChangeEvent::Insert(key) | ChangeEvent::Remove(key) => Some(key),
+            _ => None,
+        };
+
+        self.subscribers.retain(|(watched, subscriber)| match (watched, key) {
+            (Some(watched), Some(key)) if *watched != key => true,
+            _ => subscriber.send(event).is_ok(),
+        });
+    }
+}
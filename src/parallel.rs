@@ -0,0 +1,59 @@
+#[cfg(feature = "rayon")]
+use crate::{Backend, Store};
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+#[cfg(not(feature = "rayon"))]
+use std::thread;
+
+#[cfg(feature = "rayon")]
+///Applies `fun` to every item, spreading work across thread pool of `rayon`.
+///
+///Results are returned in order of `items`.
+pub(crate) fn map<T: Sync, R: Send, F: Fn(&T) -> R + Sync>(items: &[T], fun: F) -> Vec<R> {
+    items.par_iter().map(&fun).collect()
+}
+
+#[cfg(not(feature = "rayon"))]
+///Applies `fun` to every item, spreading work across available threads.
+///
+///Results are returned in order of `items`.
+pub(crate) fn map<T: Sync, R: Send, F: Fn(&T) -> R + Sync>(items: &[T], fun: F) -> Vec<R> {
+    let threads = thread::available_parallelism().map_or(1, |threads| threads.get());
+    let chunk_len = items.len().div_ceil(threads).max(1);
+    if threads == 1 || items.len() <= chunk_len {
+        return items.iter().map(fun).collect();
+    }
+
+    let fun = &fun;
+    thread::scope(|scope| {
+        let workers: Vec<_> = items.chunks(chunk_len).map(|chunk| scope.spawn(move || chunk.iter().map(fun).collect::<Vec<_>>())).collect();
+
+        let mut result = Vec::with_capacity(items.len());
+        for worker in workers {
+            match worker.join() {
+                Ok(chunk) => result.extend(chunk),
+                Err(error) => std::panic::resume_unwind(error),
+            }
+        }
+        result
+    })
+}
+
+#[cfg(feature = "rayon")]
+impl<B: Backend + Sync> Store<B> {
+    ///Retrieves values for all `keys` at once, decrypting them in parallel via `rayon`.
+    ///
+    ///Results are returned in order of `keys`, with `None` when value cannot be retrieved, as `Self::get` does.
+    pub fn get_many_parallel(&self, keys: &[&[u8]]) -> Vec<Option<Vec<u8>>> {
+        map(keys, |key| self.get(key))
+    }
+
+    ///Decrypts all values in parallel via `rayon`, returning them along with hashes of their keys.
+    ///
+    ///Value is `None` if it cannot be decrypted.
+    pub fn decrypt_all_parallel(&self) -> Vec<(u128, Option<Vec<u8>>)> {
+        let entries: Vec<_> = self.entries().collect();
+        map(&entries, |(key, value)| (*key, self.decrypt_value(*key, value)))
+    }
+}
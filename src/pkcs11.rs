@@ -0,0 +1,127 @@
+use crate::{enc, KeyWrap};
+
+use cryptoki::context::{CInitializeArgs, Pkcs11};
+use cryptoki::error::Error;
+use cryptoki::mechanism::aead::GcmParams;
+use cryptoki::mechanism::Mechanism;
+use cryptoki::object::{Attribute, KeyType, ObjectClass, ObjectHandle};
+use cryptoki::session::{Session, UserType};
+use cryptoki::types::AuthPin;
+use ring::rand::{SecureRandom, SystemRandom};
+
+use core::fmt;
+use std::path::Path;
+
+const IV_LEN: usize = 12;
+const TAG_LEN: usize = 16;
+const KEY_LEN: usize = 32;
+///Additional data, binding ciphertext to its purpose.
+const AAD: &[u8] = b"sec-store master key";
+
+///AES key of HSM, accessed via PKCS#11, that wraps master key, refer to `KeyWrap`.
+///
+///Master key is encrypted by HSM using AES-GCM with random nonce, so that key of HSM never leaves it,
+///while master key only exists in wrapped form at rest.
+///Wrapped key consists of nonce followed by ciphertext.
+pub struct Pkcs11Key {
+    session: Session,
+    key: ObjectHandle,
+}
+
+impl Pkcs11Key {
+    ///Uses AES key, labeled `label` within `session`, which is expected to be logged in, if token requires it.
+    ///
+    ///Returns `Error::InvalidValue` unless there is exactly one such key.
+    pub fn new(session: Session, label: &str) -> Result<Self, Error> {
+        let template = [
+            Attribute::Class(ObjectClass::SECRET_KEY),
+            Attribute::KeyType(KeyType::AES),
+            Attribute::Label(label.as_bytes().to_vec()),
+        ];
+        let key = match session.find_objects(&template)?.as_slice() {
+            [key] => *key,
+            _ => return Err(Error::InvalidValue),
+        };
+
+        Ok(Self {
+            session,
+            key,
+        })
+    }
+
+    ///Loads PKCS#11 `module` (e.g. `libsofthsm2.so`), and logs in as user with `pin` into token, labeled `token`,
+    ///using its AES key, labeled `label`.
+    ///
+    ///Returns `Error::InvalidValue` if there is no such token, otherwise refer to `Self::new`.
+    pub fn open<P: AsRef<Path>>(module: P, token: &str, pin: &str, label: &str) -> Result<Self, Error> {
+        let context = Pkcs11::new(module.as_ref())?;
+        match context.initialize(CInitializeArgs::OsThreads) {
+            Ok(()) | Err(Error::AlreadyInitialized) => (),
+            Err(error) => return Err(error),
+        }
+
+        let mut slot = None;
+        for candidate in context.get_slots_with_token()? {
+            if context.get_token_info(candidate)?.label() == token {
+                slot = Some(candidate);
+                break;
+            }
+        }
+        let session = match slot {
+            Some(slot) => context.open_ro_session(slot)?,
+            None => return Err(Error::InvalidValue),
+        };
+        session.login(UserType::User, Some(&AuthPin::new(pin.into())))?;
+        Self::new(session, label)
+    }
+}
+
+impl KeyWrap for Pkcs11Key {
+    type Error = Error;
+
+    fn wrap(&self, key: &[u8; 32]) -> Result<Vec<u8>, Self::Error> {
+        let mut iv = [0u8; IV_LEN];
+        if SystemRandom::new().fill(&mut iv).is_err() {
+            return Err(Error::InvalidValue);
+        }
+
+        let mut nonce = iv;
+        let mechanism = Mechanism::AesGcm(GcmParams::new(&mut nonce, AAD, ((TAG_LEN * 8) as u64).into())?);
+        let ciphertext = self.session.encrypt(&mechanism, self.key, key)?;
+        let mut result = Vec::with_capacity(IV_LEN + ciphertext.len());
+        result.extend_from_slice(&iv);
+        result.extend_from_slice(&ciphertext);
+        Ok(result)
+    }
+
+    ///Unwraps key, previously wrapped via `Self::wrap`.
+    ///
+    ///Returns `Error::InvalidValue` if `wrapped` is malformed.
+    fn unwrap(&self, wrapped: &[u8]) -> Result<[u8; 32], Self::Error> {
+        if wrapped.len() != IV_LEN + KEY_LEN + TAG_LEN {
+            return Err(Error::InvalidValue);
+        }
+
+        let mut iv = [0u8; IV_LEN];
+        iv.copy_from_slice(&wrapped[..IV_LEN]);
+        let mechanism = Mechanism::AesGcm(GcmParams::new(&mut iv, AAD, ((TAG_LEN * 8) as u64).into())?);
+        let mut plain = self.session.decrypt(&mechanism, self.key, &wrapped[IV_LEN..])?;
+        let mut result = [0u8; 32];
+        let result = match plain.len() == result.len() {
+            true => {
+                result.copy_from_slice(&plain);
+                Ok(result)
+            },
+            false => Err(Error::InvalidValue),
+        };
+        enc::wipe(&mut plain);
+        result
+    }
+}
+
+impl fmt::Debug for Pkcs11Key {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.write_str("Pkcs11Key(..)")
+    }
+}
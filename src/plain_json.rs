@@ -0,0 +1,229 @@
+//!Plaintext JSON document of entries, used for migration between systems.
+//!
+//!Document is single object, mapping key to its value, encoded as padded base64, e.g. `{"key": "dmFsdWU="}`.
+//!Entries without known name are written under `#` followed by hash of key as 32 hex digits.
+
+use crate::{base64, enc, json, Backend, Error, Store, RESERVED};
+#[cfg(feature = "audit")]
+use crate::AuditOp;
+
+use std::io::{self, Read};
+#[cfg(feature = "unsafe-exports")]
+use core::fmt::Write as _;
+#[cfg(feature = "unsafe-exports")]
+use std::io::Write;
+
+///Name of entry within document.
+enum Key {
+    Name(Vec<u8>),
+    Hash(u128),
+}
+
+impl Key {
+    fn parse(key: &[u8]) -> Self {
+        match key.split_first() {
+            Some((b'#', hash)) if hash.len() == 32 && hash.iter().all(u8::is_ascii_hexdigit) => match core::str::from_utf8(hash).ok().and_then(|hash| u128::from_str_radix(hash, 16).ok()) {
+                Some(hash) => Key::Hash(hash),
+                None => Key::Name(key.to_owned()),
+            },
+            _ => Key::Name(key.to_owned()),
+        }
+    }
+}
+
+#[inline]
+fn invalid(message: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+///Parses document, returning its entries in order.
+fn parse(input: &[u8]) -> io::Result<Vec<(Key, Vec<u8>)>> {
+    let mut result = Vec::new();
+    let mut error = "Invalid JSON document";
+    let mut text = Vec::new();
+
+    let pos = json::skip_ws(input, 0);
+    let end = match input.get(pos) {
+        Some(b'{') => json::for_each(input, pos, |key, pos| {
+            text.truncate(0);
+            let end = match json::parse_string(input, pos, Some(&mut text)) {
+                Some(end) => end,
+                None => {
+                    error = "Value must be base64 string";
+                    return Err(None);
+                },
+            };
+            match base64::decode(&text) {
+                Some(value) if !value.is_empty() => result.push((Key::parse(key.unwrap_or(&[])), value)),
+                Some(_) => {
+                    error = "Value cannot be empty";
+                    return Err(None);
+                },
+                None => {
+                    error = "Value must be base64 string";
+                    return Err(None);
+                },
+            }
+            Ok(end)
+        }),
+        _ => Err(None),
+    };
+    enc::wipe(&mut text);
+
+    match end {
+        Ok(end) if json::skip_ws(input, end) == input.len() => Ok(result),
+        _ => {
+            for (_, value) in result.iter_mut() {
+                enc::wipe(value);
+            }
+            Err(invalid(error))
+        },
+    }
+}
+
+#[cfg(feature = "unsafe-exports")]
+///Appends `text` as JSON string.
+fn push_string(out: &mut String, text: &str) {
+    out.push('"');
+    for ch in text.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            ch if (ch as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", ch as u32);
+            },
+            ch => out.push(ch),
+        }
+    }
+    out.push('"');
+}
+
+impl<B: Backend> Store<B> {
+    #[cfg(feature = "unsafe-exports")]
+    ///Writes plaintext of all entries into `writer` as JSON document, returning number of written entries.
+    ///
+    ///DANGER: document is not encrypted, so it must be handled with the same care as secrets themselves.
+    ///
+    ///Entries are named by keys, when they are known, refer to `Self::enable_key_names`.
+    ///Otherwise, as well as for names that are not valid UTF-8, key is `#` followed by hash as 32 hex digits.
+    ///Values of namespaces cannot be decrypted by store's key, hence they are skipped.
+    ///
+    ///Returns `Error::Locked` if store is locked, otherwise only errors of `writer`.
+    pub fn export_plaintext_json<W: Write>(&self, mut writer: W) -> io::Result<usize> {
+        if self.locked {
+            return Err(Error::Locked.into());
+        }
+
+        let mut names = std::collections::BTreeMap::new();
+        if let Some(known) = self.names.as_ref() {
+            for (name, hash) in known.iter() {
+                if let Ok(name) = core::str::from_utf8(name) {
+                    names.insert(*hash, name);
+                }
+            }
+        }
+
+        let mut count = 0;
+        writer.write_all(b"{")?;
+        for (hash, value) in self.entries() {
+            let mut value = match self.decrypt_value(hash, value) {
+                Some(value) => value,
+                None => continue,
+            };
+
+            let mut line = String::from(match count {
+                0 => "\n  ",
+                _ => ",\n  ",
+            });
+            match names.get(&hash) {
+                Some(name) => push_string(&mut line, name),
+                None => {
+                    let _ = write!(line, "\"#{:032x}\"", hash);
+                },
+            }
+            line.push_str(": \"");
+            base64::encode_to(&value, true, &mut line);
+            line.push('"');
+            enc::wipe(&mut value);
+
+            let mut line = line.into_bytes();
+            let result = writer.write_all(&line);
+            enc::wipe(&mut line);
+            result?;
+            count += 1;
+        }
+
+        match count {
+            0 => writer.write_all(b"}\n")?,
+            _ => writer.write_all(b"\n}\n")?,
+        }
+        writer.flush()?;
+        Ok(count)
+    }
+}
+
+impl<B: Backend + Clone> Store<B> {
+    ///Encrypts entries of JSON document, read from `reader`, returning number of inserted entries.
+    ///
+    ///Document is single object, mapping key to non-empty value, encoded as base64, e.g. `{"key": "dmFsdWU="}`.
+    ///Key, made of `#` followed by 32 hex digits, is treated as hash of key, as written by `Self::export_plaintext_json`.
+    ///Existing values are overwritten.
+    ///
+    ///Document is parsed entirely before writing anything, and store is rolled back if any entry cannot be written.
+    ///
+    ///Returns error when:
+    ///
+    ///- `Error::Locked` - store is locked.
+    ///- `Error::LimitExceeded` - entries don't fit store's limits.
+    ///- `Error::InvalidEntry` - hash is reserved for internal entries.
+    ///- `io::ErrorKind::InvalidData` - document is malformed.
+    pub fn import_plaintext_json<R: Read>(&mut self, mut reader: R) -> io::Result<usize> {
+        if self.locked {
+            return Err(Error::Locked.into());
+        }
+
+        let mut data = Vec::new();
+        let result = reader.read_to_end(&mut data).and_then(|_| parse(&data));
+        enc::wipe(&mut data);
+        let mut entries = result?;
+
+        let snapshot = self.snapshot();
+        self.suspend_autosave();
+        let mut result = Ok(entries.len());
+        for (key, value) in entries.iter_mut() {
+            let value = core::mem::take(value);
+            let inserted = match key {
+                Key::Name(name) => self.try_insert_owned(name, value),
+                Key::Hash(hash) if *hash < RESERVED => Err(Error::InvalidEntry(*hash)),
+                Key::Hash(hash) => {
+                    let result = self.check_limits(*hash, value.len(), self.sealing.sealed_len(value.len()));
+                    #[cfg(feature = "audit")]
+                    self.audit.record(AuditOp::Insert, *hash, result.is_ok());
+                    #[cfg(feature = "tracing")]
+                    tracing::trace!(op = "insert", success = result.is_ok());
+                    result.map(|()| self.inner_insert(*hash, value))
+                },
+            };
+            match inserted {
+                Ok(Some(mut previous)) => enc::wipe(&mut previous),
+                Ok(None) => (),
+                Err(error) => {
+                    result = Err(error.into());
+                    break;
+                },
+            }
+        }
+
+        if result.is_err() {
+            self.restore(snapshot);
+        }
+        self.resume_autosave();
+        for (_, value) in entries.iter_mut() {
+            enc::wipe(value);
+        }
+        result
+    }
+}
@@ -0,0 +1,195 @@
+//!Unencrypted storage for debugging.
+//!
+//!**DANGER**: values are neither encrypted, nor authenticated, so this module must never be used for real secrets.
+
+use crate::{format, Backend, Error, SecStore, Store, RESERVED};
+
+use std::collections::BTreeMap;
+use std::io;
+use std::path::Path;
+use xxhash_rust::xxh3::xxh3_128;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+///Unencrypted store with the same API and file format as `Store`.
+///
+///Keys are hashed in the same way, so file, saved by it, has the same shape as one of `Store`,
+///except that values are stored as they are, which allows to inspect and diff them.
+///
+///**DANGER**: intended only for debugging during development.
+pub struct PlaintextStore {
+    inner: BTreeMap<u128, Vec<u8>>,
+}
+
+impl PlaintextStore {
+    #[inline]
+    ///Creates new empty instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///Creates new instance with decrypted values of `store`.
+    ///
+    ///Values, that cannot be decrypted, are skipped.
+    pub fn from_store<B: Backend>(store: &Store<B>) -> Self {
+        let inner = store.entries().filter_map(|(key, value)| store.decrypt_value(key, value).map(|value| (key, value))).collect();
+        Self {
+            inner,
+        }
+    }
+
+    #[inline]
+    ///Access underlying storage, keyed by hash of keys.
+    pub fn inner(&self) -> &BTreeMap<u128, Vec<u8>> {
+        &self.inner
+    }
+
+    #[inline]
+    ///Returns underlying storage.
+    pub fn into_inner(self) -> BTreeMap<u128, Vec<u8>> {
+        self.inner
+    }
+
+    #[inline]
+    ///Retrieves value for `key`, storing it in `dest`.
+    ///
+    ///Returns `Err` when key doesn't exist.
+    ///Otherwise returns number of bytes written, or '0' in case of insufficient storage.
+    pub fn get_to(&self, key: &[u8], dest: &mut [u8]) -> Result<usize, ()> {
+        match self.inner.get(&xxh3_128(key).to_le()) {
+            Some(value) if value.len() > dest.len() => Ok(0),
+            Some(value) => {
+                dest[..value.len()].copy_from_slice(value);
+                Ok(value.len())
+            },
+            None => Err(()),
+        }
+    }
+
+    #[inline]
+    ///Retrieves value for `key` to store in `dest`, resulting in it being overwritten.
+    ///
+    ///Returns `Err` when key doesn't exist.
+    ///Otherwise returns number of bytes written.
+    pub fn get_to_vec(&self, key: &[u8], dest: &mut Vec<u8>) -> Result<usize, ()> {
+        match self.inner.get(&xxh3_128(key).to_le()) {
+            Some(value) => {
+                dest.truncate(0);
+                dest.extend_from_slice(value);
+                Ok(value.len())
+            },
+            None => Err(()),
+        }
+    }
+
+    #[inline]
+    ///Retrieves value for `key`
+    pub fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        self.inner.get(&xxh3_128(key).to_le()).cloned()
+    }
+
+    #[inline]
+    ///Checks for `key` presence within storage
+    pub fn contains(&self, key: &[u8]) -> bool {
+        self.inner.contains_key(&xxh3_128(key).to_le())
+    }
+
+    #[inline]
+    ///Inserts new `value` for `key`, returning previous one, if any.
+    ///
+    ///Returns `Error::InvalidEntry` if `value` is empty, leaving store untouched.
+    pub fn try_insert(&mut self, key: &[u8], value: &[u8]) -> Result<Option<Vec<u8>>, Error> {
+        let key = xxh3_128(key).to_le();
+        match value.is_empty() {
+            true => Err(Error::InvalidEntry(key)),
+            false => Ok(self.inner.insert(key, value.to_owned())),
+        }
+    }
+
+    #[inline]
+    ///Inserts new `value` for `key`, returning previous one, if any.
+    ///
+    ///Panics if `value` is empty, refer to `Self::try_insert`.
+    pub fn insert(&mut self, key: &[u8], value: &[u8]) -> Option<Vec<u8>> {
+        match self.try_insert(key, value) {
+            Ok(result) => result,
+            Err(error) => panic!("Cannot insert value: {}", error),
+        }
+    }
+
+    #[inline]
+    ///Removes value under `key`, returning it.
+    pub fn remove(&mut self, key: &[u8]) -> Option<Vec<u8>> {
+        self.inner.remove(&xxh3_128(key).to_le())
+    }
+
+    #[inline]
+    ///Removes `key`, returning whether it was set previously.
+    pub fn remove_key(&mut self, key: &[u8]) -> bool {
+        self.remove(key).is_some()
+    }
+
+    #[inline]
+    ///Returns number of key-value pairs
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    #[inline]
+    ///Saves storage into file at `path`, in the same format as `Store::save`.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        format::write_file(path.as_ref(), &self.inner)
+    }
+
+    ///Opens storage, previously saved via `Self::save`.
+    ///
+    ///Internal entries (e.g. of file, saved by `Store`) are skipped.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let mut inner = format::read_file(path.as_ref())?;
+        inner.retain(|key, _| *key >= RESERVED);
+        Ok(Self {
+            inner,
+        })
+    }
+}
+
+impl SecStore for PlaintextStore {
+    #[inline]
+    fn get_to_vec(&self, key: &[u8], dest: &mut Vec<u8>) -> Result<usize, ()> {
+        PlaintextStore::get_to_vec(self, key, dest)
+    }
+
+    #[inline]
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        PlaintextStore::get(self, key)
+    }
+
+    #[inline]
+    fn contains(&self, key: &[u8]) -> bool {
+        PlaintextStore::contains(self, key)
+    }
+
+    #[inline]
+    fn insert(&mut self, key: &[u8], value: &[u8]) -> Option<Vec<u8>> {
+        PlaintextStore::insert(self, key, value)
+    }
+
+    #[inline]
+    fn try_insert(&mut self, key: &[u8], value: &[u8]) -> Result<Option<Vec<u8>>, Error> {
+        PlaintextStore::try_insert(self, key, value)
+    }
+
+    #[inline]
+    fn remove(&mut self, key: &[u8]) -> Option<Vec<u8>> {
+        PlaintextStore::remove(self, key)
+    }
+
+    #[inline]
+    fn remove_key(&mut self, key: &[u8]) -> bool {
+        PlaintextStore::remove_key(self, key)
+    }
+
+    #[inline]
+    fn len(&self) -> usize {
+        PlaintextStore::len(self)
+    }
+}
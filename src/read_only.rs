@@ -0,0 +1,134 @@
+use crate::{Backend, Error, Store, ValueReader};
+
+use std::collections::BTreeMap;
+
+macro_rules! impl_getters {
+    () => {
+        #[inline]
+        ///Retrieves value for `key`, storing decrypted value in `dest`.
+        ///
+        ///Refer to `Store::get_to` for details.
+        pub fn get_to(&self, key: &[u8], dest: &mut [u8]) -> Result<usize, ()> {
+            self.store.get_to(key, dest)
+        }
+
+        #[inline]
+        ///Returns size of buffer, that is required to retrieve value for `key`.
+        ///
+        ///Refer to `Store::get_len` for details.
+        pub fn get_len(&self, key: &[u8]) -> Result<usize, Error> {
+            self.store.get_len(key)
+        }
+
+        #[inline]
+        ///Returns length of value for `key` without decrypting it.
+        ///
+        ///Refer to `Store::value_len` for details.
+        pub fn value_len(&self, key: &[u8]) -> Result<usize, Error> {
+            self.store.value_len(key)
+        }
+
+        #[inline]
+        ///Retrieves value for `key` to store in `dest`, resulting in it being overwritten.
+        ///
+        ///Refer to `Store::get_to_vec` for details.
+        pub fn get_to_vec(&self, key: &[u8], dest: &mut Vec<u8>) -> Result<usize, ()> {
+            self.store.get_to_vec(key, dest)
+        }
+
+        #[inline]
+        ///Retrieves value for `key`
+        ///
+        ///Returns `None` if decryption failed.
+        pub fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+            self.store.get(key)
+        }
+
+        #[inline]
+        ///Returns reader over decrypted value for `key`.
+        ///
+        ///Refer to `Store::get_reader` for details.
+        pub fn get_reader(&self, key: &[u8]) -> Option<ValueReader<'_>> {
+            self.store.get_reader(key)
+        }
+
+        #[inline]
+        ///Checks for `key` presence within storage
+        pub fn contains(&self, key: &[u8]) -> bool {
+            self.store.contains(key)
+        }
+
+        #[inline]
+        ///Returns number of key-value pairs
+        pub fn len(&self) -> usize {
+            self.store.len()
+        }
+
+        #[inline]
+        ///Checks whether credentials, used to create store, are correct.
+        pub fn verify_credentials(&self) -> bool {
+            self.store.verify_credentials()
+        }
+
+        #[inline]
+        ///Verifies integrity MAC stored within storage.
+        pub fn verify_mac(&self) -> bool {
+            self.store.verify_mac()
+        }
+    }
+}
+
+///Read-only view of store, created by `Store::as_read_only`.
+///
+///It only exposes getters, so it cannot be used to modify store or to access its keys.
+pub struct ReadOnlyStore<'a, B = BTreeMap<u128, Vec<u8>>> {
+    store: &'a Store<B>,
+}
+
+impl<'a, B> Clone for ReadOnlyStore<'a, B> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, B> Copy for ReadOnlyStore<'a, B> {}
+
+impl<'a, B: Backend> ReadOnlyStore<'a, B> {
+    impl_getters!();
+}
+
+///Owned read-only store, created by `Store::into_read_only`.
+///
+///It only exposes getters, so it cannot be used to modify store or to access its keys.
+pub struct OwnedReadOnlyStore<B = BTreeMap<u128, Vec<u8>>> {
+    store: Store<B>,
+}
+
+impl<B: Backend> OwnedReadOnlyStore<B> {
+    impl_getters!();
+
+    #[inline]
+    ///Borrows read-only view.
+    pub fn as_read_only(&self) -> ReadOnlyStore<'_, B> {
+        self.store.as_read_only()
+    }
+}
+
+impl<B: Backend> Store<B> {
+    #[inline]
+    ///Creates read-only view of store.
+    pub fn as_read_only(&self) -> ReadOnlyStore<'_, B> {
+        ReadOnlyStore {
+            store: self,
+        }
+    }
+
+    #[inline]
+    ///Converts store into read-only one.
+    pub fn into_read_only(self) -> OwnedReadOnlyStore<B> {
+        OwnedReadOnlyStore {
+            store: self,
+        }
+    }
+}
@@ -0,0 +1,147 @@
+//!Read-only access to saved storage, shared by lazily loaded and memory-mapped storages.
+
+use crate::{decoy, enc, header, is_legacy, open_to, open_to_vec, Error, Kdf, KeyHasher, HEADER_KEY, KDF_KEY, MAC_KEY, RESERVED};
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::io;
+use std::sync::Arc;
+
+///Source of ciphertexts, located by their offset and length within saved storage.
+pub(crate) trait Source {
+    ///Returns ciphertext under `key`, which is located at `offset` and takes `len` bytes.
+    fn get(&self, key: u128, offset: usize, len: usize) -> Option<&[u8]>;
+    ///Reads ciphertext, located at `offset` and taking `len` bytes, without keeping it.
+    fn read(&self, offset: usize, len: usize) -> Option<Cow<'_, [u8]>>;
+}
+
+///Storage, which ciphertexts are accessed via `Source`, using index of their locations.
+pub(crate) struct Reader<S> {
+    ///Offset and length of every ciphertext, by its key.
+    index: BTreeMap<u128, (usize, usize)>,
+    pub(crate) source: S,
+    enc: enc::Manager,
+    hasher: Arc<dyn KeyHasher>,
+}
+
+impl<S: Source> Reader<S> {
+    ///Validates storage, that is made of entries at `index` within `source`.
+    ///
+    ///Returns error, converted into `InvalidData`, when:
+    ///
+    ///- `Error::InvalidCredentials` - `user` or `pass` is empty.
+    ///- `Error::WrongCredentials` - credentials do not match storage.
+    ///- `Error::KeyHasherMismatch` - storage uses other hasher.
+    ///- `Error::IntegrityMismatch` - storage requires integrity MAC, which is missing or doesn't match its content,
+    ///  or storage has no header, while it is not created by earlier versions, refer to `Store::verify_credentials`.
+    ///
+    ///Verification of MAC reads every ciphertext once, without keeping it.
+    pub(crate) fn open(mut index: BTreeMap<u128, (usize, usize)>, source: S, user: &[u8], pass: &[u8], hasher: Arc<dyn KeyHasher>) -> io::Result<Self> {
+        if user.is_empty() || pass.is_empty() {
+            return Err(Error::InvalidCredentials.into());
+        } else if !index.contains_key(&HEADER_KEY) && index.range(..RESERVED).next().is_some() {
+            return Err(Error::IntegrityMismatch.into());
+        }
+
+        let kdf = index.get(&KDF_KEY).and_then(|(offset, len)| source.get(KDF_KEY, *offset, *len));
+        let enc = enc::Manager::new(Kdf::from_entry(kdf).unwrap_or_default().derive(user, pass));
+        let detector = decoy::Detector::new(&enc);
+        index.retain(|key, _| !detector.is_decoy(*key));
+
+        let result = Self {
+            index,
+            source,
+            enc,
+            hasher,
+        };
+
+        let requires_mac = match result.value(HEADER_KEY) {
+            Some(header) => header::verify(&result.enc, header, &*result.hasher)?,
+            None => {
+                result.verify_legacy()?;
+                false
+            },
+        };
+        if (requires_mac || result.index.contains_key(&MAC_KEY)) && !result.verify_mac() {
+            return Err(Error::IntegrityMismatch.into());
+        }
+
+        Ok(result)
+    }
+
+    ///Verifies credentials of storage without header, created by earlier versions, using its first value.
+    ///
+    ///Returns `Error::IntegrityMismatch` if any value is sealed in the current format, meaning that header is stripped.
+    fn verify_legacy(&self) -> Result<(), Error> {
+        let entries = || self.index.range(RESERVED..).map(|(key, (offset, len))| (*key, self.source.read(*offset, *len).unwrap_or_default()));
+        if let Some((key, value)) = entries().next() {
+            let mut plain = Vec::new();
+            let is_valid = open_to_vec(&self.enc, key, &value, &mut plain).is_ok();
+            enc::wipe(&mut plain);
+            if !is_valid {
+                return Err(Error::WrongCredentials);
+            }
+        }
+
+        match is_legacy(&self.enc, entries()) {
+            true => Ok(()),
+            false => Err(Error::IntegrityMismatch),
+        }
+    }
+
+    ///Verifies integrity MAC, stored within storage, refer to `Store::verify_mac`.
+    fn verify_mac(&self) -> bool {
+        let stored = match self.value(MAC_KEY) {
+            Some(mac) => mac,
+            None => return false,
+        };
+
+        let mut mac = self.enc.mac_context();
+        for (key, (offset, len)) in self.index.iter().filter(|(key, _)| **key != MAC_KEY) {
+            match self.source.read(*offset, *len) {
+                Some(value) => mac.update(*key, &value),
+                None => return false,
+            }
+        }
+        enc::ct_eq(&mac.sign(), stored)
+    }
+
+    #[inline]
+    ///Returns ciphertext under `key`.
+    pub(crate) fn value(&self, key: u128) -> Option<&[u8]> {
+        let (offset, len) = self.index.get(&key)?;
+        self.source.get(key, *offset, *len)
+    }
+
+    #[inline]
+    ///Returns number of key-value pairs
+    pub(crate) fn len(&self) -> usize {
+        self.index.len() - self.index.range(..RESERVED).count()
+    }
+
+    #[inline]
+    ///Checks for `key` presence within storage, without accessing its value.
+    pub(crate) fn contains(&self, key: &[u8]) -> bool {
+        self.index.contains_key(&self.hasher.hash(key))
+    }
+
+    #[inline]
+    ///Retrieves value for `key`, storing decrypted value in `dest`.
+    pub(crate) fn get_to(&self, key: &[u8], dest: &mut [u8]) -> Result<usize, ()> {
+        let key = self.hasher.hash(key);
+        match self.value(key) {
+            Some(value) => open_to(&self.enc, key, value, dest),
+            None => Err(()),
+        }
+    }
+
+    #[inline]
+    ///Retrieves value for `key` to store in `dest`, resulting in it being overwritten.
+    pub(crate) fn get_to_vec(&self, key: &[u8], dest: &mut Vec<u8>) -> Result<usize, ()> {
+        let key = self.hasher.hash(key);
+        match self.value(key) {
+            Some(value) => open_to_vec(&self.enc, key, value, dest),
+            None => Err(()),
+        }
+    }
+}
@@ -0,0 +1,166 @@
+//!Typed records, mapping fields of struct to keys of namespace.
+//!
+//!Record is usually implemented via `#[derive(SecRecord)]`, available with `derive` feature,
+//!which implements `Record` for struct with named fields, each of which implements `Field`,
+//!and generates wrapper `<Name>Store`, accessing fields one by one:
+//!
+//!```rust,ignore
+//!use sec_store::{SecRecord, Store};
+//!use sec_store::record::Record;
+//!
+//!#[derive(SecRecord)]
+//!#[sec_record(namespace = "database")]
+//!struct Database {
+//!    user: String,
+//!    port: u16,
+//!}
+//!
+//!let mut store = Store::new(b"user", b"password");
+//!Database { user: "admin".to_owned(), port: 5432 }.save(&mut store).unwrap();
+//!assert_eq!(DatabaseStore::new(&mut store).port().unwrap(), 5432);
+//!```
+//!
+//!Namespace defaults to name of struct.
+
+use crate::{Backend, Error, Namespace, Store};
+
+///Value of record's field, encoded as bytes.
+///
+///Absent key is decoded as empty value, so empty value is never stored.
+pub trait Field: Sized {
+    ///Encodes value.
+    fn encode(&self) -> Vec<u8>;
+    ///Decodes value, returning `None` if it is malformed.
+    fn decode(bytes: &[u8]) -> Option<Self>;
+}
+
+impl Field for Vec<u8> {
+    #[inline]
+    fn encode(&self) -> Vec<u8> {
+        self.clone()
+    }
+
+    #[inline]
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        Some(bytes.to_owned())
+    }
+}
+
+impl Field for String {
+    #[inline]
+    fn encode(&self) -> Vec<u8> {
+        self.as_bytes().to_owned()
+    }
+
+    #[inline]
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        core::str::from_utf8(bytes).ok().map(ToOwned::to_owned)
+    }
+}
+
+impl Field for bool {
+    #[inline]
+    fn encode(&self) -> Vec<u8> {
+        vec![*self as u8]
+    }
+
+    #[inline]
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        match bytes {
+            [0] => Some(false),
+            [1] => Some(true),
+            _ => None,
+        }
+    }
+}
+
+macro_rules! impl_int_field {
+    ($($ty:ty),*) => {$(
+        impl Field for $ty {
+            #[inline]
+            fn encode(&self) -> Vec<u8> {
+                self.to_le_bytes().to_vec()
+            }
+
+            #[inline]
+            fn decode(bytes: &[u8]) -> Option<Self> {
+                let mut result = [0u8; core::mem::size_of::<$ty>()];
+                match bytes.len() == result.len() {
+                    true => {
+                        result.copy_from_slice(bytes);
+                        Some(<$ty>::from_le_bytes(result))
+                    },
+                    false => None,
+                }
+            }
+        }
+    )*};
+}
+
+impl_int_field!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128);
+
+impl<T: Field> Field for Option<T> {
+    #[inline]
+    ///Encodes `None` as empty value, so that field is removed.
+    ///
+    ///Hence `Some` with empty value is decoded as `None`.
+    fn encode(&self) -> Vec<u8> {
+        match self {
+            Some(value) => value.encode(),
+            None => Vec::new(),
+        }
+    }
+
+    #[inline]
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        match bytes.is_empty() {
+            true => Some(None),
+            false => T::decode(bytes).map(Some),
+        }
+    }
+}
+
+///Struct, stored as set of keys within namespace.
+pub trait Record: Sized {
+    ///Name of namespace, holding fields.
+    const NAMESPACE: &'static [u8];
+    ///Keys of fields.
+    const FIELDS: &'static [&'static [u8]];
+
+    ///Writes all fields into `store`.
+    ///
+    ///Returns `Error::LimitExceeded` if field doesn't fit store's limits, in which case previous fields are written.
+    fn save<B: Backend>(&self, store: &mut Store<B>) -> Result<(), Error>;
+
+    ///Reads all fields from `store`.
+    ///
+    ///Returns `Error::NotFound` if field is absent, while its empty value cannot be decoded,
+    ///or `Error::InvalidEntry` if field cannot be decrypted or decoded.
+    fn load<B: Backend>(store: &mut Store<B>) -> Result<Self, Error>;
+}
+
+///Writes `value` of field under `key`, removing it if value is empty.
+pub fn write_field<B: Backend, T: Field>(namespace: &mut Namespace<'_, B>, key: &[u8], value: &T) -> Result<(), Error> {
+    let mut value = value.encode();
+    let result = match value.is_empty() {
+        true => {
+            namespace.remove(key);
+            Ok(())
+        },
+        false => namespace.try_insert(key, &value).map(|_| ()),
+    };
+    crate::enc::wipe(&mut value);
+    result
+}
+
+///Reads value of field under `key`, refer to `Record::load` for errors.
+pub fn read_field<B: Backend, T: Field>(namespace: &Namespace<'_, B>, key: &[u8]) -> Result<T, Error> {
+    if !namespace.contains(key) {
+        return T::decode(&[]).ok_or(Error::NotFound);
+    }
+
+    let mut value = namespace.get(key).ok_or_else(|| Error::InvalidEntry(namespace.hash(key)))?;
+    let result = T::decode(&value).ok_or_else(|| Error::InvalidEntry(namespace.hash(key)));
+    crate::enc::wipe(&mut value);
+    result
+}
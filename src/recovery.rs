@@ -0,0 +1,149 @@
+use crate::{enc, Backend, Error, MasterKey, Store, RECOVERY_KEY};
+
+use core::{fmt, str};
+use ring::rand::{SecureRandom, SystemRandom};
+
+const ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+const LEN: usize = 25;
+const GROUP_LEN: usize = 5;
+
+#[derive(Clone, PartialEq, Eq)]
+///Printable key, allowing to recover master key of store without credentials.
+///
+///It is displayed as groups of base32 characters, e.g. `ABCDE-FGHIJ-...`, and can be parsed back from such string.
+pub struct RecoveryKey([u8; LEN]);
+
+impl RecoveryKey {
+    fn manager(&self) -> enc::Manager {
+        enc::Manager::new(enc::expand_key(&self.0, b"sec-store:recovery"))
+    }
+
+    ///Recovers master key of storage, that has this key enabled via `Store::enable_recovery`.
+    ///
+    ///Returns `Error::WrongCredentials` if storage has no recovery key or it doesn't match.
+    pub fn recover<B: Backend>(&self, backend: &B) -> Result<MasterKey, Error> {
+        let wrapped = backend.get(RECOVERY_KEY).ok_or(Error::WrongCredentials)?;
+        let mut key = self.manager().open_random(wrapped).ok_or(Error::WrongCredentials)?;
+
+        let result = match key.len() {
+            32 => {
+                let mut result = [0u8; 32];
+                result.copy_from_slice(&key);
+                Ok(MasterKey::from_bytes(result))
+            },
+            _ => Err(Error::WrongCredentials),
+        };
+        enc::wipe(&mut key);
+        result
+    }
+}
+
+impl fmt::Display for RecoveryKey {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut text = [0u8; LEN * 8 / 5];
+        for (chunk, out) in self.0.chunks_exact(5).zip(text.chunks_exact_mut(8)) {
+            let mut bits = chunk.iter().fold(0u64, |acc, byte| (acc << 8) | *byte as u64);
+            for out in out.iter_mut().rev() {
+                *out = ALPHABET[(bits & 0x1f) as usize];
+                bits >>= 5;
+            }
+        }
+
+        for (idx, group) in text.chunks_exact(GROUP_LEN).enumerate() {
+            if idx > 0 {
+                fmt.write_str("-")?;
+            }
+            //Alphabet is ASCII
+            fmt.write_str(str::from_utf8(group).map_err(|_| fmt::Error)?)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Debug for RecoveryKey {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.write_str("RecoveryKey(..)")
+    }
+}
+
+impl str::FromStr for RecoveryKey {
+    type Err = Error;
+
+    ///Parses key, ignoring case, whitespaces and dashes.
+    ///
+    ///Returns `Error::InvalidCredentials` if text is not valid key.
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut result = [0u8; LEN];
+        let mut bits = 0u64;
+        let mut bits_len = 0;
+        let mut len = 0;
+
+        for ch in text.bytes().filter(|ch| *ch != b'-' && !ch.is_ascii_whitespace()) {
+            let value = match ALPHABET.iter().position(|letter| *letter == ch.to_ascii_uppercase()) {
+                Some(value) => value as u64,
+                None => return Err(Error::InvalidCredentials),
+            };
+
+            bits = (bits << 5) | value;
+            bits_len += 5;
+            if bits_len >= 8 {
+                bits_len -= 8;
+                match result.get_mut(len) {
+                    Some(byte) => *byte = (bits >> bits_len) as u8,
+                    None => return Err(Error::InvalidCredentials),
+                }
+                len += 1;
+            }
+        }
+
+        match len == LEN && bits_len == 0 {
+            true => Ok(Self(result)),
+            false => Err(Error::InvalidCredentials),
+        }
+    }
+}
+
+impl Drop for RecoveryKey {
+    #[inline]
+    fn drop(&mut self) {
+        enc::wipe(&mut self.0);
+    }
+}
+
+impl<B: Backend> Store<B> {
+    ///Generates new recovery key, which can be used to recover master key via `RecoveryKey::recover`.
+    ///
+    ///Master key is stored within storage, encrypted with recovery key.
+    ///Generating new recovery key invalidates previous one.
+    ///
+    ///Recovery key should be shown to user once and never stored alongside storage.
+    ///Returns `Error::Locked` if store is locked, and `Error::RandomFailure` if random key cannot be generated.
+    pub fn enable_recovery(&mut self) -> Result<RecoveryKey, Error> {
+        if self.locked {
+            return Err(Error::Locked);
+        }
+
+        let mut key = [0u8; LEN];
+        if SystemRandom::new().fill(&mut key).is_err() {
+            return Err(Error::RandomFailure);
+        }
+        let result = RecoveryKey(key);
+        enc::wipe(&mut key);
+
+        match result.manager().seal_random(self.enc.key()) {
+            Some(wrapped) => {
+                self.inner.insert(RECOVERY_KEY, wrapped);
+                Ok(result)
+            },
+            None => Err(Error::RandomFailure),
+        }
+    }
+
+    #[inline]
+    ///Removes recovery key, if any.
+    pub fn disable_recovery(&mut self) -> bool {
+        self.inner.remove(RECOVERY_KEY).is_some()
+    }
+}
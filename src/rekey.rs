@@ -0,0 +1,97 @@
+use crate::{chunk, enc, namespace, parallel, seal, Backend, ChangeEvent, Error, MasterKey, Store};
+use crate::{AUDIT_KEY, HEADER_KEY, HISTORY_KEY, KDF_KEY, MAC_KEY, META_KEY, NAMES_KEY, RECOVERY_KEY, RESERVED, SIGNATURES_KEY, TAGS_KEY, VERSIONS_KEY};
+
+///Re-encrypts user's value, preserving its chunking, while other values are sealed according to `sealing`.
+fn reencrypt(old: &enc::Manager, new: &enc::Manager, sealing: seal::Sealing, key: u128, value: &[u8]) -> Option<Vec<u8>> {
+    match chunk::Chunks::parse(old, key, value) {
+        Some(chunks) => {
+            let mut plain = Vec::with_capacity(chunks.plain_len());
+            let result = match chunks.decrypt_to_vec(old, &mut plain) {
+                true => chunk::seal(new, key, &mut &plain[..], chunks.chunk_size()).ok(),
+                false => None,
+            };
+            enc::wipe(&mut plain);
+            result
+        },
+        None => {
+            let mut result = Vec::with_capacity(value.len());
+            crate::open_into(old, key, value, &mut result).ok()?;
+            match sealing.seal(new, key, &mut result) {
+                true => Some(result),
+                false => None,
+            }
+        },
+    }
+}
+
+impl<B: Backend + Sync> Store<B> {
+    #[inline]
+    ///Re-encrypts whole store with new `key`.
+    ///
+    ///Refer to `Self::rekey_namespaces` for details.
+    pub fn rekey(&mut self, key: &MasterKey) -> Result<(), Error> {
+        self.rekey_namespaces(key, &[])
+    }
+
+    ///Re-encrypts whole store, including values of specified `namespaces`, with new `key`.
+    ///
+    ///Values are re-encrypted in parallel, and store is modified only once all of them succeed.
+    ///Regular values are re-encrypted according to `Self::is_randomized`.
+    ///Integrity MAC, if required, is updated, while recovery key is removed, as it can only recover old key.
+    ///Persisted function of key derivation is removed as well, as new key is not derived using it.
+    ///
+    ///Returns `Error::InvalidEntry` if any value cannot be decrypted, or `Error::Locked` if store is locked, leaving store untouched.
+    ///Note that values of namespaces, that are not specified, cannot be decrypted.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, err))]
+    pub fn rekey_namespaces(&mut self, key: &MasterKey, namespaces: &[&[u8]]) -> Result<(), Error> {
+        if self.locked {
+            return Err(Error::Locked);
+        }
+
+        self.flush_versions();
+        let new = enc::Manager::new(*key.as_bytes());
+        let olds: Vec<_> = namespaces.iter().map(|name| namespace::manager(&self.enc, name)).collect();
+        let news: Vec<_> = namespaces.iter().map(|name| namespace::manager(&new, name)).collect();
+
+        let entries: Vec<_> = self.inner.iter().filter(|(key, _)| *key != MAC_KEY && *key != RECOVERY_KEY && *key != KDF_KEY).collect();
+        let reencrypted = parallel::map(&entries, |(key, value)| {
+            let key = *key;
+            let result = match key {
+                //Header records hasher of key names, so its content is preserved.
+                HEADER_KEY => self.decrypt_value(HEADER_KEY, value).and_then(|mut header| match new.encrypt_random(HEADER_KEY, &mut header) {
+                    true => Some(header),
+                    false => None,
+                }),
+                AUDIT_KEY => self.enc.open_random(value).and_then(|log| new.seal_random(&log)),
+                NAMES_KEY | TAGS_KEY | VERSIONS_KEY | SIGNATURES_KEY | META_KEY | HISTORY_KEY => reencrypt(&self.enc, &new, self.sealing.internal(), key, value),
+                key if key < RESERVED => Some(value.to_vec()),
+                key => reencrypt(&self.enc, &new, self.sealing, key, value).or_else(|| {
+                    olds.iter().zip(news.iter()).find_map(|(old, new)| reencrypt(old, new, self.sealing, key, value))
+                }),
+            };
+            result.ok_or(Error::InvalidEntry(key))
+        });
+
+        let mut changes = Vec::with_capacity(reencrypted.len());
+        for (entry, value) in entries.iter().zip(reencrypted) {
+            changes.push((entry.0, value?));
+        }
+
+        let requires_mac = self.requires_mac();
+        self.inner.remove(RECOVERY_KEY);
+        self.inner.remove(KDF_KEY);
+        for (key, value) in changes {
+            self.inner.insert(key, value);
+        }
+        self.enc = new;
+        self.forget_decoys();
+        self.size = crate::entries_size(&self.inner);
+        if requires_mac {
+            self.update_mac();
+        }
+
+        self.notify(ChangeEvent::Rekey);
+        self.autosave_changed();
+        Ok(())
+    }
+}
@@ -0,0 +1,106 @@
+use crate::{Backend, Error, Store};
+
+use std::collections::BTreeMap;
+use std::io;
+use std::path::Path;
+
+///Name of metadata, holding schema version.
+const SCHEMA_META: &[u8] = b"sec-store:schema-version";
+
+///Migration of store's content to the next schema version.
+pub type MigrationFn<'a, B> = dyn FnMut(&mut Store<B>) -> Result<(), Error> + 'a;
+
+///Ordered set of migrations, run by `Store::migrate` or `Store::open_migrated`.
+pub struct Migrations<'a, B = BTreeMap<u128, Vec<u8>>> {
+    ///Migrations, keyed by schema version they produce.
+    steps: BTreeMap<u32, Box<MigrationFn<'a, B>>>,
+}
+
+impl<'a, B: Backend> Migrations<'a, B> {
+    #[inline]
+    ///Creates empty set of migrations.
+    pub fn new() -> Self {
+        Self {
+            steps: BTreeMap::new(),
+        }
+    }
+
+    #[inline]
+    ///Adds `migration`, upgrading store from previous schema version to `version`, replacing previous one, if any.
+    pub fn add<F: FnMut(&mut Store<B>) -> Result<(), Error> + 'a>(mut self, version: u32, migration: F) -> Self {
+        self.steps.insert(version, Box::new(migration));
+        self
+    }
+
+    #[inline]
+    ///Returns latest schema version, produced by migrations, or `0` if there are none.
+    pub fn latest(&self) -> u32 {
+        self.steps.keys().next_back().copied().unwrap_or(0)
+    }
+}
+
+impl<'a, B: Backend> Default for Migrations<'a, B> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<B: Backend> Store<B> {
+    ///Returns schema version of store's content, or `0` if it is not set.
+    ///
+    ///Version is kept within store's metadata, refer to `Self::set_meta`.
+    pub fn schema_version(&self) -> u32 {
+        match self.meta(SCHEMA_META) {
+            Some(version) if version.len() == 4 => {
+                let mut result = [0u8; 4];
+                result.copy_from_slice(&version);
+                u32::from_le_bytes(result)
+            },
+            _ => 0,
+        }
+    }
+
+    #[inline]
+    ///Sets schema version of store's content, e.g. when new store is created with the latest one.
+    ///
+    ///Returns `Error::Locked` if store is locked.
+    pub fn set_schema_version(&mut self, version: u32) -> Result<(), Error> {
+        self.set_meta(SCHEMA_META, &version.to_le_bytes()).map(|_| ())
+    }
+}
+
+impl<B: Backend + Clone> Store<B> {
+    ///Runs migrations, that produce schema version newer than current one, in ascending order,
+    ///returning number of applied migrations.
+    ///
+    ///Schema version is updated after each migration, so that it is never repeated.
+    ///If migration fails, its changes are rolled back, leaving store at the version of last successful one,
+    ///and its error is returned.
+    pub fn migrate(&mut self, migrations: &mut Migrations<'_, B>) -> Result<usize, Error> {
+        let current = self.schema_version();
+        let mut result = 0;
+        for (version, migration) in migrations.steps.range_mut(current.saturating_add(1)..) {
+            let snapshot = self.snapshot();
+            if let Err(error) = migration(self).and_then(|()| self.set_schema_version(*version)) {
+                self.restore(snapshot);
+                return Err(error);
+            }
+            result += 1;
+        }
+
+        Ok(result)
+    }
+}
+
+impl Store {
+    ///Opens storage as `Self::open` does, running `migrations` if its schema version is older than the latest one.
+    ///
+    ///Migrated store is not saved automatically, so failed migration leaves file untouched.
+    ///Refer to `Self::migrate` for details.
+    pub fn open_migrated<P: AsRef<Path>>(path: P, user: &[u8], pass: &[u8], migrations: &mut Migrations<'_>) -> io::Result<Self> {
+        let mut result = Self::open(path, user, pass)?;
+        result.migrate(migrations)?;
+        Ok(result)
+    }
+}
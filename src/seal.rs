@@ -0,0 +1,399 @@
+//!Sealing of values.
+//!
+//!Layout: `nonce | sealed len: u64 | sealed value`, where value is followed by zeroes up to its padded length.
+//!Length and value are sealed separately, using the same nonce with its top bit cleared and set respectively,
+//!so that length can be read without decrypting whole value, while neither of them is visible.
+//!
+//!Values of format `1`, refer to `Store::migrate_format`, are bare ciphertexts, which are still decrypted.
+
+use crate::{chunk, enc, Backend, Store};
+
+///Marker, separating value from padding, within format `1`.
+const PAD_MARKER: u8 = 0x80;
+///Marker of envelope, authenticated along with sealed length.
+const LEN_MARKER: u8 = 0x4C;
+///Size of sealed length.
+const SEALED_LEN: usize = 8 + enc::TAG_LEN;
+///Size of envelope, made of `nonce | sealed len`, in front of sealed value.
+const ENVELOPE_LEN: usize = enc::NONCE_LEN + SEALED_LEN;
+///Bit of nonce's last byte, that distinguishes nonce of value from nonce of its length.
+const VALUE_NONCE: u8 = 0x80;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+///Padding of values, hiding their exact length.
+///
+///Value is followed by zeroes, up to padded length, while its exact length is sealed separately.
+pub enum Padding {
+    ///Pads to multiple of specified number of bytes.
+    ///
+    ///Block of zero size is treated as `1`.
+    Block(usize),
+    ///Pads using Padmé, which leaks at most `O(log log len)` bits of length, with overhead of at most 12%.
+    Padme,
+}
+
+impl Padding {
+    ///Returns padded length of `len` bytes.
+    pub fn padded_len(&self, len: usize) -> usize {
+        match self {
+            Padding::Block(size) => {
+                let size = core::cmp::max(*size, 1);
+                len.div_ceil(size) * size
+            },
+            Padding::Padme => {
+                if len < 2 {
+                    return len;
+                }
+
+                let exponent = usize::BITS - 1 - len.leading_zeros();
+                let bits = exponent - (u32::BITS - exponent.leading_zeros());
+                let mask = (1usize << bits) - 1;
+                (len + mask) & !mask
+            },
+        }
+    }
+}
+
+///Returns associated data of padded value of format `1`, distinguishing it from others.
+pub(crate) fn padded_aad(key: u128) -> [u8; 17] {
+    let mut aad = [PAD_MARKER; 17];
+    aad[..16].copy_from_slice(&key.to_le_bytes());
+    aad
+}
+
+///Returns length of padded `value` of format `1`, without padding.
+pub(crate) fn unpad(value: &[u8]) -> Option<usize> {
+    match value.iter().rposition(|byte| *byte != 0) {
+        Some(len) if value[len] == PAD_MARKER => Some(len),
+        _ => None,
+    }
+}
+
+#[inline]
+///Returns associated data of sealed length.
+fn len_aad(key: u128) -> [u8; 17] {
+    let mut aad = [LEN_MARKER; 17];
+    aad[..16].copy_from_slice(&key.to_le_bytes());
+    aad
+}
+
+#[inline]
+///Returns associated data of sealed value, binding it to its length.
+fn value_aad(key: u128, len: usize) -> [u8; 24] {
+    let mut aad = [0u8; 24];
+    aad[..16].copy_from_slice(&key.to_le_bytes());
+    aad[16..].copy_from_slice(&(len as u64).to_le_bytes());
+    aad
+}
+
+#[inline]
+///Returns nonce of sealed value, out of envelope's `nonce`.
+fn value_nonce(mut nonce: [u8; enc::NONCE_LEN]) -> [u8; enc::NONCE_LEN] {
+    nonce[enc::NONCE_LEN - 1] |= VALUE_NONCE;
+    nonce
+}
+
+///Ensures `value` can grow up to `capacity` without re-allocation, wiping its old buffer if it has to be moved.
+pub(crate) fn reserve_wiped(value: &mut Vec<u8>, capacity: usize) {
+    if value.capacity() < capacity {
+        let mut result = Vec::with_capacity(capacity);
+        result.extend_from_slice(value);
+        enc::wipe(value);
+        *value = result;
+    }
+}
+
+///Opened envelope of sealed value.
+pub(crate) struct Envelope<'a> {
+    nonce: [u8; enc::NONCE_LEN],
+    len: usize,
+    body: &'a [u8],
+}
+
+impl<'a> Envelope<'a> {
+    ///Decrypts length of `value`, returning `None` if it is not enveloped or cannot be authenticated.
+    pub(crate) fn open(enc: &enc::Manager, key: u128, value: &'a [u8]) -> Option<Self> {
+        if value.len() < ENVELOPE_LEN + enc::TAG_LEN {
+            return None;
+        }
+
+        let mut nonce = [0u8; enc::NONCE_LEN];
+        nonce.copy_from_slice(&value[..enc::NONCE_LEN]);
+        if nonce[enc::NONCE_LEN - 1] & VALUE_NONCE != 0 {
+            return None;
+        }
+
+        let mut len = [0u8; SEALED_LEN];
+        len.copy_from_slice(&value[enc::NONCE_LEN..ENVELOPE_LEN]);
+        let len = enc.decrypt_with(nonce, &len_aad(key), &mut len)?;
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(len);
+        let len = u64::from_le_bytes(bytes);
+
+        let body = &value[ENVELOPE_LEN..];
+        match len > 0 && len <= (body.len() - enc::TAG_LEN) as u64 {
+            true => Some(Self {
+                nonce,
+                len: len as usize,
+                body,
+            }),
+            false => None,
+        }
+    }
+
+    #[inline]
+    ///Returns length of plaintext.
+    pub(crate) fn plain_len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    ///Returns sealed value, which is decrypted in place by `Self::decrypt`.
+    pub(crate) fn body(&self) -> &'a [u8] {
+        self.body
+    }
+
+    ///Decrypts copy of `Self::body` in place, leaving plaintext at start of `dest` and wiping padding.
+    ///
+    ///`dest` is wiped if value cannot be authenticated.
+    pub(crate) fn decrypt(&self, enc: &enc::Manager, key: u128, dest: &mut [u8]) -> bool {
+        match enc.decrypt_with(value_nonce(self.nonce), &value_aad(key, self.len), dest) {
+            Some(written) => {
+                enc::wipe(&mut written[self.len..]);
+                true
+            },
+            None => {
+                enc::wipe(dest);
+                false
+            },
+        }
+    }
+}
+
+///Returns length of plaintext of `value`, if it is recorded within, that is, value is either enveloped or chunked.
+pub(crate) fn plain_len(enc: &enc::Manager, key: u128, value: &[u8]) -> Option<usize> {
+    match chunk::Chunks::parse(enc, key, value) {
+        Some(chunks) => Some(chunks.plain_len()),
+        None => Envelope::open(enc, key, value).map(|envelope| envelope.plain_len()),
+    }
+}
+
+#[inline]
+///Returns whether `value` is sealed in the current format, that is, either enveloped or chunked.
+pub(crate) fn is_current(enc: &enc::Manager, key: u128, value: &[u8]) -> bool {
+    plain_len(enc, key, value).is_some()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+///Parameters of user's values encryption.
+pub(crate) struct Sealing {
+    ///Whether values are encrypted using random nonce.
+    pub(crate) randomized: bool,
+    pub(crate) padding: Option<Padding>,
+    ///Size of chunk, values larger than which are chunked.
+    pub(crate) chunk_size: Option<usize>,
+    ///Whether values are sealed in form of format `1`, that is neither enveloped nor chunked, refer to `Store::migrate_format`.
+    pub(crate) legacy: bool,
+}
+
+impl Default for Sealing {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            randomized: false,
+            padding: None,
+            chunk_size: Some(chunk::DEFAULT_CHUNK_SIZE),
+            legacy: false,
+        }
+    }
+}
+
+impl Sealing {
+    #[inline]
+    ///Returns sealing of internal entries, that are always encrypted using random nonce, in the same format as values.
+    pub(crate) fn internal(&self) -> Self {
+        Self {
+            randomized: true,
+            padding: None,
+            chunk_size: None,
+            legacy: self.legacy,
+        }
+    }
+
+    #[inline]
+    ///Returns whether values, larger than chunk, are chunked.
+    pub(crate) fn is_chunked(&self) -> bool {
+        self.chunk_size.is_some() && !self.legacy
+    }
+
+    #[inline]
+    fn chunk_size(&self, plain_len: usize) -> Option<usize> {
+        match self.legacy {
+            true => None,
+            false => self.chunk_size.filter(|chunk_size| plain_len > *chunk_size),
+        }
+    }
+
+    #[inline]
+    ///Returns length of ciphertext, sealing `plain_len` bytes.
+    pub(crate) fn sealed_len(&self, plain_len: usize) -> usize {
+        if let Some(chunk_size) = self.chunk_size(plain_len) {
+            return chunk::sealed_len(plain_len, chunk_size);
+        }
+
+        match (self.legacy, self.padding, self.randomized) {
+            (false, Some(padding), _) => ENVELOPE_LEN + padding.padded_len(plain_len) + enc::TAG_LEN,
+            (false, None, _) => ENVELOPE_LEN + plain_len + enc::TAG_LEN,
+            (true, Some(padding), _) => enc::NONCE_LEN + padding.padded_len(plain_len + 1) + enc::TAG_LEN,
+            (true, None, true) => enc::NONCE_LEN + plain_len + enc::TAG_LEN,
+            (true, None, false) => plain_len + enc::TAG_LEN,
+        }
+    }
+
+    ///Encrypts `value` in place.
+    ///
+    ///Unless value is chunked or sealing is legacy, ciphertext is enveloped with sealed length of plaintext, refer to `Envelope`.
+    pub(crate) fn seal(&self, enc: &enc::Manager, key: u128, value: &mut Vec<u8>) -> bool {
+        if let Some(chunk_size) = self.chunk_size(value.len()) {
+            return match chunk::seal(enc, key, &mut &value[..], chunk_size) {
+                Ok(sealed) => {
+                    enc::wipe(value);
+                    *value = sealed;
+                    true
+                },
+                Err(_) => false,
+            };
+        }
+
+        match self.legacy {
+            true => self.seal_legacy(enc, key, value),
+            false => self.seal_enveloped(enc, key, value),
+        }
+    }
+
+    ///Encrypts `value` in place, prefixing it with envelope.
+    fn seal_enveloped(&self, enc: &enc::Manager, key: u128, value: &mut Vec<u8>) -> bool {
+        let len = value.len();
+        let padded_len = match self.padding {
+            Some(padding) => padding.padded_len(len),
+            None => len,
+        };
+        let nonce = match (self.randomized, self.padding) {
+            (true, _) => match enc::random_nonce() {
+                Some(nonce) => nonce,
+                None => return false,
+            },
+            //Padded length is bound to nonce, so that change of padding doesn't reuse it for other padded plaintext.
+            (false, Some(_)) => enc.derive_nonce_parts(key, &[value, &(padded_len as u64).to_le_bytes()]),
+            (false, None) => enc.derive_nonce(key, value),
+        };
+        let mut nonce = nonce;
+        nonce[enc::NONCE_LEN - 1] &= !VALUE_NONCE;
+
+        let mut envelope = [0u8; ENVELOPE_LEN];
+        envelope[..enc::NONCE_LEN].copy_from_slice(&nonce);
+        envelope[enc::NONCE_LEN..enc::NONCE_LEN + 8].copy_from_slice(&(len as u64).to_le_bytes());
+        match enc.encrypt_detached(nonce, &len_aad(key), &mut envelope[enc::NONCE_LEN..enc::NONCE_LEN + 8]) {
+            Some(tag) => envelope[enc::NONCE_LEN + 8..].copy_from_slice(&tag),
+            None => return false,
+        }
+
+        reserve_wiped(value, ENVELOPE_LEN + padded_len + enc::TAG_LEN);
+        value.resize(padded_len, 0);
+        match enc.encrypt_detached(value_nonce(nonce), &value_aad(key, len), value) {
+            Some(tag) => {
+                value.extend_from_slice(&tag);
+                value.splice(..0, envelope.iter().copied());
+                true
+            },
+            None => false,
+        }
+    }
+
+    ///Encrypts `value` in place as bare ciphertext of format `1`.
+    fn seal_legacy(&self, enc: &enc::Manager, key: u128, value: &mut Vec<u8>) -> bool {
+        match (self.padding, self.randomized) {
+            (Some(padding), randomized) => {
+                let random = match randomized {
+                    true => match enc::random_nonce() {
+                        Some(nonce) => Some(nonce),
+                        None => return false,
+                    },
+                    false => None,
+                };
+
+                let len = padding.padded_len(value.len() + 1);
+                reserve_wiped(value, enc::NONCE_LEN + len + enc::TAG_LEN);
+                value.push(PAD_MARKER);
+                value.resize(len, 0);
+                //Nonce is derived from padded plaintext, so that only the same plaintext gets the same nonce.
+                let nonce = match random {
+                    Some(nonce) => nonce,
+                    None => enc.derive_nonce(key, value),
+                };
+                enc.encrypt_prefixed(nonce, &padded_aad(key), value)
+            },
+            (None, true) => enc.encrypt_random(key, value),
+            (None, false) => enc.encrypt(key, value),
+        }
+    }
+}
+
+impl<B: Backend> Store<B> {
+    #[inline]
+    ///Sets whether values are encrypted using random nonce.
+    ///
+    ///By default nonce is derived from key and value via keyed hash, so the same value always results in the same ciphertext,
+    ///which lets observer of successive snapshots tell whether value has been changed back.
+    ///Randomized encryption produces new ciphertext on every insertion.
+    ///
+    ///Values are decrypted regardless of mode, while existing ones are left as they are until overwritten or `Self::rekey`.
+    ///Note that chunked values, refer to `Self::set_chunking`, are always encrypted using random nonces.
+    pub fn set_randomized(&mut self, randomized: bool) {
+        self.sealing.randomized = randomized;
+    }
+
+    #[inline]
+    ///Returns whether values are encrypted using random nonce.
+    pub fn is_randomized(&self) -> bool {
+        self.sealing.randomized
+    }
+
+    #[inline]
+    ///Sets padding of values, hiding their exact length, with `None` disabling it.
+    ///
+    ///Exact length of padded value is sealed along with it, so it is still reported by `Self::value_len`.
+    ///Values are decrypted regardless of padding, while existing ones are left as they are until overwritten or `Self::rekey`.
+    pub fn set_padding(&mut self, padding: Option<Padding>) {
+        self.sealing.padding = padding;
+    }
+
+    #[inline]
+    ///Returns padding of values, if any.
+    pub fn padding(&self) -> Option<Padding> {
+        self.sealing.padding
+    }
+
+    #[inline]
+    ///Sets size of chunk, values larger than which are split into chunks, with `None` disabling it.
+    ///
+    ///Chunks are sealed independently, with own random nonce, after header, authenticating their number and size,
+    ///so large value can be read incrementally via `Self::get_reader`, without decrypting it whole.
+    ///Chunked values are not padded, as their length is already coarse.
+    ///By default values larger than `64KiB` are chunked.
+    ///
+    ///Panics if `chunk_size` is zero or exceeds `u32::MAX`.
+    pub fn set_chunking(&mut self, chunk_size: Option<usize>) {
+        if let Some(chunk_size) = chunk_size {
+            assert_ne!(chunk_size, 0);
+            assert!(chunk_size <= u32::MAX as usize);
+        }
+        self.sealing.chunk_size = chunk_size;
+    }
+
+    #[inline]
+    ///Returns size of chunk, values larger than which are chunked, if enabled.
+    pub fn chunking(&self) -> Option<usize> {
+        self.sealing.chunk_size
+    }
+}
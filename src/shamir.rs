@@ -0,0 +1,158 @@
+use crate::{enc, Backend, Error, MasterKey, Store};
+
+use ring::rand::{SecureRandom, SystemRandom};
+
+///Length of serialized share.
+pub const SHARE_LEN: usize = 33;
+
+#[inline]
+fn mul(mut left: u8, mut right: u8) -> u8 {
+    let mut result = 0u8;
+    for _ in 0..8 {
+        result ^= left & 0u8.wrapping_sub(right & 1);
+        let carry = 0u8.wrapping_sub(left >> 7);
+        left = (left << 1) ^ (carry & 0x1b);
+        right >>= 1;
+    }
+    result
+}
+
+#[inline]
+fn inv(value: u8) -> u8 {
+    //value^254 = value^-1
+    let mut result = value;
+    for _ in 0..6 {
+        result = mul(mul(result, result), value);
+    }
+    mul(result, result)
+}
+
+#[derive(Clone, PartialEq, Eq)]
+///Share of master key, produced by `Store::split_recovery`.
+pub struct Share {
+    index: u8,
+    value: [u8; 32],
+}
+
+impl Share {
+    #[inline]
+    ///Serializes share.
+    pub fn to_bytes(&self) -> [u8; SHARE_LEN] {
+        let mut result = [0u8; SHARE_LEN];
+        result[0] = self.index;
+        result[1..].copy_from_slice(&self.value);
+        result
+    }
+
+    ///Deserializes share, previously serialized via `Self::to_bytes`.
+    ///
+    ///Returns `None` if `bytes` cannot be valid share.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != SHARE_LEN || bytes[0] == 0 {
+            return None;
+        }
+
+        let mut value = [0u8; 32];
+        value.copy_from_slice(&bytes[1..]);
+        Some(Self {
+            index: bytes[0],
+            value,
+        })
+    }
+}
+
+impl core::fmt::Debug for Share {
+    #[inline]
+    fn fmt(&self, fmt: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(fmt, "Share({}, ..)", self.index)
+    }
+}
+
+impl Drop for Share {
+    #[inline]
+    fn drop(&mut self) {
+        enc::wipe(&mut self.value);
+    }
+}
+
+///Splits `key` into `count` shares, any `threshold` of which reconstruct it.
+fn split(key: &[u8; 32], count: u8, threshold: u8) -> Vec<Share> {
+    assert!(threshold > 0 && threshold <= count);
+
+    let mut coefficients = vec![0u8; 32 * (threshold as usize - 1)];
+    assert!(SystemRandom::new().fill(&mut coefficients).is_ok());
+
+    let mut result = Vec::with_capacity(count as usize);
+    for index in 1..=count {
+        let mut value = *key;
+        for (byte_idx, byte) in value.iter_mut().enumerate() {
+            //Horner's scheme over coefficients of highest degree first.
+            let mut acc = 0u8;
+            for coefficient in coefficients.chunks_exact(32).rev() {
+                acc = mul(acc ^ coefficient[byte_idx], index);
+            }
+            *byte ^= acc;
+        }
+
+        result.push(Share {
+            index,
+            value,
+        });
+    }
+
+    enc::wipe(&mut coefficients);
+    result
+}
+
+///Reconstructs key from `shares`.
+fn combine(shares: &[Share]) -> Result<[u8; 32], Error> {
+    if shares.is_empty() {
+        return Err(Error::InvalidShares);
+    }
+
+    for (idx, share) in shares.iter().enumerate() {
+        if share.index == 0 || shares[..idx].iter().any(|other| other.index == share.index) {
+            return Err(Error::InvalidShares);
+        }
+    }
+
+    let mut result = [0u8; 32];
+    for share in shares.iter() {
+        //Lagrange basis polynomial at x = 0
+        let mut basis = 1u8;
+        for other in shares.iter().filter(|other| other.index != share.index) {
+            basis = mul(basis, mul(other.index, inv(other.index ^ share.index)));
+        }
+
+        for (byte, value) in result.iter_mut().zip(share.value.iter()) {
+            *byte ^= mul(*value, basis);
+        }
+    }
+
+    Ok(result)
+}
+
+impl<B: Backend> Store<B> {
+    ///Splits master key into `count` shares, any `threshold` of which can recover it via `Store::recover`.
+    ///
+    ///Fewer than `threshold` shares reveal nothing about key.
+    ///
+    ///Returns `Error::Locked` if store is locked.
+    ///Panics if `threshold` is zero or greater than `count`.
+    pub fn split_recovery(&self, count: u8, threshold: u8) -> Result<Vec<Share>, Error> {
+        match self.locked {
+            true => Err(Error::Locked),
+            false => Ok(split(self.enc.key(), count, threshold)),
+        }
+    }
+}
+
+impl Store {
+    ///Recovers master key from shares, produced by `Self::split_recovery`.
+    ///
+    ///Returns `Error::InvalidShares` if no shares are provided or they have duplicates.
+    ///Insufficient number of shares produces wrong key, which is rejected when store is opened with it.
+    pub fn recover(shares: &[Share]) -> Result<MasterKey, Error> {
+        combine(shares).map(MasterKey::from_bytes)
+    }
+}
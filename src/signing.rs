@@ -0,0 +1,322 @@
+use crate::{enc, Backend, Error, Store, SIGNATURES_KEY};
+use crate::tags::{pop_bytes, push_bytes};
+
+use core::fmt;
+use std::collections::BTreeMap;
+use ring::signature::{self, Ed25519KeyPair, KeyPair};
+
+const FORMAT: u8 = 1;
+///Size of signer's public key.
+pub const PUBLIC_KEY_LEN: usize = 32;
+const SIGNATURE_LEN: usize = 64;
+const RECORD_LEN: usize = 16 + PUBLIC_KEY_LEN + SIGNATURE_LEN;
+const DOMAIN: &[u8] = b"sec-store:signature";
+
+///Ed25519 key pair of principal, writing signed entries.
+pub struct Signer {
+    pair: Ed25519KeyPair,
+}
+
+impl Signer {
+    ///Generates new key pair, returning it along with its PKCS#8 document, which should be kept secret.
+    ///
+    ///Returns `Err` only if random seed cannot be generated.
+    pub fn generate() -> Result<(Self, Vec<u8>), ()> {
+        let document = Ed25519KeyPair::generate_pkcs8(&ring::rand::SystemRandom::new()).map_err(|_| ())?;
+        let result = Self::from_pkcs8(document.as_ref())?;
+        Ok((result, document.as_ref().to_vec()))
+    }
+
+    #[inline]
+    ///Creates signer from PKCS#8 document, returning `Err` if it is malformed.
+    pub fn from_pkcs8(document: &[u8]) -> Result<Self, ()> {
+        match Ed25519KeyPair::from_pkcs8(document) {
+            Ok(pair) => Ok(Self {
+                pair,
+            }),
+            Err(_) => Err(()),
+        }
+    }
+
+    #[inline]
+    ///Returns public key, to be registered via `Store::register_signer`.
+    pub fn public_key(&self) -> [u8; PUBLIC_KEY_LEN] {
+        let mut result = [0u8; PUBLIC_KEY_LEN];
+        result.copy_from_slice(self.pair.public_key().as_ref());
+        result
+    }
+}
+
+impl fmt::Debug for Signer {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_tuple("Signer").field(&self.public_key()).finish()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+///Result of verifying signature of entry.
+pub enum SignatureStatus {
+    ///Value is written without signature.
+    Unsigned,
+    ///Value is signed by registered signer with specified name.
+    Valid(Vec<u8>),
+    ///Value is signed by key with specified public key, that is no longer registered.
+    Untrusted([u8; PUBLIC_KEY_LEN]),
+    ///Signature doesn't match value.
+    Invalid,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+///Information about entry, returned by `Store::metadata`.
+pub struct Metadata {
+    ///Length of plaintext.
+    pub len: usize,
+    ///Lamport clock of last modification, if versions are tracked.
+    pub version: Option<u64>,
+    ///Signature of value.
+    pub signature: SignatureStatus,
+}
+
+#[derive(Default)]
+///Registered signers along with signatures of entries.
+pub(crate) struct Signatures {
+    ///Names of signers, mapped to their public keys.
+    signers: BTreeMap<[u8; PUBLIC_KEY_LEN], Vec<u8>>,
+    ///Public key of signer and signature, mapped to hash of entry.
+    entries: BTreeMap<u128, ([u8; PUBLIC_KEY_LEN], [u8; SIGNATURE_LEN])>,
+}
+
+impl Signatures {
+    #[inline]
+    fn is_empty(&self) -> bool {
+        self.signers.is_empty() && self.entries.is_empty()
+    }
+}
+
+fn message(key: u128, value: &[u8]) -> Vec<u8> {
+    let mut result = Vec::with_capacity(DOMAIN.len() + 16 + value.len());
+    result.extend_from_slice(DOMAIN);
+    result.extend_from_slice(&key.to_le_bytes());
+    result.extend_from_slice(value);
+    result
+}
+
+///Encodes `signatures` as `FORMAT: u8 | number of signers: u32 | (public key | name)..`
+///followed by `(hash | public key | signature)..`, where name is prefixed with length as `u32`.
+fn encode(signatures: &Signatures) -> Vec<u8> {
+    let mut result = Vec::with_capacity(5 + signatures.entries.len() * RECORD_LEN);
+    result.push(FORMAT);
+    result.extend_from_slice(&(signatures.signers.len() as u32).to_le_bytes());
+    for (public, name) in signatures.signers.iter() {
+        result.extend_from_slice(public);
+        push_bytes(&mut result, name);
+    }
+    for (key, (public, signature)) in signatures.entries.iter() {
+        result.extend_from_slice(&key.to_le_bytes());
+        result.extend_from_slice(public);
+        result.extend_from_slice(signature);
+    }
+    result
+}
+
+fn decode(input: &[u8]) -> Option<Signatures> {
+    let mut input = match input.split_first() {
+        Some((&FORMAT, input)) if input.len() >= 4 => input,
+        _ => return None,
+    };
+
+    let mut count = [0u8; 4];
+    count.copy_from_slice(&input[..4]);
+    input = &input[4..];
+
+    let mut result = Signatures::default();
+    for _ in 0..u32::from_le_bytes(count) {
+        if input.len() < PUBLIC_KEY_LEN {
+            return None;
+        }
+        let mut public = [0u8; PUBLIC_KEY_LEN];
+        public.copy_from_slice(&input[..PUBLIC_KEY_LEN]);
+        input = &input[PUBLIC_KEY_LEN..];
+        result.signers.insert(public, pop_bytes(&mut input)?.to_owned());
+    }
+
+    if input.len() % RECORD_LEN != 0 {
+        return None;
+    }
+    for record in input.chunks_exact(RECORD_LEN) {
+        let mut key = [0u8; 16];
+        key.copy_from_slice(&record[..16]);
+        let mut public = [0u8; PUBLIC_KEY_LEN];
+        public.copy_from_slice(&record[16..16 + PUBLIC_KEY_LEN]);
+        let mut signature = [0u8; SIGNATURE_LEN];
+        signature.copy_from_slice(&record[16 + PUBLIC_KEY_LEN..]);
+        result.entries.insert(u128::from_le_bytes(key), (public, signature));
+    }
+
+    Some(result)
+}
+
+///Loads signatures from `inner`, returning empty set if they are not stored or cannot be decrypted.
+pub(crate) fn load<B: Backend>(enc: &enc::Manager, inner: &B) -> Signatures {
+    let value = match inner.get(SIGNATURES_KEY) {
+        Some(value) => value,
+        None => return Signatures::default(),
+    };
+
+    let mut plain = Vec::new();
+    match crate::open_to_vec(enc, SIGNATURES_KEY, value, &mut plain) {
+        Ok(_) => decode(&plain).unwrap_or_default(),
+        Err(_) => Signatures::default(),
+    }
+}
+
+impl<B: Backend> Store<B> {
+    fn write_signatures(&mut self) {
+        if self.locked {
+            return;
+        }
+
+        if self.signatures.is_empty() {
+            self.inner.remove(SIGNATURES_KEY);
+            return;
+        }
+
+        let mut value = encode(&self.signatures);
+        if self.sealing.internal().seal(&self.enc, SIGNATURES_KEY, &mut value) {
+            self.inner.insert(SIGNATURES_KEY, value);
+        }
+    }
+
+    ///Forgets signature of `key`, if any.
+    pub(crate) fn forget_signature(&mut self, key: u128) {
+        if self.signatures.entries.remove(&key).is_some() {
+            self.write_signatures();
+        }
+    }
+
+    ///Registers signer's `public_key` under `name`, replacing previous name, if any.
+    ///
+    ///Registered signers are kept, along with signatures of entries, within single encrypted entry.
+    ///Returns `Error::Locked` if store is locked.
+    pub fn register_signer(&mut self, name: &[u8], public_key: &[u8; PUBLIC_KEY_LEN]) -> Result<(), Error> {
+        if self.locked {
+            return Err(Error::Locked);
+        }
+
+        self.signatures.signers.insert(*public_key, name.to_owned());
+        self.write_signatures();
+        Ok(())
+    }
+
+    ///Unregisters signer's `public_key`, returning whether it was registered.
+    ///
+    ///Signatures, made by it, are kept, but are no longer trusted.
+    pub fn unregister_signer(&mut self, public_key: &[u8; PUBLIC_KEY_LEN]) -> bool {
+        if self.locked {
+            return false;
+        }
+
+        let result = self.signatures.signers.remove(public_key).is_some();
+        if result {
+            self.write_signatures();
+        }
+        result
+    }
+
+    #[inline]
+    ///Returns public keys of registered signers along with their names.
+    pub fn signers(&self) -> impl Iterator<Item = (&[u8; PUBLIC_KEY_LEN], &[u8])> + '_ {
+        self.signatures.signers.iter().map(|(public, name)| (public, name.as_slice()))
+    }
+
+    ///Inserts new `value` for `key`, signed by `signer`, returning previous value, if any.
+    ///
+    ///Signature covers hash of key and plaintext, so it survives re-keying,
+    ///while any other modification of `key`, including regular insertion, discards it.
+    ///
+    ///Returns error when:
+    ///
+    ///- `Error::WrongCredentials` - `signer` is not registered via `Self::register_signer`.
+    ///- `Error::LimitExceeded` - value doesn't fit store's limits.
+    ///
+    ///Store is left untouched on error.
+    pub fn try_insert_signed(&mut self, key: &[u8], value: &[u8], signer: &Signer) -> Result<Option<Vec<u8>>, Error> {
+        let public = signer.public_key();
+        if !self.signatures.signers.contains_key(&public) {
+            return Err(Error::WrongCredentials);
+        }
+
+        self.suspend_autosave();
+        let result = match self.try_insert(key, value) {
+            Ok(result) => result,
+            Err(error) => {
+                self.resume_autosave();
+                return Err(error);
+            },
+        };
+
+        let hash = self.hash_key(key);
+        let mut message = message(hash, value);
+        let mut signature = [0u8; SIGNATURE_LEN];
+        signature.copy_from_slice(signer.pair.sign(&message).as_ref());
+        enc::wipe(&mut message);
+        self.signatures.entries.insert(hash, (public, signature));
+        self.write_signatures();
+        self.resume_autosave();
+        Ok(result)
+    }
+
+    #[inline]
+    ///Inserts new `value` for `key`, signed by `signer`, returning previous value, if any.
+    ///
+    ///Panics on error, refer to `Self::try_insert_signed`.
+    pub fn insert_signed(&mut self, key: &[u8], value: &[u8], signer: &Signer) -> Option<Vec<u8>> {
+        match self.try_insert_signed(key, value, signer) {
+            Ok(result) => result,
+            Err(error) => panic!("Cannot insert value: {}", error),
+        }
+    }
+
+    ///Returns metadata of `key`, verifying signature of its value, if any.
+    ///
+    ///Returns error when:
+    ///
+    ///- `Error::NotFound` - `key` doesn't exist.
+    ///- `Error::Locked` - store is locked.
+    ///- `Error::InvalidEntry` - value cannot be decrypted.
+    pub fn metadata(&self, key: &[u8]) -> Result<Metadata, Error> {
+        if self.locked {
+            return Err(Error::Locked);
+        }
+
+        let version = self.version(key);
+        let hash = self.hash_key(key);
+        let mut value = match self.inner.get(hash) {
+            Some(value) => self.decrypt_value(hash, value).ok_or(Error::InvalidEntry(hash))?,
+            None => return Err(Error::NotFound),
+        };
+
+        let signature = match self.signatures.entries.get(&hash) {
+            None => SignatureStatus::Unsigned,
+            Some((public, signature)) => {
+                let mut message = message(hash, &value);
+                let is_valid = signature::UnparsedPublicKey::new(&signature::ED25519, public).verify(&message, signature).is_ok();
+                enc::wipe(&mut message);
+                match (is_valid, self.signatures.signers.get(public)) {
+                    (false, _) => SignatureStatus::Invalid,
+                    (true, Some(name)) => SignatureStatus::Valid(name.clone()),
+                    (true, None) => SignatureStatus::Untrusted(*public),
+                }
+            },
+        };
+
+        let len = value.len();
+        enc::wipe(&mut value);
+        Ok(Metadata {
+            len,
+            version,
+            signature,
+        })
+    }
+}
@@ -0,0 +1,92 @@
+use crate::{enc, Backend, Error, Store};
+
+use std::collections::BTreeMap;
+
+#[derive(Clone)]
+///Copy of store's encrypted content, created by `Store::snapshot`.
+///
+///It holds only ciphertexts, so it is safe to keep around while store is in use.
+pub struct Snapshot<B = BTreeMap<u128, Vec<u8>>> {
+    inner: B,
+}
+
+impl<B: Backend> Snapshot<B> {
+    #[inline]
+    ///Returns number of key-value pairs at the moment of snapshot.
+    pub fn len(&self) -> usize {
+        self.inner.len() - crate::reserved_len(&self.inner)
+    }
+}
+
+impl<B: Backend + Clone> Store<B> {
+    #[inline]
+    ///Captures current content of store, without decrypting anything.
+    pub fn snapshot(&self) -> Snapshot<B> {
+        let mut inner = self.inner.clone();
+        if let Some(versions) = self.pending_versions() {
+            crate::discard(inner.insert(crate::VERSIONS_KEY, versions));
+        }
+        Snapshot {
+            inner,
+        }
+    }
+
+    ///Restores content of store, previously captured by `Self::snapshot`.
+    ///
+    ///Snapshot is not validated, so it should be taken from the same store.
+    ///Discarded ciphertexts are wiped, while key names, tags and audit log are loaded from snapshot,
+    ///hence records of audit log, made since snapshot was taken, are dropped.
+    pub fn restore(&mut self, snapshot: Snapshot<B>) {
+        let mut discarded = core::mem::replace(&mut self.inner, snapshot.inner);
+        let keys: Vec<_> = discarded.iter().map(|(key, _)| key).collect();
+        for key in keys {
+            crate::discard(discarded.remove(key));
+        }
+        if let Some(names) = self.names.take() {
+            for (mut name, _) in names {
+                enc::wipe(&mut name);
+            }
+        }
+        crate::tags::wipe(&mut self.tags);
+
+        self.size = crate::entries_size(&self.inner);
+        #[cfg(feature = "audit")]
+        {
+            self.audit = crate::audit::AuditLog::load(&self.enc, &self.inner);
+        }
+        self.names = crate::names::load(&self.enc, &self.inner);
+        self.tags = crate::tags::load(&self.enc, &self.inner);
+        self.versions = crate::versions::load(&self.enc, &self.inner);
+        self.signatures = crate::signing::load(&self.enc, &self.inner);
+        if let Some(eviction) = self.eviction.as_ref() {
+            eviction.reset(self.entries().map(|(key, _)| key));
+        }
+        self.notify(crate::ChangeEvent::Restore);
+        self.autosave_changed();
+    }
+
+    ///Creates independent store over copy of ciphertexts, sharing the same encryption key.
+    ///
+    ///It allows worker threads to take owned copies of the same vault, without re-deriving key from credentials.
+    ///Copy keeps limits, sealing, number of decoys and format version,
+    ///while eviction, subscribers and autosave are left to be configured anew.
+    ///
+    ///Returns `Error::Locked` if store is locked, as its key is wiped.
+    pub fn try_clone(&self) -> Result<Self, Error> {
+        if self.locked {
+            return Err(Error::Locked);
+        }
+
+        let mut inner = self.inner.clone();
+        if let Some(versions) = self.pending_versions() {
+            crate::discard(inner.insert(crate::VERSIONS_KEY, versions));
+        }
+        let mut result = Self::with_manager(inner, enc::Manager::new(*self.enc.key()));
+        result.limits = self.limits;
+        result.sealing = self.sealing;
+        result.decoys = self.decoys;
+        result.format = self.format;
+        result.hasher = self.hasher.clone();
+        Ok(result)
+    }
+}
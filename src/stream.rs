@@ -0,0 +1,181 @@
+use crate::{chunk, enc, seal, Backend, Store};
+
+use core::cmp;
+use std::io;
+
+///Initial size of buffer, that value is read into, when it is not chunked.
+const READ_SIZE: usize = 4096;
+
+///Reads `input` until its end into `value`, wiping its old buffer whenever it has to grow.
+///
+///On error `value` is wiped.
+fn read_to_end_wiped<R: io::Read>(input: &mut R, value: &mut Vec<u8>) -> io::Result<()> {
+    let mut len = value.len();
+    loop {
+        if len == value.len() {
+            seal::reserve_wiped(value, cmp::max(len.saturating_mul(2), READ_SIZE));
+            value.resize(value.capacity(), 0);
+        }
+
+        match input.read(&mut value[len..]) {
+            Ok(0) => break,
+            Ok(read) => len += read,
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => (),
+            Err(error) => {
+                enc::wipe(value);
+                value.truncate(0);
+                return Err(error);
+            },
+        }
+    }
+
+    value.truncate(len);
+    Ok(())
+}
+
+enum Source<'a> {
+    Buffered,
+    Chunked(&'a enc::Manager, chunk::Chunks<'a>, u64),
+}
+
+///Reader over decrypted value, created by `Store::get_reader`.
+///
+///Decrypted data is wiped once reader is dropped.
+pub struct ValueReader<'a> {
+    source: Source<'a>,
+    buffer: Vec<u8>,
+    pos: usize,
+}
+
+impl<'a> ValueReader<'a> {
+    fn fill_buffer(&mut self) -> io::Result<()> {
+        if self.pos < self.buffer.len() {
+            return Ok(());
+        }
+
+        if let Source::Chunked(enc, ref chunks, ref mut next) = self.source {
+            if *next < chunks.count() {
+                self.buffer.resize(chunks.buffer_len(), 0);
+                match chunks.decrypt_to(enc, *next, &mut self.buffer) {
+                    Some(written) => {
+                        *next += 1;
+                        enc::wipe(&mut self.buffer[written..]);
+                        self.buffer.truncate(written);
+                        self.pos = 0;
+                    },
+                    None => return Err(io::Error::new(io::ErrorKind::InvalidData, "Unable to decrypt chunk")),
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a> io::Read for ValueReader<'a> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        self.fill_buffer()?;
+
+        let remaining = &self.buffer[self.pos..];
+        let len = core::cmp::min(remaining.len(), out.len());
+        out[..len].copy_from_slice(&remaining[..len]);
+        self.pos += len;
+        Ok(len)
+    }
+}
+
+impl<'a> Drop for ValueReader<'a> {
+    fn drop(&mut self) {
+        enc::wipe(&mut self.buffer);
+    }
+}
+
+impl<B: Backend> Store<B> {
+    ///Retrieves reader over value for `key`.
+    ///
+    ///Value, inserted via `Self::insert_from_reader`, is decrypted chunk by chunk, as it is read.
+    ///Otherwise value is sealed as single ciphertext, and has to be authenticated as whole before it can be read,
+    ///hence it is decrypted into reader's buffer at once.
+    ///
+    ///Returns `None` if decryption failed.
+    pub fn get_reader(&self, key: &[u8]) -> Option<ValueReader<'_>> {
+        let key = self.hash_key(key);
+        let value = match self.inner.get(key) {
+            Some(value) => value,
+            None => {
+                self.metrics.get(None);
+                self.record_get(key, false);
+                return None;
+            },
+        };
+        self.touch(key);
+
+        if let Some(chunks) = chunk::Chunks::parse(&self.enc, key, value) {
+            //Chunks are authenticated lazily, as they are read.
+            self.metrics.get(Some(Ok(chunks.plain_len())));
+            self.record_get(key, true);
+            return Some(ValueReader {
+                buffer: Vec::with_capacity(chunks.buffer_len()),
+                source: Source::Chunked(&self.enc, chunks, 0),
+                pos: 0,
+            });
+        }
+
+        let mut buffer = Vec::new();
+        let result = self.decrypt_to_vec(key, value, &mut buffer);
+        self.metrics.get(Some(result));
+        match result {
+            Ok(_) => Some(ValueReader {
+                source: Source::Buffered,
+                buffer,
+                pos: 0,
+            }),
+            Err(_) => None,
+        }
+    }
+
+    ///Inserts value for `key`, reading it from `input` until its end.
+    ///
+    ///Value is split into chunks of size, set by `Self::set_chunking`, sealed independently as they are read,
+    ///so only ciphertext is kept in memory.
+    ///Such value can be retrieved via regular getters as well.
+    ///If chunking is disabled, or once format is migrated to `1`, refer to `Self::migrate_format`, value is read whole,
+    ///wiping every buffer it outgrows.
+    ///
+    ///Returns whether `key` was set previously, or error if reading failed, leaving store untouched.
+    ///Value that doesn't fit store's limits results in `InvalidInput` error.
+    pub fn insert_from_reader<R: io::Read>(&mut self, name: &[u8], mut input: R) -> io::Result<bool> {
+        let key = self.hash_key(name);
+        let (value, plain_len) = match (self.sealing.legacy, self.sealing.chunk_size) {
+            (false, Some(chunk_size)) => {
+                let value = chunk::seal(&self.enc, key, &mut input, chunk_size)?;
+                let plain_len = chunk::Chunks::parse(&self.enc, key, &value).map_or(0, |chunks| chunks.plain_len());
+                (value, plain_len)
+            },
+            //Format `1` has no chunks, so value is read whole.
+            _ => {
+                let mut value = Vec::new();
+                read_to_end_wiped(&mut input, &mut value)?;
+                let plain_len = value.len();
+                if plain_len == 0 {
+                    return Err(io::Error::new(io::ErrorKind::InvalidInput, "Value must not be empty"));
+                } else if !self.sealing.seal(&self.enc, key, &mut value) {
+                    enc::wipe(&mut value);
+                    return Err(io::Error::other("Unable to encrypt value"));
+                }
+                (value, plain_len)
+            },
+        };
+        self.check_limits(key, plain_len, value.len())?;
+
+        #[cfg(feature = "audit")]
+        self.audit.record(crate::AuditOp::Insert, key, true);
+        #[cfg(feature = "tracing")]
+        tracing::trace!(op = "insert", success = true);
+        self.record_name(key, name);
+        let previous = self.inner_put(key, value);
+        let result = previous.is_some();
+        crate::discard(previous);
+        Ok(result)
+    }
+}
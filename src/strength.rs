@@ -0,0 +1,72 @@
+//!Rough estimation of password strength.
+//!
+//!Estimation follows scoring of zxcvbn: password is assumed to be guessed character by character,
+//!with characters, that repeat or continue sequence of previous one, being nearly free for attacker,
+//!while well-known passwords are guessed immediately.
+
+///Most common passwords, compared ignoring case and trailing digits.
+const COMMON: &[&str] = &[
+    "password", "qwerty", "qwertyuiop", "asdfgh", "zxcvbn", "letmein", "welcome", "admin", "administrator",
+    "iloveyou", "monkey", "dragon", "master", "sunshine", "princess", "football", "baseball", "shadow",
+    "superman", "trustno", "passw0rd", "p@ssw0rd", "secret", "login", "abc", "root", "user", "test", "guest",
+];
+
+///Threshold of guesses entropy in bits, for each score.
+const THRESHOLDS: [f64; 4] = [10.0, 20.0, 27.0, 33.0];
+
+#[inline]
+fn pool_size(pass: &str) -> f64 {
+    let mut pool = 0;
+    if pass.chars().any(|ch| ch.is_ascii_lowercase()) {
+        pool += 26;
+    }
+    if pass.chars().any(|ch| ch.is_ascii_uppercase()) {
+        pool += 26;
+    }
+    if pass.chars().any(|ch| ch.is_ascii_digit()) {
+        pool += 10;
+    }
+    if pass.chars().any(|ch| ch.is_ascii() && !ch.is_ascii_alphanumeric()) {
+        pool += 33;
+    }
+    if !pass.is_ascii() {
+        pool += 100;
+    }
+    pool as f64
+}
+
+#[inline]
+fn is_common(pass: &str) -> bool {
+    let pass = pass.to_lowercase();
+    let pass = pass.trim_end_matches(|ch: char| ch.is_ascii_digit());
+    pass.is_empty() || COMMON.contains(&pass)
+}
+
+///Estimates strength of `pass` as score from `0` (too guessable) to `4` (very unguessable).
+///
+///Score is only rough estimation, noticing repeats, sequences (e.g. `aaaa` or `1234`) and most common passwords.
+pub fn password_strength(pass: &[u8]) -> u8 {
+    let pass = String::from_utf8_lossy(pass);
+    if is_common(&pass) {
+        return 0;
+    }
+
+    //Run of repeats or sequence costs as its first character along with guessing its length.
+    let bits_per_char = pool_size(&pass).log2();
+    let mut bits = 0.0;
+    let mut run = 1u32;
+    let mut previous = None;
+    for ch in pass.chars() {
+        match previous {
+            Some(previous) if (ch as i64 - previous as i64).abs() <= 1 => run += 1,
+            _ => {
+                bits += f64::from(run).log2() + bits_per_char;
+                run = 1;
+            },
+        }
+        previous = Some(ch);
+    }
+    bits += f64::from(run).log2();
+
+    THRESHOLDS.iter().filter(|threshold| bits >= **threshold).count() as u8
+}
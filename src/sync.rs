@@ -0,0 +1,484 @@
+//!Replication of stores, sharing the same encryption key.
+//!
+//!Only ciphertexts are ever sent, so transport doesn't need to be trusted with secrets,
+//!although it can observe hashes of keys and sizes of values.
+//!
+//!Protocol:
+//!
+//!1. Initiator sends `MAGIC | VERSION: u8 | nonce: [u8; 32]`.
+//!2. Responder sends `MAGIC | VERSION: u8 | nonce: [u8; 32] | proof: [u8; 32]`.
+//!3. Initiator sends `proof: [u8; 32] | manifest | versions`.
+//!4. Responder sends `manifest | versions | entries | mac: [u8; 32]`.
+//!5. Initiator sends `entries | mac: [u8; 32]`.
+//!6. Responder sends `ack: u8 | mac: [u8; 32]`, once it verifies received entries.
+//!
+//!Proof is HMAC over role and both nonces, using key derived from encryption key, so each side confirms that other one
+//!has the same key before anything else is exchanged.
+//!Manifest is `count: u64 | (key: u128 | digest: u128)..` and entries are `count: u64 | (key: u128 | len: u32 | value)..`.
+//!Versions are `key: u128 | len: u32 | value`, with ciphertext of internal entry, which is empty if versioning is disabled.
+//!Both sides decide which entries to send in the same way, given both manifests and versions,
+//!which also carry tombstones of removed entries.
+//!MAC is HMAC over everything, sent by side after its proof, so neither side stores entries of tampered exchange.
+//!Entries are authenticated by MAC only, as they can belong to namespaces, which keys are unknown to store.
+//!Acknowledgement is `1` if responder accepts entries, and `0` otherwise, in which case neither side stores anything.
+
+use crate::{delta, enc, format, seal, versions, Backend, Error, Store, RESERVED, VERSIONS_KEY};
+
+use core::cmp::Ordering;
+use ring::{digest, hmac};
+use ring::rand::{SecureRandom, SystemRandom};
+use std::collections::BTreeSet;
+use std::io::{self, Read, Write};
+
+const MAGIC: &[u8; 8] = b"SECSYNC\0";
+const VERSION: u8 = 2;
+const ACK_REJECTED: u8 = 0;
+const ACK_ACCEPTED: u8 = 1;
+const NONCE_LEN: usize = 32;
+const PROOF_LEN: usize = 32;
+const PROOF_MESSAGE_LEN: usize = 1 + 2 * NONCE_LEN;
+
+///Side of replication, each of two stores must take different one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    ///Side, that starts handshake (e.g. client).
+    Initiator,
+    ///Side, that waits for handshake (e.g. server).
+    Responder,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+///Outcome of `Store::sync`.
+pub struct Report {
+    ///Number of entries, sent to other store.
+    pub sent: usize,
+    ///Number of entries, received from other store.
+    pub received: usize,
+    ///Number of entries, removed as they are removed by other store, refer to `Store::enable_versioning`.
+    pub removed: usize,
+    ///Keys present in both stores with different ciphertexts, which are left untouched.
+    ///
+    ///Note that values, encrypted with random nonce, differ even if plaintexts are the same.
+    pub conflicts: Vec<u128>,
+}
+
+#[inline]
+fn invalid_data(text: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, text)
+}
+
+fn read_u64<R: Read>(input: &mut R) -> io::Result<u64> {
+    let mut value = [0u8; 8];
+    input.read_exact(&mut value)?;
+    Ok(u64::from_le_bytes(value))
+}
+
+fn write_hello<W: Write>(out: &mut W, nonce: &[u8; NONCE_LEN]) -> io::Result<()> {
+    out.write_all(MAGIC)?;
+    out.write_all(&[VERSION])?;
+    out.write_all(nonce)
+}
+
+fn read_hello<R: Read>(input: &mut R) -> io::Result<[u8; NONCE_LEN]> {
+    let mut magic = [0u8; 9];
+    input.read_exact(&mut magic)?;
+    if magic[..8] != MAGIC[..] {
+        return Err(invalid_data("Not a sec-store sync"));
+    } else if magic[8] != VERSION {
+        return Err(invalid_data("Unsupported sync version"));
+    }
+
+    let mut nonce = [0u8; NONCE_LEN];
+    input.read_exact(&mut nonce)?;
+    Ok(nonce)
+}
+
+fn write_manifest<W: Write>(out: &mut W, manifest: &delta::Digests) -> io::Result<()> {
+    out.write_all(&(manifest.len() as u64).to_le_bytes())?;
+    for (key, digest) in manifest.iter() {
+        out.write_all(&key.to_le_bytes())?;
+        out.write_all(&digest.to_le_bytes())?;
+    }
+    Ok(())
+}
+
+fn read_manifest<R: Read>(input: &mut R) -> io::Result<delta::Digests> {
+    let mut result = delta::Digests::new();
+    for _ in 0..read_u64(input)? {
+        let key = format::read_key(input)?;
+        if key < RESERVED {
+            return Err(invalid_data("Sync of internal entry"));
+        }
+        result.insert(key, format::read_key(input)?);
+    }
+    Ok(result)
+}
+
+fn write_versions<W: Write>(out: &mut W, versions: Option<&[u8]>) -> io::Result<()> {
+    format::write_entry(out, VERSIONS_KEY, versions.unwrap_or(&[]))
+}
+
+fn read_versions<R: Read>(input: &mut R, enc: &enc::Manager) -> io::Result<Option<(versions::Entries, versions::Tombstones)>> {
+    if format::read_key(input)? != VERSIONS_KEY {
+        return Err(invalid_data("Sync of unexpected entry"));
+    }
+
+    let value = format::read_value(input)?;
+    match value.is_empty() {
+        true => Ok(None),
+        false => match versions::open(enc, &value) {
+            Some(versions) => Ok(Some(versions)),
+            None => Err(Error::InvalidEntry(VERSIONS_KEY).into()),
+        },
+    }
+}
+
+fn read_entries<R: Read>(input: &mut R, expected: &BTreeSet<u128>) -> io::Result<Vec<(u128, Vec<u8>)>> {
+    let mut result = Vec::new();
+    for _ in 0..read_u64(input)? {
+        let key = format::read_key(input)?;
+        if !expected.contains(&key) {
+            return Err(invalid_data("Sync of unexpected entry"));
+        }
+        result.push((key, format::read_value(input)?));
+    }
+    Ok(result)
+}
+
+///Versions along with tombstones of one side.
+type Versions<'a> = Option<(&'a versions::Entries, &'a versions::Tombstones)>;
+
+///Returns whether entry under `key`, missing on one side, is removed after its version on the other side.
+fn is_removed(key: &u128, versions: Versions<'_>, removals: Versions<'_>) -> bool {
+    let version = versions.and_then(|(entries, _)| entries.get(key));
+    match removals.and_then(|(_, tombstones)| tombstones.get(key)) {
+        Some(tombstone) => Some(&tombstone.version) > version,
+        None => false,
+    }
+}
+
+///Entries to exchange, that are determined by both sides in the same way.
+struct Plan {
+    send: BTreeSet<u128>,
+    receive: BTreeSet<u128>,
+    ///Local entries, that are removed by other side.
+    remove: BTreeSet<u128>,
+    conflicts: Vec<u128>,
+}
+
+impl Plan {
+    fn new(local: &delta::Digests, local_versions: Versions<'_>, remote: &delta::Digests, remote_versions: Versions<'_>) -> Self {
+        let mut result = Self {
+            send: BTreeSet::new(),
+            receive: BTreeSet::new(),
+            remove: BTreeSet::new(),
+            conflicts: Vec::new(),
+        };
+
+        for key in local.keys().chain(remote.keys().filter(|key| !local.contains_key(key))) {
+            let winner = match (local.get(key), remote.get(key)) {
+                (Some(_), None) if is_removed(key, local_versions, remote_versions) => {
+                    result.remove.insert(*key);
+                    Ordering::Equal
+                },
+                (None, Some(_)) if is_removed(key, remote_versions, local_versions) => Ordering::Equal,
+                (Some(_), None) => Ordering::Greater,
+                (None, Some(_)) => Ordering::Less,
+                (Some(local), Some(remote)) if local == remote => Ordering::Equal,
+                _ => {
+                    let local = local_versions.and_then(|(versions, _)| versions.get(key));
+                    let remote = remote_versions.and_then(|(versions, _)| versions.get(key));
+                    if local.is_none() && remote.is_none() {
+                        result.conflicts.push(*key);
+                    }
+                    local.cmp(&remote)
+                },
+            };
+
+            match winner {
+                Ordering::Greater => result.send.insert(*key),
+                Ordering::Less => result.receive.insert(*key),
+                Ordering::Equal => false,
+            };
+        }
+
+        result
+    }
+}
+
+///Transport, authenticating everything exchanged after handshake.
+///
+///MAC is computed over digest of transcript, prefixed with role of sender and both nonces.
+struct Authenticated<T> {
+    inner: T,
+    key: hmac::Key,
+    sent: ([u8; PROOF_MESSAGE_LEN], digest::Context),
+    received: ([u8; PROOF_MESSAGE_LEN], digest::Context),
+}
+
+#[inline]
+fn transcript_message(prefix: &[u8; PROOF_MESSAGE_LEN], transcript: &digest::Context) -> Vec<u8> {
+    let mut result = prefix.to_vec();
+    result.extend_from_slice(transcript.clone().finish().as_ref());
+    result
+}
+
+impl<T: Read + Write> Authenticated<T> {
+    fn new(inner: T, key: hmac::Key, role: Role, initiator: &[u8; NONCE_LEN], responder: &[u8; NONCE_LEN]) -> Self {
+        let other = match role {
+            Role::Initiator => Role::Responder,
+            Role::Responder => Role::Initiator,
+        };
+
+        Self {
+            inner,
+            key,
+            sent: (proof_message(role, initiator, responder), digest::Context::new(&digest::SHA256)),
+            received: (proof_message(other, initiator, responder), digest::Context::new(&digest::SHA256)),
+        }
+    }
+
+    ///Sends MAC of everything sent so far.
+    fn send_mac(&mut self) -> io::Result<()> {
+        let tag = hmac::sign(&self.key, &transcript_message(&self.sent.0, &self.sent.1));
+        self.inner.write_all(tag.as_ref())?;
+        self.inner.flush()
+    }
+
+    ///Verifies MAC of everything received so far.
+    fn verify_mac(&mut self) -> io::Result<()> {
+        let mut tag = [0u8; PROOF_LEN];
+        self.inner.read_exact(&mut tag)?;
+        match hmac::verify(&self.key, &transcript_message(&self.received.0, &self.received.1), &tag) {
+            Ok(()) => Ok(()),
+            Err(_) => Err(invalid_data("Sync is tampered with")),
+        }
+    }
+}
+
+impl<T: Read> Read for Authenticated<T> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        let len = self.inner.read(out)?;
+        self.received.1.update(&out[..len]);
+        Ok(len)
+    }
+}
+
+impl<T: Write> Write for Authenticated<T> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        let len = self.inner.write(data)?;
+        self.sent.1.update(&data[..len]);
+        Ok(len)
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+fn proof_message(role: Role, initiator: &[u8; NONCE_LEN], responder: &[u8; NONCE_LEN]) -> [u8; PROOF_MESSAGE_LEN] {
+    let mut result = [0u8; PROOF_MESSAGE_LEN];
+    result[0] = role as u8;
+    result[1..1 + NONCE_LEN].copy_from_slice(initiator);
+    result[1 + NONCE_LEN..].copy_from_slice(responder);
+    result
+}
+
+impl<B: Backend> Store<B> {
+    fn sync_proof(&self, role: Role, initiator: &[u8; NONCE_LEN], responder: &[u8; NONCE_LEN]) -> hmac::Tag {
+        hmac::sign(&self.enc.subkey(b"sec-store:sync"), &proof_message(role, initiator, responder))
+    }
+
+    fn verify_sync_proof<R: Read>(&self, input: &mut R, role: Role, initiator: &[u8; NONCE_LEN], responder: &[u8; NONCE_LEN]) -> io::Result<()> {
+        let mut proof = [0u8; PROOF_LEN];
+        input.read_exact(&mut proof)?;
+        match hmac::verify(&self.enc.subkey(b"sec-store:sync"), &proof_message(role, initiator, responder), &proof) {
+            Ok(()) => Ok(()),
+            Err(_) => Err(io::Error::new(io::ErrorKind::PermissionDenied, "Other store uses different key")),
+        }
+    }
+
+    fn write_sync_entries<W: Write>(&self, out: &mut W, keys: &BTreeSet<u128>) -> io::Result<()> {
+        let entries: Vec<_> = keys.iter().filter_map(|key| self.inner.get(*key).map(|value| (*key, value))).collect();
+        out.write_all(&(entries.len() as u64).to_le_bytes())?;
+        for (key, value) in entries {
+            format::write_entry(out, key, value)?;
+        }
+        Ok(())
+    }
+
+    ///Verifies that entries, received from other store, are well-formed and fit limits together.
+    ///
+    ///Entries are authenticated by transcript MAC, so they are not decrypted, as they might belong to namespaces.
+    ///Returns `Error::InvalidEntry` if entry is too short to be ciphertext.
+    fn check_sync_entries(&self, entries: &[(u128, Vec<u8>)]) -> Result<(), Error> {
+        let mut size = self.size;
+        let mut count = self.len();
+        for (key, value) in entries.iter() {
+            if value.len() <= enc::TAG_LEN {
+                return Err(Error::InvalidEntry(*key));
+            }
+            let plain_len = seal::plain_len(&self.enc, *key, value).unwrap_or(value.len());
+            let previous = self.inner.get(*key).map(<[u8]>::len);
+            self.limits.check(count, size, previous, plain_len, value.len())?;
+            count += previous.is_none() as usize;
+            size = size - previous.unwrap_or(0) + value.len();
+        }
+        Ok(())
+    }
+
+    ///Stores entries, received from other store, and removes entries, removed by it.
+    ///
+    ///Entries must be verified via `Self::check_sync_entries` beforehand.
+    fn apply_sync_entries(&mut self, entries: Vec<(u128, Vec<u8>)>, removals: &BTreeSet<u128>, remote: Option<(versions::Entries, versions::Tombstones)>) {
+        //Received entries and removals take version of other store, instead of new one.
+        let mut versions = self.versions.take();
+        self.suspend_autosave();
+        for (key, value) in entries {
+            crate::discard(self.inner_put(key, value));
+            if let Some(versions) = versions.as_mut() {
+                versions.tombstones.remove(&key);
+                match remote.as_ref().and_then(|(remote, _)| remote.get(&key)) {
+                    Some(version) => {
+                        versions.entries.insert(key, *version);
+                        versions.clock = core::cmp::max(versions.clock, version.clock);
+                    },
+                    None => {
+                        versions.entries.remove(&key);
+                    },
+                }
+            }
+        }
+        for key in removals.iter() {
+            crate::discard(self.inner_take(*key));
+            if let Some(versions) = versions.as_mut() {
+                versions.entries.remove(key);
+            }
+        }
+
+        //Tombstones are adopted as well, so that removals keep replicating to other stores.
+        if let (Some(versions), Some((_, tombstones))) = (versions.as_mut(), remote.as_ref()) {
+            for (key, tombstone) in tombstones.iter() {
+                let is_newer = match (versions.tombstones.get(key), self.inner.contains(*key)) {
+                    (_, true) => false,
+                    (Some(local), false) => tombstone.version > local.version,
+                    (None, false) => true,
+                };
+                if is_newer {
+                    versions.tombstones.insert(*key, *tombstone);
+                    versions.clock = core::cmp::max(versions.clock, tombstone.version.clock);
+                }
+            }
+        }
+        self.versions = versions;
+        self.write_versions();
+        self.resume_autosave();
+    }
+
+    ///Replicates entries with other store over `transport`, so that both converge to the same keys.
+    ///
+    ///Both stores must use the same encryption key (e.g. opened with the same credentials or `MasterKey`),
+    ///which is confirmed by handshake, and only ciphertexts are transferred, never plaintext.
+    ///Other store must take opposite `role`, refer to `sync` module for details of protocol.
+    ///
+    ///Entries, missing on one side, are copied from the other one, unless they are removed there,
+    ///in which case removal is propagated, given that it is tracked via `Self::enable_versioning` on removing side.
+    ///Keys, present on both sides with different values, are resolved by their versions, if any,
+    ///with the greater one replacing the other (refer to `Self::enable_versioning`),
+    ///otherwise they are reported as conflicts and left untouched.
+    ///Names and tags of keys are not transferred.
+    ///
+    ///Everything, exchanged after handshake, is authenticated, and received entries are verified
+    ///to fit store's limits before any of them is stored, otherwise store is left untouched, and error is returned.
+    ///Initiator stores received entries only once responder acknowledges, that it accepts entries sent to it,
+    ///so that either both sides succeed, or neither stores anything (unless transport fails after acknowledgement).
+    pub fn sync<T: Read + Write>(&mut self, mut transport: T, role: Role) -> io::Result<Report> {
+        if self.locked {
+            return Err(Error::Locked.into());
+        }
+
+        self.flush_versions();
+        let mut nonce = [0u8; NONCE_LEN];
+        if SystemRandom::new().fill(&mut nonce).is_err() {
+            return Err(io::Error::new(io::ErrorKind::Other, "Unable to generate nonce"));
+        }
+
+        let manifest: delta::Digests = delta::digests(&self.inner).into_iter().filter(|(key, _)| *key >= RESERVED).collect();
+        let local_versions = self.versions.as_ref().map(|versions| (&versions.entries, &versions.tombstones));
+        let own_versions = match self.versions.is_some() {
+            true => self.inner.get(VERSIONS_KEY),
+            false => None,
+        };
+        let (plan, received, remote_versions) = match role {
+            Role::Initiator => {
+                write_hello(&mut transport, &nonce)?;
+                transport.flush()?;
+                let responder = read_hello(&mut transport)?;
+                self.verify_sync_proof(&mut transport, Role::Responder, &nonce, &responder)?;
+                transport.write_all(self.sync_proof(Role::Initiator, &nonce, &responder).as_ref())?;
+
+                let mut transport = Authenticated::new(transport, self.enc.subkey(b"sec-store:sync-transcript"), role, &nonce, &responder);
+                write_manifest(&mut transport, &manifest)?;
+                write_versions(&mut transport, own_versions)?;
+                transport.flush()?;
+
+                let remote = read_manifest(&mut transport)?;
+                let remote_versions = read_versions(&mut transport, &self.enc)?;
+                let plan = Plan::new(&manifest, local_versions, &remote, remote_versions.as_ref().map(|(entries, tombstones)| (entries, tombstones)));
+                let received = read_entries(&mut transport, &plan.receive)?;
+                transport.verify_mac()?;
+                self.check_sync_entries(&received)?;
+                self.write_sync_entries(&mut transport, &plan.send)?;
+                transport.send_mac()?;
+
+                let mut ack = [0u8; 1];
+                transport.read_exact(&mut ack)?;
+                transport.verify_mac()?;
+                match ack[0] {
+                    ACK_ACCEPTED => (),
+                    ACK_REJECTED => return Err(invalid_data("Other store rejected sync")),
+                    _ => return Err(invalid_data("Unknown sync acknowledgement")),
+                }
+                (plan, received, remote_versions)
+            },
+            Role::Responder => {
+                let initiator = read_hello(&mut transport)?;
+                write_hello(&mut transport, &nonce)?;
+                transport.write_all(self.sync_proof(Role::Responder, &initiator, &nonce).as_ref())?;
+                transport.flush()?;
+                self.verify_sync_proof(&mut transport, Role::Initiator, &initiator, &nonce)?;
+
+                let mut transport = Authenticated::new(transport, self.enc.subkey(b"sec-store:sync-transcript"), role, &initiator, &nonce);
+                let remote = read_manifest(&mut transport)?;
+                let remote_versions = read_versions(&mut transport, &self.enc)?;
+                let plan = Plan::new(&manifest, local_versions, &remote, remote_versions.as_ref().map(|(entries, tombstones)| (entries, tombstones)));
+                write_manifest(&mut transport, &manifest)?;
+                write_versions(&mut transport, own_versions)?;
+                self.write_sync_entries(&mut transport, &plan.send)?;
+                transport.send_mac()?;
+                let received = read_entries(&mut transport, &plan.receive)?;
+                transport.verify_mac()?;
+
+                let result = self.check_sync_entries(&received);
+                let ack = match result {
+                    Ok(()) => ACK_ACCEPTED,
+                    Err(_) => ACK_REJECTED,
+                };
+                transport.write_all(&[ack])?;
+                transport.send_mac()?;
+                result?;
+                (plan, received, remote_versions)
+            },
+        };
+
+        let report = Report {
+            sent: plan.send.len(),
+            received: received.len(),
+            removed: plan.remove.len(),
+            conflicts: plan.conflicts,
+        };
+        self.apply_sync_entries(received, &plan.remove, remote_versions);
+        Ok(report)
+    }
+}
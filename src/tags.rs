@@ -0,0 +1,177 @@
+use crate::{enc, Backend, Error, Store, TAGS_KEY};
+
+use std::collections::BTreeMap;
+
+///Name and tags of keys, mapped to their hashes.
+pub(crate) type Tags = BTreeMap<u128, (Vec<u8>, Vec<Vec<u8>>)>;
+
+#[inline]
+pub(crate) fn push_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    out.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+    out.extend_from_slice(bytes);
+}
+
+pub(crate) fn pop_bytes<'a>(input: &mut &'a [u8]) -> Option<&'a [u8]> {
+    if input.len() < 4 {
+        return None;
+    }
+
+    let mut len = [0u8; 4];
+    len.copy_from_slice(&input[..4]);
+    let len = u32::from_le_bytes(len) as usize;
+    if input.len() - 4 < len {
+        return None;
+    }
+
+    let result = &input[4..4 + len];
+    *input = &input[4 + len..];
+    Some(result)
+}
+
+///Encodes `tags` as sequence of `hash | name | number of tags: u32 | tags`.
+///
+///Name and each tag are prefixed with length as `u32`.
+fn encode(tags: &Tags) -> Vec<u8> {
+    let mut result = Vec::new();
+    for (key, (name, tags)) in tags.iter() {
+        result.extend_from_slice(&key.to_le_bytes());
+        push_bytes(&mut result, name);
+        result.extend_from_slice(&(tags.len() as u32).to_le_bytes());
+        for tag in tags.iter() {
+            push_bytes(&mut result, tag);
+        }
+    }
+    result
+}
+
+fn decode(mut input: &[u8]) -> Option<Tags> {
+    let mut result = Tags::new();
+    while !input.is_empty() {
+        if input.len() < 16 {
+            return None;
+        }
+
+        let mut key = [0u8; 16];
+        key.copy_from_slice(&input[..16]);
+        input = &input[16..];
+        let name = pop_bytes(&mut input)?.to_owned();
+        if input.len() < 4 {
+            return None;
+        }
+        let mut count = [0u8; 4];
+        count.copy_from_slice(&input[..4]);
+        input = &input[4..];
+
+        let mut tags = Vec::new();
+        for _ in 0..u32::from_le_bytes(count) {
+            tags.push(pop_bytes(&mut input)?.to_owned());
+        }
+        result.insert(u128::from_le_bytes(key), (name, tags));
+    }
+
+    Some(result)
+}
+
+///Loads tags of keys from `inner`, returning empty set if they are not stored or cannot be decrypted.
+pub(crate) fn load<B: Backend>(enc: &enc::Manager, inner: &B) -> Tags {
+    let value = match inner.get(TAGS_KEY) {
+        Some(value) => value,
+        None => return Tags::new(),
+    };
+
+    let mut plain = Vec::new();
+    let result = match crate::open_to_vec(enc, TAGS_KEY, value, &mut plain) {
+        Ok(_) => decode(&plain),
+        Err(_) => None,
+    };
+    enc::wipe(&mut plain);
+    result.unwrap_or_default()
+}
+
+///Wipes `tags` from memory, leaving it empty.
+pub(crate) fn wipe(tags: &mut Tags) {
+    for (_, (mut name, tags)) in core::mem::take(tags) {
+        enc::wipe(&mut name);
+        for mut tag in tags {
+            enc::wipe(&mut tag);
+        }
+    }
+}
+
+impl<B: Backend> Store<B> {
+    fn write_tags(&mut self) {
+        if self.locked {
+            return;
+        }
+
+        if self.tags.is_empty() {
+            self.inner.remove(TAGS_KEY);
+            return;
+        }
+
+        let mut value = encode(&self.tags);
+        if self.sealing.internal().seal(&self.enc, TAGS_KEY, &mut value) {
+            self.inner.insert(TAGS_KEY, value);
+        }
+    }
+
+    ///Forgets tags of `key`, if any.
+    pub(crate) fn forget_tags(&mut self, key: u128) {
+        if self.tags.remove(&key).is_some() {
+            self.write_tags();
+        }
+    }
+
+    ///Inserts new `value` for `key` with set of `tags`, returning previous value, if any.
+    ///
+    ///Tags, along with key name, are kept within single encrypted entry, which is re-written on every change of tags.
+    ///Previous tags of `key` are replaced, while regular insertion keeps them, and removal discards them.
+    ///
+    ///Returns `Error::LimitExceeded` if value doesn't fit store's limits, leaving it untouched.
+    pub fn try_insert_with_meta(&mut self, key: &[u8], value: &[u8], tags: &[&[u8]]) -> Result<Option<Vec<u8>>, Error> {
+        self.suspend_autosave();
+        let result = match self.try_insert(key, value) {
+            Ok(result) => result,
+            Err(error) => {
+                self.resume_autosave();
+                return Err(error);
+            },
+        };
+
+        let mut tags: Vec<_> = tags.iter().map(|tag| tag.to_vec()).collect();
+        tags.sort_unstable();
+        tags.dedup();
+        self.tags.insert(self.hash_key(key), (key.to_owned(), tags));
+        self.write_tags();
+        self.resume_autosave();
+        Ok(result)
+    }
+
+    #[inline]
+    ///Inserts new `value` for `key` with set of `tags`, returning previous value, if any.
+    ///
+    ///Panics if value doesn't fit store's limits, refer to `Self::try_insert_with_meta`.
+    pub fn insert_with_meta(&mut self, key: &[u8], value: &[u8], tags: &[&[u8]]) -> Option<Vec<u8>> {
+        match self.try_insert_with_meta(key, value, tags) {
+            Ok(result) => result,
+            Err(error) => panic!("Cannot insert value: {}", error),
+        }
+    }
+
+    ///Returns tags of `key` in ascending order, if any.
+    pub fn tags(&self, key: &[u8]) -> impl Iterator<Item = &[u8]> + '_ {
+        let key = self.hash_key(key);
+        let tags = match self.inner.contains(key) {
+            true => self.tags.get(&key),
+            false => None,
+        };
+        tags.into_iter().flat_map(|(_, tags)| tags.iter().map(Vec::as_slice))
+    }
+
+    ///Returns names of keys, tagged with `tag`, in order of their hashes.
+    ///
+    ///Refer to `Self::try_insert_with_meta` for details.
+    pub fn find_by_tag<'a>(&'a self, tag: &'a [u8]) -> impl Iterator<Item = &'a [u8]> + 'a {
+        self.tags.iter().filter(move |(key, (_, tags))| self.inner.contains(**key) && tags.binary_search_by(|probe| probe.as_slice().cmp(tag)).is_ok()).map(|(_, (name, _))| name.as_slice())
+    }
+}
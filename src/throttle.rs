@@ -0,0 +1,142 @@
+use crate::{Error, Store};
+
+use core::cmp;
+use core::time::Duration;
+use std::io;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Instant;
+
+#[derive(Default)]
+struct State {
+    failures: u32,
+    ///Number of attempts, that are not yet resolved, each counting as failure until then.
+    in_flight: u32,
+    locked_until: Option<Instant>,
+}
+
+impl State {
+    fn fail(&mut self, base: Duration, max: Duration) {
+        let exponent = cmp::min(self.failures, 31);
+        self.failures = self.failures.saturating_add(1);
+        let period = cmp::min(base.saturating_mul(1 << exponent), max);
+        self.locked_until = Some(Instant::now() + period);
+    }
+}
+
+enum Outcome {
+    Success,
+    Failure,
+    Other,
+}
+
+///Attempt reserved within guard, resolved once dropped, so that panicking attempt still counts as failure.
+struct Attempt<'a> {
+    guard: &'a UnlockGuard,
+    outcome: Outcome,
+}
+
+impl Drop for Attempt<'_> {
+    fn drop(&mut self) {
+        let mut state = self.guard.state();
+        state.in_flight = state.in_flight.saturating_sub(1);
+        match self.outcome {
+            Outcome::Success => {
+                state.failures = 0;
+                state.locked_until = None;
+            },
+            Outcome::Failure => state.fail(self.guard.base, self.guard.max),
+            Outcome::Other => (),
+        }
+    }
+}
+
+///Guard of unlock attempts, slowing down guessing of credentials.
+///
+///Every consecutive failure due to `Error::WrongCredentials` locks guard out for exponentially growing period,
+///starting with `base` and doubling up to `max`, while successful attempt resets it.
+///Attempts made while locked out fail with `Error::LockedOut`, without checking credentials.
+///Attempt counts as failure until it is resolved, so concurrent attempts fail with `Error::LockedOut` too.
+///
+///Guard can be shared across threads, as it is meant to be kept by service opening stores on behalf of its users.
+pub struct UnlockGuard {
+    base: Duration,
+    max: Duration,
+    state: Mutex<State>,
+}
+
+impl UnlockGuard {
+    #[inline]
+    ///Creates new guard with lockout period starting at `base`, and growing up to `max`.
+    pub fn new(base: Duration, max: Duration) -> Self {
+        Self {
+            base,
+            max,
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    #[inline]
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|error| error.into_inner())
+    }
+
+    #[inline]
+    ///Returns number of consecutive failed attempts, including ones that are not yet resolved.
+    pub fn failures(&self) -> u32 {
+        let state = self.state();
+        state.failures.saturating_add(state.in_flight)
+    }
+
+    ///Returns time remaining until next attempt is allowed, if guard is locked out.
+    pub fn remaining(&self) -> Option<Duration> {
+        let now = Instant::now();
+        match self.state().locked_until {
+            Some(until) if until > now => Some(until - now),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    ///Forgets about failed attempts, except ones that are not yet resolved.
+    pub fn reset(&self) {
+        let mut state = self.state();
+        state.failures = 0;
+        state.locked_until = None;
+    }
+
+    ///Performs unlock attempt via `unlock`, unless guard is locked out.
+    ///
+    ///Returns `Error::LockedOut` without invoking `unlock` if guard is locked out or another attempt is in progress.
+    ///Otherwise returns result of `unlock`, with `Error::WrongCredentials` or panic counting as failed attempt.
+    pub fn attempt<T, F: FnOnce() -> Result<T, Error>>(&self, unlock: F) -> Result<T, Error> {
+        let mut attempt = {
+            let mut state = self.state();
+            if state.in_flight > 0 || state.locked_until.map_or(false, |until| until > Instant::now()) {
+                return Err(Error::LockedOut);
+            }
+            state.in_flight += 1;
+            Attempt {
+                guard: self,
+                outcome: Outcome::Failure,
+            }
+        };
+
+        let result = unlock();
+        attempt.outcome = match result {
+            Ok(_) => Outcome::Success,
+            Err(Error::WrongCredentials) => Outcome::Failure,
+            Err(_) => Outcome::Other,
+        };
+        result
+    }
+
+    #[inline]
+    ///Opens storage, previously saved via `Store::save`, as unlock attempt.
+    ///
+    ///Refer to `Store::open` and `Self::attempt` for details.
+    pub fn open<P: AsRef<Path>>(&self, path: P, user: &[u8], pass: &[u8]) -> io::Result<Store> {
+        let inner = crate::format::read_file(path.as_ref())?;
+        self.attempt(|| Store::try_from_inner(inner, user, pass)).map_err(Into::into)
+    }
+}
@@ -0,0 +1,1080 @@
+use crate::{enc, format, KeyWrap, MasterKey, Store};
+
+use aes::cipher::generic_array::GenericArray;
+use aes::cipher::{BlockEncrypt, KeyInit};
+use aes::Aes128;
+use ring::{agreement, digest, hmac};
+use ring::rand::{SecureRandom, SystemRandom};
+
+use core::fmt;
+use core::ops::Range;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+
+const TPM_ST_NO_SESSIONS: u16 = 0x8001;
+const TPM_ST_SESSIONS: u16 = 0x8002;
+
+const TPM_CC_CREATE_PRIMARY: u32 = 0x0131;
+const TPM_CC_CREATE: u32 = 0x0153;
+const TPM_CC_LOAD: u32 = 0x0157;
+const TPM_CC_UNSEAL: u32 = 0x015E;
+const TPM_CC_FLUSH_CONTEXT: u32 = 0x0165;
+const TPM_CC_START_AUTH_SESSION: u32 = 0x0176;
+const TPM_CC_PCR_READ: u32 = 0x017E;
+const TPM_CC_POLICY_PCR: u32 = 0x017F;
+
+const TPM_RH_OWNER: u32 = 0x4000_0001;
+const TPM_RH_NULL: u32 = 0x4000_0007;
+const TPM_RS_PW: u32 = 0x4000_0009;
+const TPM_SE_HMAC: u8 = 0x00;
+const TPM_SE_POLICY: u8 = 0x01;
+
+const TPM_ALG_AES: u16 = 0x0006;
+const TPM_ALG_KEYEDHASH: u16 = 0x0008;
+const TPM_ALG_SHA256: u16 = 0x000B;
+const TPM_ALG_NULL: u16 = 0x0010;
+const TPM_ALG_ECC: u16 = 0x0023;
+const TPM_ALG_CFB: u16 = 0x0043;
+const TPM_ECC_NIST_P256: u16 = 0x0003;
+
+const FIXED_TPM: u32 = 1 << 1;
+const FIXED_PARENT: u32 = 1 << 4;
+const SENSITIVE_DATA_ORIGIN: u32 = 1 << 5;
+const USER_WITH_AUTH: u32 = 1 << 6;
+const NO_DA: u32 = 1 << 10;
+const RESTRICTED: u32 = 1 << 16;
+const DECRYPT: u32 = 1 << 17;
+
+///Attribute of session, encrypting first parameter of command.
+const SESSION_DECRYPT: u8 = 0x20;
+///Attribute of session, encrypting first parameter of response.
+const SESSION_ENCRYPT: u8 = 0x40;
+
+///Number of PCRs, that can be selected.
+const PCR_COUNT: u8 = 24;
+const PCR_SELECT_LEN: usize = PCR_COUNT as usize / 8;
+const NONCE_LEN: usize = 32;
+///Size of coordinate of `NIST P-256` point.
+const COORDINATE_LEN: usize = 32;
+const AES_KEY_LEN: usize = 16;
+const RESPONSE_HEADER_LEN: usize = 10;
+const MAX_RESPONSE_LEN: usize = 4096;
+
+///TPM 2.0 of Linux machine, sealing master key to it, refer to `KeyWrap`.
+///
+///Key is sealed as data object under primary storage key of owner hierarchy, that is re-created on demand,
+///so sealed key can only be unsealed by the same TPM, and can be kept alongside storage.
+///Optionally sealed key is bound to state of PCRs within `SHA-256` bank, refer to `Self::bind_pcrs`.
+///
+///Key is passed to and from TPM within session, salted by primary key, using AES-128 parameter encryption,
+///so it never crosses bus between CPU and TPM in clear.
+///Authorization of owner hierarchy, refer to `Self::owner_auth`, is proven via HMAC, rather than sent as password.
+///
+///Windows, where TPM is accessed via TBS, is not supported.
+///Refer to `Store::open_tpm_sealed` for usage.
+#[derive(Clone)]
+pub struct Tpm {
+    device: PathBuf,
+    pcrs: [u8; PCR_SELECT_LEN],
+    owner: Vec<u8>,
+}
+
+impl Tpm {
+    #[inline]
+    ///Creates instance, accessing TPM via kernel's resource manager `/dev/tpmrm0`.
+    pub fn new() -> Self {
+        Self::with_device("/dev/tpmrm0")
+    }
+
+    #[inline]
+    ///Creates instance, accessing TPM via character `device`, e.g. `/dev/tpm0` if there is no resource manager.
+    ///
+    ///`device` can be unix socket of TPM simulator too, e.g. `swtpm socket --tpm2 --server type=unixio,path=<device>`.
+    pub fn with_device<P: Into<PathBuf>>(device: P) -> Self {
+        Self {
+            device: device.into(),
+            pcrs: [0; PCR_SELECT_LEN],
+            owner: Vec::new(),
+        }
+    }
+
+    ///Binds keys, sealed from now on, to current state of `pcrs` within `SHA-256` bank.
+    ///
+    ///Key, sealed with binding, can only be unsealed as long as these PCRs have the same values,
+    ///while binding of key is recorded within it, so unsealing doesn't depend on this setting.
+    ///
+    ///Panics if index of PCR is not below `24`.
+    pub fn bind_pcrs(mut self, pcrs: &[u8]) -> Self {
+        for pcr in pcrs {
+            assert!(*pcr < PCR_COUNT, "PCR index must be below 24");
+            self.pcrs[*pcr as usize / 8] |= 1 << (pcr % 8);
+        }
+        self
+    }
+
+    ///Sets authorization value of owner hierarchy, which is empty by default.
+    pub fn owner_auth(mut self, auth: &[u8]) -> Self {
+        enc::wipe(&mut self.owner);
+        self.owner = auth.to_vec();
+        self
+    }
+
+    fn open(&self) -> io::Result<Device> {
+        match fs::metadata(&self.device)?.file_type().is_socket() {
+            true => UnixStream::connect(&self.device).map(Device::Socket),
+            false => OpenOptions::new().read(true).write(true).open(&self.device).map(Device::File),
+        }
+    }
+
+    ///Seals `key` to TPM, returning opaque bytes, that can be kept alongside storage.
+    ///
+    ///Sealed key consists of selection of bound PCRs, public and private area of sealed object.
+    pub fn seal(&self, key: &MasterKey) -> io::Result<Vec<u8>> {
+        let key = key.as_bytes();
+        let mut device = self.open()?;
+        let policy = match self.pcrs == [0; PCR_SELECT_LEN] {
+            true => None,
+            false => Some(device.pcr_policy(&self.pcrs)?),
+        };
+
+        let parent = device.create_primary(&self.owner)?;
+        let result = device.create(&parent, key, policy.as_ref());
+        device.flush(parent.handle);
+        let (public, private) = result?;
+
+        let mut result = Vec::with_capacity(PCR_SELECT_LEN + public.len() + private.len());
+        result.extend_from_slice(&self.pcrs);
+        result.extend_from_slice(&public);
+        result.extend_from_slice(&private);
+        Ok(result)
+    }
+
+    ///Unseals key, previously sealed via `Self::seal`, satisfying PCR policy, if key is bound to PCRs.
+    pub fn unseal(&self, sealed: &[u8]) -> io::Result<MasterKey> {
+        let mut input = Input(sealed);
+        let mut pcrs = [0u8; PCR_SELECT_LEN];
+        pcrs.copy_from_slice(input.take(PCR_SELECT_LEN)?);
+        let public = input.sized()?;
+        let private = input.sized()?;
+        if !input.0.is_empty() {
+            return Err(invalid_data("Sealed key has trailing data"));
+        }
+
+        let mut device = self.open()?;
+        let parent = device.create_primary(&self.owner)?;
+        let object = device.load(&parent, public, private);
+        //Parent salts session, so it is flushed only once session is started.
+        let result = object.and_then(|object| {
+            let session = match pcrs == [0; PCR_SELECT_LEN] {
+                true => device.start_session(&parent, TPM_SE_HMAC),
+                false => device.policy_session(&parent, &pcrs),
+            };
+            let result = session.and_then(|session| device.unseal(&object, session));
+            device.flush(object.handle);
+            result
+        });
+        device.flush(parent.handle);
+        result.map(|mut key| {
+            let result = MasterKey::from_bytes(key);
+            enc::wipe(&mut key);
+            result
+        })
+    }
+}
+
+impl Default for Tpm {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for Tpm {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_struct("Tpm").field("device", &self.device).field("pcrs", &self.pcrs).finish_non_exhaustive()
+    }
+}
+
+impl Drop for Tpm {
+    #[inline]
+    fn drop(&mut self) {
+        enc::wipe(&mut self.owner);
+    }
+}
+
+impl KeyWrap for Tpm {
+    type Error = io::Error;
+
+    #[inline]
+    fn wrap(&self, key: &[u8; 32]) -> Result<Vec<u8>, Self::Error> {
+        self.seal(&MasterKey::from_bytes(*key))
+    }
+
+    #[inline]
+    fn unwrap(&self, wrapped: &[u8]) -> Result<[u8; 32], Self::Error> {
+        self.unseal(wrapped).map(|key| *key.as_bytes())
+    }
+}
+
+///Loaded object.
+struct Object {
+    handle: u32,
+    ///Name of object, used to authorize commands via HMAC.
+    name: Vec<u8>,
+}
+
+///Primary storage key.
+struct Primary {
+    handle: u32,
+    name: Vec<u8>,
+    ///Public key as uncompressed `NIST P-256` point, used to salt sessions.
+    point: Vec<u8>,
+}
+
+///Started session, that is flushed by TPM once used, as it is not continued.
+struct Session {
+    handle: u32,
+    ///Session key, derived from salt, which is empty for unsalted session.
+    key: Vec<u8>,
+    ///The latest nonce of TPM.
+    nonce: Vec<u8>,
+}
+
+impl Drop for Session {
+    #[inline]
+    fn drop(&mut self) {
+        enc::wipe(&mut self.key);
+    }
+}
+
+///Authorization of command.
+enum Auth<'a> {
+    None,
+    ///Password session with empty password, used where authorization value is empty and nothing is to be kept secret.
+    Password,
+    ///`Session`, proving authorization value of entity, and encrypting parameters according to its attributes.
+    Session(Session, &'a [u8], u8),
+}
+
+///Marshalled structures.
+struct Buffer(Vec<u8>);
+
+impl Buffer {
+    #[inline]
+    fn u16(&mut self, value: u16) {
+        self.0.extend_from_slice(&value.to_be_bytes());
+    }
+
+    #[inline]
+    fn u32(&mut self, value: u32) {
+        self.0.extend_from_slice(&value.to_be_bytes());
+    }
+
+    #[inline]
+    ///Writes `data` prefixed with its size, as `TPM2B` structure.
+    fn sized(&mut self, data: &[u8]) {
+        self.u16(data.len() as u16);
+        self.0.extend_from_slice(data);
+    }
+
+    #[inline]
+    fn pcr_selection(&mut self, pcrs: &[u8; PCR_SELECT_LEN]) {
+        self.u32(1);
+        self.u16(TPM_ALG_SHA256);
+        self.0.push(PCR_SELECT_LEN as u8);
+        self.0.extend_from_slice(pcrs);
+    }
+
+    #[inline]
+    ///Writes empty outside info and PCR selection of creation data.
+    fn no_creation_data(&mut self) {
+        self.sized(&[]);
+        self.u32(0);
+    }
+}
+
+///Command under construction.
+struct Command {
+    code: u32,
+    handles: Vec<u32>,
+    ///Names of handles, used to authorize command via HMAC.
+    names: Vec<u8>,
+    params: Buffer,
+    ///Number of handles within response.
+    response_handles: usize,
+}
+
+impl Command {
+    #[inline]
+    fn new(code: u32) -> Self {
+        Self {
+            code,
+            handles: Vec::new(),
+            names: Vec::new(),
+            params: Buffer(Vec::with_capacity(128)),
+            response_handles: 0,
+        }
+    }
+
+    #[inline]
+    ///Adds `handle` of entity, which has `name`.
+    fn handle(mut self, handle: u32, name: &[u8]) -> Self {
+        self.handles.push(handle);
+        self.names.extend_from_slice(name);
+        self
+    }
+
+    #[inline]
+    ///Adds `handle` of permanent entity, which name is handle itself.
+    fn permanent(self, handle: u32) -> Self {
+        self.handle(handle, &handle.to_be_bytes())
+    }
+
+    #[inline]
+    ///Marks that response contains handle of created entity.
+    fn returns_handle(mut self) -> Self {
+        self.response_handles = 1;
+        self
+    }
+
+    ///Returns hash of command, authorized via HMAC.
+    fn hash(&self) -> digest::Digest {
+        let mut result = digest::Context::new(&digest::SHA256);
+        result.update(&self.code.to_be_bytes());
+        result.update(&self.names);
+        result.update(&self.params.0);
+        result.finish()
+    }
+
+    ///Returns command, authorized by session with `auth` area.
+    fn finish(self, auth: Option<&[u8]>) -> Vec<u8> {
+        let mut result = Buffer(Vec::with_capacity(RESPONSE_HEADER_LEN + self.params.0.len() + 128));
+        result.u16(match auth {
+            Some(_) => TPM_ST_SESSIONS,
+            None => TPM_ST_NO_SESSIONS,
+        });
+        //Size is filled once command is complete.
+        result.u32(0);
+        result.u32(self.code);
+        for handle in self.handles.iter() {
+            result.u32(*handle);
+        }
+        if let Some(auth) = auth {
+            result.u32(auth.len() as u32);
+            result.0.extend_from_slice(auth);
+        }
+        result.0.extend_from_slice(&self.params.0);
+
+        let len = result.0.len() as u32;
+        result.0[2..6].copy_from_slice(&len.to_be_bytes());
+        let mut params = self.params.0;
+        enc::wipe(&mut params);
+        result.0
+    }
+}
+
+///Parser of response.
+struct Input<'a>(&'a [u8]);
+
+impl<'a> Input<'a> {
+    fn take(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        match self.0.len() >= len {
+            true => {
+                let (result, rest) = self.0.split_at(len);
+                self.0 = rest;
+                Ok(result)
+            },
+            false => Err(invalid_data("TPM response is truncated")),
+        }
+    }
+
+    fn u8(&mut self) -> io::Result<u8> {
+        self.take(1).map(|value| value[0])
+    }
+
+    fn u16(&mut self) -> io::Result<u16> {
+        let mut result = [0u8; 2];
+        result.copy_from_slice(self.take(2)?);
+        Ok(u16::from_be_bytes(result))
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        let mut result = [0u8; 4];
+        result.copy_from_slice(self.take(4)?);
+        Ok(u32::from_be_bytes(result))
+    }
+
+    ///Reads `TPM2B` structure, including its size.
+    fn sized(&mut self) -> io::Result<&'a [u8]> {
+        let input = self.0;
+        let len = self.u16()? as usize;
+        self.take(len)?;
+        Ok(&input[..2 + len])
+    }
+
+    ///Reads `TPM2B` structure, excluding its size.
+    fn sized_data(&mut self) -> io::Result<&'a [u8]> {
+        self.sized().map(|data| &data[2..])
+    }
+}
+
+///Open TPM.
+enum Device {
+    ///Character device of kernel's driver, which returns whole response at once.
+    File(File),
+    ///Unix socket of TPM simulator.
+    Socket(UnixStream),
+}
+
+impl Device {
+    ///Writes `command`, returning raw response of TPM.
+    fn transmit(&mut self, mut command: Vec<u8>) -> io::Result<Vec<u8>> {
+        let result = match self {
+            Device::File(file) => file.write_all(&command),
+            Device::Socket(socket) => socket.write_all(&command),
+        };
+        enc::wipe(&mut command);
+        result?;
+
+        let mut response = vec![0; MAX_RESPONSE_LEN];
+        let len = match self {
+            Device::File(file) => file.read(&mut response)?,
+            Device::Socket(socket) => {
+                socket.read_exact(&mut response[..RESPONSE_HEADER_LEN])?;
+                let size = Input(&response[2..6]).u32()? as usize;
+                match (RESPONSE_HEADER_LEN..=MAX_RESPONSE_LEN).contains(&size) {
+                    true => socket.read_exact(&mut response[RESPONSE_HEADER_LEN..size])?,
+                    false => return Err(invalid_data("TPM response has invalid size")),
+                }
+                size
+            },
+        };
+        response.truncate(len);
+        Ok(response)
+    }
+
+    ///Sends `command`, authorized via `auth`, returning response, which is wiped once dropped.
+    ///
+    ///Session, if any, is flushed on failure.
+    fn send(&mut self, command: Command, auth: Auth<'_>) -> io::Result<Response> {
+        let result = match auth {
+            Auth::None => self.send_with(command, None),
+            Auth::Password => {
+                let mut area = Buffer(Vec::with_capacity(9));
+                area.u32(TPM_RS_PW);
+                area.sized(&[]);
+                area.0.push(0);
+                area.sized(&[]);
+                self.send_with(command, Some(&area.0))
+            },
+            Auth::Session(session, auth, attributes) => {
+                //Authorization value is used without trailing zeroes.
+                let auth = &auth[..auth.iter().rposition(|byte| *byte != 0).map_or(0, |idx| idx + 1)];
+                let mut value = Vec::with_capacity(session.key.len() + auth.len());
+                value.extend_from_slice(&session.key);
+                value.extend_from_slice(auth);
+                let result = self.send_in(command, &session, &value, attributes);
+                enc::wipe(&mut value);
+                if result.is_err() {
+                    self.flush(session.handle);
+                }
+                result
+            },
+        };
+        result.map(|(response, _)| response)
+    }
+
+    ///Sends `command` with authorization `area`, returning response along with range of its authorization area.
+    fn send_with(&mut self, command: Command, area: Option<&[u8]>) -> io::Result<(Response, Range<usize>)> {
+        let handles = command.response_handles;
+        let data = self.transmit(command.finish(area))?;
+        let mut response = Response {
+            data,
+            params: 0..0,
+        };
+
+        let mut input = Input(&response.data);
+        let tag = input.u16()?;
+        let size = input.u32()? as usize;
+        let code = input.u32()?;
+        if code != 0 {
+            return Err(io::Error::other(format!("TPM command failed with code 0x{:03X}", code)));
+        } else if size != response.data.len() || size < RESPONSE_HEADER_LEN {
+            return Err(invalid_data("TPM response has invalid size"));
+        }
+
+        input.take(4 * handles)?;
+        let params = match tag {
+            TPM_ST_SESSIONS => input.u32()? as usize,
+            _ => input.0.len(),
+        };
+        let start = size - input.0.len();
+        input.take(params)?;
+        response.params = start..start + params;
+        Ok((response, start + params..size))
+    }
+
+    ///Sends `command`, authorized by `session` with its session `value`, made of session key and authorization value of entity.
+    ///
+    ///First parameter of command is encrypted, if `attributes` contain `SESSION_DECRYPT`,
+    ///while first parameter of response is decrypted, if `attributes` contain `SESSION_ENCRYPT`.
+    ///Both command and response are authenticated via HMAC, which covers policy session too.
+    fn send_in(&mut self, mut command: Command, session: &Session, value: &[u8], attributes: u8) -> io::Result<(Response, Range<usize>)> {
+        let key = hmac::Key::new(hmac::HMAC_SHA256, value);
+        let nonce = random_nonce()?;
+        if attributes & SESSION_DECRYPT != 0 {
+            let mut cfb_key = kdfa(value, b"CFB", &nonce, &session.nonce);
+            let result = first_param(&mut command.params.0).map(|data| cfb(&cfb_key, data, false));
+            enc::wipe(&mut cfb_key);
+            result?;
+        }
+
+        let mut area = Buffer(Vec::with_capacity(4 + 2 + NONCE_LEN + 1 + 2 + 32));
+        area.u32(session.handle);
+        area.sized(&nonce);
+        area.0.push(attributes);
+        area.sized(session_hmac(&key, command.hash().as_ref(), &nonce, &session.nonce, attributes).as_ref());
+
+        let code = command.code;
+        let (mut response, range) = self.send_with(command, Some(&area.0))?;
+        let mut input = Input(&response.data[range.clone()]);
+        let tpm_nonce = input.sized_data()?;
+        let response_attributes = input.u8()?;
+        let mac = input.sized_data()?;
+        let hash = response_hash(code, response.params());
+        if !enc::ct_eq(session_hmac(&key, hash.as_ref(), tpm_nonce, &nonce, response_attributes).as_ref(), mac) {
+            return Err(invalid_data("TPM response is not authenticated"));
+        }
+
+        if attributes & SESSION_ENCRYPT != 0 {
+            let mut cfb_key = kdfa(value, b"CFB", tpm_nonce, &nonce);
+            let params = response.params.clone();
+            let result = first_param(&mut response.data[params]).map(|data| cfb(&cfb_key, data, true));
+            enc::wipe(&mut cfb_key);
+            result?;
+        }
+        Ok((response, range))
+    }
+
+    ///Flushes transient object or session, ignoring failure.
+    fn flush(&mut self, handle: u32) {
+        let mut command = Command::new(TPM_CC_FLUSH_CONTEXT);
+        command.params.u32(handle);
+        let _ = self.send(command, Auth::None);
+    }
+
+    ///Creates primary storage key of owner hierarchy, which is the same for the same TPM.
+    ///
+    ///Owner's authorization value `owner` is proven via HMAC of unsalted session.
+    fn create_primary(&mut self, owner: &[u8]) -> io::Result<Primary> {
+        let session = self.start_session_with(None, TPM_SE_HMAC)?;
+        let mut command = Command::new(TPM_CC_CREATE_PRIMARY).permanent(TPM_RH_OWNER).returns_handle();
+        //Empty sensitive area, consisting of `userAuth` and `data`.
+        command.params.u16(4);
+        command.params.sized(&[]);
+        command.params.sized(&[]);
+
+        let mut public = Buffer(Vec::with_capacity(32));
+        public.u16(TPM_ALG_ECC);
+        public.u16(TPM_ALG_SHA256);
+        public.u32(FIXED_TPM | FIXED_PARENT | SENSITIVE_DATA_ORIGIN | USER_WITH_AUTH | NO_DA | RESTRICTED | DECRYPT);
+        public.sized(&[]);
+        public.u16(TPM_ALG_AES);
+        public.u16(128);
+        public.u16(TPM_ALG_CFB);
+        public.u16(TPM_ALG_NULL);
+        public.u16(TPM_ECC_NIST_P256);
+        public.u16(TPM_ALG_NULL);
+        public.sized(&[]);
+        public.sized(&[]);
+        command.params.sized(&public.0);
+        command.params.no_creation_data();
+
+        let response = self.send(command, Auth::Session(session, owner, 0))?;
+        let handle = response.handle()?;
+        let result = Input(response.params()).sized_data().and_then(|public| {
+            let mut name = TPM_ALG_SHA256.to_be_bytes().to_vec();
+            name.extend_from_slice(digest::digest(&digest::SHA256, public).as_ref());
+            Ok(Primary {
+                handle,
+                name,
+                point: ecc_point(public)?,
+            })
+        });
+        if result.is_err() {
+            self.flush(handle);
+        }
+        result
+    }
+
+    #[inline]
+    ///Starts session of `kind`, salted by `parent`, using AES-128 parameter encryption.
+    fn start_session(&mut self, parent: &Primary, kind: u8) -> io::Result<Session> {
+        self.start_session_with(Some(parent), kind)
+    }
+
+    ///Starts session of `kind`, salted by `parent`, if any.
+    ///
+    ///Only salted session uses parameter encryption, as unsalted session key is empty.
+    fn start_session_with(&mut self, parent: Option<&Primary>, kind: u8) -> io::Result<Session> {
+        let nonce = random_nonce()?;
+        let (mut command, salt) = match parent {
+            Some(parent) => {
+                let (secret, salt) = salt(&parent.point)?;
+                let mut command = Command::new(TPM_CC_START_AUTH_SESSION).handle(parent.handle, &parent.name);
+                command = command.permanent(TPM_RH_NULL).returns_handle();
+                command.params.sized(&nonce);
+                command.params.sized(&secret);
+                (command, Some(salt))
+            },
+            None => {
+                let mut command = Command::new(TPM_CC_START_AUTH_SESSION).permanent(TPM_RH_NULL).permanent(TPM_RH_NULL).returns_handle();
+                command.params.sized(&nonce);
+                command.params.sized(&[]);
+                (command, None)
+            },
+        };
+        command.params.0.push(kind);
+        match salt {
+            Some(_) => {
+                command.params.u16(TPM_ALG_AES);
+                command.params.u16(AES_KEY_LEN as u16 * 8);
+                command.params.u16(TPM_ALG_CFB);
+            },
+            None => command.params.u16(TPM_ALG_NULL),
+        }
+        command.params.u16(TPM_ALG_SHA256);
+
+        let response = self.send(command, Auth::None)?;
+        let handle = response.handle()?;
+        let tpm_nonce = match Input(response.params()).sized_data() {
+            Ok(tpm_nonce) => tpm_nonce.to_vec(),
+            Err(error) => {
+                self.flush(handle);
+                return Err(error);
+            },
+        };
+        let key = match salt {
+            Some(mut salt) => {
+                let key = kdfa(&salt, b"ATH", &tpm_nonce, &nonce).to_vec();
+                enc::wipe(&mut salt);
+                key
+            },
+            None => Vec::new(),
+        };
+        Ok(Session {
+            handle,
+            key,
+            nonce: tpm_nonce,
+        })
+    }
+
+    ///Creates object, sealing `key`, under `parent`, returning its public and private area.
+    ///
+    ///Key is encrypted within session, salted by `parent`.
+    fn create(&mut self, parent: &Primary, key: &[u8; 32], policy: Option<&[u8; 32]>) -> io::Result<(Vec<u8>, Vec<u8>)> {
+        let session = self.start_session(parent, TPM_SE_HMAC)?;
+        let mut command = Command::new(TPM_CC_CREATE).handle(parent.handle, &parent.name);
+        command.params.u16(2 + 2 + key.len() as u16);
+        command.params.sized(&[]);
+        command.params.sized(key);
+
+        let mut public = Buffer(Vec::with_capacity(64));
+        public.u16(TPM_ALG_KEYEDHASH);
+        public.u16(TPM_ALG_SHA256);
+        match policy {
+            //Without user's authorization key can only be unsealed within policy session.
+            Some(policy) => {
+                public.u32(FIXED_TPM | FIXED_PARENT | NO_DA);
+                public.sized(policy);
+            },
+            None => {
+                public.u32(FIXED_TPM | FIXED_PARENT | USER_WITH_AUTH | NO_DA);
+                public.sized(&[]);
+            },
+        }
+        public.u16(TPM_ALG_NULL);
+        public.sized(&[]);
+        command.params.sized(&public.0);
+        command.params.no_creation_data();
+
+        let response = self.send(command, Auth::Session(session, &[], SESSION_DECRYPT))?;
+        let mut input = Input(response.params());
+        let private = input.sized()?.to_vec();
+        let public = input.sized()?.to_vec();
+        Ok((public, private))
+    }
+
+    ///Loads sealed object under `parent`.
+    ///
+    ///Private area is encrypted by `parent`, so it is loaded via password session with empty password of `parent`.
+    fn load(&mut self, parent: &Primary, public: &[u8], private: &[u8]) -> io::Result<Object> {
+        let mut command = Command::new(TPM_CC_LOAD).handle(parent.handle, &parent.name).returns_handle();
+        command.params.0.extend_from_slice(private);
+        command.params.0.extend_from_slice(public);
+
+        let response = self.send(command, Auth::Password)?;
+        let handle = response.handle()?;
+        match Input(response.params()).sized_data() {
+            Ok(name) => Ok(Object {
+                handle,
+                name: name.to_vec(),
+            }),
+            Err(error) => {
+                self.flush(handle);
+                Err(error)
+            },
+        }
+    }
+
+    ///Unseals key out of `object`, authorized by salted `session`, which encrypts key.
+    fn unseal(&mut self, object: &Object, session: Session) -> io::Result<[u8; 32]> {
+        let command = Command::new(TPM_CC_UNSEAL).handle(object.handle, &object.name);
+
+        let response = self.send(command, Auth::Session(session, &[], SESSION_ENCRYPT))?;
+        let data = Input(response.params()).sized_data()?;
+        let mut result = [0u8; 32];
+        match data.len() == result.len() {
+            true => {
+                result.copy_from_slice(data);
+                Ok(result)
+            },
+            false => Err(invalid_data("Sealed key has invalid length")),
+        }
+    }
+
+    ///Reads value of `pcr` within `SHA-256` bank.
+    fn pcr_read(&mut self, pcr: usize) -> io::Result<Vec<u8>> {
+        let mut pcrs = [0u8; PCR_SELECT_LEN];
+        pcrs[pcr / 8] = 1 << (pcr % 8);
+        let mut command = Command::new(TPM_CC_PCR_READ);
+        command.params.pcr_selection(&pcrs);
+
+        let response = self.send(command, Auth::None)?;
+        let mut input = Input(response.params());
+        let _counter = input.u32()?;
+        let selections = input.u32()?;
+        for _ in 0..selections {
+            let _hash = input.u16()?;
+            let len = input.u8()? as usize;
+            input.take(len)?;
+        }
+        match input.u32()? {
+            1 => Ok(input.sized_data()?.to_vec()),
+            _ => Err(io::Error::other(format!("PCR {} is not available within SHA-256 bank", pcr))),
+        }
+    }
+
+    ///Computes digest of policy, requiring current values of `pcrs`, as `TPM2_PolicyPCR` does.
+    fn pcr_policy(&mut self, pcrs: &[u8; PCR_SELECT_LEN]) -> io::Result<[u8; 32]> {
+        let mut values = digest::Context::new(&digest::SHA256);
+        for pcr in 0..PCR_COUNT as usize {
+            if pcrs[pcr / 8] & (1 << (pcr % 8)) != 0 {
+                values.update(&self.pcr_read(pcr)?);
+            }
+        }
+
+        let mut selection = Buffer(Vec::with_capacity(10));
+        selection.pcr_selection(pcrs);
+        let mut policy = digest::Context::new(&digest::SHA256);
+        policy.update(&[0u8; 32]);
+        policy.update(&TPM_CC_POLICY_PCR.to_be_bytes());
+        policy.update(&selection.0);
+        policy.update(values.finish().as_ref());
+
+        let mut result = [0u8; 32];
+        result.copy_from_slice(policy.finish().as_ref());
+        Ok(result)
+    }
+
+    ///Starts policy session, salted by `parent`, satisfying policy of current values of `pcrs`.
+    fn policy_session(&mut self, parent: &Primary, pcrs: &[u8; PCR_SELECT_LEN]) -> io::Result<Session> {
+        let session = self.start_session(parent, TPM_SE_POLICY)?;
+        let mut command = Command::new(TPM_CC_POLICY_PCR).permanent(session.handle);
+        //Empty digest makes TPM use current values of PCRs.
+        command.params.sized(&[]);
+        command.params.pcr_selection(pcrs);
+        match self.send(command, Auth::None) {
+            Ok(_) => Ok(session),
+            Err(error) => {
+                self.flush(session.handle);
+                Err(error)
+            },
+        }
+    }
+}
+
+///Response of TPM, wiped once dropped.
+struct Response {
+    data: Vec<u8>,
+    ///Range of parameters within `data`.
+    params: Range<usize>,
+}
+
+impl Response {
+    #[inline]
+    ///Returns the first handle of response.
+    fn handle(&self) -> io::Result<u32> {
+        Input(&self.data[RESPONSE_HEADER_LEN..]).u32()
+    }
+
+    #[inline]
+    fn params(&self) -> &[u8] {
+        &self.data[self.params.clone()]
+    }
+}
+
+impl Drop for Response {
+    #[inline]
+    fn drop(&mut self) {
+        enc::wipe(&mut self.data);
+    }
+}
+
+#[inline]
+fn invalid_data(error: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+fn random_nonce() -> io::Result<[u8; NONCE_LEN]> {
+    let mut result = [0u8; NONCE_LEN];
+    match SystemRandom::new().fill(&mut result) {
+        Ok(()) => Ok(result),
+        Err(_) => Err(io::Error::other(crate::Error::RandomFailure)),
+    }
+}
+
+///Returns data of the first parameter within `params`, which is `TPM2B` structure.
+fn first_param(params: &mut [u8]) -> io::Result<&mut [u8]> {
+    let len = Input(params).u16()? as usize;
+    match params.len() >= 2 + len {
+        true => Ok(&mut params[2..2 + len]),
+        false => Err(invalid_data("TPM parameter is truncated")),
+    }
+}
+
+///Extracts public key out of `TPMT_PUBLIC` area of primary key, as uncompressed point.
+fn ecc_point(public: &[u8]) -> io::Result<Vec<u8>> {
+    let mut input = Input(public);
+    //Type, name algorithm and attributes.
+    input.take(2 + 2 + 4)?;
+    input.sized()?;
+    //Symmetric definition, scheme, curve and KDF.
+    input.take(6 + 2 + 2 + 2)?;
+
+    let mut result = Vec::with_capacity(1 + 2 * COORDINATE_LEN);
+    result.push(4);
+    for _ in 0..2 {
+        let coordinate = input.sized_data()?;
+        if coordinate.len() > COORDINATE_LEN {
+            return Err(invalid_data("Primary key of TPM has invalid point"));
+        }
+        result.resize(result.len() + COORDINATE_LEN - coordinate.len(), 0);
+        result.extend_from_slice(coordinate);
+    }
+    Ok(result)
+}
+
+///Generates salt of session, encrypted to `point` of primary key via ECDH, returning encrypted salt along with salt.
+fn salt(point: &[u8]) -> io::Result<(Vec<u8>, [u8; 32])> {
+    let random = SystemRandom::new();
+    let private = agreement::EphemeralPrivateKey::generate(&agreement::ECDH_P256, &random).map_err(|_| io::Error::other(crate::Error::RandomFailure))?;
+    let public = private.compute_public_key().map_err(|_| io::Error::other(crate::Error::RandomFailure))?;
+    let public = public.as_ref();
+    let peer = agreement::UnparsedPublicKey::new(&agreement::ECDH_P256, point);
+    let salt = agreement::agree_ephemeral(private, &peer, |shared| {
+        kdfe(shared, &public[1..1 + COORDINATE_LEN], &point[1..1 + COORDINATE_LEN])
+    }).map_err(|_| invalid_data("Primary key of TPM has invalid point"))?;
+
+    let mut secret = Buffer(Vec::with_capacity(4 + 2 * COORDINATE_LEN));
+    secret.sized(&public[1..1 + COORDINATE_LEN]);
+    secret.sized(&public[1 + COORDINATE_LEN..]);
+    Ok((secret.0, salt))
+}
+
+///Derives 256 bits of `SECRET` out of `x` coordinate of `shared` point, as `KDFe` of TPM does using `SHA-256`.
+///
+///`party_u` and `party_v` are `x` coordinates of ephemeral key and of key of TPM respectively.
+fn kdfe(shared: &[u8], party_u: &[u8], party_v: &[u8]) -> [u8; 32] {
+    let mut context = digest::Context::new(&digest::SHA256);
+    context.update(&1u32.to_be_bytes());
+    context.update(shared);
+    context.update(b"SECRET\0");
+    context.update(party_u);
+    context.update(party_v);
+    let mut result = [0u8; 32];
+    result.copy_from_slice(context.finish().as_ref());
+    result
+}
+
+///Returns `rpHash` of response to command with `code`, which succeeded with `params`.
+fn response_hash(code: u32, params: &[u8]) -> digest::Digest {
+    let mut result = digest::Context::new(&digest::SHA256);
+    result.update(&0u32.to_be_bytes());
+    result.update(&code.to_be_bytes());
+    result.update(params);
+    result.finish()
+}
+
+///Returns HMAC of session over `hash` of command or response, keyed by session key followed by authorization value.
+///
+///`nonce_newer` is nonce of sender, i.e. caller for command and TPM for response, while `nonce_older` is nonce of receiver.
+fn session_hmac(key: &hmac::Key, hash: &[u8], nonce_newer: &[u8], nonce_older: &[u8], attributes: u8) -> hmac::Tag {
+    let mut context = hmac::Context::with_key(key);
+    context.update(hash);
+    context.update(nonce_newer);
+    context.update(nonce_older);
+    context.update(&[attributes]);
+    context.sign()
+}
+
+///Derives 256 bits out of `key`, as `KDFa` of TPM does using `SHA-256`.
+fn kdfa(key: &[u8], label: &[u8], context_u: &[u8], context_v: &[u8]) -> [u8; 32] {
+    let mut context = hmac::Context::with_key(&hmac::Key::new(hmac::HMAC_SHA256, key));
+    context.update(&1u32.to_be_bytes());
+    context.update(label);
+    context.update(&[0]);
+    context.update(context_u);
+    context.update(context_v);
+    context.update(&256u32.to_be_bytes());
+    let mut result = [0u8; 32];
+    result.copy_from_slice(context.sign().as_ref());
+    result
+}
+
+///Encrypts or decrypts `data` in place via AES-128 in CFB mode, using `key` followed by IV, as derived via `kdfa`.
+fn cfb(key: &[u8; 32], data: &mut [u8], decrypt: bool) {
+    let cipher = Aes128::new(GenericArray::from_slice(&key[..AES_KEY_LEN]));
+    let mut block = GenericArray::clone_from_slice(&key[AES_KEY_LEN..]);
+    for chunk in data.chunks_mut(AES_KEY_LEN) {
+        cipher.encrypt_block(&mut block);
+        for (byte, feedback) in chunk.iter_mut().zip(block.iter_mut()) {
+            let input = *byte;
+            *byte ^= *feedback;
+            *feedback = match decrypt {
+                true => input,
+                false => *byte,
+            };
+        }
+    }
+    enc::wipe(&mut block);
+}
+
+impl Store {
+    ///Opens storage, previously saved via `Self::save`, using encryption key, sealed to `tpm`.
+    ///
+    ///Key is expected to be sealed via `Tpm::seal` with the same TPM, and kept alongside storage.
+    ///Returns error if key cannot be unsealed, e.g. on different machine or once bound PCRs change.
+    ///Refer to `Self::open_with_key` for details.
+    pub fn open_tpm_sealed<P: AsRef<Path>>(path: P, sealed: &[u8], tpm: &Tpm) -> io::Result<Self> {
+        let key = tpm.unseal(sealed)?;
+        let inner = format::read_file(path.as_ref())?;
+        Self::try_from_backend_with_key(inner, &key).map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_encrypt_via_cfb() {
+        //Test vector F.3.13 of NIST SP 800-38A.
+        let mut key = [0u8; 32];
+        key[..16].copy_from_slice(&[0x2b, 0x7e, 0x15, 0x16, 0x28, 0xae, 0xd2, 0xa6, 0xab, 0xf7, 0x15, 0x88, 0x09, 0xcf, 0x4f, 0x3c]);
+        for (idx, byte) in key[16..].iter_mut().enumerate() {
+            *byte = idx as u8;
+        }
+        let plain = [
+            0x6b, 0xc1, 0xbe, 0xe2, 0x2e, 0x40, 0x9f, 0x96, 0xe9, 0x3d, 0x7e, 0x11, 0x73, 0x93, 0x17, 0x2a,
+            0xae, 0x2d, 0x8a, 0x57, 0x1e, 0x03, 0xac, 0x9c, 0x9e, 0xb7, 0x6f, 0xac, 0x45, 0xaf, 0x8e, 0x51,
+        ];
+        let cipher = [
+            0x3b, 0x3f, 0xd9, 0x2e, 0xb7, 0x2d, 0xad, 0x20, 0x33, 0x34, 0x49, 0xf8, 0xe8, 0x3c, 0xfb, 0x4a,
+            0xc8, 0xa6, 0x45, 0x37, 0xa0, 0xb3, 0xa9, 0x3f, 0xcd, 0xe3, 0xcd, 0xad, 0x9f, 0x1c, 0xe5, 0x8b,
+        ];
+
+        let mut data = plain;
+        cfb(&key, &mut data, false);
+        assert_eq!(data, cipher);
+        cfb(&key, &mut data, true);
+        assert_eq!(data, plain);
+
+        //Partial block
+        let mut data = plain;
+        cfb(&key, &mut data[..20], false);
+        assert_eq!(data[..20], cipher[..20]);
+        cfb(&key, &mut data[..20], true);
+        assert_eq!(data, plain);
+    }
+
+    //Expected values of tests below are computed independently out of definitions of TPM 2.0 Part 1,
+    //via Python's `hmac` and `hashlib`, rather than taken out of TPM.
+
+    #[test]
+    fn should_derive_via_kdfa() {
+        let key: Vec<u8> = (0..32).collect();
+        let nonce_newer: Vec<u8> = (0x40..0x50).collect();
+        let nonce_older: Vec<u8> = (0x80..0x90).collect();
+        let expected = [
+            0x0e, 0x35, 0x5a, 0x35, 0x85, 0x26, 0xa4, 0x8a, 0x7f, 0x8e, 0x34, 0xc2, 0xdb, 0xe5, 0xc6, 0xe8,
+            0x34, 0xb4, 0x64, 0xae, 0x4a, 0x6d, 0x87, 0xe7, 0xc5, 0x24, 0x66, 0x5e, 0xd5, 0xb3, 0x0e, 0x4c,
+        ];
+
+        assert_eq!(kdfa(&key, b"CFB", &nonce_newer, &nonce_older), expected);
+        assert_ne!(kdfa(&key, b"CFB", &nonce_older, &nonce_newer), expected);
+        assert_ne!(kdfa(&key, b"ATH", &nonce_newer, &nonce_older), expected);
+    }
+
+    #[test]
+    fn should_derive_via_kdfe() {
+        let shared: Vec<u8> = (0xa0..0xc0).collect();
+        let expected = [
+            0x45, 0xa8, 0x1d, 0xb7, 0xe7, 0x5d, 0x94, 0xcf, 0xdb, 0x5f, 0x7a, 0x67, 0x5c, 0x89, 0xd2, 0x04,
+            0x81, 0x77, 0xd0, 0xcc, 0xba, 0x52, 0xf1, 0x3c, 0x97, 0xa8, 0x12, 0x13, 0x56, 0x65, 0xa6, 0xde,
+        ];
+
+        assert_eq!(kdfe(&shared, &[0x11; COORDINATE_LEN], &[0x22; COORDINATE_LEN]), expected);
+        assert_ne!(kdfe(&shared, &[0x22; COORDINATE_LEN], &[0x11; COORDINATE_LEN]), expected);
+    }
+
+    #[test]
+    fn should_compute_session_hmac() {
+        let mut name = vec![0x00, 0x0b];
+        name.extend_from_slice(&[0xaa; 32]);
+        let command = Command::new(TPM_CC_UNSEAL).handle(0x8000_0001, &name);
+        let cp_hash = [
+            0xfb, 0x43, 0xe7, 0x03, 0xc4, 0xf8, 0x62, 0x2b, 0xaf, 0xb2, 0x40, 0xe0, 0x30, 0x88, 0xb3, 0x24,
+            0x9e, 0xe7, 0x10, 0x82, 0x2e, 0x62, 0x08, 0xf1, 0x30, 0xbc, 0xe0, 0x0f, 0xfb, 0x3c, 0x5c, 0x42,
+        ];
+        assert_eq!(command.hash().as_ref(), cp_hash);
+
+        let rp_hash = [
+            0x8b, 0xd7, 0xd2, 0x1d, 0x02, 0x3d, 0x2b, 0x34, 0xcb, 0x07, 0xc5, 0x71, 0xf9, 0xbe, 0x87, 0x85,
+            0xeb, 0x8a, 0xcd, 0x98, 0x19, 0x3c, 0x45, 0x1e, 0xfd, 0x0b, 0xf0, 0x44, 0x8d, 0xc4, 0x26, 0x9d,
+        ];
+        assert_eq!(response_hash(TPM_CC_UNSEAL, b"\x00\x04key!").as_ref(), rp_hash);
+
+        //Session key, followed by authorization value.
+        let mut value = vec![0x33; 32];
+        value.extend_from_slice(b"pass");
+        let key = hmac::Key::new(hmac::HMAC_SHA256, &value);
+        let nonce_caller: Vec<u8> = (0x01..0x21).collect();
+        let nonce_tpm: Vec<u8> = (0x41..0x61).collect();
+        let command_hmac = [
+            0x23, 0x35, 0xb1, 0x75, 0x80, 0x07, 0xc6, 0x81, 0x7e, 0xba, 0xfb, 0xf7, 0x97, 0xaf, 0xcc, 0x3b,
+            0x65, 0x01, 0xcf, 0x9b, 0x52, 0x4d, 0xbb, 0x70, 0xca, 0x3c, 0x82, 0x29, 0xb7, 0xf8, 0xce, 0x43,
+        ];
+        let response_hmac = [
+            0xae, 0x13, 0x72, 0x47, 0xb9, 0xca, 0x2f, 0x11, 0x5d, 0x9e, 0xdf, 0xc0, 0x82, 0x28, 0x72, 0x3c,
+            0x0b, 0x0e, 0x2b, 0x2f, 0x6b, 0xaf, 0x9b, 0x0e, 0xb8, 0x38, 0x0f, 0x4d, 0x04, 0xf6, 0x6f, 0xcb,
+        ];
+        assert_eq!(session_hmac(&key, &cp_hash, &nonce_caller, &nonce_tpm, SESSION_ENCRYPT).as_ref(), command_hmac);
+        assert_eq!(session_hmac(&key, &rp_hash, &nonce_tpm, &nonce_caller, SESSION_ENCRYPT).as_ref(), response_hmac);
+        assert_ne!(session_hmac(&key, &cp_hash, &nonce_caller, &nonce_tpm, SESSION_DECRYPT).as_ref(), command_hmac);
+    }
+}
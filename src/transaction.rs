@@ -0,0 +1,171 @@
+use crate::{Backend, Error, Store};
+
+use std::collections::BTreeMap;
+
+///Set of staged modifications, created by `Store::transaction`.
+///
+///Reads observe staged modifications, while store itself is modified only once transaction succeeds.
+pub struct Transaction<'a, B = BTreeMap<u128, Vec<u8>>> {
+    store: &'a Store<B>,
+    staged: BTreeMap<u128, Option<Vec<u8>>>,
+    ///Names of inserted keys, if store keeps them.
+    names: Vec<(u128, Vec<u8>)>,
+    ///Number of entries with staged modifications applied.
+    len: usize,
+    ///Size of ciphertexts with staged modifications applied.
+    size: usize,
+    ///Number of staged insertions.
+    inserted: usize,
+}
+
+impl<'a, B: Backend> Transaction<'a, B> {
+    #[inline]
+    ///Retrieves value for `key`, taking into account staged modifications.
+    ///
+    ///Returns `None` if decryption failed.
+    pub fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        let key = self.store.hash_key(key);
+
+        match self.staged.get(&key) {
+            Some(Some(value)) => self.store.decrypt_value(key, value),
+            Some(None) => None,
+            None => self.store.inner.get(key).and_then(|value| self.store.decrypt_value(key, value)),
+        }
+    }
+
+    #[inline]
+    ///Checks for `key` presence, taking into account staged modifications.
+    pub fn contains(&self, key: &[u8]) -> bool {
+        let key = self.store.hash_key(key);
+
+        match self.staged.get(&key) {
+            Some(value) => value.is_some(),
+            None => self.store.inner.contains(key),
+        }
+    }
+
+    #[inline]
+    fn ciphertext_len(&self, key: u128) -> Option<usize> {
+        match self.staged.get(&key) {
+            Some(value) => value.as_ref().map(Vec::len),
+            None => self.store.inner.get(key).map(<[u8]>::len),
+        }
+    }
+
+    ///Stages insertion of `value` for `key`.
+    ///
+    ///Returns `Error::LimitExceeded` if value doesn't fit store's limits, leaving transaction untouched.
+    ///Insertions are limited by store's capacity, if eviction is enabled, so that transaction never evicts its own entries.
+    pub fn try_insert(&mut self, name: &[u8], value: &[u8]) -> Result<(), Error> {
+        if self.store.locked {
+            return Err(Error::Locked);
+        }
+
+        let key = self.store.hash_key(name);
+        if value.is_empty() {
+            return Err(Error::InvalidEntry(key));
+        }
+        let previous = self.ciphertext_len(key);
+        let len = self.store.sealing.sealed_len(value.len());
+        self.store.limits.check(self.len, self.size, previous, value.len(), len)?;
+        let inserted = match self.staged.get(&key) {
+            Some(Some(_)) => self.inserted,
+            _ => self.inserted + 1,
+        };
+        if matches!(self.store.capacity(), Some(capacity) if inserted > capacity) {
+            return Err(Error::LimitExceeded);
+        }
+
+        let mut value = value.to_owned();
+        assert!(self.store.sealing.seal(&self.store.enc, key, &mut value));
+        self.staged.insert(key, Some(value));
+        self.inserted = inserted;
+        if self.store.names.is_some() {
+            self.names.push((key, name.to_owned()));
+        }
+        self.len += previous.is_none() as usize;
+        self.size = self.size - previous.unwrap_or(0) + len;
+        Ok(())
+    }
+
+    #[inline]
+    ///Stages insertion of `value` for `key`.
+    ///
+    ///Panics if value doesn't fit store's limits, refer to `Self::try_insert`.
+    pub fn insert(&mut self, key: &[u8], value: &[u8]) {
+        if let Err(error) = self.try_insert(key, value) {
+            panic!("Cannot insert value: {}", error);
+        }
+    }
+
+    ///Stages removal of `key`, returning whether it is set at this point of transaction.
+    pub fn remove(&mut self, key: &[u8]) -> bool {
+        let key = self.store.hash_key(key);
+        let previous = self.ciphertext_len(key);
+        if let Some(Some(_)) = self.staged.insert(key, None) {
+            self.inserted -= 1;
+        }
+        if let Some(previous) = previous {
+            self.len -= 1;
+            self.size -= previous;
+        }
+        previous.is_some()
+    }
+}
+
+impl<B: Backend> Store<B> {
+    ///Runs `cb` within transaction, applying all its modifications only if it returns `Ok`.
+    ///
+    ///On `Err` all staged modifications are discarded, leaving store untouched.
+    ///
+    ///Modifications are checked against store's limits while being staged, and applied all at once.
+    ///If eviction is enabled, least recently used entries are evicted only after all of them are applied.
+    pub fn transaction<T, E, F: FnOnce(&mut Transaction<'_, B>) -> Result<T, E>>(&mut self, cb: F) -> Result<T, E> {
+        let mut transaction = Transaction {
+            len: self.len(),
+            size: self.size,
+            store: self,
+            staged: BTreeMap::new(),
+            names: Vec::new(),
+            inserted: 0,
+        };
+
+        let result = cb(&mut transaction)?;
+        let staged = transaction.staged;
+        let names = transaction.names;
+        self.suspend_autosave();
+        for (key, name) in names {
+            if let Some(Some(_)) = staged.get(&key) {
+                self.record_name(key, &name);
+            }
+        }
+
+        //Eviction is postponed, so that partially applied transaction is never observed by it.
+        let eviction = self.eviction.take();
+        let mut keys = Vec::with_capacity(staged.len());
+        for (key, value) in staged {
+            keys.push((key, value.is_some()));
+            match value {
+                Some(value) => {
+                    crate::discard(self.inner_put(key, value));
+                },
+                None => {
+                    crate::discard(self.inner_take(key));
+                },
+            }
+        }
+        self.eviction = eviction;
+        for (key, is_inserted) in keys {
+            match is_inserted {
+                true => self.touch(key),
+                false => if let Some(eviction) = self.eviction.as_ref() {
+                    eviction.forget(key);
+                },
+            }
+        }
+        self.evict();
+        self.resume_autosave();
+
+        Ok(result)
+    }
+}
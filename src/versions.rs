@@ -0,0 +1,331 @@
+use crate::{enc, Backend, Error, Store, VERSIONS_KEY};
+
+use core::convert::TryFrom;
+use core::time::Duration;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const FORMAT: u8 = 1;
+///Format, followed by tombstones.
+const FORMAT_TOMBSTONES: u8 = 2;
+const RECORD_LEN: usize = 16 + 8 + 8;
+const TOMBSTONE_LEN: usize = RECORD_LEN + 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+///Version of entry, ordered by lamport clock first and replica, that made modification, next.
+pub(crate) struct Version {
+    pub clock: u64,
+    pub replica: u64,
+}
+
+///Versions of entries, keyed by their hashes.
+pub(crate) type Entries = BTreeMap<u128, Version>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+///Removal of entry, replicated by `Store::sync`.
+pub(crate) struct Tombstone {
+    pub version: Version,
+    ///Unix time of removal, in seconds.
+    pub time: u64,
+}
+
+///Tombstones of removed entries, keyed by their hashes.
+pub(crate) type Tombstones = BTreeMap<u128, Tombstone>;
+
+#[inline]
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_secs()).unwrap_or(0)
+}
+
+pub(crate) struct Versions {
+    ///Random identifier of current session, so that concurrent modifications never share version.
+    replica: u64,
+    ///Greatest clock, observed so far.
+    pub clock: u64,
+    pub entries: Entries,
+    pub tombstones: Tombstones,
+    ///Whether versions are modified since they were written into storage.
+    dirty: bool,
+    ///Ciphertext of modified versions, sealed once on demand until they are written into storage.
+    sealed: Mutex<Option<Vec<u8>>>,
+}
+
+impl Versions {
+    fn new(entries: Entries, tombstones: Tombstones) -> Self {
+        let mut replica = [0u8; 8];
+        if let Some(nonce) = enc::random_nonce() {
+            replica.copy_from_slice(&nonce[..8]);
+        }
+
+        Self {
+            replica: u64::from_le_bytes(replica),
+            clock: entries.values().chain(tombstones.values().map(|tombstone| &tombstone.version)).map(|version| version.clock).max().unwrap_or(0),
+            entries,
+            tombstones,
+            dirty: false,
+            sealed: Mutex::new(None),
+        }
+    }
+
+    #[inline]
+    fn sealed(&self) -> std::sync::MutexGuard<'_, Option<Vec<u8>>> {
+        self.sealed.lock().unwrap_or_else(|error| error.into_inner())
+    }
+
+    #[inline]
+    ///Marks versions as modified, discarding their outdated ciphertext.
+    pub(crate) fn touch(&mut self) {
+        self.dirty = true;
+        *self.sealed.get_mut().unwrap_or_else(|error| error.into_inner()) = None;
+    }
+}
+
+#[inline]
+fn push_version(out: &mut Vec<u8>, key: u128, version: &Version) {
+    out.extend_from_slice(&key.to_le_bytes());
+    out.extend_from_slice(&version.clock.to_le_bytes());
+    out.extend_from_slice(&version.replica.to_le_bytes());
+}
+
+fn pop_version(record: &[u8]) -> (u128, Version) {
+    let mut key = [0u8; 16];
+    key.copy_from_slice(&record[..16]);
+    let mut clock = [0u8; 8];
+    clock.copy_from_slice(&record[16..24]);
+    let mut replica = [0u8; 8];
+    replica.copy_from_slice(&record[24..RECORD_LEN]);
+    (u128::from_le_bytes(key), Version {
+        clock: u64::from_le_bytes(clock),
+        replica: u64::from_le_bytes(replica),
+    })
+}
+
+///Encodes `entries` as `FORMAT: u8 | (key: u128 | clock: u64 | replica: u64)..`.
+///
+///If there are `tombstones`, they follow as `FORMAT_TOMBSTONES: u8 | count: u64 | entries | (key: u128 | clock: u64 | replica: u64 | time: u64)..`,
+///so that format stays readable by previous versions otherwise.
+fn encode(entries: &Entries, tombstones: &Tombstones) -> Vec<u8> {
+    let mut result = Vec::with_capacity(9 + entries.len() * RECORD_LEN + tombstones.len() * TOMBSTONE_LEN);
+    match tombstones.is_empty() {
+        true => result.push(FORMAT),
+        false => {
+            result.push(FORMAT_TOMBSTONES);
+            result.extend_from_slice(&(entries.len() as u64).to_le_bytes());
+        },
+    }
+    for (key, version) in entries.iter() {
+        push_version(&mut result, *key, version);
+    }
+    for (key, tombstone) in tombstones.iter() {
+        push_version(&mut result, *key, &tombstone.version);
+        result.extend_from_slice(&tombstone.time.to_le_bytes());
+    }
+    result
+}
+
+fn decode(input: &[u8]) -> Option<(Entries, Tombstones)> {
+    let (records, tombstones) = match input.split_first()? {
+        (&FORMAT, records) => (records, &[][..]),
+        (&FORMAT_TOMBSTONES, input) if input.len() >= 8 => {
+            let mut count = [0u8; 8];
+            count.copy_from_slice(&input[..8]);
+            let len = usize::try_from(u64::from_le_bytes(count)).ok()?.checked_mul(RECORD_LEN)?;
+            match input.len() - 8 >= len {
+                true => input[8..].split_at(len),
+                false => return None,
+            }
+        },
+        _ => return None,
+    };
+
+    if records.len() % RECORD_LEN != 0 || tombstones.len() % TOMBSTONE_LEN != 0 {
+        return None;
+    }
+    let entries = records.chunks_exact(RECORD_LEN).map(pop_version).collect();
+    let tombstones = tombstones.chunks_exact(TOMBSTONE_LEN).map(|record| {
+        let (key, version) = pop_version(record);
+        let mut time = [0u8; 8];
+        time.copy_from_slice(&record[RECORD_LEN..]);
+        (key, Tombstone {
+            version,
+            time: u64::from_le_bytes(time),
+        })
+    }).collect();
+    Some((entries, tombstones))
+}
+
+///Decrypts versions from ciphertext of internal entry.
+pub(crate) fn open(enc: &enc::Manager, value: &[u8]) -> Option<(Entries, Tombstones)> {
+    let mut plain = Vec::new();
+    crate::open_to_vec(enc, VERSIONS_KEY, value, &mut plain).ok()?;
+    decode(&plain)
+}
+
+///Loads versions of entries from `inner`, returning `None` if they are not stored or cannot be decrypted.
+pub(crate) fn load<B: Backend>(enc: &enc::Manager, inner: &B) -> Option<Versions> {
+    open(enc, inner.get(VERSIONS_KEY)?).map(|(entries, tombstones)| Versions::new(entries, tombstones))
+}
+
+impl<B: Backend> Store<B> {
+    ///Returns ciphertext of versions, if they are modified since written into storage.
+    ///
+    ///Ciphertext is sealed once, so that every save writes the same content until versions are modified again.
+    pub(crate) fn pending_versions(&self) -> Option<Vec<u8>> {
+        match self.versions.as_ref() {
+            Some(versions) if versions.dirty && !self.locked => {
+                let mut sealed = versions.sealed();
+                if sealed.is_none() {
+                    let mut value = encode(&versions.entries, &versions.tombstones);
+                    if self.sealing.internal().seal(&self.enc, VERSIONS_KEY, &mut value) {
+                        *sealed = Some(value);
+                    }
+                }
+                sealed.clone()
+            },
+            _ => None,
+        }
+    }
+
+    #[inline]
+    ///Iterates over entries as they are saved, with `versions`, returned by `Self::pending_versions`, in place of outdated ones.
+    pub(crate) fn stored_entries<'a>(&'a self, versions: Option<&'a [u8]>) -> impl Iterator<Item = (u128, &'a [u8])> + 'a {
+        self.inner.iter().map(move |(key, value)| match versions {
+            Some(versions) if key == VERSIONS_KEY => (key, versions),
+            _ => (key, value),
+        })
+    }
+
+    ///Writes versions into storage.
+    pub(crate) fn write_versions(&mut self) {
+        if self.locked {
+            return;
+        }
+
+        let value = match self.pending_versions() {
+            Some(value) => Some(value),
+            None => self.versions.as_ref().and_then(|versions| {
+                let mut value = encode(&versions.entries, &versions.tombstones);
+                match self.sealing.internal().seal(&self.enc, VERSIONS_KEY, &mut value) {
+                    true => Some(value),
+                    false => None,
+                }
+            }),
+        };
+        if let (Some(value), Some(versions)) = (value, self.versions.as_mut()) {
+            versions.dirty = false;
+            *versions.sealed.get_mut().unwrap_or_else(|error| error.into_inner()) = None;
+            self.inner.insert(VERSIONS_KEY, value);
+        }
+    }
+
+    ///Writes versions of keys into storage, if they are modified since last write.
+    ///
+    ///Versions are kept in memory while keys are modified, so that each modification doesn't re-encrypt all of them.
+    ///Saving writes them along with entries, while it is done automatically by `Self::update_mac`, `Self::sync`,
+    ///`Self::lock` and `Self::into_inner`, so it is only required before accessing storage via `Self::inner`.
+    pub fn flush_versions(&mut self) {
+        if matches!(self.versions.as_ref(), Some(versions) if versions.dirty) {
+            self.write_versions();
+        }
+    }
+
+    ///Advances version of `key`, if versioning is enabled.
+    pub(crate) fn record_version(&mut self, key: u128) {
+        if let Some(versions) = self.versions.as_mut() {
+            versions.clock += 1;
+            let version = Version {
+                clock: versions.clock,
+                replica: versions.replica,
+            };
+            versions.entries.insert(key, version);
+            versions.tombstones.remove(&key);
+            versions.touch();
+        }
+    }
+
+    ///Replaces version of removed `key` with tombstone, if versioning is enabled.
+    pub(crate) fn record_removal(&mut self, key: u128) {
+        if let Some(versions) = self.versions.as_mut() {
+            versions.clock += 1;
+            let version = Version {
+                clock: versions.clock,
+                replica: versions.replica,
+            };
+            versions.entries.remove(&key);
+            versions.tombstones.insert(key, Tombstone {
+                version,
+                time: now(),
+            });
+            versions.touch();
+        }
+    }
+
+    ///Enables tracking of versions, allowing `Self::sync` to resolve concurrent modifications.
+    ///
+    ///Each modification of key stores lamport clock along with random identifier of modifying session,
+    ///within single encrypted entry, which is written on save, refer to `Self::flush_versions`.
+    ///During sync, value with greater version wins, regardless of side, so both stores converge to the same content.
+    ///Keys, modified before enabling it, have no version, hence lose to any versioned modification.
+    ///
+    ///Removal of key, including eviction, leaves tombstone with version in place of it,
+    ///so that removal replicates as well, refer to `Self::purge_tombstones`.
+    ///
+    ///Does nothing if it is already enabled.
+    pub fn enable_versioning(&mut self) {
+        if self.versions.is_none() && !self.locked {
+            self.versions = Some(Versions::new(Entries::new(), Tombstones::new()));
+            self.write_versions();
+        }
+    }
+
+    #[inline]
+    ///Returns whether versions are tracked, refer to `Self::enable_versioning`.
+    pub fn has_versioning(&self) -> bool {
+        self.versions.is_some()
+    }
+
+    ///Returns lamport clock of last modification of `key`, if it is tracked.
+    ///
+    ///Refer to `Self::enable_versioning` for details.
+    pub fn version(&self, key: &[u8]) -> Option<u64> {
+        let key = self.hash_key(key);
+        match self.inner.contains(key) {
+            true => self.versions.as_ref()?.entries.get(&key).map(|version| version.clock),
+            false => None,
+        }
+    }
+
+    #[inline]
+    ///Returns number of tombstones of removed keys, refer to `Self::enable_versioning`.
+    pub fn tombstones_len(&self) -> usize {
+        self.versions.as_ref().map(|versions| versions.tombstones.len()).unwrap_or(0)
+    }
+
+    ///Removes tombstones of keys, removed `older_than` ago, returning number of purged tombstones.
+    ///
+    ///Removal of key is no longer replicated once its tombstone is purged,
+    ///so replica, that was not synchronized since, brings removed value back.
+    ///Hence `older_than` should exceed period, within which all replicas are synchronized.
+    ///
+    ///Returns `Error::Locked` if store is locked.
+    pub fn purge_tombstones(&mut self, older_than: Duration) -> Result<usize, Error> {
+        if self.locked {
+            return Err(Error::Locked);
+        }
+
+        let now = now();
+        let versions = match self.versions.as_mut() {
+            Some(versions) => versions,
+            None => return Ok(0),
+        };
+        let len = versions.tombstones.len();
+        versions.tombstones.retain(|_, tombstone| now.saturating_sub(tombstone.time) < older_than.as_secs());
+        let result = len - versions.tombstones.len();
+        if result > 0 {
+            self.write_versions();
+            self.autosave_changed();
+        }
+        Ok(result)
+    }
+}
@@ -0,0 +1,117 @@
+use crate::{enc, format, MasterKey, Store};
+
+use challenge_response::config::{Config, Mode, Slot};
+use challenge_response::error::ChallengeResponseError;
+use challenge_response::ChallengeResponse;
+
+use std::io;
+use std::path::Path;
+
+///Maximum size of challenge, accepted by YubiKey.
+const CHALLENGE_LEN: usize = 64;
+
+///YubiKey, configured for HMAC-SHA1 challenge-response, that is mixed into key derivation.
+///
+///Token computes HMAC-SHA1 of challenge using secret, that never leaves it, so that store cannot be opened
+///without both password and token, similarly to KeePassXC.
+///Challenge is expected to be the same each time (e.g. random value, kept alongside storage).
+///
+///By default the first found token is used with its second slot, as it is conventional for challenge-response.
+///Refer to `Store::open_with_yubikey` for usage.
+#[derive(Debug, Clone)]
+pub struct YubiKey {
+    serial: Option<u32>,
+    slot: Slot,
+}
+
+impl YubiKey {
+    #[inline]
+    ///Uses the first found token.
+    pub const fn new() -> Self {
+        Self {
+            serial: None,
+            slot: Slot::Slot2,
+        }
+    }
+
+    #[inline]
+    ///Uses token with specified `serial` number.
+    pub const fn with_serial(serial: u32) -> Self {
+        Self {
+            serial: Some(serial),
+            slot: Slot::Slot2,
+        }
+    }
+
+    #[inline]
+    ///Uses specified `slot` of token.
+    ///
+    ///Panics unless `slot` is 1 or 2.
+    pub fn slot(mut self, slot: usize) -> Self {
+        self.slot = match Slot::from_int(slot) {
+            Some(slot) => slot,
+            None => panic!("YubiKey has no slot {}", slot),
+        };
+        self
+    }
+
+    ///Computes response of token to `challenge`, which is expected to be touched, if slot requires it.
+    ///
+    ///Returns `InvalidInput` error if `challenge` is longer than 64 bytes, or `NotFound` error if there is no token.
+    pub fn respond(&self, challenge: &[u8]) -> io::Result<[u8; 20]> {
+        if challenge.len() > CHALLENGE_LEN {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "challenge is longer than 64 bytes"));
+        }
+
+        let mut token = ChallengeResponse::new().map_err(token_error)?;
+        let device = match self.serial {
+            Some(serial) => token.find_device_from_serial(serial),
+            None => token.find_device(),
+        };
+        let config = Config::new_from(device.map_err(token_error)?).set_variable_size(true).set_mode(Mode::Sha1).set_slot(self.slot.clone());
+        let mut response = token.challenge_response_hmac(challenge, config).map_err(token_error)?;
+        let result = response.0;
+        enc::wipe(&mut response.0);
+        Ok(result)
+    }
+
+    ///Derives key from credentials, mixing in response of token to `challenge`.
+    ///
+    ///Returns `InvalidInput` error if `user` or `pass` is empty, otherwise refer to `Self::respond`.
+    ///Refer to `MasterKey::derive_with_secret` for details.
+    pub fn derive_key(&self, user: &[u8], pass: &[u8], challenge: &[u8]) -> io::Result<MasterKey> {
+        let mut response = self.respond(challenge)?;
+        let result = MasterKey::derive_with_secret(user, pass, &response);
+        enc::wipe(&mut response);
+        result.map_err(|error| io::Error::new(io::ErrorKind::InvalidInput, error))
+    }
+}
+
+impl Default for YubiKey {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+///Converts error of token, with missing token being `NotFound`.
+fn token_error(error: ChallengeResponseError) -> io::Error {
+    match error {
+        ChallengeResponseError::DeviceNotFound => io::Error::new(io::ErrorKind::NotFound, "YubiKey is not found"),
+        ChallengeResponseError::IOError(error) => error,
+        error => io::Error::other(error.to_string()),
+    }
+}
+
+impl Store {
+    ///Opens storage, previously saved via `Self::save`, using encryption key, derived with `yubikey`.
+    ///
+    ///Store is expected to be created with key from `YubiKey::derive_key` using the same credentials and `challenge`.
+    ///Returns error if token cannot respond, e.g. it is not plugged in.
+    ///Refer to `Self::open_with_key` for details.
+    pub fn open_with_yubikey<P: AsRef<Path>>(path: P, user: &[u8], pass: &[u8], challenge: &[u8], yubikey: &YubiKey) -> io::Result<Self> {
+        let key = yubikey.derive_key(user, pass, challenge)?;
+        let inner = format::read_file(path.as_ref())?;
+        Self::try_from_backend_with_key(inner, &key).map_err(Into::into)
+    }
+}
@@ -0,0 +1,84 @@
+#![cfg(feature = "audit")]
+
+use sec_store::{Store, AuditOp};
+use xxhash_rust::xxh3::xxh3_128;
+
+const USER: &[u8] = b"loli";
+const PASS: &[u8] = b"pass";
+
+#[test]
+fn should_record_audit_log() {
+    let mut store = Store::new(USER, PASS);
+    store.insert(b"1", b"1");
+    assert!(store.get(b"1").is_some());
+    assert!(store.get(b"2").is_none());
+    assert!(store.remove_key(b"1"));
+
+    let log = store.audit_log();
+    let log: Vec<_> = log.iter().map(|record| (record.op, record.key, record.success)).collect();
+    assert_eq!(log, [
+        (AuditOp::Insert, xxh3_128(b"1"), true),
+        (AuditOp::Get, xxh3_128(b"1"), true),
+        (AuditOp::Get, xxh3_128(b"2"), false),
+        (AuditOp::Remove, xxh3_128(b"1"), true),
+    ]);
+
+    let inner = store.into_inner();
+    assert_eq!(Store::from_inner(inner.clone(), USER, b"WRONG").audit_log().len(), 0);
+
+    let mut store = Store::from_inner(inner, USER, PASS);
+    assert_eq!(store.audit_log().len(), 4);
+    assert!(store.get(b"1").is_none());
+    store.update_mac();
+    assert!(store.verify_mac());
+
+    let mut store = Store::from_inner(store.into_inner(), USER, PASS);
+    assert_eq!(store.audit_log().len(), 5);
+    assert_eq!(store.len(), 0);
+
+    //Log is restored along with storage.
+    let snapshot = store.snapshot();
+    store.insert(b"2", b"2");
+    assert_eq!(store.audit_log().len(), 6);
+    store.restore(snapshot);
+    assert_eq!(store.audit_log().len(), 5);
+}
+
+#[test]
+fn should_record_every_decryption() {
+    use std::io::Read;
+
+    let mut store = Store::new(USER, PASS);
+    store.enable_key_names();
+    store.insert(b"1", b"{\"field\":1}");
+    let hash = xxh3_128(b"1");
+    let gets = |store: &Store| store.audit_log().iter().filter(|record| record.op == AuditOp::Get && record.key == hash && record.success).count();
+    assert_eq!(gets(&store), 0);
+
+    store.decrypt_all().unwrap();
+    assert_eq!(gets(&store), 1);
+    store.decrypt_all_named().unwrap();
+    assert_eq!(gets(&store), 2);
+    let mut value = Vec::new();
+    store.get_reader(b"1").unwrap().read_to_end(&mut value).unwrap();
+    assert_eq!(gets(&store), 3);
+    let recipient = Store::new(USER, b"recipient").deposit_key().unwrap();
+    store.export_entry_for(b"1", &recipient).unwrap();
+    assert_eq!(gets(&store), 4);
+    //Previous value is handed out too
+    store.insert(b"1", b"{\"field\":2}");
+    assert_eq!(gets(&store), 5);
+    #[cfg(feature = "json")]
+    {
+        assert_eq!(store.get_field(b"1", "field").unwrap(), b"2");
+        assert_eq!(gets(&store), 6);
+    }
+    #[cfg(feature = "rayon")]
+    {
+        let gets_before = gets(&store);
+        store.decrypt_all_parallel();
+        assert_eq!(gets(&store), gets_before + 1);
+        store.get_many_parallel(&[b"1"]);
+        assert_eq!(gets(&store), gets_before + 2);
+    }
+}
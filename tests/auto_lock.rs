@@ -0,0 +1,30 @@
+#![cfg(feature = "auto-lock")]
+
+use sec_store::{Store, AutoLock};
+
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn should_lock_after_inactivity() {
+    let mut store = Store::new(b"user", b"pass");
+    store.insert(b"1", b"one");
+
+    let store = AutoLock::new(store, Duration::from_millis(100));
+    for _ in 0..5 {
+        thread::sleep(Duration::from_millis(30));
+        assert_eq!(store.with(|store| store.get(b"1")).unwrap(), b"one");
+    }
+    assert!(!store.is_locked());
+
+    thread::sleep(Duration::from_millis(200));
+    assert!(store.is_locked());
+    assert!(store.with(|store| store.get(b"1")).is_none());
+
+    store.with(|store| store.unlock(b"user", b"pass")).unwrap();
+    assert_eq!(store.with(|store| store.get(b"1")).unwrap(), b"one");
+
+    let store = store.into_inner();
+    assert!(!store.is_locked());
+    assert_eq!(store.get(b"1").unwrap(), b"one");
+}
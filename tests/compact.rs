@@ -0,0 +1,47 @@
+#![cfg(feature = "compact")]
+
+use sec_store::Store;
+
+use std::fs;
+
+#[test]
+fn should_encode_compact_bytes() {
+    let path = std::env::temp_dir().join(format!("sec-store-{}-compact", std::process::id()));
+
+    let mut store = Store::new(b"user", b"pass");
+    store.insert(b"1", b"one");
+    store.insert(b"2", &[2u8; 1000]);
+    store.insert_from_reader(b"3", &[3u8; 100_000][..]).unwrap();
+    store.update_mac();
+
+    let bytes = store.to_bytes();
+    store.save(&path).unwrap();
+    assert!(bytes.len() < fs::read(&path).unwrap().len());
+    let _ = fs::remove_file(&path);
+
+    let decoded = Store::from_bytes(&bytes, b"user", b"pass").unwrap();
+    assert_eq!(decoded.len(), 3);
+    assert_eq!(decoded.get(b"1").unwrap(), b"one");
+    assert_eq!(decoded.get(b"2").unwrap(), [2u8; 1000]);
+    assert_eq!(decoded.get(b"3").unwrap(), [3u8; 100_000]);
+    assert_eq!(decoded.to_bytes(), bytes);
+
+    assert!(Store::from_bytes(&bytes, b"user", b"wrong").is_err());
+    for len in [0, 8, 9, 10, 30, bytes.len() - 1] {
+        assert!(Store::from_bytes(&bytes[..len], b"user", b"pass").is_err());
+    }
+    let mut trailing = bytes.clone();
+    trailing.push(0);
+    assert!(Store::from_bytes(&trailing, b"user", b"pass").is_err());
+    let mut version = bytes.clone();
+    version[8] = 0;
+    assert!(Store::from_bytes(&version, b"user", b"pass").is_err());
+
+    let mut store = Store::new(b"user", b"pass");
+    assert!(Store::from_bytes(&store.to_bytes(), b"user", b"pass").unwrap().len() == 0);
+    store.insert(b"1", b"one");
+    store.migrate_format(1).unwrap();
+    let bytes = store.to_bytes();
+    assert_eq!(bytes[8], 1);
+    assert_eq!(Store::from_bytes(&bytes, b"user", b"pass").unwrap().get(b"1").unwrap(), b"one");
+}
@@ -0,0 +1,48 @@
+#![cfg(feature = "unsafe-exports")]
+
+use sec_store::Store;
+use xxhash_rust::xxh3::xxh3_128;
+
+#[test]
+fn should_export_plaintext_json() {
+    let mut store = Store::new(b"user", b"pass");
+    let mut out = Vec::new();
+    assert_eq!(store.export_plaintext_json(&mut out).unwrap(), 0);
+    assert_eq!(out, b"{}\n");
+
+    store.insert(b"unnamed", b"one");
+    store.enable_key_names();
+    store.insert(b"a \"quoted\"\n", b"value");
+    store.namespace(b"ns").insert(b"hidden", b"secret");
+
+    let mut out = Vec::new();
+    assert_eq!(store.export_plaintext_json(&mut out).unwrap(), 2);
+    let out = String::from_utf8(out).unwrap();
+    assert!(out.starts_with("{\n  "));
+    assert!(out.ends_with("\n}\n"));
+    assert!(out.contains("\"a \\\"quoted\\\"\\n\": \"dmFsdWU=\""));
+    let unnamed = format!("\"#{:032x}\": \"b25l\"", xxh3_128(b"unnamed").to_le());
+    assert!(out.contains(&unnamed));
+    assert_eq!(out.matches(",\n  ").count(), 1);
+
+    store.lock();
+    assert!(store.export_plaintext_json(&mut Vec::new()).is_err());
+}
+
+#[test]
+fn should_import_exported_plaintext_json() {
+    let mut store = Store::new(b"user", b"pass");
+    store.insert(b"unnamed", b"one");
+    store.enable_key_names();
+    store.insert(b"named", &[0, 1, 2, 255]);
+
+    let mut document = Vec::new();
+    assert_eq!(store.export_plaintext_json(&mut document).unwrap(), 2);
+
+    let mut other = Store::new(b"other", b"pass");
+    other.enable_key_names();
+    assert_eq!(other.import_plaintext_json(document.as_slice()).unwrap(), 2);
+    assert_eq!(other.get(b"unnamed").unwrap(), b"one");
+    assert_eq!(other.get(b"named").unwrap(), [0, 1, 2, 255]);
+    assert_eq!(other.keys().collect::<Vec<_>>(), [&b"named"[..]]);
+}
@@ -0,0 +1,1117 @@
+use sec_store::{Store, UnlockGuard};
+
+use std::fs;
+use std::path::PathBuf;
+
+const USER: &[u8] = b"loli";
+const PASS: &[u8] = b"pass";
+
+fn temp_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("sec-store-{}-{}", std::process::id(), name));
+    let _ = fs::remove_file(&path);
+    path
+}
+
+#[test]
+fn should_save_and_open_file() {
+    let path = temp_path("file");
+
+    let mut store = Store::new(USER, PASS);
+    store.insert(b"1", b"1");
+    store.insert_from_reader(b"2", &[2u8; 100_000][..]).unwrap();
+    store.save(&path).unwrap();
+
+    let store = Store::open(&path, USER, PASS).unwrap();
+    assert_eq!(store.len(), 2);
+    assert_eq!(store.get(b"1").unwrap(), b"1");
+    assert_eq!(store.get(b"2").unwrap(), [2u8; 100_000]);
+    assert!(Store::open(&path, USER, b"WRONG").is_err());
+
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn should_save_fresh_integrity_mac() {
+    let path = temp_path("fresh-mac");
+
+    let mut store = Store::new(USER, PASS);
+    store.insert(b"1", b"1");
+    store.update_mac();
+    store.insert(b"2", b"2");
+    store.remove(b"1");
+    assert!(!store.verify_mac());
+    store.save(&path).unwrap();
+
+    let mut store = Store::open(&path, USER, PASS).unwrap();
+    assert!(store.verify_mac());
+    assert_eq!(store.len(), 1);
+    assert_eq!(store.get(b"2").unwrap(), b"2");
+
+    store.insert(b"3", b"3");
+    let mut written = Vec::new();
+    store.save_to_writer(&mut written).unwrap();
+    let store = Store::load_from_reader(written.as_slice(), USER, PASS).unwrap();
+    assert!(store.verify_mac());
+    assert_eq!(store.get(b"3").unwrap(), b"3");
+
+    let _ = fs::remove_file(&path);
+}
+
+#[cfg(all(unix, feature = "mmap"))]
+#[test]
+fn should_read_memory_mapped_file() {
+    use sec_store::mmap::MappedStore;
+
+    let path = temp_path("mmap");
+
+    let mut store = Store::new(USER, PASS);
+    store.insert(b"1", b"1");
+    store.insert_from_reader(b"2", &[2u8; 100_000][..]).unwrap();
+    store.save(&path).unwrap();
+
+    let mapped = MappedStore::open(&path, USER, PASS).unwrap();
+    assert_eq!(mapped.len(), 2);
+    assert!(mapped.contains(b"1"));
+    assert!(!mapped.contains(b"3"));
+    assert_eq!(mapped.get(b"1").unwrap(), b"1");
+    assert_eq!(mapped.get(b"2").unwrap(), [2u8; 100_000]);
+    let mut bytes = [0u8; 32];
+    assert_eq!(mapped.get_to(b"1", &mut bytes).unwrap(), 1);
+    assert_eq!(mapped.get_to(b"2", &mut bytes).unwrap(), 0);
+    assert!(mapped.get_to(b"3", &mut bytes).is_err());
+
+    assert!(MappedStore::open(&path, USER, b"WRONG").is_err());
+
+    store.update_mac();
+    store.save(&path).unwrap();
+    assert_eq!(MappedStore::open(&path, USER, PASS).unwrap().get(b"1").unwrap(), b"1");
+    let (_, value) = store.get_encrypted(b"1").unwrap();
+    let mut file = fs::read(&path).unwrap();
+    let offset = file.windows(value.len()).position(|window| window == value).unwrap();
+    file[offset] ^= 1;
+    fs::write(&path, file).unwrap();
+    assert!(MappedStore::open(&path, USER, PASS).is_err());
+
+    drop(mapped);
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn should_open_legacy_storage() {
+    //Storage of 1.1.0, which has no header, saved in format `1`.
+    const LEGACY: &[u8] = include_bytes!("data/legacy.secstore");
+    let path = temp_path("legacy");
+    fs::write(&path, LEGACY).unwrap();
+
+    let lazy = Store::open_lazy(&path, USER, PASS).unwrap();
+    assert_eq!(lazy.len(), 3);
+    assert_eq!(lazy.get(b"secret").unwrap(), b"value of 1.1.0");
+    assert!(Store::open_lazy(&path, USER, b"WRONG").is_err());
+    drop(lazy);
+
+    assert!(Store::open(&path, USER, b"WRONG").is_err());
+    let mut store = Store::open(&path, USER, PASS).unwrap();
+    assert!(store.verify_credentials());
+    assert_eq!(store.len(), 3);
+    assert_eq!(store.get(b"1").unwrap(), b"one");
+    assert_eq!(store.get(b"2").unwrap(), b"two");
+    assert_eq!(store.get(b"secret").unwrap(), b"value of 1.1.0");
+
+    //Header is written by the next save.
+    store.insert(b"3", b"three");
+    store.save(&path).unwrap();
+    let saved = fs::read(&path).unwrap();
+    assert_ne!(saved[..], LEGACY[..]);
+    let store = Store::open(&path, USER, PASS).unwrap();
+    assert_eq!(store.get(b"secret").unwrap(), b"value of 1.1.0");
+    assert_eq!(store.get(b"3").unwrap(), b"three");
+    assert!(Store::open(&path, USER, b"WRONG").is_err());
+
+    //Header of the current storage cannot be stripped, even along with other internal entries.
+    let mut inner = store.into_inner();
+    inner.retain(|key, _| *key >= 0x1_0000);
+    let stripped = Store::from_inner(inner, USER, PASS);
+    stripped.save(&path).unwrap();
+    let error = Store::open_lazy(&path, USER, PASS).err().unwrap();
+    assert_eq!(error.get_ref().unwrap().downcast_ref::<sec_store::Error>(), Some(&sec_store::Error::IntegrityMismatch));
+    let error = Store::open(&path, USER, PASS).err().unwrap();
+    assert_eq!(error.get_ref().unwrap().downcast_ref::<sec_store::Error>(), Some(&sec_store::Error::IntegrityMismatch));
+
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn should_load_lazily() {
+    let path = temp_path("lazy");
+
+    let mut store = Store::new(USER, PASS);
+    store.insert(b"1", b"1");
+    store.insert(b"2", b"2");
+    store.insert_from_reader(b"3", &[3u8; 100_000][..]).unwrap();
+    store.save(&path).unwrap();
+
+    let lazy = Store::open_lazy(&path, USER, PASS).unwrap();
+    assert_eq!(lazy.len(), 3);
+    //Header is loaded to verify credentials
+    assert_eq!(lazy.loaded_len(), 1);
+    assert!(lazy.contains(b"1"));
+    assert_eq!(lazy.loaded_len(), 1);
+
+    assert_eq!(lazy.get(b"1").unwrap(), b"1");
+    assert_eq!(lazy.loaded_len(), 2);
+    assert_eq!(lazy.get(b"1").unwrap(), b"1");
+    assert_eq!(lazy.get(b"3").unwrap(), [3u8; 100_000]);
+    assert_eq!(lazy.loaded_len(), 3);
+    assert!(lazy.get(b"4").is_none());
+
+    assert!(Store::open_lazy(&path, USER, b"WRONG").is_err());
+
+    store.update_mac();
+    store.save(&path).unwrap();
+    assert_eq!(Store::open_lazy(&path, USER, PASS).unwrap().get(b"2").unwrap(), b"2");
+    let (_, value) = store.get_encrypted(b"2").unwrap();
+    let mut file = fs::read(&path).unwrap();
+    let offset = file.windows(value.len()).position(|window| window == value).unwrap();
+    file[offset] ^= 1;
+    fs::write(&path, file).unwrap();
+    let error = Store::open_lazy(&path, USER, PASS).err().unwrap();
+    assert_eq!(error.get_ref().unwrap().downcast_ref::<sec_store::Error>(), Some(&sec_store::Error::IntegrityMismatch));
+
+    drop(lazy);
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn should_open_file_with_master_key() {
+    use sec_store::MasterKey;
+
+    let path = temp_path("key");
+
+    let mut store = Store::new(USER, PASS);
+    store.insert(b"1", b"1");
+    store.save(&path).unwrap();
+
+    let key = MasterKey::from_bytes(*store.master_key().unwrap().as_bytes());
+    assert_eq!(key.as_bytes(), MasterKey::derive(USER, PASS).unwrap().as_bytes());
+    assert_eq!(format!("{:?}", key), "MasterKey(..)");
+    let store = Store::open_with_key(&path, &key).unwrap();
+    assert_eq!(store.get(b"1").unwrap(), b"1");
+
+    let wrong = MasterKey::derive(USER, b"WRONG").unwrap();
+    assert!(Store::open_with_key(&path, &wrong).is_err());
+    assert!(MasterKey::derive(USER, b"").is_err());
+
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn should_open_file_with_wrapped_key() {
+    use sec_store::{KeyWrap, MasterKey};
+
+    struct Xor(u8);
+
+    impl KeyWrap for Xor {
+        type Error = ();
+
+        fn wrap(&self, key: &[u8; 32]) -> Result<Vec<u8>, ()> {
+            Ok(key.iter().map(|byte| byte ^ self.0).collect())
+        }
+
+        fn unwrap(&self, wrapped: &[u8]) -> Result<[u8; 32], ()> {
+            let mut result = [0u8; 32];
+            match wrapped.len() == result.len() {
+                true => {
+                    for (byte, wrapped) in result.iter_mut().zip(wrapped) {
+                        *byte = wrapped ^ self.0;
+                    }
+                    Ok(result)
+                },
+                false => Err(()),
+            }
+        }
+    }
+
+    let path = temp_path("wrapped");
+
+    let mut store = Store::new(USER, PASS);
+    store.insert(b"1", b"1");
+    store.save(&path).unwrap();
+
+    let wrapped = store.master_key().unwrap().wrap(&Xor(0x5a)).unwrap();
+    assert_ne!(wrapped[..], store.master_key().unwrap().as_bytes()[..]);
+    assert!(MasterKey::unwrap(&wrapped[1..], &Xor(0x5a)).is_err());
+
+    let key = MasterKey::unwrap(&wrapped, &Xor(0x5a)).unwrap();
+    let store = Store::open_with_key(&path, &key).unwrap();
+    assert_eq!(store.get(b"1").unwrap(), b"1");
+
+    let key = MasterKey::unwrap(&wrapped, &Xor(0xa5)).unwrap();
+    assert!(Store::open_with_key(&path, &key).is_err());
+
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn should_open_file_with_biometric() {
+    use sec_store::{Biometric, Error, KeyWrap};
+    use std::io::ErrorKind;
+
+    struct Platform(u8);
+
+    impl KeyWrap for Platform {
+        type Error = &'static str;
+
+        fn wrap(&self, key: &[u8; 32]) -> Result<Vec<u8>, Self::Error> {
+            Ok(key.iter().map(|byte| byte ^ self.0).collect())
+        }
+
+        fn unwrap(&self, wrapped: &[u8]) -> Result<[u8; 32], Self::Error> {
+            let mut result = [0u8; 32];
+            match wrapped.len() == result.len() {
+                true => {
+                    for (byte, wrapped) in result.iter_mut().zip(wrapped) {
+                        *byte = wrapped ^ self.0;
+                    }
+                    Ok(result)
+                },
+                false => Err("malformed key"),
+            }
+        }
+    }
+
+    struct Prompt(bool);
+
+    impl Biometric for Prompt {
+        type Error = &'static str;
+        type Key = Platform;
+
+        fn authenticate(&self, reason: &str) -> Result<Self::Key, Self::Error> {
+            assert_eq!(reason, "unlock");
+            match self.0 {
+                true => Ok(Platform(0x5a)),
+                false => Err("cancelled"),
+            }
+        }
+    }
+
+    let path = temp_path("biometric");
+
+    let mut store = Store::new(USER, PASS);
+    store.insert(b"1", b"1");
+    store.save(&path).unwrap();
+
+    let error = store.wrap_biometric("unlock", &Prompt(false)).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::PermissionDenied);
+    assert_eq!(error.to_string(), "cancelled");
+    let wrapped = store.wrap_biometric("unlock", &Prompt(true)).unwrap();
+    assert_ne!(wrapped[..], store.master_key().unwrap().as_bytes()[..]);
+
+    let mut locked = Store::open(&path, USER, PASS).unwrap();
+    locked.lock();
+    let error = locked.wrap_biometric("unlock", &Prompt(true)).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::PermissionDenied);
+    assert_eq!(error.into_inner().unwrap().downcast::<Error>().ok().map(|error| *error), Some(Error::Locked));
+
+    assert_eq!(Store::open_biometric(&path, &wrapped, "unlock", &Prompt(false)).err().unwrap().kind(), ErrorKind::PermissionDenied);
+    assert_eq!(Store::open_biometric(&path, &wrapped[1..], "unlock", &Prompt(true)).err().unwrap().kind(), ErrorKind::PermissionDenied);
+    let store = Store::open_biometric(&path, &wrapped, "unlock", &Prompt(true)).unwrap();
+    assert_eq!(store.get(b"1").unwrap(), b"1");
+
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn should_require_token_secret() {
+    use sec_store::MasterKey;
+
+    let path = temp_path("token");
+
+    let key = MasterKey::derive_with_secret(USER, PASS, b"token response").unwrap();
+    assert_ne!(key.as_bytes(), MasterKey::derive(USER, PASS).unwrap().as_bytes());
+    assert!(MasterKey::derive_with_secret(USER, PASS, b"").is_err());
+
+    let mut store = Store::with_key(&key);
+    assert!(store.verify_credentials());
+    store.insert(b"1", b"1");
+    store.save(&path).unwrap();
+
+    assert!(Store::open(&path, USER, PASS).is_err());
+    let other = MasterKey::derive_with_secret(USER, PASS, b"other response").unwrap();
+    assert!(Store::open_with_key(&path, &other).is_err());
+    let store = Store::open_with_key(&path, &key).unwrap();
+    assert_eq!(store.get(b"1").unwrap(), b"1");
+
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn should_save_decoys() {
+    use sec_store::MasterKey;
+
+    let path = temp_path("decoys");
+
+    let mut store = Store::builder(USER, PASS).decoys(5).build().unwrap();
+    assert_eq!(store.decoys(), 5);
+    store.insert(b"1", b"1");
+    store.insert(b"2", b"2");
+    store.update_mac();
+    let len = store.inner().len() as u64;
+    store.save(&path).unwrap();
+
+    let count = |path: &PathBuf| {
+        let data = fs::read(path).unwrap();
+        let mut count = [0u8; 8];
+        count.copy_from_slice(&data[9..17]);
+        u64::from_le_bytes(count)
+    };
+    assert_eq!(count(&path), len + 5);
+    //Decoys are sealed like values, so their ciphertexts have the same length as values of that length
+    let mut data = &fs::read(&path).unwrap()[17..];
+    let mut decoys = Vec::new();
+    while !data.is_empty() {
+        let mut key = [0u8; 16];
+        key.copy_from_slice(&data[..16]);
+        let mut value_len = [0u8; 4];
+        value_len.copy_from_slice(&data[16..20]);
+        let value_len = u32::from_le_bytes(value_len) as usize;
+        if !store.inner().contains_key(&u128::from_le_bytes(key)) {
+            decoys.push(value_len);
+        }
+        data = &data[20 + value_len..];
+    }
+    let value_len = store.get_encrypted(b"1").unwrap().1.len();
+    assert_eq!(decoys, [value_len; 5]);
+    let first = fs::read(&path).unwrap();
+    //Decoys are kept, so they don't stand out of values, that stay the same
+    store.save(&path).unwrap();
+    assert_eq!(fs::read(&path).unwrap(), first);
+
+    let mut store = Store::open(&path, USER, PASS).unwrap();
+    assert_eq!(store.len(), 2);
+    assert_eq!(store.inner().len() as u64, len);
+    assert!(store.verify_mac());
+    assert_eq!(store.get(b"1").unwrap(), b"1");
+    assert_eq!(store.decoys(), 0);
+
+    let lazy = Store::open_lazy(&path, USER, PASS).unwrap();
+    assert_eq!(lazy.len(), 2);
+    assert_eq!(lazy.get(b"2").unwrap(), b"2");
+
+    store.set_decoys(5);
+    store.save(&path).unwrap();
+    assert_eq!(fs::read(&path).unwrap(), first);
+
+    //Decoys are sealed anew along with values
+    store.rekey(&MasterKey::derive(USER, b"new").unwrap()).unwrap();
+    store.save(&path).unwrap();
+    assert_eq!(count(&path), len + 5);
+    let records = |mut data: &[u8]| {
+        let mut records = Vec::new();
+        while !data.is_empty() {
+            let mut value_len = [0u8; 4];
+            value_len.copy_from_slice(&data[16..20]);
+            let value_len = u32::from_le_bytes(value_len) as usize;
+            records.push(data[..20 + value_len].to_vec());
+            data = &data[20 + value_len..];
+        }
+        records
+    };
+    let previous = records(&first[17..]);
+    assert!(records(&fs::read(&path).unwrap()[17..]).iter().all(|record| !previous.contains(record)));
+
+    store.set_decoys(0);
+    store.save(&path).unwrap();
+    assert_eq!(count(&path), len);
+
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn should_lock_out_after_failed_unlock() {
+    use std::io::ErrorKind;
+    use std::time::Duration;
+
+    let path = temp_path("guard");
+    let mut store = Store::new(USER, PASS);
+    store.insert(b"1", b"1");
+    store.save(&path).unwrap();
+
+    let guard = UnlockGuard::new(Duration::from_millis(50), Duration::from_millis(80));
+    assert_eq!(guard.open(&path, USER, b"WRONG").err().unwrap().kind(), ErrorKind::InvalidData);
+    assert_eq!(guard.failures(), 1);
+    assert!(guard.remaining().unwrap() <= Duration::from_millis(50));
+    //Correct credentials are not checked while locked out
+    assert_eq!(guard.open(&path, USER, PASS).err().unwrap().kind(), ErrorKind::PermissionDenied);
+    assert_eq!(guard.failures(), 1);
+
+    std::thread::sleep(Duration::from_millis(60));
+    assert!(guard.remaining().is_none());
+    assert!(guard.open(&path, USER, b"WRONG").is_err());
+    assert_eq!(guard.failures(), 2);
+    //Period is capped
+    assert!(guard.remaining().unwrap() <= Duration::from_millis(80));
+
+    std::thread::sleep(Duration::from_millis(90));
+    assert_eq!(guard.open(&path, USER, PASS).unwrap().get(b"1").unwrap(), b"1");
+    assert_eq!(guard.failures(), 0);
+    assert!(guard.remaining().is_none());
+
+    //Attempt in progress counts as failure, so concurrent attempt is not allowed
+    let nested = guard.attempt(|| {
+        assert_eq!(guard.failures(), 1);
+        guard.attempt(|| Ok(()))
+    });
+    assert_eq!(nested.unwrap_err(), sec_store::Error::LockedOut);
+    assert_eq!(guard.failures(), 0);
+    //Panic is failure too
+    let panicked = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| guard.attempt::<(), _>(|| panic!("unlock"))));
+    assert!(panicked.is_err());
+    assert_eq!(guard.failures(), 1);
+    assert!(guard.remaining().is_some());
+
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn should_open_file_with_credentials() {
+    use sec_store::{Credentials, Error};
+
+    assert_eq!(Credentials::new(b"", PASS).unwrap_err(), Error::InvalidCredentials);
+    assert_eq!(Credentials::new(USER, b"").unwrap_err(), Error::InvalidCredentials);
+    let credentials = Credentials::new(USER, PASS).unwrap();
+    assert_eq!(credentials.user(), USER);
+    assert_eq!(format!("{:?}", credentials), "Credentials(..)");
+
+    let path = temp_path("credentials");
+    let mut store = Store::with_credentials(&credentials);
+    store.insert(b"1", b"1");
+    store.save(&path).unwrap();
+
+    //Compatible with raw credentials
+    assert_eq!(Store::open(&path, USER, PASS).unwrap().get(b"1").unwrap(), b"1");
+    let store = Store::open_with_credentials(&path, &credentials).unwrap();
+    assert_eq!(store.get(b"1").unwrap(), b"1");
+
+    let wrong = Credentials::new(USER, b"WRONG").unwrap();
+    assert!(Store::open_with_credentials(&path, &wrong).is_err());
+    assert_eq!(Store::from_inner_with_credentials(store.into_inner(), &wrong).err(), Some(Error::WrongCredentials));
+
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn should_require_keyfile() {
+    use sec_store::{Credentials, Error, MasterKey};
+
+    let keyfile = temp_path("keyfile.key");
+    fs::write(&keyfile, b"keyfile content").unwrap();
+
+    let credentials = Credentials::new(USER, PASS).unwrap();
+    assert!(!credentials.has_keyfile());
+    assert_eq!(credentials.clone().with_keyfile(b"").unwrap_err(), Error::InvalidCredentials);
+    assert!(credentials.clone().with_keyfile_at(temp_path("missing.key")).is_err());
+    let with_keyfile = credentials.clone().with_keyfile_at(&keyfile).unwrap();
+    assert!(with_keyfile.has_keyfile());
+    assert_eq!(with_keyfile.master_key().as_bytes(), MasterKey::derive_with_secret(USER, PASS, b"keyfile content").unwrap().as_bytes());
+    assert_eq!(with_keyfile.master_key_with_progress(|_, _| ()).as_bytes(), with_keyfile.master_key().as_bytes());
+
+    let path = temp_path("keyfile");
+    let mut store = Store::with_credentials(&with_keyfile);
+    store.insert(b"1", b"1");
+    store.save(&path).unwrap();
+
+    assert!(Store::open(&path, USER, PASS).is_err());
+    assert!(Store::open_with_credentials(&path, &credentials).is_err());
+    let other = credentials.with_keyfile(b"other content").unwrap();
+    assert!(Store::open_with_credentials(&path, &other).is_err());
+    let store = Store::open_with_credentials(&path, &with_keyfile).unwrap();
+    assert_eq!(store.get(b"1").unwrap(), b"1");
+
+    let _ = fs::remove_file(&path);
+    let _ = fs::remove_file(&keyfile);
+}
+
+#[test]
+fn should_bind_to_machine() {
+    use sec_store::{machine_id, Credentials};
+
+    let id = match machine_id() {
+        Ok(id) => id,
+        Err(_) => return,
+    };
+    assert!(!id.is_empty());
+    assert_eq!(machine_id().unwrap(), id);
+
+    let credentials = Credentials::new(USER, PASS).unwrap();
+    assert!(!credentials.is_machine_bound());
+    let bound = credentials.clone().bind_to_machine().unwrap();
+    assert!(bound.is_machine_bound());
+    assert_eq!(bound.master_key().as_bytes(), credentials.clone().bind_to(id).master_key().as_bytes());
+
+    let path = temp_path("machine");
+    let mut store = Store::with_credentials(&bound);
+    store.insert(b"1", b"1");
+    store.save(&path).unwrap();
+
+    assert!(Store::open_with_credentials(&path, &credentials).is_err());
+    let other = credentials.bind_to(b"other machine".to_vec());
+    assert!(Store::open_with_credentials(&path, &other).is_err());
+    assert_eq!(Store::open_with_credentials(&path, &bound).unwrap().get(b"1").unwrap(), b"1");
+
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn should_save_incremental() {
+    use sec_store::Error;
+
+    let path = temp_path("incremental");
+    let mut store = Store::new(USER, PASS);
+    store.enable_key_names();
+    for idx in 0..100u32 {
+        store.insert(&idx.to_le_bytes(), &[1; 100]);
+    }
+    store.save(&path).unwrap();
+
+    let mut delta = Vec::new();
+    assert_eq!(store.save_incremental(&mut delta).unwrap(), 0);
+
+    store.insert(&1u32.to_le_bytes(), b"changed");
+    store.insert(b"new", b"new");
+    store.remove_key(&2u32.to_le_bytes());
+    let mut delta = Vec::new();
+    //Changed, new and removed entries, along with names.
+    assert_eq!(store.save_incremental(&mut delta).unwrap(), 4);
+    assert!((delta.len() as u64) < fs::metadata(&path).unwrap().len() / 2);
+    let mut next = Vec::new();
+    assert_eq!(store.save_incremental(&mut next).unwrap(), 0);
+
+    let mut restored = Store::open(&path, USER, PASS).unwrap();
+    assert_eq!(restored.apply_incremental(&mut &next[..]).unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+    assert_eq!(restored.len(), 100);
+    //Manifest is authenticated
+    let mut forged = delta.clone();
+    let idx = forged.len() - 33;
+    forged[idx] ^= 1;
+    assert_eq!(restored.apply_incremental(&mut &forged[..]).unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+    let mut foreign = Vec::new();
+    Store::new(USER, b"other").save_incremental(&mut foreign).unwrap();
+    assert_eq!(restored.apply_incremental(&mut &foreign[..]).unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+    assert_eq!(restored.apply_incremental(&mut &delta[..delta.len() - 1]).unwrap_err().kind(), std::io::ErrorKind::UnexpectedEof);
+    assert_eq!(restored.len(), 100);
+    assert_eq!(restored.apply_incremental(&mut &delta[..]).unwrap(), 4);
+    assert_eq!(restored.inner(), store.inner());
+    assert_eq!(restored.get(&1u32.to_le_bytes()).unwrap(), b"changed");
+    assert_eq!(restored.get(b"new").unwrap(), b"new");
+    assert!(!restored.contains(&2u32.to_le_bytes()));
+    assert!(restored.keys().any(|key| key == b"new"));
+    //Applying the same delta again is harmless
+    assert_eq!(restored.apply_incremental(&mut &delta[..]).unwrap(), 4);
+    assert_eq!(restored.inner(), store.inner());
+    assert_eq!(restored.apply_incremental(&mut &next[..]).unwrap(), 0);
+    assert!(restored.apply_incremental(&mut &b"SECSTORE"[..]).is_err());
+
+    assert_eq!(Store::try_from_inner(restored.into_inner(), USER, PASS).err(), None::<Error>);
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn should_track_dirty_state() {
+    use xxhash_rust::xxh3::xxh3_128;
+
+    let path = temp_path("dirty");
+    let mut store = Store::new(USER, PASS);
+    assert!(store.is_dirty());
+    assert!(store.changed_since_save().is_empty());
+    store.insert(b"1", b"1");
+    store.insert(b"2", b"2");
+    let mut expected = vec![xxh3_128(b"1").to_le(), xxh3_128(b"2").to_le()];
+    expected.sort_unstable();
+    assert_eq!(store.changed_since_save(), expected);
+
+    store.save(&path).unwrap();
+    assert!(!store.is_dirty());
+    assert!(store.changed_since_save().is_empty());
+
+    store.insert(b"1", b"1");
+    assert!(!store.is_dirty());
+    store.insert(b"1", b"changed");
+    store.remove_key(b"2");
+    assert!(store.is_dirty());
+    expected.sort_unstable();
+    assert_eq!(store.changed_since_save(), expected);
+    store.save_incremental(std::io::sink()).unwrap();
+    assert!(!store.is_dirty());
+
+    store.update_mac();
+    assert!(store.is_dirty());
+    assert!(store.changed_since_save().is_empty());
+
+    let store = Store::open(&path, USER, PASS).unwrap();
+    assert!(!store.is_dirty());
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn should_report_key_derivation_progress() {
+    use sec_store::MasterKey;
+
+    let path = temp_path("progress");
+    let mut store = Store::new(USER, PASS);
+    store.insert(b"1", b"1");
+    store.save(&path).unwrap();
+
+    let mut last = None;
+    let mut calls = 0;
+    let key = MasterKey::derive_with_progress(USER, PASS, |done, total| {
+        assert!(done <= total);
+        calls += 1;
+        last = Some((done, total));
+    }).unwrap();
+    assert!(calls > 1);
+    assert!(matches!(last, Some((done, total)) if done == total));
+    assert_eq!(key.as_bytes(), MasterKey::derive(USER, PASS).unwrap().as_bytes());
+    assert!(MasterKey::derive_with_progress(USER, b"", |_, _| ()).is_err());
+
+    let store = Store::open_with_key(&path, &key).unwrap();
+    assert_eq!(store.get(b"1").unwrap(), b"1");
+
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn should_use_scrypt_kdf() {
+    use sec_store::{Kdf, MasterKey, ScryptParams};
+
+    assert!(ScryptParams::new(0, 8, 1).is_none());
+    assert!(ScryptParams::new(31, 8, 1).is_none());
+    assert!(ScryptParams::new(10, 0, 1).is_none());
+    assert!(ScryptParams::new(10, 8, 0).is_none());
+    assert!(ScryptParams::new(16, 1, 1).is_none());
+    let params = ScryptParams::new(10, 8, 1).unwrap();
+    assert_eq!((params.log_n(), params.r(), params.p()), (10, 8, 1));
+    assert_eq!(ScryptParams::default().log_n(), 15);
+
+    let path = temp_path("scrypt");
+    let mut store = Store::builder(USER, PASS).kdf(Kdf::Scrypt(params)).build().unwrap();
+    assert_eq!(store.kdf(), Kdf::Scrypt(params));
+    assert_eq!(store.len(), 0);
+    store.insert(b"1", b"1");
+    store.update_mac();
+    store.save(&path).unwrap();
+
+    let store = Store::open(&path, USER, PASS).unwrap();
+    assert_eq!(store.kdf(), Kdf::Scrypt(params));
+    assert_eq!(store.get(b"1").unwrap(), b"1");
+    assert!(store.verify_mac());
+    assert!(Store::open(&path, USER, b"WRONG").is_err());
+    assert_eq!(Store::open_lazy(&path, USER, PASS).unwrap().get(b"1").unwrap(), b"1");
+
+    //Key derived via PBKDF2 doesn't match
+    assert!(Store::open_with_key(&path, &MasterKey::derive(USER, PASS).unwrap()).is_err());
+    let key = MasterKey::derive_with_kdf(USER, PASS, Kdf::Scrypt(params)).unwrap();
+    let mut store = Store::open_with_key(&path, &key).unwrap();
+
+    store.rekey(&MasterKey::derive(USER, b"new").unwrap()).unwrap();
+    assert_eq!(store.kdf(), Kdf::Pbkdf2);
+    store.save(&path).unwrap();
+    assert_eq!(Store::open(&path, USER, b"new").unwrap().get(b"1").unwrap(), b"1");
+
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn should_lock_file() {
+    use std::io::ErrorKind;
+
+    let path = temp_path("lock");
+    let mut lock = path.as_os_str().to_owned();
+    lock.push(".lock");
+
+    let mut store = Store::new(USER, PASS);
+    store.insert(b"1", b"1");
+    store.save(&path).unwrap();
+
+    let mut locked = Store::open_exclusive(&path, USER, PASS).unwrap();
+    assert_eq!(locked.path(), path);
+    assert_eq!(Store::open_exclusive(&path, USER, PASS).err().unwrap().kind(), ErrorKind::WouldBlock);
+    assert_eq!(Store::open_shared_read(&path, USER, PASS).err().unwrap().kind(), ErrorKind::WouldBlock);
+    locked.insert(b"2", b"2");
+    locked.save().unwrap();
+    drop(locked);
+
+    let first = Store::open_shared_read(&path, USER, PASS).unwrap();
+    let second = Store::open_shared_read(&path, USER, PASS).unwrap();
+    assert_eq!(first.get(b"2").unwrap(), b"2");
+    assert_eq!(second.len(), 2);
+    assert_eq!(Store::open_exclusive(&path, USER, PASS).err().unwrap().kind(), ErrorKind::WouldBlock);
+    drop(first);
+    assert!(Store::open_exclusive(&path, USER, PASS).is_err());
+    drop(second);
+
+    assert!(Store::open_exclusive(&path, USER, b"WRONG").is_err());
+    let store = Store::open_exclusive(&path, USER, PASS).unwrap().into_inner();
+    assert_eq!(store.len(), 2);
+    assert!(Store::open_exclusive(&path, USER, PASS).is_ok());
+
+    let _ = fs::remove_file(&path);
+    let _ = fs::remove_file(&lock);
+}
+
+#[test]
+fn should_watch_file() {
+    let path = temp_path("watch");
+    let mut store = Store::new(USER, PASS);
+    store.insert(b"1", b"1");
+    store.save(&path).unwrap();
+
+    let mut writer = Store::open_exclusive(&path, USER, PASS).unwrap();
+    let mut first = Store::open_watched(&path, USER, PASS).unwrap();
+    let mut second = Store::open_watched(&path, USER, PASS).unwrap();
+    assert_eq!(first.path(), path);
+    assert!(!first.has_changed().unwrap());
+    assert!(!first.refresh().unwrap());
+
+    writer.insert(b"2", b"2");
+    writer.save().unwrap();
+    assert!(first.has_changed().unwrap());
+    assert!(second.has_changed().unwrap());
+    assert!(first.get(b"2").is_none());
+    assert!(first.refresh().unwrap());
+    assert_eq!(first.get(b"2").unwrap(), b"2");
+    assert!(!first.has_changed().unwrap());
+    assert!(!first.refresh().unwrap());
+
+    writer.remove_key(b"1");
+    writer.save().unwrap();
+    assert!(second.refresh().unwrap());
+    assert!(second.get(b"1").is_none());
+    assert_eq!(second.len(), 1);
+    assert!(first.has_changed().unwrap());
+    drop(writer);
+
+    assert!(Store::open_watched(&path, USER, b"WRONG").is_err());
+    let store = second.into_inner();
+    assert_eq!(store.get(b"2").unwrap(), b"2");
+
+    let _ = fs::remove_file(&path);
+    for extension in [".lock", ".gen"].iter() {
+        let mut sibling = path.as_os_str().to_owned();
+        sibling.push(extension);
+        let _ = fs::remove_file(sibling);
+    }
+}
+
+#[test]
+fn should_rotate_backups() {
+    let path = temp_path("backups");
+    let backup = |idx: usize| {
+        let mut backup = path.as_os_str().to_owned();
+        backup.push(format!(".bak.{}", idx));
+        PathBuf::from(backup)
+    };
+
+    let mut store = Store::new(USER, PASS);
+    for idx in 1..=4u8 {
+        store.insert(b"version", &[idx]);
+        store.update_mac();
+        store.save_with_backups(&path, 2).unwrap();
+    }
+    assert!(backup(1).exists());
+    assert!(backup(2).exists());
+    assert!(!backup(3).exists());
+    assert_eq!(Store::open(backup(1), USER, PASS).unwrap().get(b"version").unwrap(), [3]);
+    assert_eq!(Store::open(backup(2), USER, PASS).unwrap().get(b"version").unwrap(), [2]);
+    assert_eq!(Store::open_latest_valid(&path, USER, PASS).unwrap().get(b"version").unwrap(), [4]);
+
+    //Failed save
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    fs::create_dir(&tmp).unwrap();
+    store.insert(b"version", &[5]);
+    assert!(store.save_with_backups(&path, 2).is_err());
+    fs::remove_dir(&tmp).unwrap();
+    assert_eq!(Store::open(&path, USER, PASS).unwrap().get(b"version").unwrap(), [4]);
+    assert_eq!(Store::open(backup(1), USER, PASS).unwrap().get(b"version").unwrap(), [3]);
+    assert_eq!(Store::open(backup(2), USER, PASS).unwrap().get(b"version").unwrap(), [2]);
+
+    //Interrupted write
+    let data = fs::read(&path).unwrap();
+    fs::write(&path, &data[..data.len() / 2]).unwrap();
+    assert!(Store::open(&path, USER, PASS).is_err());
+    assert_eq!(Store::open_latest_valid(&path, USER, PASS).unwrap().get(b"version").unwrap(), [3]);
+
+    //Tampering with entry
+    let mut data = fs::read(backup(1)).unwrap();
+    let len = data.len();
+    data[len - 1] ^= 1;
+    fs::write(backup(1), &data).unwrap();
+    assert_eq!(Store::open_latest_valid(&path, USER, PASS).unwrap().get(b"version").unwrap(), [2]);
+    assert!(Store::open_latest_valid(&path, USER, b"WRONG").is_err());
+
+    fs::remove_file(backup(2)).unwrap();
+    assert!(Store::open_latest_valid(&path, USER, PASS).is_err());
+
+    let _ = fs::remove_file(&path);
+    let _ = fs::remove_file(backup(1));
+}
+
+#[test]
+fn should_migrate_schema() {
+    use sec_store::{Error, Migrations};
+
+    let path = temp_path("schema");
+
+    let mut store = Store::new(USER, PASS);
+    assert_eq!(store.schema_version(), 0);
+    store.insert(b"token", b"abc");
+    store.save(&path).unwrap();
+
+    let mut runs = Vec::new();
+    let mut migrations = Migrations::new().add(1, |store: &mut Store| {
+        let token = store.get(b"token").unwrap();
+        store.insert(b"auth/token", &token);
+        store.remove(b"token");
+        Ok(())
+    });
+    migrations = migrations.add(2, |store: &mut Store| {
+        store.insert(b"auth/kind", b"bearer");
+        Ok(())
+    });
+    assert_eq!(migrations.latest(), 2);
+
+    let store = Store::open_migrated(&path, USER, PASS, &mut migrations).unwrap();
+    assert_eq!(store.schema_version(), 2);
+    assert_eq!(store.get(b"auth/token").unwrap(), b"abc");
+    assert_eq!(store.get(b"auth/kind").unwrap(), b"bearer");
+    assert!(store.get(b"token").is_none());
+
+    //Migrations are not repeated and failed one is rolled back
+    let mut store = store;
+    let mut failing = Migrations::new().add(2, |_: &mut Store| panic!("Repeated migration")).add(3, |store: &mut Store| {
+        runs.push(3);
+        store.insert(b"partial", b"1");
+        Err(Error::LimitExceeded)
+    });
+    assert_eq!(store.migrate(&mut failing).err(), Some(Error::LimitExceeded));
+    drop(failing);
+    assert_eq!(runs, [3]);
+    assert_eq!(store.schema_version(), 2);
+    assert!(store.get(b"partial").is_none());
+
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn should_open_lossy() {
+    let path = temp_path("lossy");
+
+    let mut store = Store::new(USER, PASS);
+    store.insert(b"1", b"one");
+    store.insert(b"2", b"two");
+    store.insert(b"3", b"three");
+    store.update_mac();
+    let (corrupted, value) = store.get_encrypted(b"2").unwrap();
+    store.save(&path).unwrap();
+    let mut file = fs::read(&path).unwrap();
+    let offset = file.windows(value.len()).position(|window| window == value).unwrap();
+    file[offset] ^= 1;
+    fs::write(&path, file).unwrap();
+
+    assert!(Store::open(&path, USER, PASS).is_err());
+    assert!(Store::open_lossy(&path, USER, b"WRONG").is_err());
+
+    let store = Store::open_lossy(&path, USER, PASS).unwrap();
+    assert_eq!(store.len(), 2);
+    assert_eq!(store.get(b"1").unwrap(), b"one");
+    assert_eq!(store.get(b"3").unwrap(), b"three");
+    assert!(store.get(b"2").is_none());
+    let quarantined: Vec<_> = store.quarantined().iter().map(|(key, _)| *key).collect();
+    assert_eq!(quarantined.len(), 2);
+    assert!(quarantined.contains(&corrupted));
+    //MAC is required by storage, so it covers loaded entries
+    assert!(store.verify_mac());
+
+    //Truncated file keeps entries before the incomplete one
+    let data = fs::read(&path).unwrap();
+    fs::write(&path, &data[..data.len() - 2]).unwrap();
+    let store = Store::open_lossy(&path, USER, PASS).unwrap();
+    assert!(store.len() < 2);
+    assert!(store.quarantined().len() >= 2);
+
+    let mut store = store;
+    assert!(!store.take_quarantined().is_empty());
+    assert!(store.quarantined().is_empty());
+
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn should_save_pending_versions() {
+    let path = temp_path("versions");
+
+    let mut store = Store::new(USER, PASS);
+    store.enable_versioning();
+    for idx in 0..3u8 {
+        store.insert(&[idx], b"value");
+    }
+    store.save(&path).unwrap();
+    assert!(!store.is_dirty());
+    store.flush_versions();
+    assert!(!store.is_dirty());
+
+    let copy = store.try_clone().unwrap();
+    assert_eq!(copy.version(&[2]), Some(3));
+
+    let mut store = Store::open(&path, USER, PASS).unwrap();
+    assert_eq!(store.version(&[0]), Some(1));
+    assert_eq!(store.version(&[2]), Some(3));
+    assert!(store.remove_key(&[1]));
+    assert_eq!(store.tombstones_len(), 1);
+    let snapshot = store.snapshot();
+    store.insert(&[3], b"value");
+    store.restore(snapshot);
+    assert_eq!(store.tombstones_len(), 1);
+    assert_eq!(store.version(&[3]), None);
+
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn should_migrate_format() {
+    let path = temp_path("format");
+
+    let mut store = Store::new(USER, PASS);
+    store.insert(b"1", b"1");
+    store.insert_from_reader(b"2", &[2u8; 100_000][..]).unwrap();
+    store.enable_versioning();
+    store.insert(b"3", b"3");
+    store.update_mac();
+    assert_eq!(store.format_version(), 2);
+
+    assert!(store.migrate_format(1).unwrap() > 0);
+    assert_eq!(store.format_version(), 1);
+    assert_eq!(store.get(b"1").unwrap(), b"1");
+    assert!(store.verify_mac());
+    store.insert(b"4", b"4");
+    store.insert_from_reader(b"5", &[5u8; 100_000][..]).unwrap();
+    store.update_mac();
+    store.save(&path).unwrap();
+    assert_eq!(fs::read(&path).unwrap()[8], 1);
+
+    //Saved entries are the same as in memory, so integrity MAC still matches once reopened.
+    let mut store = Store::open(&path, USER, PASS).unwrap();
+    assert!(store.verify_mac());
+    assert!(store.remove(b"5").is_some());
+    assert_eq!(store.len(), 4);
+    assert_eq!(store.get(b"1").unwrap(), b"1");
+    assert_eq!(store.get(b"2").unwrap(), [2u8; 100_000]);
+    assert_eq!(store.get(b"4").unwrap(), b"4");
+    assert_eq!(store.version(b"3"), Some(1));
+
+    assert_eq!(store.migrate_format(2).unwrap(), 4);
+    assert_eq!(store.migrate_format(2).unwrap(), 0);
+    store.save(&path).unwrap();
+    assert_eq!(fs::read(&path).unwrap()[8], 2);
+
+    let store = Store::open(&path, USER, PASS).unwrap();
+    assert!(store.verify_mac());
+    assert_eq!(store.len(), 4);
+    assert_eq!(store.get(b"3").unwrap(), b"3");
+    assert_eq!(store.get(b"2").unwrap(), [2u8; 100_000]);
+
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn should_save_to_writer() {
+    let path = temp_path("writer");
+
+    let mut store = Store::new(USER, PASS);
+    store.insert(b"1", b"one");
+    store.insert_from_reader(b"2", &[2u8; 100_000][..]).unwrap();
+
+    let mut written = Vec::new();
+    store.save_to_writer(&mut written).unwrap();
+    store.save(&path).unwrap();
+    assert_eq!(written, fs::read(&path).unwrap());
+
+    let loaded = Store::load_from_reader(std::io::BufReader::new(fs::File::open(&path).unwrap()), USER, PASS).unwrap();
+    assert_eq!(loaded.len(), 2);
+    assert_eq!(loaded.get(b"2").unwrap(), [2u8; 100_000]);
+    assert!(Store::load_from_reader(&written[..written.len() - 1], USER, PASS).is_err());
+    assert!(Store::load_from_reader(written.as_slice(), USER, b"wrong").is_err());
+
+    store.set_decoys(3);
+    let mut written = Vec::new();
+    store.save_to_writer(&mut written).unwrap();
+    let loaded = Store::load_from_reader(written.as_slice(), USER, PASS).unwrap();
+    assert_eq!(loaded.len(), 2);
+    assert_eq!(loaded.get(b"1").unwrap(), b"one");
+
+    store.lock();
+    assert!(store.save_to_writer(&mut Vec::new()).is_err());
+
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn should_record_key_hasher() {
+    use sec_store::{Error, KeyHasher, Sha256, StoreBuilder};
+
+    struct Reversed;
+
+    impl KeyHasher for Reversed {
+        fn id(&self) -> &[u8] {
+            b"reversed"
+        }
+
+        fn hash(&self, key: &[u8]) -> u128 {
+            let mut key = key.to_vec();
+            key.reverse();
+            xxhash_rust::xxh3::xxh3_128(&key)
+        }
+    }
+
+    let path = temp_path("hasher");
+
+    let mut store = StoreBuilder::new(USER, PASS).key_hasher(Sha256).build().unwrap();
+    assert_eq!(store.key_hasher().id(), b"sha256-128");
+    store.insert(b"1", b"one");
+    assert!(store.inner().contains_key(&Sha256.hash(b"1")));
+    assert!(!store.inner().contains_key(&xxhash_rust::xxh3::xxh3_128(b"1").to_le()));
+    store.save(&path).unwrap();
+
+    let error = Store::open(&path, USER, PASS).err().unwrap();
+    assert_eq!(error.get_ref().unwrap().downcast_ref::<Error>(), Some(&Error::KeyHasherMismatch));
+    assert_eq!(Store::try_from_inner(store.inner().clone(), USER, PASS).err(), Some(Error::KeyHasherMismatch));
+    assert!(StoreBuilder::new(USER, PASS).key_hasher(Reversed).open(&path).is_err());
+    assert!(StoreBuilder::new(USER, PASS).key_hasher(Sha256).open(&path).is_ok());
+    assert!(StoreBuilder::new(USER, b"wrong").key_hasher(Sha256).open(&path).is_err());
+
+    let lazy = sec_store::lazy::LazyStore::open_with_hasher(&path, USER, PASS, Sha256).unwrap();
+    assert_eq!(lazy.get(b"1").unwrap(), b"one");
+    assert!(sec_store::lazy::LazyStore::open(&path, USER, PASS).is_err());
+
+    let mut store = StoreBuilder::new(USER, PASS).key_hasher(Sha256).open(&path).unwrap();
+    assert_eq!(store.get(b"1").unwrap(), b"one");
+    store.lock();
+    store.unlock(USER, PASS).unwrap();
+    assert!(store.try_clone().unwrap().contains(b"1"));
+
+    store.namespace(b"ns").insert(b"1", b"namespaced");
+    assert!(store.inner().contains_key(&Sha256.hash_namespaced(b"sec-store:namespace:ns", b"1")));
+    let key = sec_store::MasterKey::derive(USER, b"new").unwrap();
+    store.rekey_namespaces(&key, &[b"ns"]).unwrap();
+    store.lock();
+    store.unlock_with_key(&key).unwrap();
+    assert_eq!(store.get(b"1").unwrap(), b"one");
+    assert_eq!(store.namespace(b"ns").get(b"1").unwrap(), b"namespaced");
+
+    let mut store = StoreBuilder::new(USER, PASS).key_hasher(Reversed).build().unwrap();
+    store.insert(b"12", b"two");
+    assert!(store.inner().contains_key(&xxhash_rust::xxh3::xxh3_128(b"21")));
+    assert_eq!(store.get(b"12").unwrap(), b"two");
+
+    let _ = fs::remove_file(&path);
+}
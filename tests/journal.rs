@@ -0,0 +1,142 @@
+use sec_store::journal::Journaled;
+
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+
+const USER: &[u8] = b"loli";
+const PASS: &[u8] = b"pass";
+
+fn temp_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("sec-store-{}-{}", std::process::id(), name));
+    let _ = fs::remove_file(&path);
+    let _ = fs::remove_file(path.with_extension("journal"));
+    path
+}
+
+#[test]
+fn should_replay_and_compact_journal() {
+    let path = temp_path("journal");
+
+    let mut store = Journaled::open(&path, USER, PASS).unwrap();
+    store.set_compact_threshold(4);
+    assert!(store.insert(b"1", b"1").unwrap().is_none());
+    assert!(store.insert(b"2", b"2").unwrap().is_none());
+    assert!(store.remove_key(b"1").unwrap());
+    assert!(!store.remove_key(b"1").unwrap());
+    assert_eq!(store.journal_len(), 3);
+    drop(store);
+
+    let mut store = Journaled::open(&path, USER, PASS).unwrap();
+    assert_eq!(store.journal_len(), 3);
+    assert!(!store.store().contains(b"1"));
+    assert_eq!(store.store().get(b"2").unwrap(), b"2");
+
+    store.set_compact_threshold(4);
+    store.insert(b"3", b"3").unwrap();
+    assert_eq!(store.journal_len(), 0);
+    store.insert(b"4", b"4").unwrap();
+    drop(store);
+
+    //Simulate interrupted write
+    let mut journal = fs::OpenOptions::new().append(true).open(path.with_extension("journal")).unwrap();
+    journal.write_all(&[1, 2, 3]).unwrap();
+    drop(journal);
+
+    let store = Journaled::open(&path, USER, PASS).unwrap();
+    assert_eq!(store.journal_len(), 1);
+    assert_eq!(store.store().len(), 3);
+    assert_eq!(store.store().get(b"4").unwrap(), b"4");
+
+    let store = store.into_store().unwrap();
+    assert!(store.verify_mac());
+    assert!(Journaled::open(&path, USER, b"WRONG").is_err());
+
+    let _ = fs::remove_file(&path);
+    let _ = fs::remove_file(path.with_extension("journal"));
+}
+
+#[test]
+fn should_authenticate_journal_records() {
+    let path = temp_path("journal-mac");
+    let journal = path.with_extension("journal");
+
+    let mut store = Journaled::open(&path, USER, PASS).unwrap();
+    store.insert(b"1", b"1").unwrap();
+    drop(store);
+
+    //Torn tail is truncated, before next record is appended
+    let mut file = fs::OpenOptions::new().append(true).open(&journal).unwrap();
+    file.write_all(&[1, 2, 3]).unwrap();
+    drop(file);
+    let mut store = Journaled::open(&path, USER, PASS).unwrap();
+    store.insert(b"2", b"2").unwrap();
+    drop(store);
+
+    let store = Journaled::open(&path, USER, PASS).unwrap();
+    assert_eq!(store.journal_len(), 2);
+    assert_eq!(store.store().get(b"2").unwrap(), b"2");
+    drop(store);
+
+    //Tampering with record
+    let valid = fs::read(&journal).unwrap();
+    let mut data = valid.clone();
+    let len = data.len();
+    data[len - 40] ^= 1;
+    fs::write(&journal, &data).unwrap();
+    assert_eq!(Journaled::open(&path, USER, PASS).err().unwrap().kind(), std::io::ErrorKind::InvalidData);
+
+    //Journal of previous snapshot, which doesn't end with compaction, cannot roll back records appended since
+    fs::write(&journal, &valid).unwrap();
+    let mut store = Journaled::open(&path, USER, PASS).unwrap();
+    store.compact().unwrap();
+    store.insert(b"3", b"3").unwrap();
+    drop(store);
+    fs::write(&journal, &valid).unwrap();
+    assert_eq!(Journaled::open(&path, USER, PASS).err().unwrap().kind(), std::io::ErrorKind::InvalidData);
+
+    let _ = fs::remove_file(&path);
+    let _ = fs::remove_file(&journal);
+}
+
+#[test]
+fn should_discard_journal_of_interrupted_compaction() {
+    let path = temp_path("journal-compact");
+    let journal = path.with_extension("journal");
+
+    //Snapshot cannot replace directory, so compaction fails after its record is appended
+    let mut store = Journaled::open(&path, USER, PASS).unwrap();
+    store.insert(b"1", b"1").unwrap();
+    fs::create_dir(&path).unwrap();
+    assert!(store.compact().is_err());
+    store.insert(b"2", b"2").unwrap();
+    drop(store);
+    fs::remove_dir(&path).unwrap();
+
+    //Failed compaction is ignored, as snapshot still precedes journal
+    let mut store = Journaled::open(&path, USER, PASS).unwrap();
+    assert_eq!(store.journal_len(), 2);
+    assert_eq!(store.store().get(b"2").unwrap(), b"2");
+
+    //Journal, which was not truncated after snapshot is written, is already included into it
+    fs::create_dir(&path).unwrap();
+    assert!(store.compact().is_err());
+    fs::remove_dir(&path).unwrap();
+    store.store().save(&path).unwrap();
+    let stale = fs::read(&journal).unwrap();
+    drop(store);
+
+    let mut store = Journaled::open(&path, USER, PASS).unwrap();
+    assert_eq!(store.journal_len(), 0);
+    assert_eq!(store.store().len(), 2);
+    assert!(fs::read(&journal).unwrap().len() < stale.len());
+    store.insert(b"3", b"3").unwrap();
+    drop(store);
+
+    let store = Journaled::open(&path, USER, PASS).unwrap();
+    assert_eq!(store.journal_len(), 1);
+    assert_eq!(store.store().get(b"3").unwrap(), b"3");
+
+    let _ = fs::remove_file(&path);
+    let _ = fs::remove_file(&journal);
+}
@@ -0,0 +1,38 @@
+#![cfg(feature = "json")]
+
+use sec_store::Store;
+
+#[test]
+fn should_get_json_field() {
+    let mut store = Store::new(b"user", b"pass");
+    assert!(store.insert_json(b"1", r#"{"a": {"b":"é\n"}"#).is_err());
+    assert!(store.insert_json(b"1", "[1, 2,]").is_err());
+    assert!(!store.contains(b"1"));
+
+    let document = r#" {
+        "db": {"host": "localhost", "port": 5432, "tls": true, "users": [{"name": "admin"}, {"name": "gu\"esté😀"}]},
+        "empty": {},
+        "a.b": null,
+        "num": -1.5e+3
+    } "#;
+    assert!(store.insert_json(b"1", document).unwrap().is_none());
+    store.insert(b"2", b"not json");
+
+    assert_eq!(store.get_field(b"1", "db.port").unwrap(), b"5432");
+    assert_eq!(store.get_field(b"1", "db.host").unwrap(), b"\"localhost\"");
+    assert_eq!(store.get_field_str(b"1", "db.host").unwrap(), "localhost");
+    assert_eq!(store.get_field(b"1", "db.tls").unwrap(), b"true");
+    assert_eq!(store.get_field(b"1", "db.users.0").unwrap(), br#"{"name": "admin"}"#);
+    assert_eq!(store.get_field_str(b"1", "db.users.1.name").unwrap(), "gu\"est\u{e9}\u{1f600}");
+    assert_eq!(store.get_field(b"1", "empty").unwrap(), b"{}");
+    assert_eq!(store.get_field(b"1", "num").unwrap(), b"-1.5e+3");
+    assert_eq!(store.get_field(b"1", "").unwrap(), document.trim().as_bytes());
+
+    assert!(store.get_field_str(b"1", "db.port").is_none());
+    assert!(store.get_field(b"1", "db.users.2").is_none());
+    assert!(store.get_field(b"1", "db.missing").is_none());
+    assert!(store.get_field(b"1", "db.port.0").is_none());
+    assert!(store.get_field(b"1", "a.b").is_none());
+    assert!(store.get_field(b"2", "").is_none());
+    assert!(store.get_field(b"3", "").is_none());
+}
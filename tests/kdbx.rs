@@ -0,0 +1,75 @@
+#![cfg(feature = "kdbx")]
+
+use sec_store::Store;
+
+const AES: &[u8] = include_bytes!("data/aes.kdbx");
+const ARGON2: &[u8] = include_bytes!("data/argon2.kdbx");
+
+fn verify(database: &[u8]) {
+    let mut store = Store::new(b"user", b"pass");
+    assert!(store.import_kdbx(database, b"wrong").is_err());
+    assert!(store.import_kdbx(&database[..database.len() - 1], b"password").is_err());
+    assert_eq!(store.len(), 0);
+
+    let names = store.import_kdbx(database, b"password").unwrap();
+    assert_eq!(names, [&b"Mail"[..], b"Work/VPN", b"Mail#00112233445566778899aabbccddeeff"]);
+
+    let mail = store.namespace(b"Mail");
+    assert_eq!(mail.get(b"title").unwrap(), b"Mail");
+    assert_eq!(mail.get(b"username").unwrap(), b"alice");
+    assert_eq!(mail.get(b"password").unwrap(), b"hunter2 & <co>");
+    assert_eq!(mail.get(b"url").unwrap(), b"https://mail.example/?a=1&b=2");
+    assert_eq!(mail.get(b"notes").unwrap(), "line1\nline2 \u{263a} <raw>".as_bytes());
+    assert_eq!(mail.get(b"PIN").unwrap(), b"1234");
+    assert!(!mail.contains(b"Empty"));
+    assert_eq!(mail.len(), 6);
+
+    let vpn = store.namespace(b"Work/VPN");
+    assert_eq!(vpn.get(b"username").unwrap(), b"bob");
+    assert_eq!(vpn.get(b"password").unwrap(), b"correct horse battery staple");
+
+    let mail = store.namespace(b"Mail#00112233445566778899aabbccddeeff");
+    assert_eq!(mail.get(b"password").unwrap(), b"second");
+    assert!(store.namespace(b"Recycle Bin/Trash").get(b"password").is_none());
+}
+
+#[test]
+fn should_import_kdbx_with_aes() {
+    verify(AES);
+}
+
+#[test]
+fn should_import_kdbx_with_argon2() {
+    verify(ARGON2);
+}
+
+///Replaces 64bit parameter `name` of KDF within `database`, updating hash of its header.
+fn with_kdf_param(database: &[u8], name: u8, value: u64) -> Vec<u8> {
+    let mut result = database.to_owned();
+    let needle = [1, 0, 0, 0, name, 8, 0, 0, 0];
+    let pos = result.windows(needle.len()).position(|window| window == needle).expect("To find parameter") + needle.len();
+    let header = (12..result.len() - 32).find(|len| ring::digest::digest(&ring::digest::SHA256, &result[..*len]).as_ref() == &result[*len..*len + 32]).expect("To find header");
+
+    result[pos..pos + 8].copy_from_slice(&value.to_le_bytes());
+    let hash = ring::digest::digest(&ring::digest::SHA256, &result[..header]);
+    result[header..header + 32].copy_from_slice(hash.as_ref());
+    result
+}
+
+#[test]
+fn should_reject_kdbx_with_excessive_kdf() {
+    let mut store = Store::new(b"user", b"pass");
+
+    let database = with_kdf_param(ARGON2, b'M', u32::MAX as u64 * 1024);
+    let error = store.import_kdbx(&database[..], b"password").unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+
+    let database = with_kdf_param(ARGON2, b'I', u32::MAX as u64);
+    let error = store.import_kdbx(&database[..], b"password").unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+
+    let database = with_kdf_param(AES, b'R', u64::MAX);
+    let error = store.import_kdbx(&database[..], b"password").unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    assert_eq!(store.len(), 0);
+}
@@ -0,0 +1,34 @@
+#![cfg(feature = "keychain")]
+
+use sec_store::{Keychain, MasterKey, Store};
+
+use std::fs;
+use std::io::ErrorKind;
+
+const USER: &[u8] = b"loli";
+const PASS: &[u8] = b"pass";
+
+#[test]
+fn should_open_store_with_keychain() {
+    //Mock keeps secret within entry, rather than within platform keychain.
+    keyring::set_default_credential_builder(keyring::mock::default_credential_builder());
+
+    let path = std::env::temp_dir().join(format!("sec-store-{}-keychain", std::process::id()));
+    let mut store = Store::new(USER, PASS);
+    store.insert(b"1", b"1");
+    store.save(&path).unwrap();
+
+    let keychain = Keychain::new("sec-store", "loli").unwrap();
+    assert_eq!(Store::open_with_keychain(&path, &keychain).err().unwrap().kind(), ErrorKind::NotFound);
+    keychain.store_key(&store.master_key().unwrap()).unwrap();
+    assert_eq!(keychain.load_key().unwrap().as_bytes(), MasterKey::derive(USER, PASS).unwrap().as_bytes());
+    assert_eq!(Store::open_with_keychain(&path, &keychain).unwrap().get(b"1").unwrap(), b"1");
+
+    keychain.store_key(&MasterKey::derive(USER, b"WRONG").unwrap()).unwrap();
+    assert_eq!(Store::open_with_keychain(&path, &keychain).err().unwrap().kind(), ErrorKind::InvalidData);
+
+    keychain.remove_key().unwrap();
+    assert!(matches!(keychain.load_key(), Err(keyring::Error::NoEntry)));
+
+    let _ = fs::remove_file(&path);
+}
@@ -0,0 +1,10 @@
+#![cfg(feature = "pkcs11")]
+
+use sec_store::Pkcs11Key;
+
+#[test]
+fn should_fail_without_pkcs11_module() {
+    let module = std::env::temp_dir().join(format!("sec-store-{}-pkcs11.so", std::process::id()));
+    let error = Pkcs11Key::open(&module, "token", "1234", "sec-store").unwrap_err();
+    assert!(matches!(error, cryptoki::error::Error::LibraryLoading(_)));
+}
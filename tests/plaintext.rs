@@ -0,0 +1,38 @@
+#![cfg(feature = "danger-plaintext")]
+
+use sec_store::{Store, SecStore};
+use sec_store::plaintext::PlaintextStore;
+
+use std::fs;
+
+#[test]
+fn should_store_values_in_plaintext() {
+    let path = std::env::temp_dir().join(format!("sec-store-{}-plaintext", std::process::id()));
+
+    let mut store = Store::new(b"user", b"pass");
+    store.insert(b"1", b"one");
+    store.insert(b"2", b"two");
+
+    let mut plain = PlaintextStore::from_store(&store);
+    assert_eq!(plain.len(), 2);
+    assert_eq!(plain.get(b"1").unwrap(), b"one");
+    let mut dest = [0u8; 3];
+    assert_eq!(plain.get_to(b"2", &mut dest).unwrap(), 3);
+    assert_eq!(dest, *b"two");
+    assert_eq!(plain.get_to(b"2", &mut dest[..2]).unwrap(), 0);
+    assert!(plain.get_to(b"3", &mut dest).is_err());
+    assert!(SecStore::insert(&mut plain, b"3", b"three").is_none());
+    assert!(plain.try_insert(b"4", b"").is_err());
+    assert!(!plain.contains(b"4"));
+    assert!(plain.remove_key(b"2"));
+
+    plain.save(&path).unwrap();
+    let content = fs::read(&path).unwrap();
+    assert!(content.windows(5).any(|window| window == b"three"));
+    assert_eq!(PlaintextStore::open(&path).unwrap(), plain);
+
+    store.save(&path).unwrap();
+    assert_eq!(PlaintextStore::open(&path).unwrap().len(), 2);
+
+    let _ = fs::remove_file(&path);
+}
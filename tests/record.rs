@@ -0,0 +1,63 @@
+#![cfg(feature = "derive")]
+
+use sec_store::{Error, SecRecord, Store};
+use sec_store::record::Record;
+
+const USER: &[u8] = b"loli";
+const PASS: &[u8] = b"pass";
+
+#[derive(SecRecord, Debug, PartialEq)]
+#[sec_record(namespace = "database")]
+pub struct Database {
+    ///Login of user.
+    pub user: String,
+    pub password: Vec<u8>,
+    port: u16,
+    replica: Option<String>,
+    r#type: bool,
+}
+
+#[derive(SecRecord, Debug, PartialEq)]
+struct Token {
+    value: String,
+}
+
+#[test]
+fn should_derive_record() {
+    let mut store = Store::new(USER, PASS);
+    assert_eq!(Database::NAMESPACE, b"database");
+    assert_eq!(Database::FIELDS, [&b"user"[..], b"password", b"port", b"replica", b"type"]);
+    assert_eq!(Token::NAMESPACE, b"Token");
+    assert_eq!(Database::load(&mut store).err(), Some(Error::NotFound));
+
+    let database = Database {
+        user: "admin".to_owned(),
+        password: b"secret".to_vec(),
+        port: 5432,
+        replica: None,
+        r#type: true,
+    };
+    database.save(&mut store).unwrap();
+    assert_eq!(store.len(), 4);
+    assert!(store.get(b"user").is_none());
+    assert_eq!(store.namespace(b"database").get(b"port").unwrap(), 5432u16.to_le_bytes());
+    assert_eq!(Database::load(&mut store).unwrap(), database);
+
+    let mut accessor = DatabaseStore::new(&mut store);
+    assert_eq!(accessor.user().unwrap(), "admin");
+    assert_eq!(accessor.replica().unwrap(), None);
+    accessor.set_replica(&Some("backup".to_owned())).unwrap();
+    accessor.set_port(&6432).unwrap();
+    accessor.set_type(&false).unwrap();
+    assert!(!accessor.r#type().unwrap());
+
+    let loaded = Database::load(&mut store).unwrap();
+    assert_eq!(loaded.port, 6432);
+    assert_eq!(loaded.replica.as_deref(), Some("backup"));
+
+    store.namespace(b"database").insert(b"port", b"bad");
+    assert!(matches!(Database::load(&mut store), Err(Error::InvalidEntry(_))));
+
+    Token { value: String::new() }.save(&mut store).unwrap();
+    assert_eq!(TokenStore::new(&mut store).value().unwrap(), "");
+}
@@ -1,4 +1,7 @@
-use sec_store::Store;
+#![allow(clippy::redundant_slicing)]
+
+use sec_store::{Store, MergePolicy, Error, ChangeEvent, Backend, Stats, Padding};
+use xxhash_rust::xxh3::{xxh3_64, xxh3_128, xxh3_128_with_seed};
 
 ///Obviously do not store credentials like that.
 const USER: &[u8] = b"loli";
@@ -62,3 +65,2443 @@ fn should_securely_manage_values() {
     assert!(store.remove(b"1").is_none());
     assert_eq!(store.len(), 1);
 }
+
+#[test]
+fn should_merge_stores() {
+    let mut local = Store::new(USER, PASS);
+    let mut remote = Store::new(USER, b"remote");
+
+    local.insert(b"1", b"local");
+    local.insert(b"2", b"same");
+    remote.insert(b"2", b"same");
+    remote.insert(b"3", b"remote");
+    remote.insert(b"1", b"remote");
+
+    let mut merged = Store::from_inner(local.inner().clone(), USER, PASS);
+    assert_eq!(merged.merge(&remote, MergePolicy::KeepLocal).unwrap(), 1);
+    assert_eq!(merged.len(), 3);
+    assert_eq!(merged.get(b"1").unwrap(), b"local");
+    assert_eq!(merged.get(b"3").unwrap(), b"remote");
+
+    let mut merged = Store::from_inner(local.inner().clone(), USER, PASS);
+    assert_eq!(merged.merge(&remote, MergePolicy::KeepRemote).unwrap(), 2);
+    assert_eq!(merged.get(b"1").unwrap(), b"remote");
+    assert_eq!(merged.get(b"2").unwrap(), b"same");
+
+    let mut conflicts = 0;
+    {
+        let mut resolve = |_: u128, local: &[u8], remote: &[u8]| {
+            conflicts += 1;
+            [local, remote].concat()
+        };
+        assert_eq!(local.merge(&remote, MergePolicy::Resolve(&mut resolve)).unwrap(), 2);
+    }
+    assert_eq!(conflicts, 1);
+    assert_eq!(local.get(b"1").unwrap(), b"localremote");
+
+    remote.insert(b"1", b"conflict");
+    let mut resolve = |_: u128, _: &[u8], _: &[u8]| Vec::new();
+    assert_eq!(local.merge(&remote, MergePolicy::Resolve(&mut resolve)), Err(Error::InvalidEntry(xxh3_128(b"1").to_le())));
+    assert_eq!(local.get(b"1").unwrap(), b"localremote");
+
+    let remote = Store::from_inner(remote.into_inner(), USER, PASS);
+    assert!(matches!(local.merge(&remote, MergePolicy::KeepLocal), Err(Error::InvalidEntry(_))));
+    assert_eq!(local.get(b"1").unwrap(), b"localremote");
+}
+
+#[test]
+fn should_diff_stores() {
+    let mut old = Store::new(USER, PASS);
+    old.insert(b"1", b"1");
+    old.insert(b"2", b"2");
+    old.insert(b"3", b"3");
+
+    let mut new = Store::from_inner(old.inner().clone(), USER, PASS);
+    assert!(old.diff(&new).is_empty());
+
+    new.remove_key(b"1");
+    new.insert(b"2", b"changed");
+    new.insert(b"4", b"4");
+
+    let diff = old.diff(&new);
+    assert_eq!(diff.added, [xxh3_128(b"4")]);
+    assert_eq!(diff.removed, [xxh3_128(b"1")]);
+    assert_eq!(diff.changed, [xxh3_128(b"2")]);
+
+    let mut other = Store::new(USER, b"other");
+    other.insert(b"2", b"2");
+    other.insert(b"3", b"3");
+    let diff = old.diff(&other);
+    assert!(diff.added.is_empty());
+    assert!(diff.changed.is_empty());
+    assert_eq!(diff.removed, [xxh3_128(b"1")]);
+}
+
+#[test]
+fn should_diff_stores_by_names() {
+    let mut old = Store::new(USER, PASS);
+    old.enable_key_names();
+    old.insert(b"1", b"1");
+    old.insert(b"2", b"2");
+
+    let mut new = Store::new(USER, b"other");
+    new.enable_key_names();
+    new.insert(b"2", b"changed");
+    new.insert(b"3", b"3");
+    //Values of namespaces are never named, hence skipped
+    new.namespace(b"namespace").insert(b"4", b"4");
+
+    let diff = old.diff_named(&new);
+    assert_eq!(diff.added, [b"3".to_vec()]);
+    assert_eq!(diff.removed, [b"1".to_vec()]);
+    assert_eq!(diff.changed, [b"2".to_vec()]);
+    assert_eq!(old.diff(&new).added.len(), 2);
+    assert!(old.diff_named(&old).is_empty());
+}
+
+#[test]
+fn should_verify_integrity_mac() {
+    let mut store = Store::new(USER, PASS);
+    store.insert(b"1", b"1");
+    store.insert(b"2", b"2");
+    assert!(!store.verify_mac());
+
+    store.update_mac();
+    assert!(store.verify_mac());
+    assert_eq!(store.len(), 2);
+
+    let inner = store.into_inner();
+    let store = Store::from_inner_verified(inner.clone(), USER, PASS).expect("valid MAC");
+    assert_eq!(store.get(b"1").unwrap(), b"1");
+    assert!(Store::from_inner_verified(inner.clone(), USER, b"WRONG").is_none());
+
+    let mut removed = inner.clone();
+    removed.remove(&xxh3_128(b"2"));
+    assert!(Store::from_inner_verified(removed.clone(), USER, PASS).is_none());
+
+    let mut old = Store::new(USER, PASS);
+    old.insert(b"1", b"old");
+    let mut rolled_back = inner;
+    rolled_back.insert(xxh3_128(b"1"), old.inner()[&xxh3_128(b"1")].clone());
+    assert!(Store::from_inner_verified(rolled_back.clone(), USER, PASS).is_none());
+    assert_eq!(Store::try_from_inner(rolled_back.clone(), USER, PASS).err(), Some(Error::IntegrityMismatch));
+    let rolled_back = Store::from_inner(rolled_back, USER, PASS);
+    assert_eq!(rolled_back.get(b"1").unwrap(), b"old");
+    assert!(!rolled_back.verify_mac());
+
+    //Removing MAC along with entry doesn't help, as header records that it is required.
+    let mut stripped = removed;
+    stripped.remove(&1);
+    assert_eq!(Store::try_from_inner(stripped.clone(), USER, PASS).err(), Some(Error::IntegrityMismatch));
+
+    //Neither does removing header too, as it is required by storage with values of the current format.
+    stripped.remove(&2);
+    assert_eq!(Store::try_from_inner(stripped, USER, PASS).err(), Some(Error::IntegrityMismatch));
+}
+
+#[test]
+fn should_verify_credentials() {
+    let store = Store::new(USER, PASS);
+    assert!(store.verify_credentials());
+    assert_eq!(store.len(), 0);
+
+    let inner = store.into_inner();
+    assert!(Store::from_inner(inner.clone(), USER, PASS).verify_credentials());
+    assert!(!Store::from_inner(inner.clone(), USER, b"WRONG").verify_credentials());
+    assert!(!Store::from_inner(inner, b"WRONG", PASS).verify_credentials());
+
+    //Storage of earlier versions has neither header nor values of the current format.
+    let mut legacy = Store::new(USER, PASS);
+    legacy.insert(b"1", b"1");
+    legacy.migrate_format(1).unwrap();
+    let mut inner = legacy.into_inner();
+    //Header is removed along with other internal entries.
+    inner.retain(|key, _| *key >= 0x1_0000);
+    assert!(!Store::from_inner(inner.clone(), USER, b"WRONG").verify_credentials());
+    assert_eq!(Store::try_from_inner(inner.clone(), USER, b"WRONG").err(), Some(Error::WrongCredentials));
+    //Header is written once credentials are verified by the first value.
+    let legacy = Store::try_from_inner(inner, USER, PASS).unwrap();
+    assert!(legacy.verify_credentials());
+    assert_eq!(legacy.get(b"1").unwrap(), b"1");
+    let inner = legacy.into_inner();
+    assert!(inner.contains_key(&2));
+    assert!(Store::try_from_inner(inner.clone(), USER, PASS).is_ok());
+    assert_eq!(Store::try_from_inner(inner, USER, b"WRONG").err(), Some(Error::WrongCredentials));
+
+    //Values of the current format mean that header is stripped.
+    let mut stripped = Store::new(USER, PASS);
+    stripped.insert(b"1", b"1");
+    let mut inner = stripped.into_inner();
+    inner.retain(|key, _| *key >= 0x1_0000);
+    assert_eq!(Store::try_from_inner(inner.clone(), USER, PASS).err(), Some(Error::IntegrityMismatch));
+    assert!(!Store::from_inner(inner, USER, PASS).inner().contains_key(&2));
+}
+
+#[test]
+fn should_validate_on_fallible_creation() {
+    assert_eq!(Store::try_new(b"", PASS).err(), Some(Error::InvalidCredentials));
+    assert_eq!(Store::try_new(USER, b"").err(), Some(Error::InvalidCredentials));
+
+    let mut store = Store::try_new(USER, PASS).unwrap();
+    store.insert(b"1", b"1");
+    let inner = store.into_inner();
+
+    assert_eq!(Store::try_from_inner(inner.clone(), USER, b"").err(), Some(Error::InvalidCredentials));
+    assert_eq!(Store::try_from_inner(inner.clone(), USER, b"WRONG").err(), Some(Error::WrongCredentials));
+    let mut store = Store::try_from_inner(inner.clone(), USER, PASS).unwrap();
+    assert_eq!(store.get(b"1").unwrap(), b"1");
+
+    store.update_mac();
+    let mut inner = store.into_inner();
+    assert!(Store::try_from_inner(inner.clone(), USER, PASS).is_ok());
+
+    let mut tampered = inner.clone();
+    tampered.remove(&xxh3_128(b"1"));
+    assert_eq!(Store::try_from_inner(tampered, USER, PASS).err(), Some(Error::IntegrityMismatch));
+
+    inner.insert(xxh3_128(b"2"), vec![1, 2, 3]);
+    assert_eq!(Store::try_from_inner(inner, USER, PASS).err(), Some(Error::InvalidEntry(xxh3_128(b"2"))));
+}
+
+#[test]
+fn should_apply_transaction_atomically() {
+    let mut store = Store::new(USER, PASS);
+    store.insert(b"token", b"old-token");
+    store.insert(b"refresh", b"old-refresh");
+    store.insert(b"obsolete", b"1");
+
+    let result: Result<(), ()> = store.transaction(|tx| {
+        tx.insert(b"token", b"new-token");
+        assert!(tx.remove(b"obsolete"));
+        assert!(!tx.contains(b"obsolete"));
+        assert_eq!(tx.get(b"token").unwrap(), b"new-token");
+        Err(())
+    });
+    assert!(result.is_err());
+    assert_eq!(store.get(b"token").unwrap(), b"old-token");
+    assert!(store.contains(b"obsolete"));
+
+    let result = store.transaction(|tx| {
+        tx.insert(b"token", b"new-token");
+        tx.insert(b"refresh", b"new-refresh");
+        tx.remove(b"obsolete");
+        Ok::<_, ()>(tx.get(b"refresh"))
+    });
+    assert_eq!(result.unwrap().unwrap(), b"new-refresh");
+    assert_eq!(store.len(), 2);
+    assert_eq!(store.get(b"token").unwrap(), b"new-token");
+    assert_eq!(store.get(b"refresh").unwrap(), b"new-refresh");
+    assert!(!store.contains(b"obsolete"));
+}
+
+#[test]
+fn should_apply_transaction_with_eviction_atomically() {
+    let mut store = Store::builder(USER, PASS).evict_lru(2, |_, _| {}).build().unwrap();
+    store.insert(b"old", b"old");
+
+    let result = store.transaction(|tx| {
+        tx.try_insert(b"token", b"new-token")?;
+        tx.try_insert(b"refresh", b"new-refresh")?;
+        tx.try_insert(b"extra", b"extra")
+    });
+    assert_eq!(result, Err(Error::LimitExceeded));
+    assert_eq!(store.len(), 1);
+
+    let result = store.transaction(|tx| {
+        tx.try_insert(b"token", b"new-token")?;
+        tx.try_insert(b"refresh", b"new-refresh")
+    });
+    assert_eq!(result, Ok(()));
+    assert_eq!(store.len(), 2);
+    assert!(!store.contains(b"old"));
+    assert_eq!(store.get(b"token").unwrap(), b"new-token");
+    assert_eq!(store.get(b"refresh").unwrap(), b"new-refresh");
+
+    store.transaction(|tx| {
+        assert!(tx.remove(b"token"));
+        tx.try_insert(b"extra", b"extra")
+    }).unwrap();
+    store.insert(b"last", b"last");
+    assert_eq!(store.len(), 2);
+    assert!(!store.contains(b"refresh"));
+    assert!(store.contains(b"extra"));
+}
+
+#[test]
+fn should_restore_snapshot() {
+    let mut store = Store::new(USER, PASS);
+    store.insert(b"1", b"1");
+    store.insert(b"2", b"2");
+
+    let snapshot = store.snapshot();
+    assert_eq!(snapshot.len(), 2);
+
+    store.insert(b"1", b"changed");
+    store.remove_key(b"2");
+    store.insert(b"3", b"3");
+    assert_eq!(store.len(), 2);
+
+    store.restore(snapshot);
+    assert_eq!(store.len(), 2);
+    assert_eq!(store.get(b"1").unwrap(), b"1");
+    assert_eq!(store.get(b"2").unwrap(), b"2");
+    assert!(!store.contains(b"3"));
+    assert!(store.verify_credentials());
+
+    //Key names and tags are restored as well.
+    let mut store = Store::new(USER, PASS);
+    store.enable_key_names();
+    store.insert_with_meta(b"1", b"1", &[b"first", b"odd"]);
+    store.insert_with_meta(b"2", b"2", &[b"even"]);
+    let snapshot = store.snapshot();
+
+    store.insert_with_meta(b"1", b"changed", &[b"changed"]);
+    store.remove_key(b"2");
+    store.insert_with_meta(b"3", b"3", &[b"odd"]);
+    assert_eq!(store.keys().collect::<Vec<_>>(), [b"1", b"3"]);
+
+    store.restore(snapshot);
+    assert_eq!(store.keys().collect::<Vec<_>>(), [b"1", b"2"]);
+    assert_eq!(store.tags(b"1").collect::<Vec<_>>(), [&b"first"[..], b"odd"]);
+    assert_eq!(store.tags(b"2").collect::<Vec<_>>(), [b"even"]);
+    assert_eq!(store.tags(b"3").count(), 0);
+    assert_eq!(store.find_by_tag(b"odd").collect::<Vec<_>>(), [b"1"]);
+    assert_eq!(store.find_by_tag(b"changed").count(), 0);
+    assert_eq!(store.get(b"1").unwrap(), b"1");
+}
+
+#[test]
+fn should_notify_subscribers() {
+    let mut store = Store::new(USER, PASS);
+    let events = store.subscribe();
+    let dropped = store.subscribe();
+    drop(dropped);
+
+    let snapshot = store.snapshot();
+    store.insert(b"1", b"1");
+    store.remove_key(b"1");
+    store.remove_key(b"1");
+    let _ = store.transaction(|tx| {
+        tx.insert(b"2", b"2");
+        Ok::<_, ()>(())
+    });
+    store.restore(snapshot);
+
+    let events: Vec<_> = events.try_iter().collect();
+    assert_eq!(events, [
+        ChangeEvent::Insert(xxh3_128(b"1")),
+        ChangeEvent::Remove(xxh3_128(b"1")),
+        ChangeEvent::Insert(xxh3_128(b"2")),
+        ChangeEvent::Restore,
+    ]);
+}
+
+#[test]
+fn should_read_value_via_reader() {
+    use std::io::Read;
+
+    let value: Vec<u8> = (0..=255).cycle().take(10_000).collect();
+    let mut store = Store::new(USER, PASS);
+    store.insert(b"1", &value);
+
+    let mut reader = store.get_reader(b"1").unwrap();
+    let mut chunk = [0u8; 100];
+    assert_eq!(reader.read(&mut chunk).unwrap(), chunk.len());
+    assert_eq!(chunk[..], value[..100]);
+
+    let mut rest = Vec::new();
+    reader.read_to_end(&mut rest).unwrap();
+    assert_eq!(rest, value[100..]);
+    assert_eq!(reader.read(&mut chunk).unwrap(), 0);
+    drop(reader);
+
+    assert!(store.get_reader(b"2").is_none());
+    let store = Store::from_inner(store.into_inner(), USER, b"WRONG");
+    assert!(store.get_reader(b"1").is_none());
+}
+
+#[test]
+fn should_chunk_large_values() {
+    use std::io::Read;
+    use sec_store::StoreBuilder;
+
+    let value: Vec<u8> = (0..=255).cycle().take(100 * 1024 + 3).collect();
+    let mut store = Store::new(USER, PASS);
+    assert_eq!(store.chunking(), Some(64 * 1024));
+    store.insert(b"1", &value);
+    store.insert(b"2", &value[..64 * 1024]);
+    let key = xxh3_128(b"1").to_le();
+    assert_eq!(store.inner().get(&key).unwrap().len(), 12 + 32 + 16 + value.len() + 2 * (12 + 16));
+    assert_eq!(store.inner().get(&xxh3_128(b"2").to_le()).unwrap().len(), 12 + 24 + 64 * 1024 + 16);
+    assert_eq!(store.get(b"1").unwrap(), value);
+    assert_eq!(store.get(b"2").unwrap(), value[..64 * 1024]);
+    assert_eq!(store.get_len(b"1").unwrap(), value.len());
+
+    //Chunks are sealed using random nonces, so overwriting value doesn't reuse them
+    let first = store.inner().get(&key).unwrap().clone();
+    store.insert(b"1", &value);
+    let second = store.inner().get(&key).unwrap();
+    assert_ne!(second[..12], first[..12]);
+    assert_ne!(second[12 + 32 + 16..12 + 32 + 16 + 12], first[12 + 32 + 16..12 + 32 + 16 + 12]);
+    assert_eq!(store.get(b"1").unwrap(), value);
+
+    let mut reader = store.get_reader(b"1").unwrap();
+    let mut rest = Vec::new();
+    reader.read_to_end(&mut rest).unwrap();
+    assert_eq!(rest, value);
+    drop(reader);
+
+    store.set_chunking(Some(1000));
+    store.set_randomized(true);
+    assert_eq!(store.insert(b"1", &value[..1001]).unwrap(), value);
+    assert_eq!(store.inner().get(&key).unwrap().len(), 12 + 32 + 16 + 1001 + 2 * (12 + 16));
+    assert_eq!(store.get(b"1").unwrap(), value[..1001]);
+    store.set_chunking(None);
+    assert_eq!(store.chunking(), None);
+    assert_eq!(store.insert(b"1", &value).unwrap(), value[..1001]);
+    assert_eq!(store.inner().get(&key).unwrap().len(), 12 + 24 + value.len() + 16);
+    assert_eq!(store.get(b"1").unwrap(), value);
+
+    let mut store = StoreBuilder::new(USER, PASS).chunking(Some(100)).build().unwrap();
+    assert_eq!(store.chunking(), Some(100));
+    store.insert(b"1", &value[..250]);
+    assert_eq!(store.inner().get(&key).unwrap().len(), 12 + 32 + 16 + 250 + 3 * (12 + 16));
+    assert_eq!(store.get(b"1").unwrap(), value[..250]);
+}
+
+#[test]
+fn should_get_to_uninit() {
+    use core::mem::MaybeUninit;
+
+    let value: Vec<u8> = (0..=255).cycle().take(200 * 1024 + 7).collect();
+    let mut store = Store::new(USER, PASS);
+    store.insert(b"1", b"value");
+    store.insert_from_reader(b"2", &value[..]).unwrap();
+
+    let mut buffer = [MaybeUninit::<u8>::uninit(); 64];
+    assert_eq!(store.get_to_uninit(b"1", &mut buffer).unwrap(), b"value");
+    assert_eq!(store.get_to_uninit(b"3", &mut buffer).unwrap_err(), Error::NotFound);
+    let required = match store.get_to_uninit(b"1", &mut buffer[..1]).unwrap_err() {
+        Error::BufferTooSmall(required) => required,
+        error => panic!("Unexpected error: {}", error),
+    };
+    assert!(required > b"value".len());
+    assert_eq!(store.get_len(b"1").unwrap(), required);
+    assert_eq!(store.get_len(b"3").unwrap_err(), Error::NotFound);
+    let mut bytes = vec![0u8; required];
+    assert_eq!(store.get_to(b"1", &mut bytes[..required - 1]).unwrap(), 0);
+    assert_eq!(store.get_to(b"1", &mut bytes).unwrap(), b"value".len());
+    assert_eq!(store.get_to_uninit(b"1", &mut buffer[..required]).unwrap(), b"value");
+
+    let mut buffer = vec![MaybeUninit::<u8>::uninit(); value.len()];
+    assert_eq!(store.get_to_uninit(b"2", &mut buffer[..value.len() - 1]).unwrap_err(), Error::BufferTooSmall(value.len()));
+    assert_eq!(store.get_len(b"2").unwrap(), value.len());
+    assert_eq!(store.get_to_uninit(b"2", &mut buffer).unwrap(), &value[..]);
+
+    let key = xxh3_128(b"1").to_le();
+    let mut inner = store.into_inner();
+    inner.get_mut(&key).unwrap()[0] ^= 1;
+    let hash = xxh3_128(b"2").to_le();
+    let chunked = inner.get_mut(&hash).unwrap();
+    let last = chunked.len() - 1;
+    chunked[last] ^= 1;
+    let store = Store::from_inner(inner, USER, PASS);
+    assert_eq!(store.get_to_uninit(b"1", &mut buffer).unwrap_err(), Error::InvalidEntry(key));
+
+    //On error only initialized part of buffer is wiped, while the rest is left untouched
+    for (key, hash, len) in [(&b"1"[..], key, 128), (b"2", hash, value.len() + 64)] {
+        let mut buffer = vec![MaybeUninit::new(0xAAu8); len];
+        assert_eq!(store.get_to_uninit(key, &mut buffer).unwrap_err(), Error::InvalidEntry(hash));
+        //Every byte of buffer is initialized upfront.
+        let bytes: Vec<u8> = buffer.iter().map(|byte| unsafe { byte.assume_init() }).collect();
+        assert!(bytes.iter().all(|byte| *byte == 0 || *byte == 0xAA));
+        assert_eq!(bytes[len - 64..], [0xAA; 64]);
+    }
+}
+
+#[test]
+fn should_insert_value_from_reader_with_configured_chunking() {
+    let value: Vec<u8> = (0..=255).cycle().take(200 * 1024 + 7).collect();
+    let mut store = Store::new(USER, PASS);
+    store.set_chunking(Some(1000));
+    store.insert_from_reader(b"1", &value[..]).unwrap();
+    store.insert(b"2", &value);
+    assert_eq!(store.get_encrypted(b"1").unwrap().1.len(), store.get_encrypted(b"2").unwrap().1.len());
+    assert_eq!(store.get(b"1").unwrap(), value);
+
+    //Value is read whole, being sealed as by regular insertion
+    store.set_chunking(None);
+    store.insert_from_reader(b"3", &value[..]).unwrap();
+    let sealed = store.get_encrypted(b"3").unwrap().1.to_vec();
+    store.insert(b"3", &value);
+    assert_eq!(store.get_encrypted(b"3").unwrap().1, &sealed[..]);
+    assert_eq!(store.get(b"3").unwrap(), value);
+}
+
+#[test]
+fn should_insert_chunked_value_from_reader() {
+    use std::io::Read;
+
+    let value: Vec<u8> = (0..=255).cycle().take(200 * 1024 + 7).collect();
+    let mut store = Store::new(USER, PASS);
+    assert!(!store.insert_from_reader(b"1", &value[..]).unwrap());
+    assert!(store.insert_from_reader(b"2", &b""[..]).is_err());
+    assert!(!store.contains(b"2"));
+    assert!(store.insert_from_reader(b"2", &value[..1024]).is_ok());
+
+    let mut reader = store.get_reader(b"1").unwrap();
+    let mut chunk = [0u8; 100];
+    assert_eq!(reader.read(&mut chunk).unwrap(), chunk.len());
+    assert_eq!(chunk[..], value[..100]);
+    let mut rest = Vec::new();
+    reader.read_to_end(&mut rest).unwrap();
+    assert_eq!(rest, value[100..]);
+    drop(reader);
+
+    assert_eq!(store.get(b"1").unwrap(), value);
+    assert_eq!(store.get(b"2").unwrap(), value[..1024]);
+    let mut bytes = vec![0u8; value.len()];
+    assert_eq!(store.get_to(b"1", &mut bytes[..value.len() - 1]).unwrap(), 0);
+    assert_eq!(store.get_to(b"1", &mut bytes).unwrap(), value.len());
+    assert_eq!(bytes, value);
+
+    assert_eq!(store.insert(b"1", b"small").unwrap(), value);
+    assert!(store.insert_from_reader(b"1", &value[..]).unwrap());
+
+    let mut inner = store.into_inner();
+    let wrong = Store::from_inner(inner.clone(), USER, b"WRONG");
+    assert!(wrong.get(b"1").is_none());
+    assert!(wrong.get_reader(b"1").is_none());
+
+    let truncated = inner.get_mut(&xxh3_128(b"1")).unwrap();
+    let len = truncated.len();
+    truncated.truncate(len - 64 * 1024);
+    let store = Store::from_inner(inner, USER, PASS);
+    assert!(store.get(b"1").is_none());
+}
+
+#[derive(Default)]
+struct VecBackend(Vec<(u128, Vec<u8>)>);
+
+impl Backend for VecBackend {
+    fn get(&self, key: u128) -> Option<&[u8]> {
+        self.0.iter().find(|(entry, _)| *entry == key).map(|(_, value)| value.as_slice())
+    }
+
+    fn insert(&mut self, key: u128, value: Vec<u8>) -> Option<Vec<u8>> {
+        let result = self.remove(key);
+        self.0.push((key, value));
+        result
+    }
+
+    fn remove(&mut self, key: u128) -> Option<Vec<u8>> {
+        let idx = self.0.iter().position(|(entry, _)| *entry == key)?;
+        Some(self.0.swap_remove(idx).1)
+    }
+
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = (u128, &[u8])> + '_> {
+        Box::new(self.0.iter().map(|(key, value)| (*key, value.as_slice())))
+    }
+}
+
+#[test]
+fn should_work_with_custom_backend() {
+    let mut store = Store::new_in(VecBackend::default(), USER, PASS);
+    assert!(store.insert(b"2", b"two").is_none());
+    assert!(store.insert(b"1", b"one").is_none());
+    assert_eq!(store.len(), 2);
+    store.update_mac();
+
+    let mut expected = Store::new(USER, PASS);
+    expected.insert(b"1", b"one");
+    expected.insert(b"2", b"two");
+    assert!(store.diff(&expected).is_empty());
+
+    let store = Store::try_from_backend(store.into_inner(), USER, PASS).unwrap();
+    assert!(store.verify_mac());
+    assert_eq!(store.get(b"1").unwrap(), b"one");
+    assert_eq!(store.get(b"2").unwrap(), b"two");
+    assert!(Store::try_from_backend(store.into_inner(), USER, b"WRONG").is_err());
+}
+
+#[test]
+fn should_work_with_hash_map_backend() {
+    use std::collections::HashMap;
+
+    let mut store = Store::new_in(HashMap::new(), USER, PASS);
+    for idx in 0..16u8 {
+        assert!(store.insert(&[idx], &[idx; 4]).is_none());
+    }
+    assert_eq!(store.len(), 16);
+    assert_eq!(store.remove(&[0]).unwrap(), [0; 4]);
+    store.update_mac();
+
+    let snapshot = store.snapshot();
+    assert!(store.insert(&[0], b"zero").is_none());
+    store.restore(snapshot);
+    assert!(!store.contains(&[0]));
+
+    let store = Store::try_from_backend(store.into_inner(), USER, PASS).unwrap();
+    assert!(store.verify_mac());
+    assert_eq!(store.len(), 15);
+    assert_eq!(store.get(&[15]).unwrap(), [15; 4]);
+}
+
+#[test]
+fn should_enforce_limits() {
+    let mut store = Store::builder(USER, PASS).max_entries(2).max_value_size(8).max_total_size(116).build().unwrap();
+    assert_eq!(store.limits().max_entries, Some(2));
+    assert_eq!(store.try_insert(b"1", &[1; 9]).err(), Some(Error::LimitExceeded));
+    assert!(store.try_insert(b"1", &[1; 8]).unwrap().is_none());
+    assert_eq!(store.size(), 12 + 24 + 8 + 16);
+    assert_eq!(store.try_insert(b"2", &[2; 8]).err(), Some(Error::LimitExceeded));
+    assert!(store.try_insert(b"2", &[2; 4]).unwrap().is_none());
+    assert_eq!(store.try_insert(b"3", &[3; 1]).err(), Some(Error::LimitExceeded));
+    assert_eq!(store.try_insert(b"1", &[1; 1]).unwrap().unwrap(), [1; 8]);
+    assert_eq!(store.len(), 2);
+    assert!(store.insert_from_reader(b"3", &[3; 1][..]).is_err());
+
+    let result = store.transaction(|transaction| {
+        assert_eq!(transaction.try_insert(b"3", &[3; 1]), Err(Error::LimitExceeded));
+        assert!(transaction.remove(b"1"));
+        transaction.try_insert(b"3", &[3; 1])
+    });
+    assert!(result.is_ok());
+    assert!(!store.contains(b"1"));
+    assert_eq!(store.size(), 2 * (12 + 24 + 16) + 4 + 1);
+
+    let mut other = Store::new(USER, PASS);
+    other.insert(b"4", b"4");
+    assert_eq!(store.merge(&other, MergePolicy::KeepRemote), Err(Error::LimitExceeded));
+    assert!(!store.contains(b"4"));
+
+    let inner = store.into_inner();
+    assert!(Store::builder(USER, PASS).backend(inner.clone()).build().is_ok());
+    assert_eq!(Store::builder(USER, PASS).backend(inner.clone()).max_entries(1).build().err(), Some(Error::LimitExceeded));
+    assert_eq!(Store::builder(USER, PASS).backend(inner.clone()).max_value_size(3).build().err(), Some(Error::LimitExceeded));
+    assert_eq!(Store::builder(USER, b"WRONG").backend(inner).build().err(), Some(Error::WrongCredentials));
+}
+
+#[test]
+fn should_evict_least_recently_used() {
+    use std::sync::{Arc, Mutex};
+
+    let evicted = Arc::new(Mutex::new(Vec::new()));
+    let sink = evicted.clone();
+    let mut store = Store::builder(USER, PASS).evict_lru(2, move |key, value| sink.lock().unwrap().push((key, value))).build().unwrap();
+    assert_eq!(store.capacity(), Some(2));
+
+    store.insert(b"1", b"one");
+    store.insert(b"2", b"two");
+    assert_eq!(store.get(b"1").unwrap(), b"one");
+    store.insert(b"3", b"three");
+    assert_eq!(store.len(), 2);
+    assert!(!store.contains(b"2"));
+    assert_eq!(*evicted.lock().unwrap(), [(xxh3_128(b"2"), Some(b"two".to_vec()))]);
+
+    assert!(store.remove_key(b"1"));
+    store.insert(b"4", b"four");
+    assert_eq!(store.len(), 2);
+    store.insert(b"5", b"five");
+    assert!(!store.contains(b"3"));
+    assert_eq!(evicted.lock().unwrap().len(), 2);
+    assert_eq!(evicted.lock().unwrap()[1].0, xxh3_128(b"3"));
+
+    let sink = evicted.clone();
+    let store = Store::builder(USER, PASS).backend(store.into_inner()).evict_lru(1, move |key, value| sink.lock().unwrap().push((key, value))).build().unwrap();
+    assert_eq!(store.len(), 1);
+    assert_eq!(evicted.lock().unwrap().len(), 3);
+}
+
+#[test]
+fn should_recover_key_from_shares() {
+    use sec_store::Share;
+
+    let mut store = Store::new(USER, PASS);
+    store.insert(b"1", b"one");
+
+    let shares = store.split_recovery(5, 3).unwrap();
+    assert_eq!(shares.len(), 5);
+    let key = store.master_key().unwrap();
+
+    for (first, second, third) in [(0, 1, 2), (4, 2, 0), (1, 3, 4)] {
+        let recovered = Store::recover(&[shares[first].clone(), shares[second].clone(), shares[third].clone()]).unwrap();
+        assert_eq!(recovered.as_bytes(), key.as_bytes());
+    }
+
+    let recovered = Store::recover(&shares[..2]).unwrap();
+    assert_ne!(recovered.as_bytes(), key.as_bytes());
+    assert_eq!(Store::recover(&[]).err(), Some(Error::InvalidShares));
+    assert_eq!(Store::recover(&[shares[0].clone(), shares[0].clone()]).err(), Some(Error::InvalidShares));
+
+    let bytes = shares[3].to_bytes();
+    assert_eq!(Share::from_bytes(&bytes).unwrap(), shares[3]);
+    assert!(Share::from_bytes(&bytes[1..]).is_none());
+
+    let single = store.split_recovery(1, 1).unwrap();
+    assert_eq!(Store::recover(&single).unwrap().as_bytes(), key.as_bytes());
+
+    let inner = store.into_inner();
+    let recovered = Store::recover(&shares[2..]).unwrap();
+    let store = Store::try_from_backend_with_key(inner, &recovered).unwrap();
+    assert_eq!(store.get(b"1").unwrap(), b"one");
+}
+
+#[test]
+fn should_recover_key_with_recovery_key() {
+    use sec_store::RecoveryKey;
+
+    let mut store = Store::new(USER, PASS);
+    store.insert(b"1", b"one");
+    let recovery = store.enable_recovery().unwrap();
+    assert_eq!(store.len(), 1);
+
+    let text = recovery.to_string();
+    assert_eq!(text.len(), 8 * 5 + 7);
+    assert_eq!(text.parse::<RecoveryKey>().unwrap(), recovery);
+    assert_eq!(text.to_lowercase().replace('-', " ").parse::<RecoveryKey>().unwrap(), recovery);
+    assert!(text[1..].parse::<RecoveryKey>().is_err());
+    assert!(format!("0{}", &text[1..]).parse::<RecoveryKey>().is_err());
+
+    let key = recovery.recover(store.inner()).unwrap();
+    assert_eq!(key.as_bytes(), store.master_key().unwrap().as_bytes());
+
+    let other = store.enable_recovery().unwrap();
+    assert_ne!(other, recovery);
+    assert_eq!(recovery.recover(store.inner()).err(), Some(Error::WrongCredentials));
+
+    store.update_mac();
+    let inner = store.into_inner();
+    let key = other.recover(&inner).unwrap();
+    let mut store = Store::try_from_backend_with_key(inner, &key).unwrap();
+    assert_eq!(store.get(b"1").unwrap(), b"one");
+
+    assert!(store.disable_recovery());
+    assert_eq!(other.recover(store.inner()).err(), Some(Error::WrongCredentials));
+}
+
+#[test]
+fn should_expose_read_only_view() {
+    use std::io::Read;
+
+    let mut store = Store::new(USER, PASS);
+    store.insert(b"1", b"one");
+    store.update_mac();
+
+    let view = store.as_read_only();
+    let copy = view;
+    assert_eq!(view.len(), 1);
+    assert!(copy.contains(b"1"));
+    assert_eq!(view.get(b"1").unwrap(), b"one");
+    assert!(view.get(b"2").is_none());
+    assert!(view.verify_credentials());
+    assert!(view.verify_mac());
+    let mut buffer = [0u8; 32];
+    assert_eq!(view.get_to(b"1", &mut buffer).unwrap(), 3);
+
+    let store = store.into_read_only();
+    let mut value = Vec::new();
+    store.get_reader(b"1").unwrap().read_to_end(&mut value).unwrap();
+    assert_eq!(value, b"one");
+    assert_eq!(store.get_to_vec(b"1", &mut value).unwrap(), 3);
+    assert_eq!(store.as_read_only().get(b"1").unwrap(), b"one");
+}
+
+#[test]
+fn should_separate_namespaces() {
+    let mut store = Store::new(USER, PASS);
+    store.insert(b"1", b"root");
+    assert!(store.namespace(b"first").insert(b"1", b"first").is_none());
+    assert!(store.namespace(b"second").insert(b"1", b"second").is_none());
+    assert_eq!(store.namespace(b"second").insert(b"2", b"second2"), None);
+    assert_eq!(store.len(), 4);
+
+    assert_eq!(store.get(b"1").unwrap(), b"root");
+    assert_eq!(store.namespace(b"first").get(b"1").unwrap(), b"first");
+    assert_eq!(store.namespace(b"second").get(b"1").unwrap(), b"second");
+    assert!(!store.namespace(b"first").contains(b"2"));
+    assert_eq!(store.namespace(b"first").len(), 1);
+    assert_eq!(store.namespace(b"second").len(), 2);
+    assert_eq!(store.namespace(b"third").len(), 0);
+
+    let exported = store.namespace(b"second").export().unwrap();
+    assert_eq!(exported.len(), 2);
+    let mut exported = Store::try_from_inner(exported.into_inner(), USER, PASS).unwrap();
+    assert!(exported.get(b"1").is_none());
+    assert_eq!(exported.namespace(b"second").get(b"2").unwrap(), b"second2");
+
+    assert_eq!(store.namespace(b"first").remove(b"1").unwrap(), b"first");
+    assert!(store.namespace(b"first").remove(b"1").is_none());
+    assert_eq!(store.get(b"1").unwrap(), b"root");
+
+    let mut wrong = Store::from_inner(store.into_inner(), USER, b"WRONG");
+    assert!(wrong.namespace(b"second").get(b"1").is_none());
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn should_decrypt_in_parallel() {
+    let mut store = Store::new(USER, PASS);
+    let keys: Vec<_> = (0..1000u32).map(|idx| idx.to_le_bytes()).collect();
+    for key in keys.iter() {
+        store.insert(key, key);
+    }
+
+    let mut requested: Vec<&[u8]> = keys.iter().map(|key| &key[..]).collect();
+    requested.push(b"missing");
+    let values = store.get_many_parallel(&requested);
+    assert_eq!(values.len(), requested.len());
+    for (key, value) in requested.iter().zip(values.iter()).take(keys.len()) {
+        assert_eq!(value.as_deref(), Some(*key));
+    }
+    assert!(values[keys.len()].is_none());
+
+    let all = store.decrypt_all_parallel();
+    assert_eq!(all.len(), keys.len());
+    for (key, value) in all {
+        let value = value.unwrap();
+        assert_eq!(key, xxh3_128(&value));
+    }
+
+    let wrong = Store::from_inner(store.into_inner(), USER, b"WRONG");
+    assert!(wrong.decrypt_all_parallel().iter().all(|(_, value)| value.is_none()));
+}
+
+#[test]
+fn should_rekey_store() {
+    use sec_store::MasterKey;
+
+    let mut store = Store::new(USER, PASS);
+    for idx in 0..100u8 {
+        store.insert(&[idx], &[idx; 8]);
+    }
+    store.insert_from_reader(b"big", &[7u8; 200_000][..]).unwrap();
+    store.namespace(b"ns").insert(b"1", b"namespaced");
+    let recovery = store.enable_recovery().unwrap();
+    store.update_mac();
+    let events = store.subscribe();
+
+    let new_key = MasterKey::derive(USER, b"NEW").unwrap();
+    assert_eq!(store.rekey(&new_key).err(), Some(Error::InvalidEntry(xxh3_128_with_seed(b"1", xxh3_64(b"sec-store:namespace:ns")))));
+    assert_eq!(store.get(&[1]).unwrap(), [1; 8]);
+
+    store.rekey_namespaces(&new_key, &[b"ns"]).unwrap();
+    assert_eq!(events.try_recv().unwrap(), ChangeEvent::Rekey);
+    assert!(store.verify_mac());
+    assert!(recovery.recover(store.inner()).is_err());
+    assert_eq!(store.get(&[99]).unwrap(), [99; 8]);
+    assert_eq!(store.namespace(b"ns").get(b"1").unwrap(), b"namespaced");
+
+    let inner = store.into_inner();
+    assert!(Store::try_from_inner(inner.clone(), USER, PASS).is_err());
+    let store = Store::try_from_inner(inner.clone(), USER, b"NEW").unwrap();
+    assert_eq!(store.len(), 102);
+    assert_eq!(store.get(b"big").unwrap(), [7u8; 200_000]);
+}
+
+#[test]
+fn should_read_value_with_callback() {
+    let mut store = Store::new(USER, PASS);
+    store.insert(b"1", b"one");
+    store.insert_from_reader(b"2", &[2u8; 100_000][..]).unwrap();
+
+    assert_eq!(store.get_with(b"1", |value| value.to_vec()).unwrap(), b"one");
+    assert_eq!(store.get_with(b"2", |value| value.len()).unwrap(), 100_000);
+    assert_eq!(store.get_with(b"1", |value| value.len()).unwrap(), 3);
+    assert!(store.get_with(b"3", |value| value.len()).is_none());
+
+    let nested = store.get_with(b"1", |outer| store.get_with(b"2", |inner| (outer.to_vec(), inner[0])));
+    assert_eq!(nested.unwrap().unwrap(), (b"one".to_vec(), 2));
+
+    let wrong = Store::from_inner(store.into_inner(), USER, b"WRONG");
+    assert!(wrong.get_with(b"1", |value| value.len()).is_none());
+}
+
+#[test]
+fn should_guard_decrypted_value() {
+    let mut store = Store::new(USER, PASS);
+    store.insert(b"1", b"one");
+    store.insert(b"2", b"two");
+
+    let first = store.get_guarded(b"1").unwrap();
+    let second = store.get_guarded(b"2").unwrap();
+    assert_eq!(&*first, b"one");
+    assert_eq!(second.as_ref(), b"two");
+    drop(first);
+    assert_eq!(&*store.get_guarded(b"2").unwrap(), b"two");
+    assert!(store.get_guarded(b"3").is_none());
+    drop(second);
+
+    let wrong = Store::from_inner(store.into_inner(), USER, b"WRONG");
+    assert!(wrong.get_guarded(b"1").is_none());
+}
+
+#[test]
+fn should_share_concurrent_store_across_threads() {
+    use sec_store::ConcurrentStore;
+
+    let store = ConcurrentStore::new(USER, PASS, 4).unwrap();
+    assert_eq!(store.shards(), 4);
+    std::thread::scope(|scope| {
+        for thread in 0..4u8 {
+            let store = &store;
+            scope.spawn(move || {
+                for idx in 0..50u8 {
+                    assert!(store.insert(&[thread, idx], &[thread, idx, 1]).is_none());
+                    assert_eq!(store.get(&[thread, idx]).unwrap(), [thread, idx, 1]);
+                }
+            });
+        }
+    });
+    assert_eq!(store.len(), 200);
+    assert!(store.remove_key(&[0, 0]));
+    assert_eq!(store.remove(&[0, 1]).unwrap(), [0, 1, 1]);
+    assert!(!store.contains(&[0, 1]));
+
+    let merged = store.into_store();
+    assert_eq!(merged.len(), 198);
+    assert_eq!(merged.get(&[3, 49]).unwrap(), [3, 49, 1]);
+    let merged = Store::try_from_inner(merged.into_inner(), USER, PASS).unwrap();
+
+    let store = ConcurrentStore::from_store(merged, 3).unwrap();
+    assert_eq!(store.len(), 198);
+    let mut value = Vec::new();
+    assert_eq!(store.get_to_vec(&[2, 2], &mut value).unwrap(), 3);
+    assert!(ConcurrentStore::new(USER, b"", 1).is_err());
+}
+
+#[test]
+fn should_watch_individual_key() {
+    let mut store = Store::new(USER, PASS);
+    let watcher = store.watch(b"1");
+    let hash = xxh3_128(b"1");
+
+    store.insert(b"2", b"2");
+    assert!(watcher.try_recv().is_err());
+    store.insert(b"1", b"1");
+    assert_eq!(watcher.try_recv().unwrap(), ChangeEvent::Insert(hash));
+    store.remove_key(b"2");
+    assert!(store.remove_key(b"1"));
+    assert_eq!(watcher.try_recv().unwrap(), ChangeEvent::Remove(hash));
+    assert!(watcher.try_recv().is_err());
+
+    let snapshot = store.snapshot();
+    store.restore(snapshot);
+    assert_eq!(watcher.try_recv().unwrap(), ChangeEvent::Restore);
+
+    drop(watcher);
+    store.insert(b"1", b"1");
+}
+
+#[test]
+fn should_count_operations() {
+    let mut store = Store::new(b"user", b"pass");
+    assert_eq!(store.stats(), Stats::default());
+
+    store.insert(b"1", b"one");
+    store.insert(b"2", b"two");
+    assert_eq!(store.get(b"1").unwrap(), b"one");
+    assert!(store.get(b"3").is_none());
+    assert!(store.get_guarded(b"2").is_some());
+    assert!(store.remove_key(b"2"));
+    assert!(!store.remove_key(b"2"));
+
+    let stats = store.stats();
+    assert_eq!(stats.gets, 3);
+    assert_eq!(stats.hits, 2);
+    assert_eq!(stats.misses, 1);
+    assert_eq!(stats.decrypt_failures, 0);
+    assert_eq!(stats.inserts, 2);
+    assert_eq!(stats.removes, 1);
+    assert_eq!(stats.bytes_encrypted as usize, 2 * (12 + 24 + 3 + 16));
+    assert_eq!(stats.bytes_decrypted, 6);
+
+    store.reset_stats();
+    assert_eq!(store.stats(), Stats::default());
+
+    //Value under wrong key hash cannot be decrypted
+    let value = store.inner().get(&xxh3_128(b"1").to_le()).unwrap().clone();
+    let mut inner = store.into_inner();
+    inner.insert(xxh3_128(b"4").to_le(), value);
+    let store = Store::from_inner(inner, b"user", b"pass");
+    assert!(store.get(b"4").is_none());
+
+    let stats = store.stats();
+    assert_eq!(stats.gets, 1);
+    assert_eq!(stats.hits, 0);
+    assert_eq!(stats.decrypt_failures, 1);
+}
+
+#[test]
+fn should_encrypt_with_random_nonce() {
+    let mut store = Store::builder(b"user", b"pass").randomized(true).build().unwrap();
+    assert!(store.is_randomized());
+    let hash = xxh3_128(b"1").to_le();
+
+    store.insert(b"1", b"one");
+    let first = store.inner().get(&hash).unwrap().clone();
+    assert_eq!(first.len(), 12 + 24 + 3 + 16);
+    store.insert(b"1", b"one");
+    assert_ne!(*store.inner().get(&hash).unwrap(), first);
+    assert_eq!(store.get(b"1").unwrap(), b"one");
+    assert!(store.try_insert(b"2", b"two").is_ok());
+    assert_eq!(store.size(), 2 * (12 + 24 + 3 + 16));
+    assert_eq!(store.get_guarded(b"2").unwrap().as_ref(), b"two");
+
+    //Ciphertext is bound to its key
+    let mut inner = store.into_inner();
+    let value = inner.get(&hash).unwrap().clone();
+    inner.insert(xxh3_128(b"3").to_le(), value);
+    let mut store = Store::from_inner(inner, b"user", b"pass");
+    assert!(!store.is_randomized());
+    assert!(store.get(b"3").is_none());
+    store.remove_key(b"3");
+
+    //Both modes are readable
+    store.insert(b"4", b"four");
+    assert_eq!(store.inner().get(&xxh3_128(b"4").to_le()).unwrap().len(), 12 + 24 + 4 + 16);
+    assert_eq!(store.get(b"1").unwrap(), b"one");
+    assert_eq!(store.get(b"4").unwrap(), b"four");
+
+    //Derived nonce depends on value, so overwriting with other value doesn't reuse it
+    let first = store.inner().get(&xxh3_128(b"4").to_le()).unwrap().clone();
+    store.insert(b"4", b"FOUR");
+    assert_ne!(store.inner().get(&xxh3_128(b"4").to_le()).unwrap()[..12], first[..12]);
+    store.insert(b"4", b"four");
+    assert_eq!(*store.inner().get(&xxh3_128(b"4").to_le()).unwrap(), first);
+
+    store.set_randomized(true);
+    store.rekey(&sec_store::MasterKey::derive(b"user2", b"pass2").unwrap()).unwrap();
+    assert_eq!(store.inner().get(&xxh3_128(b"4").to_le()).unwrap().len(), 12 + 24 + 4 + 16);
+    assert_eq!(store.get(b"1").unwrap(), b"one");
+    assert_eq!(store.get(b"2").unwrap(), b"two");
+    assert_eq!(store.get(b"4").unwrap(), b"four");
+}
+
+#[test]
+fn should_pad_values() {
+    assert_eq!(Padding::Block(64).padded_len(1), 64);
+    assert_eq!(Padding::Block(64).padded_len(64), 64);
+    assert_eq!(Padding::Block(64).padded_len(65), 128);
+    assert_eq!(Padding::Block(0).padded_len(5), 5);
+    assert_eq!(Padding::Padme.padded_len(1), 1);
+    assert_eq!(Padding::Padme.padded_len(9), 10);
+    assert_eq!(Padding::Padme.padded_len(100), 104);
+    assert_eq!(Padding::Padme.padded_len(1000), 1024);
+
+    let mut store = Store::builder(b"user", b"pass").padding(Padding::Block(64)).build().unwrap();
+    assert_eq!(store.padding(), Some(Padding::Block(64)));
+
+    store.insert(b"1", b"one");
+    store.insert(b"2", b"second value");
+    store.insert(b"3", &[0; 63]);
+    for key in [&b"1"[..], b"2", b"3"] {
+        assert_eq!(store.inner().get(&xxh3_128(key).to_le()).unwrap().len(), 12 + 24 + 64 + 16);
+    }
+    assert!(store.insert(b"4", &[0; 65]).is_none());
+    assert_eq!(store.inner().get(&xxh3_128(b"4").to_le()).unwrap().len(), 12 + 24 + 128 + 16);
+    assert_eq!(store.size(), 3 * (12 + 24 + 64 + 16) + 12 + 24 + 128 + 16);
+
+    assert_eq!(store.get(b"1").unwrap(), b"one");
+    assert_eq!(store.get(b"2").unwrap(), b"second value");
+    assert_eq!(store.get(b"3").unwrap(), [0; 63]);
+    assert_eq!(store.get(b"4").unwrap(), [0; 65].to_vec());
+    let mut buffer = [0u8; 256];
+    assert_eq!(store.get_to(b"2", &mut buffer).unwrap(), 12);
+    assert_eq!(&buffer[..12], b"second value");
+
+    //Deterministic padding keeps ciphertext, while randomized doesn't
+    let first = store.inner().get(&xxh3_128(b"1").to_le()).unwrap().clone();
+    store.insert(b"1", b"one");
+    assert_eq!(*store.inner().get(&xxh3_128(b"1").to_le()).unwrap(), first);
+    store.set_randomized(true);
+    assert_eq!(store.insert(b"1", b"one").unwrap(), b"one");
+    assert_ne!(*store.inner().get(&xxh3_128(b"1").to_le()).unwrap(), first);
+    assert_eq!(store.get(b"1").unwrap(), b"one");
+
+    //Padding applies only to new values
+    store.set_padding(None);
+    store.set_randomized(false);
+    store.insert(b"5", b"five");
+    assert_eq!(store.inner().get(&xxh3_128(b"5").to_le()).unwrap().len(), 12 + 24 + 4 + 16);
+    assert_eq!(store.get(b"2").unwrap(), b"second value");
+
+    let store = Store::from_inner(store.into_inner(), b"user", b"pass");
+    assert_eq!(store.get(b"1").unwrap(), b"one");
+    assert_eq!(store.get(b"2").unwrap(), b"second value");
+    assert_eq!(store.get(b"5").unwrap(), b"five");
+}
+
+#[test]
+fn should_not_reuse_nonce_of_padded_values() {
+    let hash = xxh3_128(b"key").to_le();
+    let nonce = |store: &Store| store.inner().get(&hash).unwrap()[..12].to_vec();
+
+    //Format `1`
+    let mut store = Store::builder(USER, PASS).padding(Padding::Block(64)).build().unwrap();
+    store.migrate_format(1).unwrap();
+    store.insert(b"key", b"one");
+    let first = nonce(&store);
+    store.insert(b"key", b"two");
+    assert_ne!(nonce(&store), first);
+    store.insert(b"key", b"one");
+    assert_eq!(nonce(&store), first);
+    assert_eq!(store.get(b"key").unwrap(), b"one");
+
+    //Padding is part of plaintext, so its change results in other nonce
+    let mut store = Store::builder(USER, PASS).padding(Padding::Block(16)).build().unwrap();
+    store.insert(b"key", b"one");
+    let first = nonce(&store);
+    store.insert(b"key", b"two");
+    assert_ne!(nonce(&store), first);
+    store.set_padding(Some(Padding::Block(64)));
+    store.insert(b"key", b"one");
+    assert_ne!(nonce(&store), first);
+    assert_eq!(store.get(b"key").unwrap(), b"one");
+}
+
+#[test]
+fn should_require_password_strength() {
+    use sec_store::{password_strength, Kdf, ScryptParams};
+
+    assert_eq!(password_strength(b""), 0);
+    assert_eq!(password_strength(b"123456"), 0);
+    assert_eq!(password_strength(b"Password123"), 0);
+    assert_eq!(password_strength(b"aaaaaaaaaaaa"), 0);
+    assert_eq!(password_strength(b"abcdefghijklmnop"), 0);
+    assert!(password_strength(b"hunter2x") >= 2);
+    assert_eq!(password_strength(b"correct horse battery staple"), 4);
+    assert_eq!(password_strength("пароль-на-русском".as_bytes()), 4);
+
+    match Store::builder(b"user", b"123456").require_strength(3).build() {
+        Err(Error::WeakPassword { score: 0, required: 3 }) => (),
+        _ => panic!("Weak password is accepted"),
+    }
+    //Password is checked before key is derived, which takes long with such parameters.
+    let kdf = Kdf::Scrypt(ScryptParams::new(16, 8, 256).unwrap());
+    match Store::builder(b"user", b"123456").kdf(kdf).require_strength(3).build() {
+        Err(Error::WeakPassword { score: 0, required: 3 }) => (),
+        _ => panic!("Weak password is accepted"),
+    }
+    assert_eq!(Store::builder(b"user", b"").require_strength(3).build().err(), Some(Error::InvalidCredentials));
+    let store = Store::builder(b"user", b"correct horse battery staple").require_strength(3).build().unwrap();
+
+    //Existing storage is not checked
+    let mut weak = Store::new(b"user", b"123456");
+    weak.insert(b"1", b"1");
+    let weak = Store::builder(b"user", b"123456").backend(weak.into_inner()).require_strength(3).build().unwrap();
+    assert_eq!(weak.get(b"1").unwrap(), b"1");
+    assert_eq!(store.len(), 0);
+}
+
+#[test]
+fn should_lock_and_unlock_store() {
+    let mut store = Store::new(b"user", b"pass");
+    store.insert(b"1", b"one");
+    store.update_mac();
+    assert!(!store.is_locked());
+
+    store.lock();
+    assert!(store.is_locked());
+    assert_eq!(store.len(), 1);
+    assert!(store.contains(b"1"));
+    assert!(store.get(b"1").is_none());
+    assert!(store.get_guarded(b"1").is_none());
+    assert_eq!(store.try_insert(b"2", b"two"), Err(Error::Locked));
+    assert_eq!(store.namespace(b"ns").try_insert(b"2", b"two").unwrap_err(), Error::Locked);
+    assert!(store.transaction(|tx| tx.try_insert(b"2", b"two")).is_err());
+    assert_eq!(store.rekey(&sec_store::MasterKey::derive(b"user", b"new").unwrap()), Err(Error::Locked));
+    assert_eq!(store.master_key().err(), Some(Error::Locked));
+    assert_eq!(store.split_recovery(3, 2).err(), Some(Error::Locked));
+    assert_eq!(store.enable_recovery().err(), Some(Error::Locked));
+    assert_eq!(store.namespace(b"ns").export().err(), Some(Error::Locked));
+    //MAC is kept as is
+    store.update_mac();
+    assert!(!store.verify_mac());
+    assert!(!store.contains(b"2"));
+
+    assert_eq!(store.unlock(b"user", b"wrong"), Err(Error::WrongCredentials));
+    assert!(store.is_locked());
+    assert_eq!(store.unlock(b"", b"pass"), Err(Error::InvalidCredentials));
+    store.unlock(b"user", b"pass").unwrap();
+    assert!(!store.is_locked());
+    assert!(store.verify_mac());
+    assert_eq!(store.get(b"1").unwrap(), b"one");
+    store.insert(b"2", b"two");
+
+    let key = store.master_key().unwrap();
+    store.lock();
+    store.unlock_with_key(&key).unwrap();
+    assert_eq!(store.get(b"2").unwrap(), b"two");
+
+    store.lock();
+    assert_eq!(sec_store::ConcurrentStore::from_store(store, 2).err(), Some(Error::Locked));
+}
+
+#[test]
+fn should_substitute_store_via_trait() {
+    use sec_store::{ConcurrentStore, SecStore};
+    use std::collections::BTreeMap;
+
+    fn roundtrip<S: SecStore>(store: &mut S) {
+        assert!(store.is_empty());
+        assert!(store.insert(b"1", b"value").is_none());
+        assert_eq!(store.insert(b"1", b"other").unwrap(), b"value");
+        assert!(store.contains(b"1"));
+        assert_eq!(store.len(), 1);
+        assert_eq!(store.get(b"1").unwrap(), b"other");
+        let mut dest = vec![1; 10];
+        assert_eq!(store.get_to_vec(b"1", &mut dest).unwrap(), 5);
+        assert_eq!(dest, b"other");
+        assert!(store.get_to_vec(b"2", &mut dest).is_err());
+        assert_eq!(store.remove(b"1").unwrap(), b"other");
+        assert!(store.remove(b"1").is_none());
+        store.insert(b"2", b"value");
+        assert!(store.remove_key(b"2"));
+        assert!(!store.remove_key(b"2"));
+        assert!(store.is_empty());
+    }
+
+    roundtrip(&mut Store::new(USER, PASS));
+    roundtrip(&mut ConcurrentStore::new(USER, PASS, 2).unwrap());
+    roundtrip(&mut BTreeMap::<Vec<u8>, Vec<u8>>::new());
+}
+
+#[test]
+fn should_query_key_names() {
+    use core::ops::Bound;
+
+    let mut store = Store::new(USER, PASS);
+    store.insert(b"unnamed", b"1");
+    assert!(!store.has_key_names());
+    assert_eq!(store.keys().count(), 0);
+    store.enable_key_names();
+    assert!(store.has_key_names());
+    let inner_len = store.inner().len();
+
+    store.insert(b"db/user", b"1");
+    store.insert(b"db/pass", b"2");
+    store.insert(b"dc", b"3");
+    store.insert(b"api/token", b"4");
+    store.insert_from_reader(b"db/cert", &b"5"[..]).unwrap();
+    store.transaction(|transaction| {
+        transaction.insert(b"db/host", b"6");
+        transaction.insert(b"db/removed", b"7");
+        transaction.remove(b"db/removed");
+        Ok::<_, ()>(())
+    }).unwrap();
+    assert_eq!(store.inner().len(), inner_len + 6);
+    assert_eq!(store.len(), 7);
+
+    assert_eq!(store.keys().collect::<Vec<_>>(), [&b"api/token"[..], b"db/cert", b"db/host", b"db/pass", b"db/user", b"dc"]);
+    assert_eq!(store.keys_with_prefix(b"db/").collect::<Vec<_>>(), [&b"db/cert"[..], b"db/host", b"db/pass", b"db/user"]);
+    assert_eq!(store.keys_with_prefix(b"none/").count(), 0);
+    assert_eq!(store.keys_range((Bound::Included(&b"db/h"[..]), Bound::Excluded(&b"dc"[..]))).collect::<Vec<_>>(), [&b"db/host"[..], b"db/pass", b"db/user"]);
+
+    assert!(store.remove_key(b"db/pass"));
+    assert_eq!(store.keys_with_prefix(b"db/").collect::<Vec<_>>(), [&b"db/cert"[..], b"db/host", b"db/user"]);
+
+    let key = sec_store::MasterKey::derive(USER, b"NEW").unwrap();
+    store.rekey(&key).unwrap();
+    store.lock();
+    assert_eq!(store.keys().count(), 0);
+    store.unlock_with_key(&key).unwrap();
+    assert_eq!(store.keys().count(), 5);
+
+    let store = Store::from_inner(store.into_inner(), USER, b"NEW");
+    assert!(store.has_key_names());
+    assert_eq!(store.keys_with_prefix(b"db/").collect::<Vec<_>>(), [&b"db/cert"[..], b"db/host", b"db/user"]);
+    let store = Store::from_inner(store.into_inner(), USER, PASS);
+    assert!(!store.has_key_names());
+}
+
+#[test]
+fn should_find_by_tag() {
+    let mut store = Store::new(USER, PASS);
+    assert!(store.insert_with_meta(b"db/pass", b"1", &[b"prod", b"db", b"prod"]).is_none());
+    store.insert_with_meta(b"db/test", b"2", &[b"test", b"db"]);
+    store.insert_with_meta(b"api", b"3", &[b"prod"]);
+    store.insert(b"plain", b"4");
+    assert!(store.inner().contains(7));
+
+    let mut prod: Vec<_> = store.find_by_tag(b"prod").collect();
+    prod.sort_unstable();
+    assert_eq!(prod, [&b"api"[..], b"db/pass"]);
+    assert_eq!(store.find_by_tag(b"test").collect::<Vec<_>>(), [&b"db/test"[..]]);
+    assert_eq!(store.find_by_tag(b"none").count(), 0);
+    assert_eq!(store.tags(b"db/pass").collect::<Vec<_>>(), [&b"db"[..], b"prod"]);
+    assert_eq!(store.tags(b"plain").count(), 0);
+
+    //Regular insertion keeps tags, while insertion with meta replaces them.
+    assert_eq!(store.insert(b"api", b"5").unwrap(), b"3");
+    assert_eq!(store.tags(b"api").collect::<Vec<_>>(), [&b"prod"[..]]);
+    assert_eq!(store.insert_with_meta(b"db/test", b"6", &[b"prod"]).unwrap(), b"2");
+    assert_eq!(store.find_by_tag(b"test").count(), 0);
+    assert_eq!(store.find_by_tag(b"prod").count(), 3);
+
+    assert_eq!(store.remove(b"api").unwrap(), b"5");
+    assert_eq!(store.find_by_tag(b"prod").count(), 2);
+
+    let key = sec_store::MasterKey::derive(USER, b"NEW").unwrap();
+    store.rekey(&key).unwrap();
+    store.lock();
+    assert_eq!(store.find_by_tag(b"prod").count(), 0);
+    store.unlock_with_key(&key).unwrap();
+    assert_eq!(store.find_by_tag(b"prod").count(), 2);
+
+    let mut store = Store::from_inner(store.into_inner(), USER, b"NEW");
+    assert_eq!(store.find_by_tag(b"db").collect::<Vec<_>>(), [&b"db/pass"[..]]);
+    store.remove_key(b"db/pass");
+    store.remove_key(b"db/test");
+    assert!(!store.inner().contains(7));
+}
+
+#[test]
+fn should_autosave() {
+    use sec_store::AutosavePolicy;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    let saves = Arc::new(Mutex::new(Vec::new()));
+    let sink = {
+        let saves = saves.clone();
+        move |store: &Store| {
+            saves.lock().unwrap().push(store.len());
+            Ok(())
+        }
+    };
+
+    let mut store = Store::new(USER, PASS);
+    store.set_autosave(sink.clone(), AutosavePolicy::Immediate);
+    store.insert(b"1", b"1");
+    store.insert(b"2", b"2");
+    store.remove_key(b"1");
+    assert!(!store.remove_key(b"1"));
+    assert_eq!(*saves.lock().unwrap(), [1, 2, 1]);
+    store.transaction(|transaction| {
+        transaction.insert(b"3", b"3");
+        transaction.insert(b"4", b"4");
+        transaction.remove(b"2");
+        Ok::<_, ()>(())
+    }).unwrap();
+    store.insert_with_meta(b"5", b"5", &[b"tag"]);
+    assert_eq!(*saves.lock().unwrap(), [1, 2, 1, 2, 3]);
+    assert!(!store.has_pending_autosave());
+
+    saves.lock().unwrap().clear();
+    store.set_autosave(sink.clone(), AutosavePolicy::EveryChanges(3));
+    store.insert(b"1", b"1");
+    store.insert(b"2", b"2");
+    assert!(store.has_pending_autosave());
+    assert!(saves.lock().unwrap().is_empty());
+    store.insert(b"6", b"6");
+    assert_eq!(*saves.lock().unwrap(), [6]);
+    store.insert(b"7", b"7");
+    store.flush_autosave().unwrap();
+    assert_eq!(*saves.lock().unwrap(), [6, 7]);
+    store.flush_autosave().unwrap();
+    assert_eq!(*saves.lock().unwrap(), [6, 7]);
+
+    saves.lock().unwrap().clear();
+    store.set_autosave(sink, AutosavePolicy::Debounced(Duration::from_millis(100)));
+    store.insert(b"8", b"8");
+    assert!(saves.lock().unwrap().is_empty());
+    std::thread::sleep(Duration::from_millis(150));
+    store.insert(b"9", b"9");
+    store.insert(b"10", b"10");
+    assert_eq!(*saves.lock().unwrap(), [9]);
+    assert!(store.has_pending_autosave());
+
+    store.set_autosave(|_: &Store| Err(std::io::Error::other("failed")), AutosavePolicy::Immediate);
+    store.insert(b"11", b"11");
+    assert!(store.has_pending_autosave());
+    assert_eq!(store.take_autosave_error().unwrap().to_string(), "failed");
+    assert!(store.take_autosave_error().is_none());
+    assert_eq!(store.flush_autosave().unwrap_err().to_string(), "failed");
+
+    store.disable_autosave();
+    assert!(!store.has_pending_autosave());
+}
+
+struct Pipe {
+    tx: std::sync::mpsc::Sender<Vec<u8>>,
+    rx: std::sync::mpsc::Receiver<Vec<u8>>,
+    buffer: Vec<u8>,
+}
+
+impl Pipe {
+    fn pair() -> (Self, Self) {
+        let (first_tx, first_rx) = std::sync::mpsc::channel();
+        let (second_tx, second_rx) = std::sync::mpsc::channel();
+        (Self { tx: first_tx, rx: second_rx, buffer: Vec::new() }, Self { tx: second_tx, rx: first_rx, buffer: Vec::new() })
+    }
+}
+
+impl std::io::Read for Pipe {
+    fn read(&mut self, out: &mut [u8]) -> std::io::Result<usize> {
+        if self.buffer.is_empty() {
+            match self.rx.recv() {
+                Ok(data) => self.buffer = data,
+                Err(_) => return Ok(0),
+            }
+        }
+        let len = core::cmp::min(out.len(), self.buffer.len());
+        out[..len].copy_from_slice(&self.buffer[..len]);
+        self.buffer.drain(..len);
+        Ok(len)
+    }
+}
+
+impl std::io::Write for Pipe {
+    fn write(&mut self, data: &[u8]) -> std::io::Result<usize> {
+        let _ = self.tx.send(data.to_owned());
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn should_sync_stores() {
+    use sec_store::sync::Role;
+
+    let key = Store::new(USER, PASS).master_key().unwrap();
+    let mut local = Store::with_key(&key);
+    local.insert(b"1", b"1");
+    local.insert(b"2", b"2");
+    local.insert(b"3", b"local");
+    let mut remote = Store::with_key(&key);
+    remote.insert(b"3", b"remote");
+    remote.insert(b"4", b"4");
+
+    let (client, server) = Pipe::pair();
+    let (local_report, remote_report) = std::thread::scope(|scope| {
+        let server = scope.spawn(|| remote.sync(server, Role::Responder).unwrap());
+        (local.sync(client, Role::Initiator).unwrap(), server.join().unwrap())
+    });
+    assert_eq!(local_report.sent, 2);
+    assert_eq!(local_report.received, 1);
+    assert_eq!(remote_report.sent, 1);
+    assert_eq!(remote_report.received, 2);
+    assert_eq!(local_report.conflicts, remote_report.conflicts);
+    assert_eq!(local_report.conflicts.len(), 1);
+    for store in [&local, &remote].iter() {
+        assert_eq!(store.len(), 4);
+        assert_eq!(store.get(b"1").unwrap(), b"1");
+        assert_eq!(store.get(b"4").unwrap(), b"4");
+    }
+    assert_eq!(local.get(b"3").unwrap(), b"local");
+    assert_eq!(remote.get(b"3").unwrap(), b"remote");
+
+    let mut other = Store::new(USER, b"WRONG");
+    let (client, server) = Pipe::pair();
+    let (local_result, other_result) = std::thread::scope(|scope| {
+        let server = scope.spawn(|| other.sync(server, Role::Responder));
+        let result = local.sync(client, Role::Initiator);
+        (result, server.join().unwrap())
+    });
+    assert_eq!(local_result.unwrap_err().kind(), std::io::ErrorKind::PermissionDenied);
+    assert!(other_result.is_err());
+    assert_eq!(other.len(), 0);
+    assert_eq!(local.len(), 4);
+
+    //Namespace entries are replicated as they are
+    local.namespace(b"ns").insert(b"5", b"5");
+    let (client, server) = Pipe::pair();
+    std::thread::scope(|scope| {
+        let server = scope.spawn(|| remote.sync(server, Role::Responder).unwrap());
+        assert_eq!(local.sync(client, Role::Initiator).unwrap().sent, 1);
+        assert_eq!(server.join().unwrap().received, 1);
+    });
+    assert_eq!(remote.namespace(b"ns").get(b"5").unwrap(), b"5");
+
+    //Rejection by responder leaves both sides untouched
+    let mut limited = Store::builder(USER, PASS).max_entries(1).build().unwrap();
+    assert_eq!(limited.master_key().unwrap().as_bytes(), key.as_bytes());
+    limited.insert(b"6", b"6");
+    let (client, server) = Pipe::pair();
+    let (local_result, limited_result) = std::thread::scope(|scope| {
+        let server = scope.spawn(|| limited.sync(server, Role::Responder));
+        let result = local.sync(client, Role::Initiator);
+        (result, server.join().unwrap())
+    });
+    assert_eq!(local_result.unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+    assert!(limited_result.is_err());
+    assert_eq!(limited.len(), 1);
+    assert!(!local.contains(b"6"));
+}
+
+#[test]
+fn should_sync_by_versions() {
+    use sec_store::sync::Role;
+
+    fn sync(local: &mut Store, remote: &mut Store) -> (sec_store::sync::Report, sec_store::sync::Report) {
+        let (client, server) = Pipe::pair();
+        std::thread::scope(|scope| {
+            let server = scope.spawn(|| remote.sync(server, Role::Responder).unwrap());
+            (local.sync(client, Role::Initiator).unwrap(), server.join().unwrap())
+        })
+    }
+
+    let key = Store::new(USER, PASS).master_key().unwrap();
+    let mut local = Store::with_key(&key);
+    local.insert(b"unversioned", b"local");
+    local.enable_versioning();
+    assert!(local.has_versioning());
+    local.insert(b"1", b"1");
+    assert_eq!(local.version(b"1"), Some(1));
+    assert_eq!(local.version(b"unversioned"), None);
+    assert_eq!(local.version(b"2"), None);
+
+    let mut remote = Store::with_key(&key);
+    remote.enable_versioning();
+    remote.insert(b"unversioned", b"remote");
+    remote.insert(b"2", b"2");
+
+    let (local_report, remote_report) = sync(&mut local, &mut remote);
+    assert_eq!(local_report.sent, 1);
+    assert_eq!(local_report.received, 2);
+    assert_eq!(remote_report.sent, 2);
+    assert!(local_report.conflicts.is_empty());
+    assert_eq!(local.get(b"unversioned").unwrap(), b"remote");
+    assert_eq!(local.get(b"2").unwrap(), b"2");
+    assert_eq!(local.version(b"2"), remote.version(b"2"));
+    assert_eq!(remote.get(b"1").unwrap(), b"1");
+    assert_eq!(remote.version(b"1"), Some(1));
+
+    //Later modification wins regardless of side.
+    local.insert(b"1", b"local");
+    remote.insert(b"1", b"remote");
+    remote.insert(b"1", b"remote");
+    local.insert(b"2", b"local");
+    let (local_report, remote_report) = sync(&mut local, &mut remote);
+    assert_eq!(local_report.sent, 1);
+    assert_eq!(remote_report.sent, 1);
+    for store in [&local, &remote].iter() {
+        assert_eq!(store.get(b"1").unwrap(), b"remote");
+        assert_eq!(store.get(b"2").unwrap(), b"local");
+        assert_eq!(store.get(b"unversioned").unwrap(), b"remote");
+    }
+
+    //Converged stores have nothing to exchange.
+    let (local_report, remote_report) = sync(&mut local, &mut remote);
+    assert_eq!(local_report, remote_report);
+    assert_eq!(local_report.sent + local_report.received, 0);
+
+    //Versions survive saving.
+    let inner = local.inner().clone();
+    let local = Store::from_backend_with_key(inner, &key);
+    assert!(local.has_versioning());
+    assert_eq!(local.version(b"1"), remote.version(b"1"));
+}
+
+#[test]
+fn should_get_value_len() {
+    let mut store = Store::new(USER, PASS);
+    let value: Vec<u8> = (0..=255).cycle().take(100 * 1024).collect();
+    store.insert(b"1", b"one");
+    store.insert(b"2", &value);
+    assert_eq!(store.value_len(b"1").unwrap(), 3);
+    assert_eq!(store.value_len(b"2").unwrap(), value.len());
+    assert_eq!(store.value_len(b"3"), Err(Error::NotFound));
+    store.set_randomized(true);
+    store.insert(b"3", b"three");
+    assert_eq!(store.value_len(b"3").unwrap(), 5);
+    store.set_padding(Some(Padding::Block(64)));
+    store.insert(b"4", b"four");
+    assert_eq!(store.value_len(b"4").unwrap(), 4);
+    assert_eq!(store.as_read_only().value_len(b"1").unwrap(), 3);
+
+    //Value of format `1`, without envelope
+    let hash = xxh3_128(b"1").to_le();
+    let mut legacy = Store::new(USER, PASS);
+    legacy.migrate_format(1).unwrap();
+    legacy.insert(b"1", b"one");
+    let mut inner = store.into_inner();
+    inner.insert(hash, legacy.inner().get(&hash).unwrap().clone());
+    let store = Store::from_inner(inner, USER, PASS);
+    assert_eq!(store.value_len(b"1").unwrap(), 3);
+    assert_eq!(store.get(b"1").unwrap(), b"one");
+    let inner = store.into_inner();
+    let store = Store::from_inner(inner.clone(), USER, b"WRONG");
+    assert_eq!(store.value_len(b"1"), Err(Error::InvalidEntry(hash)));
+    //Length is not visible without key
+    assert_eq!(store.value_len(b"3"), Err(Error::InvalidEntry(xxh3_128(b"3").to_le())));
+
+    //Length is sealed, so neither it nor value can be tampered with
+    let mut inner = inner;
+    let hash = xxh3_128(b"3").to_le();
+    let mut tampered = inner.get(&hash).unwrap().clone();
+    tampered[12] ^= 1;
+    inner.insert(hash, tampered);
+    let mut tampered = inner.get(&xxh3_128(b"4").to_le()).unwrap().clone();
+    tampered[12 + 24] ^= 1;
+    inner.insert(xxh3_128(b"4").to_le(), tampered);
+    let store = Store::from_inner(inner, USER, PASS);
+    assert_eq!(store.value_len(b"3"), Err(Error::InvalidEntry(hash)));
+    assert!(store.get(b"3").is_none());
+    assert_eq!(store.value_len(b"4").unwrap(), 4);
+    assert!(store.get(b"4").is_none());
+    let mut buffer = [0u8; 64];
+    assert!(store.get_to(b"3", &mut buffer).is_err());
+    let mut buffer = [core::mem::MaybeUninit::uninit(); 64];
+    assert!(store.get_to_uninit(b"3", &mut buffer).is_err());
+}
+
+#[test]
+fn should_get_encrypted() {
+    let mut store = Store::new(USER, PASS);
+    store.insert(b"1", b"one");
+    let (hash, value) = store.get_encrypted(b"1").unwrap();
+    assert_eq!(hash, xxh3_128(b"1").to_le());
+    assert_eq!(value, store.inner().get(&hash).unwrap().as_slice());
+    assert_ne!(value, b"one");
+    assert!(store.get_encrypted(b"2").is_none());
+
+    let mut other = Store::from_backend_with_key(std::collections::BTreeMap::new(), &store.master_key().unwrap());
+    let mut inner = other.into_inner();
+    inner.insert(hash, value.to_vec());
+    other = Store::from_backend_with_key(inner, &store.master_key().unwrap());
+    assert_eq!(other.get(b"1").unwrap(), b"one");
+}
+
+#[test]
+fn should_insert_encrypted() {
+    let mut store = Store::new(USER, PASS);
+    store.insert(b"1", b"one");
+    store.insert(b"2", b"two");
+    let mut replica = Store::with_key(&store.master_key().unwrap());
+    replica.insert(b"2", b"old");
+
+    let (hash, value) = store.get_encrypted(b"1").unwrap();
+    assert!(replica.insert_encrypted(hash, value.to_vec()).unwrap().is_none());
+    let (hash, value) = store.get_encrypted(b"2").unwrap();
+    let previous = replica.insert_encrypted(hash, value.to_vec()).unwrap().unwrap();
+    assert_ne!(previous, value);
+    assert_eq!(replica.len(), 2);
+    assert_eq!(replica.get(b"1").unwrap(), b"one");
+    assert_eq!(replica.get(b"2").unwrap(), b"two");
+
+    assert_eq!(replica.insert_encrypted(1, value.to_vec()), Err(Error::InvalidEntry(1)));
+    assert_eq!(replica.insert_encrypted(hash, vec![0; 16]), Err(Error::InvalidEntry(hash)));
+
+    //Validation is deferred to read
+    let mut other = Store::new(USER, b"other");
+    let (hash, value) = store.get_encrypted(b"1").unwrap();
+    assert!(other.insert_encrypted(hash, value.to_vec()).is_ok());
+    assert!(other.contains(b"1"));
+    assert!(other.get(b"1").is_none());
+
+    let mut limited = Store::builder(USER, PASS).max_value_size(2).build().unwrap();
+    assert_eq!(limited.insert_encrypted(hash, value.to_vec()), Err(Error::LimitExceeded));
+}
+
+#[test]
+fn should_seal_blobs() {
+    use sec_store::crypto::{Sealer, OVERHEAD};
+
+    let store = Store::new(USER, PASS);
+    let sealer = store.sealer().unwrap();
+    let sealed = sealer.seal(b"payload", b"file.txt").unwrap();
+    assert_eq!(sealed.len(), 7 + OVERHEAD);
+    assert_ne!(sealer.seal(b"payload", b"file.txt").unwrap(), sealed);
+    assert_eq!(sealer.open(&sealed, b"file.txt").unwrap(), b"payload");
+    assert!(sealer.open(&sealed, b"other.txt").is_err());
+    assert!(sealer.open(&sealed[..OVERHEAD - 1], b"file.txt").is_err());
+
+    let mut tampered = sealed.clone();
+    tampered[OVERHEAD / 2] ^= 1;
+    assert!(sealer.open(&tampered, b"file.txt").is_err());
+
+    //Same key is derived from credentials and master key
+    let derived = Sealer::derive(USER, PASS).unwrap();
+    assert_eq!(derived.open(&sealed, b"file.txt").unwrap(), b"payload");
+    assert_eq!(Sealer::new(&store.master_key().unwrap()).open(&sealed, b"file.txt").unwrap(), b"payload");
+    assert!(Sealer::derive(USER, b"WRONG").unwrap().open(&sealed, b"file.txt").is_err());
+    assert_eq!(Sealer::derive(USER, b"").err(), Some(Error::InvalidCredentials));
+
+    let mut in_place = b"in place".to_vec();
+    sealer.seal_in_place(&mut in_place, &[]).unwrap();
+    assert_eq!(sealer.open_in_place(&mut in_place, &[]).unwrap(), b"in place");
+    assert_eq!(format!("{:?}", sealer), "Sealer");
+
+    let mut store = store;
+    store.lock();
+    assert_eq!(store.sealer().err(), Some(Error::Locked));
+}
+
+#[test]
+fn should_seal_detached() {
+    let mut store = Store::new(USER, PASS);
+    let sealed = store.seal_detached(b"sidecar", b"content").unwrap();
+    assert_eq!(store.len(), 0);
+    assert_eq!(store.open_detached(b"sidecar", &sealed).unwrap(), b"content");
+    assert_eq!(store.open_detached(b"other", &sealed).err(), Some(Error::IntegrityMismatch));
+    assert_eq!(store.sealer().unwrap().open(&sealed, b"sidecar").unwrap(), b"content");
+
+    let other = Store::new(USER, b"other password");
+    assert_eq!(other.open_detached(b"sidecar", &sealed).err(), Some(Error::IntegrityMismatch));
+
+    store.lock();
+    assert_eq!(store.open_detached(b"sidecar", &sealed).err(), Some(Error::Locked));
+    assert_eq!(store.seal_detached(b"sidecar", b"content").err(), Some(Error::Locked));
+}
+
+#[test]
+fn should_sign_entries() {
+    use sec_store::{Signer, SignatureStatus};
+
+    let (alice, document) = Signer::generate().unwrap();
+    let (bob, _) = Signer::generate().unwrap();
+    assert_eq!(Signer::from_pkcs8(&document).unwrap().public_key(), alice.public_key());
+    assert!(Signer::from_pkcs8(b"garbage").is_err());
+
+    let mut store = Store::new(USER, PASS);
+    store.register_signer(b"alice", &alice.public_key()).unwrap();
+    assert_eq!(store.signers().collect::<Vec<_>>(), vec![(&alice.public_key(), &b"alice"[..])]);
+    assert_eq!(store.try_insert_signed(b"key", b"value", &bob).err(), Some(Error::WrongCredentials));
+    assert_eq!(store.len(), 0);
+
+    assert!(store.insert_signed(b"key", b"value", &alice).is_none());
+    store.insert(b"plain", b"value");
+    let metadata = store.metadata(b"key").unwrap();
+    assert_eq!(metadata.len, 5);
+    assert_eq!(metadata.signature, SignatureStatus::Valid(b"alice".to_vec()));
+    assert_eq!(store.metadata(b"plain").unwrap().signature, SignatureStatus::Unsigned);
+    assert_eq!(store.metadata(b"missing").err(), Some(Error::NotFound));
+
+    //Signatures survive save and load
+    let store = Store::from_backend(store.inner().clone(), USER, PASS);
+    assert_eq!(store.metadata(b"key").unwrap().signature, SignatureStatus::Valid(b"alice".to_vec()));
+    assert_eq!(store.len(), 2);
+
+    let mut store = store;
+    assert!(store.unregister_signer(&alice.public_key()));
+    assert!(!store.unregister_signer(&alice.public_key()));
+    assert_eq!(store.metadata(b"key").unwrap().signature, SignatureStatus::Untrusted(alice.public_key()));
+
+    //Unsigned modification discards signature
+    store.insert(b"key", b"other");
+    assert_eq!(store.metadata(b"key").unwrap().signature, SignatureStatus::Unsigned);
+}
+
+#[test]
+fn should_accept_deposits() {
+    use sec_store::crypto::deposit;
+
+    let mut store = Store::new(USER, PASS);
+    let key = store.deposit_key().unwrap();
+    assert_eq!(Store::new(USER, PASS).deposit_key().unwrap(), key);
+    assert_ne!(Store::new(USER, b"other password").deposit_key().unwrap(), key);
+
+    let sealed = deposit(&key, b"ci-token", b"secret").unwrap();
+    assert_ne!(deposit(&key, b"ci-token", b"secret").unwrap(), sealed);
+    assert!(deposit(&[0; 32], b"ci-token", b"secret").is_err());
+
+    assert!(store.accept_deposit(&sealed).unwrap().is_none());
+    assert_eq!(store.get(b"ci-token").unwrap(), b"secret");
+    assert_eq!(store.accept_deposit(&sealed).unwrap().unwrap(), b"secret");
+
+    let mut other = Store::new(USER, b"other password");
+    assert_eq!(other.accept_deposit(&sealed).err(), Some(Error::IntegrityMismatch));
+    assert_eq!(other.accept_deposit(&sealed[..40]).err(), Some(Error::IntegrityMismatch));
+    let mut tampered = sealed.clone();
+    tampered[50] ^= 1;
+    assert_eq!(store.accept_deposit(&tampered).err(), Some(Error::IntegrityMismatch));
+
+    //Empty value is rejected, as store cannot hold it
+    let empty = deposit(&key, b"empty", b"").unwrap();
+    assert_eq!(store.accept_deposit(&empty).err(), Some(Error::InvalidEntry(xxh3_128(b"empty").to_le())));
+    assert!(!store.contains(b"empty"));
+
+    store.lock();
+    assert_eq!(store.deposit_key().err(), Some(Error::Locked));
+    assert_eq!(store.accept_deposit(&sealed).err(), Some(Error::Locked));
+}
+
+#[test]
+fn should_share_entries() {
+    let mut alice = Store::new(USER, PASS);
+    let mut bob = Store::new(b"bob", PASS);
+    alice.insert(b"shared", b"secret");
+
+    let entry = alice.export_entry_for(b"shared", &bob.deposit_key().unwrap()).unwrap();
+    assert_eq!(alice.export_entry_for(b"missing", &bob.deposit_key().unwrap()).err(), Some(Error::NotFound));
+    assert_eq!(alice.export_entry_for(b"shared", &[0; 32]).err(), Some(Error::InvalidCredentials));
+
+    //Only recipient can import it, and it is not usable as deposit
+    assert_eq!(alice.import_shared_entry(&entry).err(), Some(Error::IntegrityMismatch));
+    assert_eq!(bob.accept_deposit(&entry).err(), Some(Error::IntegrityMismatch));
+    assert_eq!(bob.accept_deposit(&entry[32..]).err(), Some(Error::IntegrityMismatch));
+
+    //Sender cannot be forged
+    let mut forged = entry.clone();
+    forged[..32].copy_from_slice(&Store::new(b"eve", PASS).deposit_key().unwrap());
+    assert_eq!(bob.import_shared_entry(&forged).err(), Some(Error::IntegrityMismatch));
+
+    let (sender, previous) = bob.import_shared_entry(&entry).unwrap();
+    assert_eq!(sender, alice.deposit_key().unwrap());
+    assert!(previous.is_none());
+    assert_eq!(bob.get(b"shared").unwrap(), b"secret");
+}
+
+#[test]
+fn should_store_meta() {
+    let mut store = Store::new(USER, PASS);
+    assert!(store.meta(b"name").is_none());
+    assert!(store.set_meta(b"name", b"Personal").unwrap().is_none());
+    assert_eq!(store.set_meta(b"name", b"Work").unwrap().unwrap(), b"Personal");
+    store.set_meta(b"schema", &[2]).unwrap();
+    assert_eq!(store.len(), 0);
+    assert!(store.get(b"name").is_none());
+
+    let mut store = Store::from_backend(store.inner().clone(), USER, PASS);
+    assert_eq!(store.meta(b"name").unwrap(), b"Work");
+    assert_eq!(store.remove_meta(b"schema").unwrap().unwrap(), [2]);
+    assert!(store.remove_meta(b"schema").unwrap().is_none());
+    assert!(store.meta(b"schema").is_none());
+
+    store.rekey(&sec_store::MasterKey::derive(USER, b"new password").unwrap()).unwrap();
+    assert_eq!(store.meta(b"name").unwrap(), b"Work");
+
+    store.lock();
+    assert!(store.meta(b"name").is_none());
+    assert_eq!(store.set_meta(b"name", b"Other").err(), Some(Error::Locked));
+}
+
+#[test]
+fn should_check_integrity() {
+    let mut store = Store::new(USER, PASS);
+    store.insert(b"1", b"one");
+    store.insert(b"2", b"two");
+    store.namespace(b"app").insert(b"3", b"three");
+    store.set_meta(b"name", b"vault").unwrap();
+
+    let report = store.check_integrity_namespaces(&[b"app"]).unwrap();
+    assert!(report.is_ok());
+    assert_eq!(report.checked, store.inner().len());
+    assert_eq!(report.mac, None);
+
+    //Namespaced entry cannot be authenticated by store's key
+    let report = store.check_integrity().unwrap();
+    assert_eq!(report.corrupt.len(), 1);
+
+    store.update_mac();
+    let (key, value) = store.get_encrypted(b"2").unwrap();
+    let mut value = value.to_vec();
+    let last = value.len() - 1;
+    value[last] ^= 1;
+    store.insert_encrypted(key, value).unwrap();
+
+    let report = store.check_integrity_namespaces(&[b"app"]).unwrap();
+    assert!(!report.is_ok());
+    assert_eq!(report.corrupt, [key]);
+    assert_eq!(report.mac, Some(false));
+
+    store.lock();
+    assert_eq!(store.check_integrity().err(), Some(Error::Locked));
+}
+
+#[test]
+fn should_export_age() {
+    use ring::{aead, agreement, hkdf, hmac};
+    use sec_store::age::Recipient;
+
+    fn derive(salt: &[u8], secret: &[u8], info: &[u8]) -> [u8; 32] {
+        let mut out = [0u8; 32];
+        hkdf::Salt::new(hkdf::HKDF_SHA256, salt).extract(secret).expand(&[info], hkdf::HKDF_SHA256).unwrap().fill(&mut out).unwrap();
+        out
+    }
+
+    fn open(key: &[u8; 32], nonce: [u8; 12], data: &[u8]) -> Vec<u8> {
+        let key = aead::LessSafeKey::new(aead::UnboundKey::new(&aead::CHACHA20_POLY1305, key).unwrap());
+        let mut data = data.to_vec();
+        let len = key.open_in_place(aead::Nonce::assume_unique_for_key(nonce), aead::Aad::empty(), &mut data).unwrap().len();
+        data.truncate(len);
+        data
+    }
+
+    fn decode(text: &str) -> Vec<u8> {
+        const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+        let bits: Vec<u8> = text.bytes().flat_map(|ch| {
+            let value = ALPHABET.iter().position(|alpha| *alpha == ch).unwrap() as u8;
+            (0..6).rev().map(move |bit| (value >> bit) & 1)
+        }).collect();
+        bits.chunks_exact(8).map(|byte| byte.iter().fold(0, |acc, bit| (acc << 1) | bit)).collect()
+    }
+
+    assert!(Recipient::parse("age1ql3z7hjy54pw3hyww5ayyfg7zqgvc7w3j2elw8zmrj2kg5sfn9aqmcac8p").is_ok());
+    assert!(Recipient::parse("age1ql3z7hjy54pw3hyww5ayyfg7zqgvc7w3j2elw8zmrj2kg5sfn9aqmcac8q").is_err());
+    assert!(Recipient::parse("AGE1ql3z7hjy54pw3hyww5ayyfg7zqgvc7w3j2elw8zmrj2kg5sfn9aqmcac8p").is_err());
+    assert!(Recipient::parse("age1qqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqeh4dyr").is_err());
+    match Recipient::parse("AGE13AQVTTDK3UJKYJH9KG2W5AN6DMY5MQ5A84A4UXK3HFHNUGFC9P0SY5P2WH").unwrap() {
+        Recipient::X25519(public) => assert_eq!(public[..4], [143, 64, 197, 173]),
+        Recipient::Scrypt { .. } => unreachable!(),
+    }
+
+    let rng = ring::rand::SystemRandom::new();
+    let other = agreement::EphemeralPrivateKey::generate(&agreement::X25519, &rng).unwrap();
+    let identity = agreement::EphemeralPrivateKey::generate(&agreement::X25519, &rng).unwrap();
+    let mut public = [[0u8; 32]; 2];
+    public[0].copy_from_slice(other.compute_public_key().unwrap().as_ref());
+    public[1].copy_from_slice(identity.compute_public_key().unwrap().as_ref());
+
+    let mut store = Store::new(USER, PASS);
+    store.insert(b"key", b"value");
+    store.insert(b"big", &[7u8; 100_000]);
+    store.insert(b"other", b"secret");
+    assert_eq!(store.export_age_entries(&[b"key", b"missing"], &[Recipient::X25519(public[1])]).unwrap_err(), Error::NotFound);
+
+    let file = store.export_age_entries(&[b"key", b"big"], &[Recipient::X25519(public[0]), Recipient::X25519(public[1])]).unwrap();
+    let header_len = file.windows(4).position(|window| window == b"\n---").unwrap() + 4;
+    let end = header_len + file[header_len..].iter().position(|byte| *byte == b'\n').unwrap() + 1;
+    let header = core::str::from_utf8(&file[..end]).unwrap();
+    let lines: Vec<&str> = header.lines().collect();
+    assert_eq!(lines.len(), 6);
+    assert_eq!(lines[0], "age-encryption.org/v1");
+    assert!(lines[1].starts_with("-> X25519 "));
+    assert!(lines[3].starts_with("-> X25519 "));
+
+    let share = decode(&lines[3][10..]);
+    let shared = agreement::agree_ephemeral(identity, &agreement::UnparsedPublicKey::new(&agreement::X25519, &share), |shared| shared.to_vec()).unwrap();
+    let key = derive(&[&share[..], &public[1][..]].concat(), &shared, b"age-encryption.org/v1/X25519");
+    let file_key = open(&key, [0; 12], &decode(lines[4]));
+    assert_eq!(file_key.len(), 16);
+
+    let mac = derive(&[], &file_key, b"header");
+    hmac::verify(&hmac::Key::new(hmac::HMAC_SHA256, &mac), &file[..header_len], &decode(&lines[5][4..])).unwrap();
+
+    let key = derive(&file[end..end + 16], &file_key, b"payload");
+    let mut payload = Vec::new();
+    for (idx, chunk) in file[end + 16..].chunks(64 * 1024 + 16).enumerate() {
+        let mut nonce = [0u8; 12];
+        nonce[10] = idx as u8;
+        nonce[11] = (end + 16 + (idx + 1) * (64 * 1024 + 16) >= file.len()) as u8;
+        payload.extend_from_slice(&open(&key, nonce, chunk));
+    }
+    let mut expected = String::from("a2V5 dmFsdWU=\nYmln ");
+    expected.push_str(&"BwcH".repeat(100_000 / 3));
+    expected.push_str("Bw==\n");
+    assert!(payload == expected.as_bytes());
+
+    assert_eq!(store.export_age(&[]).unwrap_err(), Error::InvalidRecipient);
+    assert_eq!(store.export_age(&[Recipient::passphrase(b"backup", 2), Recipient::X25519(public[0])]).unwrap_err(), Error::InvalidRecipient);
+    assert_eq!(store.export_age(&[Recipient::Scrypt { passphrase: b"backup".to_vec(), log_n: 40 }]).unwrap_err(), Error::InvalidRecipient);
+    assert_eq!(store.export_age_entries(&[b"key"], &[Recipient::X25519([0; 32])]).unwrap_err(), Error::InvalidRecipient);
+    let file = store.export_age(&[Recipient::passphrase(b"backup", 2)]).unwrap();
+    let header = core::str::from_utf8(&file[..file.windows(4).position(|window| window == b"\n---").unwrap()]).unwrap();
+    let stanza: Vec<&str> = header.lines().nth(1).unwrap().split(' ').collect();
+    assert_eq!(stanza.len(), 4);
+    assert_eq!(stanza[..2], ["->", "scrypt"]);
+    assert_eq!(decode(stanza[2]).len(), 16);
+    assert_eq!(stanza[3], "2");
+}
+
+///Decrypts age file at `path` with `identity`, using reference implementation, if it is installed.
+fn age_decrypt(path: &std::path::Path, identity: &std::path::Path) -> Option<Vec<u8>> {
+    use std::process::Command;
+
+    for tool in ["age", "rage"].iter() {
+        match Command::new(tool).arg("--decrypt").arg("--identity").arg(identity).arg(path).output() {
+            Ok(output) => {
+                assert!(output.status.success(), "{} cannot decrypt: {}", tool, String::from_utf8_lossy(&output.stderr));
+                return Some(output.stdout);
+            },
+            Err(_) => continue,
+        }
+    }
+    None
+}
+
+#[test]
+fn should_export_age_for_reference_implementation() {
+    use sec_store::age::Recipient;
+
+    //`entries.age` is exported to identity `age.txt` (as produced by `age-keygen`), so that it can be checked by hand:
+    //`age --decrypt --identity tests/data/age.txt tests/data/entries.age`
+    const IDENTITY: &str = include_str!("data/age.txt");
+    const ENTRIES: &[u8] = include_bytes!("data/entries.age");
+
+    let public = IDENTITY.lines().find_map(|line| line.strip_prefix("# public key: ")).unwrap();
+    let mut store = Store::new(USER, PASS);
+    store.insert(b"key", b"value");
+    store.insert(b"notes", &[b'x'; 50_000]);
+    let mut expected = String::from("a2V5 dmFsdWU=\nbm90ZXM= ");
+    expected.push_str(&"eHh4".repeat(50_000 / 3));
+    expected.push_str("eHg=\n");
+
+    let file = store.export_age_entries(&[b"key", b"notes"], &[Recipient::parse(public).unwrap()]).unwrap();
+    assert_eq!(file.len(), ENTRIES.len());
+    assert_eq!(file[..23], ENTRIES[..23]);
+
+    let dir = std::env::current_dir().unwrap().join("tests").join("data");
+    let path = std::env::temp_dir().join(format!("sec-store-{}-entries.age", std::process::id()));
+    std::fs::write(&path, &file).unwrap();
+    let fixture = age_decrypt(&dir.join("entries.age"), &dir.join("age.txt"));
+    let exported = age_decrypt(&path, &dir.join("age.txt"));
+    std::fs::remove_file(&path).unwrap();
+
+    match (fixture, exported) {
+        (Some(fixture), Some(exported)) => {
+            assert!(fixture == expected.as_bytes());
+            assert!(exported == expected.as_bytes());
+        },
+        _ => eprintln!("Neither age nor rage is installed, skipping decryption by reference implementation"),
+    }
+}
+
+#[test]
+fn should_import_plaintext_json() {
+    let mut store = Store::new(b"user", b"pass");
+    store.insert(b"existing", b"old");
+
+    let hash = xxh3_128(b"hashed").to_le();
+    let document = format!("{{\n  \"existing\": \"bmV3\",\n  \"esc\\u0061ped\": \"dmFsdWU\",\n  \"#{:032x}\": \"aGFzaA==\"\n}}\n", hash);
+    assert_eq!(store.import_plaintext_json(document.as_bytes()).unwrap(), 3);
+    assert_eq!(store.len(), 3);
+    assert_eq!(store.get(b"existing").unwrap(), b"new");
+    assert_eq!(store.get(b"escaped").unwrap(), b"value");
+    assert_eq!(store.get(b"hashed").unwrap(), b"hash");
+    assert_eq!(store.import_plaintext_json(&b" {} "[..]).unwrap(), 0);
+
+    for document in [&b"[]"[..], b"{\"a\": 1}", b"{\"a\": \"!!\"}", b"{\"a\": \"\"}", b"{\"a\": \"YQ==\",}", b"{\"a\": \"YQ==\"} {}"] {
+        let error = store.import_plaintext_json(document).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    let reserved = format!("{{\"new\": \"YQ==\", \"#{:032x}\": \"YQ==\"}}", 1);
+    assert!(store.import_plaintext_json(reserved.as_bytes()).is_err());
+    assert!(!store.contains(b"new"));
+    assert_eq!(store.len(), 3);
+
+    store.lock();
+    assert!(store.import_plaintext_json(&b"{}"[..]).is_err());
+}
+
+#[test]
+fn should_armor_store() {
+    let mut store = Store::new(b"user", b"pass");
+    store.insert(b"1", b"one");
+    store.insert(b"2", &[2u8; 1000]);
+
+    let armored = store.to_armored().unwrap();
+    assert!(armored.starts_with("-----BEGIN SEC-STORE-----\n"));
+    assert!(armored.ends_with("\n-----END SEC-STORE-----\n"));
+    assert!(armored.lines().all(|line| line.len() <= 64 && line.is_ascii()));
+
+    let restored = Store::from_armored(&armored, b"user", b"pass").unwrap();
+    assert_eq!(restored.len(), 2);
+    assert_eq!(restored.get(b"1").unwrap(), b"one");
+    assert_eq!(restored.get(b"2").unwrap(), [2u8; 1000]);
+
+    let pasted = format!("Backup of vault:\r\n\r\n{}\r\nRegards", armored.replace('\n', "\r\n  "));
+    assert_eq!(Store::from_armored(&pasted, b"user", b"pass").unwrap().get(b"1").unwrap(), b"one");
+
+    assert!(Store::from_armored(&armored, b"user", b"wrong").is_err());
+    assert!(Store::from_armored(&armored[1..], b"user", b"pass").is_err());
+    assert!(Store::from_armored(&armored[..armored.len() - 2], b"user", b"pass").is_err());
+    let corrupted = armored.replacen("\n", "\n!", 2);
+    assert!(Store::from_armored(&corrupted, b"user", b"pass").is_err());
+}
+
+#[test]
+fn should_update_value_in_place() {
+    let mut store = Store::builder(USER, PASS).max_value_size(8).build().unwrap();
+    store.insert(b"counter", b"1");
+
+    store.update(b"counter", |value| {
+        assert_eq!(value, b"1");
+        value.push(b'2');
+    }).unwrap();
+    assert_eq!(store.get(b"counter").unwrap(), b"12");
+
+    assert_eq!(store.update(b"counter", |value| value.extend_from_slice(&[0; 8])), Err(Error::LimitExceeded));
+    assert_eq!(store.get(b"counter").unwrap(), b"12");
+    assert_eq!(store.update(b"missing", |_| panic!("Should not be called")), Err(Error::NotFound));
+    assert!(!store.contains(b"missing"));
+
+    store.lock();
+    assert_eq!(store.update(b"counter", |_| panic!("Should not be called")), Err(Error::Locked));
+}
+
+#[test]
+fn should_get_or_insert_with() {
+    let mut store = Store::builder(USER, PASS).max_value_size(8).build().unwrap();
+
+    assert_eq!(store.get_or_insert_with(b"token", || b"new".to_vec()), b"new");
+    assert_eq!(store.get_or_insert_with(b"token", || panic!("Should not be called")), b"new");
+    assert_eq!(store.get(b"token").unwrap(), b"new");
+    assert_eq!(store.len(), 1);
+
+    assert_eq!(store.try_get_or_insert_with(b"large", || vec![1; 9]), Err(Error::LimitExceeded));
+    assert!(!store.contains(b"large"));
+
+    store.lock();
+    assert_eq!(store.try_get_or_insert_with(b"token", || panic!("Should not be called")), Err(Error::Locked));
+}
+
+#[test]
+fn should_insert_if_absent() {
+    let mut store = Store::builder(USER, PASS).max_value_size(8).build().unwrap();
+
+    assert!(store.insert_if_absent(b"secret", b"first"));
+    assert!(!store.insert_if_absent(b"secret", b"second"));
+    assert_eq!(store.get(b"secret").unwrap(), b"first");
+
+    assert_eq!(store.try_insert_if_absent(b"large", &[1; 9]), Err(Error::LimitExceeded));
+    assert_eq!(store.try_insert_if_absent(b"secret", &[1; 9]), Ok(false));
+
+    store.lock();
+    assert_eq!(store.try_insert_if_absent(b"other", b"value"), Err(Error::Locked));
+}
+
+#[test]
+fn should_compare_and_swap() {
+    let mut store = Store::builder(USER, PASS).max_value_size(8).build().unwrap();
+    store.insert(b"token", b"v1");
+
+    assert_eq!(store.compare_and_swap(b"token", b"v0", b"v2"), Ok(false));
+    assert_eq!(store.get(b"token").unwrap(), b"v1");
+    assert_eq!(store.compare_and_swap(b"token", b"v1", b"v2"), Ok(true));
+    assert_eq!(store.get(b"token").unwrap(), b"v2");
+    assert_eq!(store.compare_and_swap(b"token", b"v1", b"v3"), Ok(false));
+    assert_eq!(store.compare_and_swap(b"token", b"v", b"v3"), Ok(false));
+
+    assert_eq!(store.compare_and_swap(b"token", b"v2", &[1; 9]), Err(Error::LimitExceeded));
+    assert_eq!(store.get(b"token").unwrap(), b"v2");
+    assert_eq!(store.compare_and_swap(b"missing", b"v2", b"v3"), Err(Error::NotFound));
+
+    store.lock();
+    assert_eq!(store.compare_and_swap(b"token", b"v2", b"v3"), Err(Error::Locked));
+}
+
+#[test]
+fn should_rename_key() {
+    let mut store = Store::builder(USER, PASS).max_entries(2).build().unwrap();
+    store.insert(b"old", b"secret");
+    store.insert(b"other", b"value");
+
+    assert_eq!(store.rename(b"old", b"new"), Ok(()));
+    assert!(!store.contains(b"old"));
+    assert_eq!(store.get(b"new").unwrap(), b"secret");
+    assert_eq!(store.len(), 2);
+
+    assert_eq!(store.rename(b"new", b"other"), Ok(()));
+    assert!(!store.contains(b"new"));
+    assert_eq!(store.get(b"other").unwrap(), b"secret");
+    assert_eq!(store.len(), 1);
+
+    assert_eq!(store.rename(b"other", b"other"), Ok(()));
+    assert_eq!(store.get(b"other").unwrap(), b"secret");
+    assert_eq!(store.rename(b"missing", b"new"), Err(Error::NotFound));
+
+    store.lock();
+    assert_eq!(store.rename(b"other", b"new"), Err(Error::Locked));
+}
+
+#[test]
+fn should_append_to_value() {
+    use sec_store::StoreBuilder;
+
+    let value: Vec<u8> = (0..=255).cycle().take(400).collect();
+    let mut store = StoreBuilder::new(USER, PASS).chunking(Some(100)).max_value_size(350).build().unwrap();
+    store.insert(b"codes", &value[..250]);
+    store.insert(b"small", b"code1");
+
+    let key = xxh3_128(b"codes").to_le();
+    let before = store.inner().get(&key).unwrap().clone();
+    assert_eq!(store.append(b"codes", &value[250..280]), Ok(()));
+    let after = store.inner().get(&key).unwrap();
+    assert_eq!(after.len(), 12 + 32 + 16 + 280 + 3 * (12 + 16));
+    //Only header and last chunk are re-sealed, each using fresh nonce
+    let chunk = 12 + 100 + 16;
+    let chunks = 12 + 32 + 16;
+    assert_ne!(after[..12], before[..12]);
+    assert_eq!(after[chunks..chunks + 2 * chunk], before[chunks..chunks + 2 * chunk]);
+    assert_ne!(after[chunks + 2 * chunk..chunks + 2 * chunk + 12], before[chunks + 2 * chunk..chunks + 2 * chunk + 12]);
+    assert_eq!(store.append(b"codes", &value[280..330]), Ok(()));
+    assert_eq!(store.inner().get(&key).unwrap().len(), 12 + 32 + 16 + 330 + 4 * (12 + 16));
+    assert_eq!(store.get(b"codes").unwrap(), value[..330]);
+
+    assert_eq!(store.append(b"small", b",code2"), Ok(()));
+    assert_eq!(store.get(b"small").unwrap(), b"code1,code2");
+
+    assert_eq!(store.append(b"codes", &value[330..]), Err(Error::LimitExceeded));
+    assert_eq!(store.get(b"codes").unwrap(), value[..330]);
+    assert_eq!(store.append(b"missing", b"code"), Err(Error::NotFound));
+
+    store.lock();
+    assert_eq!(store.append(b"small", b",code3"), Err(Error::Locked));
+}
+
+#[test]
+fn should_drain_store() {
+    use sec_store::EntryKey;
+
+    let mut store = Store::new(USER, PASS);
+    store.insert(b"unnamed", b"1");
+    store.enable_key_names();
+    store.insert(b"named", b"2");
+    store.namespace(b"ns").insert(b"hidden", b"3");
+
+    let mut drained: Vec<_> = store.drain().collect();
+    assert_eq!(drained.len(), 3);
+    drained.retain(|(_, value)| value.is_ok());
+    drained.sort_by(|left, right| left.1.as_ref().ok().cmp(&right.1.as_ref().ok()));
+    assert_eq!(drained, [
+        (EntryKey::Hash(xxh3_128(b"unnamed").to_le()), Ok(b"1".to_vec())),
+        (EntryKey::Name(b"named".to_vec()), Ok(b"2".to_vec())),
+    ]);
+    assert_eq!(store.len(), 1);
+    assert_eq!(store.namespace(b"ns").get(b"hidden").unwrap(), b"3");
+
+    store.insert(b"named", b"2");
+    store.lock();
+    assert!(store.drain().all(|(_, value)| value == Err(Error::Locked)));
+    assert_eq!(store.len(), 2);
+}
+
+#[test]
+fn should_extend_from_plaintext() {
+    let mut store = Store::builder(USER, PASS).max_entries(3).build().unwrap();
+    store.enable_key_names();
+    store.insert(b"db", b"old");
+
+    let vars = [("db", "postgres://"), ("token", "secret")];
+    assert_eq!(store.extend_from_plaintext(vars.iter().copied()), Ok(2));
+    assert_eq!(store.get(b"db").unwrap(), b"postgres://");
+    assert_eq!(store.get(b"token").unwrap(), b"secret");
+    let mut keys: Vec<_> = store.keys().collect();
+    keys.sort();
+    assert_eq!(keys, [&b"db"[..], b"token"]);
+
+    let vars = vec![(b"a".to_vec(), b"1".to_vec()), (b"b".to_vec(), b"2".to_vec())];
+    assert_eq!(store.extend_from_plaintext(vars), Err(Error::LimitExceeded));
+    assert_eq!(store.len(), 2);
+    assert!(!store.contains(b"a"));
+    assert_eq!(store.keys().count(), 2);
+
+    store.lock();
+    assert_eq!(store.extend_from_plaintext([("c", "3")]), Err(Error::Locked));
+}
+
+#[test]
+fn should_reject_empty_values() {
+    let mut store = Store::new(USER, PASS);
+    store.insert(b"1", b"one");
+    let hash = xxh3_128(b"2").to_le();
+
+    assert_eq!(store.try_insert(b"2", b""), Err(Error::InvalidEntry(hash)));
+    assert_eq!(sec_store::SecStore::try_insert(&mut store, b"2", b""), Err(Error::InvalidEntry(hash)));
+    assert!(matches!(store.namespace(b"ns").try_insert(b"2", b""), Err(Error::InvalidEntry(_))));
+    assert_eq!(store.transaction(|tx| tx.try_insert(b"2", b"")), Err(Error::InvalidEntry(hash)));
+    assert_eq!(store.extend_from_plaintext([("3", "three"), ("2", "")]), Err(Error::InvalidEntry(hash)));
+    assert_eq!(store.len(), 1);
+    assert!(!store.contains(b"3"));
+
+    let mut map = std::collections::BTreeMap::new();
+    assert_eq!(sec_store::SecStore::try_insert(&mut map, b"2", b""), Err(Error::InvalidEntry(hash)));
+    assert!(map.is_empty());
+}
+
+#[test]
+fn should_decrypt_all() {
+    let mut store = Store::new(USER, PASS);
+    store.insert(b"unnamed", b"1");
+    assert_eq!(store.decrypt_all_named().unwrap().len(), 0);
+    store.enable_key_names();
+    store.insert(b"named", b"2");
+    store.namespace(b"ns").insert(b"hidden", b"3");
+
+    let all = store.decrypt_all().unwrap();
+    assert_eq!(all.len(), 2);
+    assert_eq!(all[&xxh3_128(b"unnamed").to_le()], b"1");
+    assert_eq!(all[&xxh3_128(b"named").to_le()], b"2");
+
+    let named = store.decrypt_all_named().unwrap();
+    assert_eq!(named.len(), 1);
+    assert_eq!(named[&b"named"[..]], b"2");
+
+    store.lock();
+    assert_eq!(store.decrypt_all(), Err(Error::Locked));
+    assert_eq!(store.decrypt_all_named(), Err(Error::Locked));
+}
+
+#[test]
+fn should_clone_store() {
+    let mut store = Store::builder(USER, PASS).max_entries(2).build().unwrap();
+    store.enable_key_names();
+    store.insert(b"1", b"1");
+
+    let mut clone = store.try_clone().unwrap();
+    assert_eq!(clone.get(b"1").unwrap(), b"1");
+    assert_eq!(clone.keys().collect::<Vec<_>>(), [b"1"]);
+    clone.insert(b"2", b"2");
+    assert_eq!(clone.try_insert(b"3", b"3"), Err(Error::LimitExceeded));
+    assert!(!store.contains(b"2"));
+
+    store.insert(b"1", b"4");
+    assert_eq!(clone.get(b"1").unwrap(), b"1");
+    assert_eq!(store.get(b"1").unwrap(), b"4");
+
+    store.lock();
+    assert!(matches!(store.try_clone(), Err(Error::Locked)));
+    assert_eq!(clone.get(b"2").unwrap(), b"2");
+}
+
+#[test]
+fn should_verify_value() {
+    let mut store = Store::new(USER, PASS);
+    store.insert(b"password", b"hunter2");
+
+    assert_eq!(store.verify(b"password", b"hunter2"), Ok(true));
+    assert_eq!(store.verify(b"password", b"hunter3"), Ok(false));
+    assert_eq!(store.verify(b"password", b"hunter"), Ok(false));
+    assert_eq!(store.verify(b"password", b""), Ok(false));
+    assert_eq!(store.verify(b"missing", b"hunter2"), Err(Error::NotFound));
+
+    let guard = store.get_guarded(b"password").unwrap();
+    assert_eq!(store.verify(b"password", b"hunter2"), Ok(true));
+    drop(guard);
+
+    store.lock();
+    assert_eq!(store.verify(b"password", b"hunter2"), Err(Error::Locked));
+}
+
+#[test]
+fn should_access_value_by_handle() {
+    let mut store = Store::builder(USER, PASS).max_value_size(8).build().unwrap();
+    store.enable_key_names();
+    let counter = store.key_handle(b"counter");
+    assert_eq!(counter.hash(), xxh3_128(b"counter").to_le());
+    assert_eq!(counter, store.key_handle(b"counter"));
+    assert!(!store.contains_by_handle(counter));
+
+    assert_eq!(store.insert_by_handle(counter, &[1]), None);
+    for idx in 2..10u8 {
+        assert_eq!(store.insert_by_handle(counter, &[idx]).unwrap(), [idx - 1]);
+    }
+    assert!(store.contains_by_handle(counter));
+    assert_eq!(store.get_by_handle(counter).unwrap(), [9]);
+    assert_eq!(store.get(b"counter").unwrap(), [9]);
+    assert_eq!(store.keys().count(), 0);
+
+    assert_eq!(store.try_insert_by_handle(counter, &[0; 9]), Err(Error::LimitExceeded));
+    assert_eq!(store.remove_by_handle(counter).unwrap(), [9]);
+    assert!(!store.contains(b"counter"));
+    assert_eq!(store.remove_by_handle(counter), None);
+}
+
+#[test]
+fn should_keep_history_of_values() {
+    let mut store = Store::new(USER, PASS);
+    store.insert(b"password", b"v0");
+    assert!(!store.has_history());
+    assert_eq!(store.history(b"password").unwrap(), []);
+
+    store.set_history_depth(2).unwrap();
+    assert!(store.has_history());
+    assert_eq!(store.len(), 1);
+    store.insert(b"password", b"v1");
+    store.insert(b"password", b"v2");
+    store.insert(b"password", b"v3");
+    store.insert(b"other", b"value");
+    store.namespace(b"ns").insert(b"password", b"a");
+    store.namespace(b"ns").insert(b"password", b"b");
+
+    assert_eq!(store.history(b"password").unwrap(), [(2, b"v2".to_vec()), (1, b"v1".to_vec())]);
+    assert_eq!(store.history(b"other").unwrap(), []);
+    assert_eq!(store.rollback(b"password", 0), Err(Error::NotFound));
+    store.rollback(b"password", 1).unwrap();
+    assert_eq!(store.get(b"password").unwrap(), b"v1");
+    assert_eq!(store.history(b"password").unwrap(), [(3, b"v3".to_vec()), (2, b"v2".to_vec())]);
+
+    let path = std::env::temp_dir().join(format!("sec-store-{}-history", std::process::id()));
+    store.save(&path).unwrap();
+    let mut store = Store::open(&path, USER, PASS).unwrap();
+    let _ = std::fs::remove_file(&path);
+    assert_eq!(store.history(b"password").unwrap().len(), 2);
+
+    store.set_history_depth(1).unwrap();
+    assert_eq!(store.history(b"password").unwrap(), [(3, b"v3".to_vec())]);
+    store.remove(b"password");
+    assert_eq!(store.history(b"password").unwrap(), []);
+
+    store.insert(b"other", b"new");
+    store.lock();
+    assert_eq!(store.history(b"other"), Err(Error::Locked));
+    assert_eq!(store.rollback(b"other", 0), Err(Error::Locked));
+    store.unlock(USER, PASS).unwrap();
+    assert_eq!(store.history(b"other").unwrap(), [(0, b"value".to_vec())]);
+
+    store.set_history_depth(0).unwrap();
+    assert!(!store.has_history());
+    assert_eq!(store.history(b"other").unwrap(), []);
+}
+
+#[test]
+fn should_sync_removals() {
+    use sec_store::sync::Role;
+
+    fn sync(local: &mut Store, remote: &mut Store) -> (sec_store::sync::Report, sec_store::sync::Report) {
+        let (client, server) = Pipe::pair();
+        std::thread::scope(|scope| {
+            let server = scope.spawn(|| remote.sync(server, Role::Responder).unwrap());
+            (local.sync(client, Role::Initiator).unwrap(), server.join().unwrap())
+        })
+    }
+
+    let key = Store::new(USER, PASS).master_key().unwrap();
+    let mut local = Store::with_key(&key);
+    local.enable_versioning();
+    local.insert(b"1", b"1");
+    local.insert(b"2", b"2");
+    local.insert(b"3", b"3");
+    let mut remote = Store::with_key(&key);
+    remote.enable_versioning();
+    let mut third = Store::with_key(&key);
+    third.enable_versioning();
+    sync(&mut local, &mut remote);
+    sync(&mut remote, &mut third);
+    assert_eq!(third.len(), 3);
+
+    assert_eq!(local.remove(b"1").unwrap(), b"1");
+    assert_eq!(local.tombstones_len(), 1);
+    //Re-insertion after removal wins over it.
+    remote.remove(b"2");
+    local.insert(b"2", b"new");
+    let (local_report, remote_report) = sync(&mut local, &mut remote);
+    assert_eq!(local_report.removed, 0);
+    assert_eq!(remote_report.removed, 1);
+    assert_eq!(local_report.sent, 1);
+    for store in [&local, &remote].iter() {
+        assert_eq!(store.len(), 2);
+        assert!(!store.contains(b"1"));
+        assert_eq!(store.get(b"2").unwrap(), b"new");
+        assert_eq!(store.tombstones_len(), 1);
+    }
+
+    //Tombstone replicates further, even though entry is already removed.
+    let (remote_report, third_report) = sync(&mut remote, &mut third);
+    assert_eq!(third_report.removed, 1);
+    assert_eq!(remote_report.sent, 1);
+    assert!(!third.contains(b"1"));
+    assert_eq!(third.get(b"2").unwrap(), b"new");
+
+    //Tombstones survive saving.
+    let inner = third.inner().clone();
+    let mut third = Store::from_backend_with_key(inner, &key);
+    assert_eq!(third.tombstones_len(), 1);
+
+    assert_eq!(third.purge_tombstones(std::time::Duration::from_secs(3600)), Ok(0));
+    assert_eq!(third.purge_tombstones(std::time::Duration::from_secs(0)), Ok(1));
+    assert_eq!(third.tombstones_len(), 0);
+    third.lock();
+    assert_eq!(third.purge_tombstones(std::time::Duration::from_secs(0)), Err(Error::Locked));
+}
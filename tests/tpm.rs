@@ -0,0 +1,57 @@
+#![cfg(all(target_os = "linux", feature = "tpm"))]
+
+use sec_store::{MasterKey, Store, Tpm};
+
+use std::io::ErrorKind;
+
+#[test]
+fn should_fail_sealing_without_tpm() {
+    let path = std::env::temp_dir().join(format!("sec-store-{}-tpm", std::process::id()));
+    let tpm = Tpm::with_device(&path).bind_pcrs(&[0, 7]);
+    let key = MasterKey::derive(b"loli", b"pass").unwrap();
+    assert_eq!(tpm.seal(&key).unwrap_err().kind(), ErrorKind::NotFound);
+    assert_eq!(key.wrap(&tpm).unwrap_err().kind(), ErrorKind::NotFound);
+
+    //Sealed key is parsed before accessing TPM
+    assert_eq!(tpm.unseal(&[0; 3]).unwrap_err().kind(), ErrorKind::InvalidData);
+    assert_eq!(tpm.unseal(&[0, 0, 0, 0, 2, 0]).unwrap_err().kind(), ErrorKind::InvalidData);
+    assert_eq!(tpm.unseal(&[0, 0, 0, 0, 1, 0, 0, 0, 0]).unwrap_err().kind(), ErrorKind::InvalidData);
+    assert_eq!(tpm.unseal(&[0, 0, 0, 0, 1, 0, 0, 0]).unwrap_err().kind(), ErrorKind::NotFound);
+    assert_eq!(Store::open_tpm_sealed(&path, &[0, 0, 0, 0, 0, 0, 0], &tpm).err().unwrap().kind(), ErrorKind::NotFound);
+}
+
+#[test]
+#[should_panic]
+fn should_reject_invalid_pcr() {
+    let _ = Tpm::new().bind_pcrs(&[24]);
+}
+
+#[test]
+#[ignore = "requires TPM, e.g. `swtpm socket --tpm2 --server type=unixio,path=<path> --flags not-need-init,startup-clear` with SEC_STORE_TPM=<path>"]
+fn should_seal_and_unseal_key() {
+    let device = std::env::var_os("SEC_STORE_TPM").unwrap_or_else(|| "/dev/tpmrm0".into());
+    let path = std::env::temp_dir().join(format!("sec-store-{}-tpm-sealed", std::process::id()));
+    let key = MasterKey::derive(b"loli", b"pass").unwrap();
+
+    let tpm = Tpm::with_device(&device);
+    let sealed = tpm.seal(&key).unwrap();
+    assert_eq!(tpm.unseal(&sealed).unwrap().as_bytes(), key.as_bytes());
+    assert_eq!(MasterKey::unwrap(&key.wrap(&tpm).unwrap(), &tpm).unwrap().as_bytes(), key.as_bytes());
+
+    //Binding is recorded within sealed key
+    let bound = Tpm::with_device(&device).bind_pcrs(&[0, 7]).seal(&key).unwrap();
+    assert_eq!(tpm.unseal(&bound).unwrap().as_bytes(), key.as_bytes());
+
+    let mut tampered = sealed.clone();
+    let last = tampered.len() - 1;
+    tampered[last] ^= 1;
+    assert!(tpm.unseal(&tampered).is_err());
+
+    let mut store = Store::with_key(&key);
+    store.insert(b"1", b"one");
+    store.save(&path).unwrap();
+    let store = Store::open_tpm_sealed(&path, &bound, &tpm).unwrap();
+    assert_eq!(store.get(b"1").unwrap(), b"one");
+
+    let _ = std::fs::remove_file(&path);
+}
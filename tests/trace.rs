@@ -0,0 +1,102 @@
+#![cfg(feature = "tracing")]
+
+use sec_store::{MasterKey, Store};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+#[derive(Default)]
+struct Recorder {
+    spans: AtomicU64,
+    records: Mutex<Vec<String>>,
+}
+
+impl Recorder {
+    fn take(&self) -> Vec<String> {
+        core::mem::take(&mut *self.records.lock().unwrap())
+    }
+}
+
+///Collects fields of event as `op success` or `error`.
+#[derive(Default)]
+struct Fields(Vec<String>);
+
+impl Visit for Fields {
+    fn record_str(&mut self, _: &Field, value: &str) {
+        self.0.push(value.to_owned());
+    }
+
+    fn record_bool(&mut self, _: &Field, value: bool) {
+        self.0.push(value.to_string());
+    }
+
+    fn record_debug(&mut self, field: &Field, _: &dyn core::fmt::Debug) {
+        self.0.push(field.name().to_owned());
+    }
+}
+
+impl Subscriber for Recorder {
+    fn enabled(&self, _: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, span: &Attributes<'_>) -> Id {
+        self.records.lock().unwrap().push(span.metadata().name().to_owned());
+        Id::from_u64(self.spans.fetch_add(1, Ordering::Relaxed) + 1)
+    }
+
+    fn record(&self, _: &Id, _: &Record<'_>) {}
+
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+        self.records.lock().unwrap().push(fields.0.join(" "));
+    }
+
+    fn enter(&self, _: &Id) {}
+
+    fn exit(&self, _: &Id) {}
+}
+
+#[test]
+fn should_trace_operations() {
+    let recorder = Arc::new(Recorder::default());
+    tracing::subscriber::with_default(recorder.clone(), || {
+        let mut store = Store::new(b"user", b"pass");
+        store.insert(b"key", b"value");
+        assert_eq!(store.get(b"key").unwrap(), b"value");
+        assert!(store.get(b"missing").is_none());
+        assert!(store.remove(b"key").is_some());
+        assert_eq!(recorder.take(), ["insert true", "get true", "get false", "remove true"]);
+
+        let mut file = Vec::new();
+        store.save_to_writer(&mut file).unwrap();
+        assert!(Store::load_from_reader(&file[..], b"user", b"pass").is_ok());
+        assert!(Store::load_from_reader(&file[..], b"user", b"wrong").is_err());
+        assert_eq!(recorder.take(), [
+            "save_to_writer",
+            "load_from_reader", "try_from_backend",
+            "load_from_reader", "try_from_backend", "error", "error",
+        ]);
+
+        let inner = store.into_inner();
+        let mut store = Store::from_inner(inner, b"user", b"pass");
+        store.rekey(&MasterKey::derive(b"user", b"new").unwrap()).unwrap();
+        store.lock();
+        assert!(store.rekey(&MasterKey::derive(b"user", b"other").unwrap()).is_err());
+        assert_eq!(recorder.take(), ["from_inner", "rekey_namespaces", "rekey_namespaces", "error"]);
+
+        let path = std::env::temp_dir().join(format!("sec-store-{}-trace", std::process::id()));
+        let store = Store::new(b"user", b"pass");
+        store.save(&path).unwrap();
+        assert!(Store::open_lazy(&path, b"user", b"pass").is_ok());
+        assert!(Store::open_exclusive(&path, b"user", b"pass").is_ok());
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(recorder.take(), ["save", "open_lazy", "open_exclusive", "open", "try_from_backend"]);
+    });
+}
@@ -0,0 +1,22 @@
+#![cfg(feature = "yubikey")]
+
+use sec_store::{Store, YubiKey};
+
+use std::io::ErrorKind;
+
+#[test]
+fn should_reject_long_challenge() {
+    let yubikey = YubiKey::new().slot(1);
+    let challenge = [0u8; 65];
+    assert_eq!(yubikey.respond(&challenge).unwrap_err().kind(), ErrorKind::InvalidInput);
+    assert_eq!(yubikey.derive_key(b"loli", b"pass", &challenge).unwrap_err().kind(), ErrorKind::InvalidInput);
+
+    let path = std::env::temp_dir().join(format!("sec-store-{}-yubikey", std::process::id()));
+    assert_eq!(Store::open_with_yubikey(&path, b"loli", b"pass", &challenge, &yubikey).err().unwrap().kind(), ErrorKind::InvalidInput);
+}
+
+#[test]
+#[should_panic]
+fn should_reject_invalid_slot() {
+    let _ = YubiKey::with_serial(1).slot(3);
+}